@@ -1,42 +1,57 @@
-import JSZip from 'jszip'
-
 import { saveFile } from './utils.js'
 
 
 // STL
 export async function exportStl(component, path) {
-  const stl = component.real.export_stl(component.title)
+  const stl = component.real.export_stl(component.title, true, true)
   return await saveFile(stl, 'stl', path, component.title)
 }
 
 
 // 3MF
-const header3mf = `
-<?xml version="1.0" encoding="UTF-8"?>
-<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
- <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml" />
- <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml" />
- <Default Extension="png" ContentType="image/png" />
-</Types>
-`
-const rels3mf = `
-<?xml version="1.0" encoding="UTF-8"?>
-<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
- <Relationship Target="/3D/3dmodel.model" Id="rel-1" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel" />
- <Relationship Target="/Metadata/thumbnail.png" Id="rel-2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/thumbnail" />
-</Relationships>
-`
-
-export function export3mf(component, path) {
-  var zip = new JSZip()
-  zip.file('[Content_Types].xml', header3mf)
-  const Metadata = zip.folder('Metadata')
-  // Metadata.file('thumbnail.png', imgData, {base64: true})
-  const threeD = zip.folder('3D')
-  threeD.file('3dmodel.model', component.real.export_3mf())
-  var _rels = zip.folder('_rels')
-  _rels.file('.rels', rels3mf)
-  zip.generateAsync({type:'uint8array'}).then(function(binarystring) {
-    saveFile(binarystring, '3mf', path, component.title)
+export async function export3mf(component, path, thumbnail) {
+  const data = component.real.export_3mf(componentInfo(component), thumbnail)
+  return await saveFile(data, '3mf', path, component.title)
+}
+
+
+// STEP
+export async function exportStep(component, path) {
+  const step = component.real.export_step(componentInfo(component))
+  return await saveFile(step, 'step', path, component.title)
+}
+
+
+// OBJ
+// The material library is saved next to the OBJ file
+export async function exportObj(component, path) {
+  const info = componentInfo(component)
+  const mtllib = (component.title || 'untitled').replace(/ /g, '_') + '.mtl'
+  path = await saveFile(component.real.export_obj(info, mtllib), 'obj', path, component.title)
+  const mtlPath = path && path.replace(/[^\/\\]*$/, mtllib)
+  await saveFile(component.real.export_mtl(info), 'mtl', mtlPath, component.title)
+  return path
+}
+
+
+// Titles by component id, along with the materials of their solids by solid id
+function componentInfo(component, info = {}) {
+  // Bodies use the material of their component for now
+  const material = component.getMaterial()
+  const materials = {}
+  if(material) component.solids.forEach(solid => {
+    materials[solid.get_id()] = {
+      name: material.title,
+      color: [
+        ...material.displayMaterial.color.toArray().map(c => Math.round(c * 255) ),
+        Math.round((1.0 - material.transparency) * 255),
+      ],
+    }
   })
+  info[component.id] = {
+    title: component.UIData.title,
+    materials,
+  }
+  component.children.forEach(child => componentInfo(child, info) )
+  return info
 }
@@ -361,10 +361,11 @@ export class DraftFeature extends Feature {
 
 export class SweepFeature extends Feature {
   constructor(document, real) {
-    super(document, real, true, 'Sweep', 'edit', {
-      profile: {
+    super(document, real, true, 'Sweep', 'route', {
+      profiles: {
         title: 'Profile',
         type: 'profile',
+        multi: true,
       },
       rail: {
         title: 'Rail',
@@ -374,24 +375,209 @@ export class SweepFeature extends Feature {
         title: 'Bounds',
         type: 'bounds',
       },
+      fixedNormal: {
+        title: 'Framing',
+        type: 'bool',
+        icons: ['route', 'thumbtack'],
+      },
     })
 
-    this.profile = null
+    this.profiles = null
     this.rail = null
     this.bounds = [0.0, 1.0]
+    this.fixedNormal = false
+  }
+
+  isComplete() {
+    return this.rail && this.profiles && this.profiles().length
+  }
+
+  updateFeature() {
+    const list = new window.alcWasm.JsProfileRefList()
+    this.profiles().forEach(profile => {
+      list.push(profile)
+    })
+    const sketch = this.document.tree.findSketch(this.profiles()[0].get_sketch_id())
+    const comp_ref = sketch.component_id()
+    const [start, end] = this.bounds
+    this.real.sweep(comp_ref, list, this.rail(), start, end, this.fixedNormal, this.operation)
+  }
+
+  confirm() {
+    // Refetch profiles in case they've been repaired
+    this.profiles().forEach(profile => profile.update())
+  }
+}
+
+
+export class LoftFeature extends Feature {
+  constructor(document, real) {
+    super(document, real, true, 'Loft', 'layer-group', {
+      profiles: {
+        title: 'Profiles',
+        type: 'profile',
+        multi: true,
+      },
+      guides: {
+        title: '(Guides)',
+        type: 'curve',
+        multi: true,
+        optional: true,
+      },
+      tangent: {
+        title: 'Mode',
+        type: 'bool',
+        icons: ['project-diagram', 'wave-square'],
+      },
+    })
+
+    this.profiles = null
+    this.guides = null
+    this.tangent = true
+  }
+
+  isComplete() {
+    return this.profiles && this.profiles().length >= 2
+  }
+
+  updateFeature() {
+    const profiles = new window.alcWasm.JsProfileRefList()
+    this.profiles().forEach(profile => {
+      profiles.push(profile)
+    })
+    const guides = new window.alcWasm.JsCurveRefList()
+    const guideRefs = (this.guides && this.guides()) || []
+    guideRefs.forEach(guide => {
+      guides.push(guide)
+    })
+    const sketch = this.document.tree.findSketch(this.profiles()[0].get_sketch_id())
+    const comp_ref = sketch.component_id()
+    this.real.loft(comp_ref, profiles, guides, this.tangent, this.operation)
+  }
+
+  confirm() {
+    // Refetch profiles in case they've been repaired
+    this.profiles().forEach(profile => profile.update())
+  }
+}
+
+
+export class FilletFeature extends Feature {
+  constructor(document, real) {
+    super(document, real, false, 'Fillet', 'clone', {
+      edges: {
+        title: 'Edges',
+        type: 'edge',
+        multi: true,
+        autoMulti: true,
+      },
+      radius: {
+        title: 'Radius',
+        type: 'length',
+      },
+    })
+
+    this.edges = null
+    this.radius = 0.1
+  }
+
+  isComplete() {
+    return this.edges && this.edges().length
+  }
+
+  updateFeature() {
+    const list = new window.alcWasm.JsEdgeRefList()
+    this.edges().forEach(edge => {
+      list.push(edge)
+    })
+    this.real.fillet(list, this.radius)
+  }
+
+  confirm() {
+    // Refetch edges in case they've been repaired
+    this.edges().forEach(edgeRef => edgeRef.free())
+    const edges = this.real.get_edge_refs()
+    this.edges = () => edges
+  }
+}
+
+
+export class ChamferFeature extends Feature {
+  constructor(document, real) {
+    super(document, real, false, 'Chamfer', 'screwdriver', {
+      edges: {
+        title: 'Edges',
+        type: 'edge',
+        multi: true,
+        autoMulti: true,
+      },
+      distance: {
+        title: 'Distance',
+        type: 'length',
+      },
+    })
+
+    this.edges = null
+    this.distance = 0.1
   }
 
   isComplete() {
-    return this.profile && this.rail
+    return this.edges && this.edges().length
   }
 
-  preview() {
-    this.profile.noFree = true
-    return this.profile.extrude_preview(1.0)
+  updateFeature() {
+    const list = new window.alcWasm.JsEdgeRefList()
+    this.edges().forEach(edge => {
+      list.push(edge)
+    })
+    this.real.chamfer(list, this.distance)
   }
 
   confirm() {
-    this.profile.extrude(1.0)
+    // Refetch edges in case they've been repaired
+    this.edges().forEach(edgeRef => edgeRef.free())
+    const edges = this.real.get_edge_refs()
+    this.edges = () => edges
+  }
+}
+
+
+export class ShellFeature extends Feature {
+  constructor(document, real) {
+    super(document, real, false, 'Shell', 'magnet', {
+      faces: {
+        title: 'Open Faces',
+        type: 'face',
+        multi: true,
+        autoMulti: true,
+      },
+      thickness: {
+        title: 'Thickness',
+        type: 'length',
+      },
+    })
+
+    this.faces = null
+    this.thickness = 0.1
+  }
+
+  isComplete() {
+    return this.faces && this.faces().length
+  }
+
+  updateFeature() {
+    const list = new window.alcWasm.JsFaceRefList()
+    this.faces().forEach(face => {
+      list.push(face)
+    })
+    this.real.shell(this.document.activeComponent.real.id(), list, this.thickness)
+  }
+
+  confirm() {
+    // Refetch faces in case they've been repaired
+    this.faces().forEach(faceRef => faceRef.free())
+    const faces = this.real.get_face_refs()
+    this.faces = () => faces
   }
 }
 
@@ -210,6 +210,14 @@ export default class Renderer {
     this.emitter.emit('render')
   }
 
+  // PNG image of the current view, read back before the drawing buffer gets cleared
+  thumbnail() {
+    this.render()
+    const dataUrl = this.renderer.domElement.toDataURL('image/png')
+    const binary = atob(dataUrl.split(',')[1])
+    return Uint8Array.from(binary, char => char.charCodeAt(0) )
+  }
+
   startAnimation() {
     clearTimeout(this.dampingTimeout)
     if(!this.isAnimating) {
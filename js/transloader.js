@@ -68,6 +68,7 @@ export default class Transloader {
         return obj.component
 
       case alcWasm.JsFace:
+      case alcWasm.JsEdge:
         return obj.solid.component
     }
   }
@@ -204,32 +204,32 @@ impl CurveType {
       CurveType::Line(line) => match other {
         CurveType::Line(other) => intersection::line_line(line, other).map_or(vec![], |isect| vec![isect] ),
         CurveType::Circle(other) => intersection::line_circle(line, other),
-        CurveType::Arc(_other) => vec![],
+        CurveType::Arc(other) => intersection::line_arc(line, other),
         CurveType::Spline(other) => intersection::line_spline(line, other),
       },
 
       // Arc
-      CurveType::Arc(_arc) => match other {
-        CurveType::Line(_other) => vec![],
-        CurveType::Circle(_other) => vec![],
-        CurveType::Arc(_other) => vec![],
-        CurveType::Spline(_other) => vec![],
+      CurveType::Arc(arc) => match other {
+        CurveType::Line(other) => Self::invert_intersections(intersection::line_arc(other, arc)),
+        CurveType::Circle(other) => intersection::arc_circle(arc, other),
+        CurveType::Arc(other) => intersection::arc_arc(arc, other),
+        CurveType::Spline(other) => intersection::arc_spline(arc, other),
       },
 
       // Circle
       CurveType::Circle(circle) => match other {
         CurveType::Line(other) => Self::invert_intersections(intersection::line_circle(other, circle)),
-        CurveType::Circle(_other) => vec![],
-        CurveType::Arc(_other) => vec![],
-        CurveType::Spline(_other) => vec![],
+        CurveType::Circle(other) => intersection::circle_circle(circle, other),
+        CurveType::Arc(other) => Self::invert_intersections(intersection::arc_circle(other, circle)),
+        CurveType::Spline(other) => intersection::circle_spline(circle, other),
       },
 
       // Bezier Spline
       CurveType::Spline(spline) => match other {
         CurveType::Line(other) => Self::invert_intersections(intersection::line_spline(other, spline)),
-        CurveType::Circle(_other) => vec![],
-        CurveType::Arc(_other) => vec![],
-        CurveType::Spline(_other) => vec![],
+        CurveType::Circle(other) => Self::invert_intersections(intersection::circle_spline(other, spline)),
+        CurveType::Arc(other) => Self::invert_intersections(intersection::arc_spline(other, spline)),
+        CurveType::Spline(other) => intersection::spline_spline(spline, other),
       },
     }
   }
@@ -295,7 +295,10 @@ impl TrimmedCurve {
     let curve = self.base.as_curve();
     let trims = (curve.unsample(&bounds.0), curve.unsample(&bounds.1));
     self.is_forward = true;
-    self.trims = if trims.0 <= trims.1 {
+    self.trims = if let CurveType::Circle(_) = self.base {
+      // Circles are trimmed in forward direction, possibly across their seam
+      if trims.1 <= trims.0 { (trims.0, trims.1 + 1.0) } else { trims }
+    } else if trims.0 <= trims.1 {
       (trims.0, trims.1)
     } else {
       self.is_forward = false;
@@ -325,7 +328,11 @@ impl TrimmedCurve {
   }
 
   pub fn param_from_base(&self, t_base: f64) -> f64 {
-    let t_base = if self.is_forward { t_base } else { 1.0 - t_base };
+    let mut t_base = if self.is_forward { t_base } else { 1.0 - t_base };
+    if let CurveType::Circle(_) = self.base {
+      let offset = t_base - self.trims.0;
+      if !offset.almost(0.0) { t_base -= offset.floor() }
+    }
     (t_base - self.trims.0) / (self.trims.1 - self.trims.0)
   }
 
@@ -565,11 +572,16 @@ impl Curve for Arc {
   fn unsample(&self, p: &Point3) -> f64 {
     let circle = Circle::from_plane(self.plane.clone(), self.radius);
     let param = circle.unsample(p);
-    (param - self.bounds.0) / self.range()
+    // Measure distance from start in the direction of the arc, wrapping around the seam
+    let mut offset = (param - self.bounds.0) * self.range().signum();
+    offset -= offset.floor();
+    if offset.almost(1.0) { offset = 0.0 }
+    offset / self.range().abs()
   }
 
-  fn tangent_at(&self, _t: f64) -> Vec3 {
-    todo!()
+  fn tangent_at(&self, t: f64) -> Vec3 {
+    let circle = Circle::from_plane(self.plane.clone(), self.radius);
+    circle.tangent_at(self.convert_param(t)) * self.range().signum()
   }
 
   fn curvature_at(&self, _t: f64) -> f64 {
@@ -581,13 +593,18 @@ impl Curve for Arc {
   }
 
   fn length_between(&self, start: f64, end: f64) -> f64 {
-    std::f64::consts::PI * 2.0 * self.radius * (start - end).abs()
+    std::f64::consts::PI * 2.0 * self.radius * self.range().abs() * (start - end).abs()
   }
 }
 
 impl Splittable for Arc {
-  fn split_at(&self, _t: f64) -> Option<(Self, Self)> {
-    todo!()
+  fn split_at(&self, t: f64) -> Option<(Self, Self)> {
+    if t.almost(0.0) || t.almost(1.0) { return None }
+    let middle = self.bounds.0 + t * self.range();
+    Some((
+      Self::from_plane(self.plane.clone(), self.radius, self.bounds.0, middle),
+      Self::from_plane(self.plane.clone(), self.radius, middle, self.bounds.1),
+    ))
   }
 
   fn into_enum(self) -> CurveType {
@@ -670,8 +687,9 @@ impl Curve for Circle {
     }
   }
 
-  fn tangent_at(&self, _t: f64) -> Vec3 {
-    todo!()
+  fn tangent_at(&self, t: f64) -> Vec3 {
+    let t = t * std::f64::consts::PI * 2.0;
+    (self.plane.u * t.cos() - self.plane.v * t.sin()).normalize()
   }
 
   fn curvature_at(&self, _t: f64) -> f64 {
@@ -703,8 +721,8 @@ impl Splittable for Circle {
     if points.len() >= 2 {
       let mut params: Vec<f64> = points.iter().map(|p| self.unsample(p) ).collect();
       params.sort_by(|a, b| a.partial_cmp(b).unwrap() );
-      let first_arc = Arc::new(self.plane.origin, self.radius, params[0], params[1]);
-      let second_arc = Arc::new(self.plane.origin, self.radius, params[1], params[0]);
+      let first_arc = Arc::from_plane(self.plane.clone(), self.radius, params[0], params[1]);
+      let second_arc = Arc::from_plane(self.plane.clone(), self.radius, params[1], params[0] + 1.0);
       if points.len() > 2 {
         let remaining_points = points.iter().skip(2).cloned().collect();
         let mut arcs = vec![first_arc.into_enum()];
@@ -738,6 +756,8 @@ pub struct BasisSpline {
   pub degree: usize,
   pub controls: Vec<Point3>,
   pub knots: Vec<f64>,
+  // Splines stored without weights are non-rational
+  #[serde(default)]
   pub weights: Vec<f64>,
 }
 
@@ -755,6 +775,79 @@ impl BasisSpline {
     }
   }
 
+  // Straight segments between the given points
+  pub fn polyline(points: Vec<Point3>) -> Self {
+    let n = points.len();
+    if n < 2 { panic!() }
+    Self {
+      id: Uuid::new_v4(),
+      degree: 1,
+      controls: points,
+      knots: Self::clamped_knots(n, 1),
+      weights: vec![1.0; n],
+    }
+  }
+
+  // Cubic spline passing through the given points
+  // Parameters are distributed by chord length and knots are averaged from them
+  pub fn fit(points: &[Point3]) -> Self {
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| a.almost(*b) );
+    let n = points.len();
+    if n < 2 { panic!() }
+    let total_length: f64 = points.windows(2).map(|pair| pair[0].distance(pair[1]) ).sum();
+    let mut params = vec![0.0];
+    for pair in points.windows(2) {
+      params.push(params.last().unwrap() + pair[0].distance(pair[1]) / total_length);
+    }
+    params[n - 1] = 1.0;
+    Self::fit_with_params(&points, &params)
+  }
+
+  // Cubic spline passing through the given points at the given parameters
+  // Splines fitted to different points with the same parameters share their knots
+  pub fn fit_with_params(points: &[Point3], params: &[f64]) -> Self {
+    let n = points.len();
+    if n < 2 || params.len() != n { panic!() }
+    let degree = (n - 1).min(3);
+    let inner_knots: Vec<f64> = (1..n - degree).map(|j| {
+      params[j..j + degree].iter().sum::<f64>() / degree as f64
+    }).collect();
+    let knots = [vec![0.0; degree + 1], inner_knots, vec![1.0; degree + 1]].concat();
+    let matrix = params.iter().map(|&t| Self::basis_functions(degree, &knots, t) ).collect();
+    Self {
+      id: Uuid::new_v4(),
+      degree,
+      controls: solve_linear(matrix, points.to_vec()),
+      knots,
+      weights: vec![1.0; n],
+    }
+  }
+
+  // Values of all basis functions at parameter t, using the Cox-de Boor recursion
+  fn basis_functions(degree: usize, knots: &[f64], t: f64) -> Vec<f64> {
+    let last = knots.len() - 1;
+    // The end of the knot vector belongs to the last non-empty span
+    let span = if t >= knots[last] {
+      (0..last).rev().find(|&i| knots[i] < knots[i + 1] ).unwrap()
+    } else {
+      (0..last).find(|&i| knots[i] <= t && t < knots[i + 1] ).unwrap()
+    };
+    let mut basis = vec![0.0; last];
+    basis[span] = 1.0;
+    for k in 1..=degree {
+      for i in 0..last - k {
+        let left = knots[i + k] - knots[i];
+        let right = knots[i + k + 1] - knots[i + 1];
+        let a = if left > 0.0 { (t - knots[i]) / left * basis[i] } else { 0.0 };
+        let b = if right > 0.0 { (knots[i + k + 1] - t) / right * basis[i + 1] } else { 0.0 };
+        basis[i] = a + b;
+      }
+    }
+    basis.truncate(last - degree);
+    basis
+  }
+
   #[allow(dead_code)]
   fn uniform_knots(n: usize, degree: usize) -> Vec<f64> {
     if degree >= n {return vec![]}
@@ -773,52 +866,224 @@ impl BasisSpline {
     ].concat()
   }
 
-  fn real_split(&self, t: f64, controls: &[Point3], left: &mut Vec<Point3>, right: &mut Vec<Point3>) -> Point3 {
-    if controls.len() == 1 {
-      let p = controls[0];
-      left.push(p);
-      right.push(p);
-      p
-    } else {
-      let len = controls.len() - 1;
-      let mut new_controls: Vec<Point3> = vec![];
-      for i in 0..len {
-        if i == 0 { left.push(controls[i]) }
-        if i == len - 1 { right.push(controls[i + 1]) }
-        new_controls.push(controls[i] * (1.0 - t) + (controls[i + 1] * t).to_vec());
-      }
-      self.real_split(t, &new_controls, left, right)
+  // Range of the knot vector that is covered by the curve
+  fn knot_range(&self) -> (f64, f64) {
+    (self.knots[self.degree], self.knots[self.controls.len()])
+  }
+
+  pub fn is_rational(&self) -> bool {
+    self.weights.iter().any(|w| *w != 1.0 )
+  }
+
+  // Control points premultiplied by their weights
+  fn homogeneous(&self) -> Vec<Vec4> {
+    self.controls.iter().enumerate().map(|(i, p)| {
+      let w = self.weights.get(i).cloned().unwrap_or(1.0);
+      (p.to_vec() * w).extend(w)
+    }).collect()
+  }
+
+  // Exact rational arc, made from quadratic segments spanning a quarter turn at most
+  fn arc(plane: &Plane, radius: f64, start: f64, end: f64) -> Self {
+    let turn = std::f64::consts::PI * 2.0;
+    let num_segments = ((end - start).abs() * 4.0).ceil().max(1.0) as usize;
+    let half_angle = (end - start) * turn / num_segments as f64 / 2.0;
+    let point_at = |angle: f64, distance: f64| plane.sample(angle.sin() * distance, angle.cos() * distance);
+    let mut controls = vec![point_at(start * turn, radius)];
+    let mut weights = vec![1.0];
+    let mut knots = vec![0.0; 3];
+    for i in 0..num_segments {
+      let angle = start * turn + half_angle * (2 * i + 1) as f64;
+      controls.push(point_at(angle, radius / half_angle.cos()));
+      controls.push(point_at(angle + half_angle, radius));
+      weights.push(half_angle.cos());
+      weights.push(1.0);
+      let knot = (i + 1) as f64 / num_segments as f64;
+      knots.push(knot);
+      knots.push(knot);
+    }
+    knots.push(1.0);
+    Self {
+      id: Uuid::new_v4(),
+      degree: 2,
+      controls,
+      knots,
+      weights,
     }
   }
 
+  // Insert a single knot using Boehm's algorithm, operating on homogeneous control points
+  fn insert_knot(&mut self, u: f64) {
+    let p = self.degree;
+    let n = self.controls.len();
+    let span = (p..n).rev().find(|&i| self.knots[i] <= u ).unwrap_or(p);
+    let homogeneous = self.homogeneous();
+    let inserted: Vec<Vec4> = (0..=n).map(|i| {
+      if i + p <= span {
+        homogeneous[i]
+      } else if i > span {
+        homogeneous[i - 1]
+      } else {
+        let alpha = (u - self.knots[i]) / (self.knots[i + p] - self.knots[i]);
+        homogeneous[i - 1] * (1.0 - alpha) + homogeneous[i] * alpha
+      }
+    }).collect();
+    self.controls = inserted.iter().map(|h| Point3::from_vec(h.truncate() / h.w) ).collect();
+    self.weights = inserted.iter().map(|h| h.w ).collect();
+    self.knots.insert(span + 1, u);
+  }
+
+  // Parameters of the knots inside the given range, including its bounds
+  fn span_params(&self, start: f64, end: f64) -> Vec<f64> {
+    let (low, high) = self.knot_range();
+    let mut params = vec![start];
+    params.extend(self.knots.iter()
+      .map(|k| (k - low) / (high - low) )
+      .filter(|&t| t > start && t < end ));
+    params.push(end);
+    params.dedup();
+    params
+  }
+
   // https://stackoverflow.com/questions/25453159/getting-consistent-normals-from-a-3d-cubic-bezier-path
   pub fn normal(&self, t: f64) -> Vec3 {
-    let derivative = self.derive();
-    let tan = derivative.sample(t).to_vec().normalize();
-    let tan2 = (tan + derivative.derive().sample(t).to_vec()).normalize();
+    let (_, d1, d2) = Derivatives::new(self).at(t);
+    let tan = d1.normalize();
+    let tan2 = (tan + d2).normalize();
     let c = tan2.cross(tan);
     c.cross(tan).normalize()
   }
 
+  // Derivative with respect to the normalized curve parameter
+  // Weights are ignored, use Derivatives for rational splines
   pub fn derive(&self) -> Self {
-    let len = self.controls.len() - 1;
-    let controls = (0..len).map(|i| (self.controls[i + 1] - self.controls[i].to_vec()) * len as f64 ).collect();
-    Self::new(controls)
-  }
-
-  fn unsample_recursive(&self, sample1: (f64, f64), sample2: (f64, f64), target: &Point3) -> f64 {
-    if sample1.0.almost(sample2.0) { return sample1.0 }
-    let t_center = (sample1.0 + sample2.0) / 2.0;
-    let p_center = self.sample(t_center);
-    let dist_center = p_center.distance2(*target);
-    if p_center.almost(*target) { return t_center }
-    let sample_center = (t_center, dist_center);
-    if sample1.1 < sample2.1 {
-      self.unsample_recursive(sample1, sample_center, target)
-    } else {
-      self.unsample_recursive(sample_center, sample2, target)
+    let p = self.degree;
+    let n = self.controls.len();
+    let (low, high) = self.knot_range();
+    if p == 0 {
+      return Self {
+        id: self.id,
+        degree: 0,
+        controls: vec![Point3::origin(); n],
+        knots: self.knots.clone(),
+        weights: vec![1.0; n],
+      }
+    }
+    let controls = (0..n - 1).map(|i| {
+      let span = self.knots[i + p + 1] - self.knots[i + 1];
+      if span > 0.0 {
+        Point3::from_vec((self.controls[i + 1] - self.controls[i]) * (p as f64 / span * (high - low)))
+      } else {
+        Point3::origin()
+      }
+    }).collect();
+    Self {
+      id: self.id,
+      degree: p - 1,
+      controls,
+      knots: self.knots[1..self.knots.len() - 1].to_vec(),
+      weights: vec![1.0; n - 1],
+    }
+  }
+}
+
+
+// Derivatives of a rational spline, computed from the polynomial splines
+// of its homogeneous numerator and its weight function
+struct Derivatives {
+  numerator: [BasisSpline; 3],
+  denominator: [BasisSpline; 3],
+}
+
+impl Derivatives {
+  fn new(spline: &BasisSpline) -> Self {
+    let homogeneous = spline.homogeneous();
+    let polynomial = |controls: Vec<Point3>| {
+      let first = BasisSpline {
+        id: spline.id,
+        degree: spline.degree,
+        weights: vec![1.0; controls.len()],
+        controls,
+        knots: spline.knots.clone(),
+      };
+      let second = first.derive();
+      let third = second.derive();
+      [first, second, third]
+    };
+    Self {
+      numerator: polynomial(homogeneous.iter().map(|h| Point3::from_vec(h.truncate()) ).collect()),
+      denominator: polynomial(homogeneous.iter().map(|h| Point3::new(h.w, 0.0, 0.0) ).collect()),
+    }
+  }
+
+  // Point along with its first and second derivative
+  fn at(&self, t: f64) -> (Point3, Vec3, Vec3) {
+    let [a0, a1, a2] = &self.numerator;
+    let [w0, w1, w2] = &self.denominator;
+    let (a0, a1, a2) = (a0.sample(t).to_vec(), a1.sample(t).to_vec(), a2.sample(t).to_vec());
+    let (w0, w1, w2) = (w0.sample(t).x, w1.sample(t).x, w2.sample(t).x);
+    let c = a0 / w0;
+    let d1 = (a1 - c * w1) / w0;
+    let d2 = (a2 - d1 * (2.0 * w1) - c * w2) / w0;
+    (Point3::from_vec(c), d1, d2)
+  }
+}
+
+
+// Adaptive five point Gauss-Legendre quadrature
+fn integrate(f: &impl Fn(f64) -> f64, start: f64, end: f64, depth: usize) -> f64 {
+  let whole = gauss_legendre(f, start, end);
+  let center = (start + end) / 2.0;
+  let halves = gauss_legendre(f, start, center) + gauss_legendre(f, center, end);
+  if depth >= 16 || (whole - halves).abs() <= EPSILON * halves.abs().max(1.0) {
+    halves
+  } else {
+    integrate(f, start, center, depth + 1) + integrate(f, center, end, depth + 1)
+  }
+}
+
+// Nodes and weights of five point Gauss-Legendre quadrature on [-1, 1]
+pub(crate) const GAUSS_LEGENDRE: [(f64, f64); 5] = [
+  (0.0, 0.5688888888888889),
+  (-0.5384693101056831, 0.4786286704993665),
+  (0.5384693101056831, 0.4786286704993665),
+  (-0.906179845938664, 0.2369268850561891),
+  (0.906179845938664, 0.2369268850561891),
+];
+
+fn gauss_legendre(f: &impl Fn(f64) -> f64, start: f64, end: f64) -> f64 {
+  let half = (end - start) / 2.0;
+  let center = (start + end) / 2.0;
+  GAUSS_LEGENDRE.iter().map(|(x, w)| w * f(center + half * x) ).sum::<f64>() * half
+}
+
+
+// Solve a square linear system with points as right hand side by Gaussian elimination
+fn solve_linear(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<Point3>) -> Vec<Point3> {
+  let n = rhs.len();
+  for col in 0..n {
+    let pivot = (col..n).max_by(|&a, &b| matrix[a][col].abs().partial_cmp(&matrix[b][col].abs()).unwrap() ).unwrap();
+    matrix.swap(col, pivot);
+    rhs.swap(col, pivot);
+    for row in col + 1..n {
+      let factor = matrix[row][col] / matrix[col][col];
+      if factor == 0.0 { continue }
+      let pivot_row = matrix[col].clone();
+      for (value, pivot_value) in matrix[row].iter_mut().zip(pivot_row).skip(col) {
+        *value -= pivot_value * factor;
+      }
+      rhs[row] = rhs[row] - rhs[col].to_vec() * factor;
     }
   }
+  let mut result = vec![Point3::origin(); n];
+  for row in (0..n).rev() {
+    let mut sum = rhs[row].to_vec();
+    for (p, factor) in result.iter().zip(&matrix[row]).skip(row + 1) {
+      sum -= p.to_vec() * *factor;
+    }
+    result[row] = Point3::from_vec(sum / matrix[row][row]);
+  }
+  result
 }
 
 
@@ -832,7 +1097,7 @@ impl Curve for BasisSpline {
     // Find knot interval that contains t
     let span = (self.degree..n).find(|&i| t <= self.knots[i + 1] ).unwrap();
     // Premultiply weights
-    let mut homogeneous: Vec<Vec4> = (0..n).map(|i| (self.controls[i].to_vec() * self.weights[i]).extend(self.weights[i]) ).collect();
+    let mut homogeneous = self.homogeneous();
     // de Boor's algorithm
     for l in 1..=self.degree + 1 {
       for i in (span - self.degree + l..=span).rev() {
@@ -843,34 +1108,69 @@ impl Curve for BasisSpline {
     Point3::from_vec(homogeneous[span].truncate() / homogeneous[span].w)
   }
 
-  //XXX Exact solution -> Page 219 https://scholarsarchive.byu.edu/cgi/viewcontent.cgi?article=1000&context=facpub
+  // Newton iteration on the squared distance, seeded with the closest of a set of samples
   fn unsample(&self, point: &Point3) -> f64 {
-    self.unsample_recursive(
-      (0.0, self.sample(0.0).distance2(*point)),
-      (1.0, self.sample(1.0).distance2(*point)),
-      point,
-    )
+    let num_samples = (self.controls.len() * 8).max(16);
+    let mut t = (0..=num_samples)
+      .map(|i| i as f64 / num_samples as f64 )
+      .min_by(|&a, &b| self.sample(a).distance2(*point).partial_cmp(&self.sample(b).distance2(*point)).unwrap() )
+      .unwrap();
+    let derivatives = Derivatives::new(self);
+    let max_step = 1.0 / num_samples as f64;
+    for _ in 0..32 {
+      let (p, d1, d2) = derivatives.at(t);
+      let offset = p - point;
+      if offset.magnitude2() <= EPSILON * EPSILON { break }
+      let slope = d2.dot(offset) + d1.magnitude2();
+      if slope.abs() <= EPSILON { break }
+      let step = (-d1.dot(offset) / slope).clamp(-max_step, max_step);
+      let next = (t + step).clamp(0.0, 1.0);
+      if (next - t).abs() <= EPSILON { break }
+      t = next;
+    }
+    t
   }
 
   fn tangent_at(&self, t: f64) -> Vec3 {
-    self.derive().sample(t).to_vec().normalize()
+    let (_, d1, _) = Derivatives::new(self).at(t);
+    d1.normalize()
   }
 
-  fn curvature_at(&self, _t: f64) -> f64 {
-    todo!()
+  fn curvature_at(&self, t: f64) -> f64 {
+    let (_, d1, d2) = Derivatives::new(self).at(t);
+    d1.cross(d2).magnitude() / d1.magnitude().powi(3)
   }
 
   fn tesselate(&self) -> Vec<Point3> {
     self.tesselate_adaptive(0.025, Deg(20.0), (0.0, 1.0))
   }
 
-  fn length_between(&self, start: f64, end: f64) -> f64 { //XXX Replace with proper solution
-    let mut last_p = self.sample(start);
-    self.tesselate_adaptive(0.025, Deg(20.0), (start, end)).iter().fold(0.0, |acc, p| {
-      let dist = last_p.distance(*p);
-      last_p = *p;
-      acc + dist
-    })
+  fn length_between(&self, start: f64, end: f64) -> f64 {
+    let (start, end) = if start <= end { (start, end) } else { (end, start) };
+    let derivatives = Derivatives::new(self);
+    let speed = |t| derivatives.at(t).1.magnitude();
+    // Integrate knot spans separately, as speed is only smooth inside of them
+    self.span_params(start, end).windows(2).map(|pair| integrate(&speed, pair[0], pair[1], 0) ).sum()
+  }
+
+  // Invert arc length with Newton's method, falling back to bisection
+  fn param_at_length(&self, length: f64) -> f64 {
+    let total = self.length();
+    if length <= 0.0 { return 0.0 }
+    if length >= total { return 1.0 }
+    let derivatives = Derivatives::new(self);
+    let mut t = length / total;
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..64 {
+      let error = self.length_between(0.0, t) - length;
+      if error.abs() <= EPSILON * total.max(1.0) { break }
+      if error > 0.0 { high = t } else { low = t }
+      let speed = derivatives.at(t).1.magnitude();
+      let next = t - error / speed;
+      t = if speed > 0.0 && next > low && next < high { next } else { (low + high) / 2.0 };
+      if high - low <= EPSILON { break }
+    }
+    t
   }
 
   fn endpoints(&self) -> (Point3, Point3) {
@@ -881,10 +1181,33 @@ impl Curve for BasisSpline {
 impl Splittable for BasisSpline {
   fn split_at(&self, t: f64) -> Option<(Self, Self)> {
     if t.almost(0.0) || t.almost(1.0) { return None }
-    let mut left = vec![];
-    let mut right = vec![];
-    self.real_split(t, &self.controls, &mut left, &mut right);
-    Some((Self::new(left), Self::new(right)))
+    let (low, high) = self.knot_range();
+    let u = low + t * (high - low);
+    let p = self.degree;
+    // Raise multiplicity of the split knot to the degree, which makes the curve pass through a control point
+    let mut spline = self.clone();
+    spline.weights = spline.homogeneous().iter().map(|h| h.w ).collect();
+    let copies = p.max(1);
+    let multiplicity = spline.knots.iter().filter(|&&k| k == u ).count();
+    for _ in multiplicity..copies {
+      spline.insert_knot(u);
+    }
+    let first = spline.knots.iter().position(|&k| k == u ).unwrap();
+    let left = Self {
+      id: Uuid::new_v4(),
+      degree: p,
+      controls: spline.controls[..first].to_vec(),
+      knots: [&spline.knots[..first], &vec![u; p + 1][..]].concat(),
+      weights: spline.weights[..first].to_vec(),
+    };
+    let right = Self {
+      id: Uuid::new_v4(),
+      degree: p,
+      controls: spline.controls[first - 1..].to_vec(),
+      knots: [&vec![u; p + 1][..], &spline.knots[first + copies..]].concat(),
+      weights: spline.weights[first - 1..].to_vec(),
+    };
+    Some((left, right))
   }
 
   fn into_enum(self) -> CurveType {
@@ -893,10 +1216,23 @@ impl Splittable for BasisSpline {
 }
 
 impl Transformable for BasisSpline {
+  // Projective transforms are applied to homogeneous points and end up in the weights
   fn transform(&mut self, transform: &Matrix4) {
-    for p in  &mut self.controls {
-      *p = transform.transform_point(*p);
-    }
+    let homogeneous: Vec<Vec4> = self.controls.iter().map(|p| transform * p.to_homogeneous() ).collect();
+    self.weights = homogeneous.iter().zip(self.homogeneous()).map(|(h, original)| h.w * original.w ).collect();
+    self.controls = homogeneous.iter().map(|h| Point3::from_homogeneous(*h) ).collect();
+  }
+}
+
+impl From<&Arc> for BasisSpline {
+  fn from(arc: &Arc) -> Self {
+    Self::arc(&arc.plane, arc.radius, arc.bounds.0, arc.bounds.1)
+  }
+}
+
+impl From<&Circle> for BasisSpline {
+  fn from(circle: &Circle) -> Self {
+    Self::arc(&circle.plane, circle.radius, 0.0, 1.0)
   }
 }
 
@@ -1013,6 +1349,28 @@ mod tests {
     almost_eq(circle.unsample(&Point3::new(0.0, 1.0, 0.0)),                                 0.000);
   }
 
+  #[test]
+  fn split_crossing_circles() {
+    let circle = Circle::new(Point3::origin(), 1.0);
+    let other = Circle::new(Point3::new(1.0, 0.0, 0.0), 1.0).into_enum();
+    let arcs = circle.split_with(&other).unwrap();
+    assert_eq!(arcs.len(), 2);
+    let lengths: Vec<f64> = arcs.iter().map(|arc| arc.as_curve().length() ).collect();
+    almost_eq(lengths[0] + lengths[1], circle.circumfence());
+    almost_eq(arcs[0].as_curve().endpoints().1, arcs[1].as_curve().endpoints().0);
+    almost_eq(arcs[1].as_curve().endpoints().1, arcs[0].as_curve().endpoints().0);
+  }
+
+  #[test]
+  fn split_arc_with_line() {
+    let arc = Arc::new(Point3::origin(), 1.0, 0.0, 0.5);
+    let line = Line::new(Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 0.0, 0.0)).into_enum();
+    let segments = arc.split_with(&line).unwrap();
+    assert_eq!(segments.len(), 2);
+    almost_eq(segments[0].as_curve().endpoints().1, Point3::new(1.0, 0.0, 0.0));
+    almost_eq(segments[1].as_curve().endpoints().1, arc.sample(1.0));
+  }
+
   #[test]
   fn unsample_spline() {
     let spline = test_data::s_curve();
@@ -1021,6 +1379,122 @@ mod tests {
     assert_eq!(0.5, spline.unsample(&p));
   }
 
+  #[test]
+  fn fit_spline() {
+    let points: Vec<Point3> = (0..=8).map(|i| {
+      let t = i as f64 / 8.0 * std::f64::consts::PI;
+      Point3::new(t.cos(), t.sin(), 0.0)
+    }).collect();
+    let spline = BasisSpline::fit(&points);
+    assert_eq!(spline.degree, 3);
+    almost_eq(spline.sample(0.0), points[0]);
+    almost_eq(spline.sample(1.0), points[8]);
+    // Fitted spline stays close to the circle in between samples
+    for i in 0..=32 {
+      let radius = spline.sample(i as f64 / 32.0).to_vec().magnitude();
+      assert!((radius - 1.0).abs() < 1.0e-3);
+    }
+  }
+
+  #[test]
+  fn unsample_spline_offcurve() {
+    let spline = test_data::s_curve();
+    let p = spline.sample(0.3) + Vec3::new(0.0, 0.0, 0.5);
+    let t = spline.unsample(&p);
+    // Projection is perpendicular to the curve
+    assert!((spline.sample(t) - p).dot(spline.tangent_at(t)).abs() < 1.0e-7);
+  }
+
+  #[test]
+  fn spline_length() {
+    // Straight spline with unevenly spaced controls
+    let spline = BasisSpline::new(vec![
+      Point3::new(0.0, 0.0, 0.0),
+      Point3::new(0.2, 0.0, 0.0),
+      Point3::new(0.4, 0.0, 0.0),
+      Point3::new(3.0, 0.0, 0.0),
+    ]);
+    almost_eq(spline.length(), 3.0);
+    almost_eq(spline.midpoint(), Point3::new(1.5, 0.0, 0.0));
+    almost_eq(spline.length_between(0.6, 0.2), spline.length_between(0.2, 0.6));
+  }
+
+  #[test]
+  fn split_spline() {
+    let points: Vec<Point3> = (0..=6).map(|i| Point3::new(i as f64, (i as f64).sin(), 0.0) ).collect();
+    let spline = BasisSpline::fit(&points);
+    let (left, right) = spline.split_at(0.3).unwrap();
+    almost_eq(left.sample(1.0), spline.sample(0.3));
+    almost_eq(right.sample(0.0), spline.sample(0.3));
+    almost_eq(left.sample(0.5), spline.sample(0.15));
+    almost_eq(right.sample(0.5), spline.sample(0.65));
+    almost_eq(right.sample(1.0), points[6]);
+  }
+
+  #[test]
+  fn circle_to_spline() {
+    let circle = Circle::new(Point3::new(1.0, 2.0, 0.0), 3.0);
+    let spline = BasisSpline::from(&circle);
+    assert!(spline.is_rational());
+    for i in 0..=16 {
+      let p = spline.sample(i as f64 / 16.0);
+      almost_eq(p.distance(Point3::new(1.0, 2.0, 0.0)), 3.0);
+      almost_eq(p.z, 0.0);
+    }
+    almost_eq(spline.length(), circle.circumfence());
+    almost_eq(spline.curvature_at(0.3), 1.0 / 3.0);
+  }
+
+  #[test]
+  fn arc_to_spline() {
+    let arc = Arc::new(Point3::origin(), 2.0, 0.1, 0.45);
+    let spline = BasisSpline::from(&arc);
+    almost_eq(spline.sample(0.0), arc.sample(0.0));
+    almost_eq(spline.sample(1.0), arc.sample(1.0));
+    almost_eq(spline.length(), arc.length());
+    almost_eq(spline.midpoint(), arc.midpoint());
+    let p = arc.sample(0.3);
+    almost_eq(spline.sample(spline.unsample(&p)), p);
+    almost_eq(spline.tangent_at(spline.unsample(&p)), arc.tangent_at(0.3));
+  }
+
+  #[test]
+  fn split_rational_spline() {
+    let spline = BasisSpline::from(&Circle::new(Point3::origin(), 1.0));
+    let (left, right) = spline.split_at(0.4).unwrap();
+    almost_eq(left.sample(1.0), spline.sample(0.4));
+    almost_eq(right.sample(0.0), spline.sample(0.4));
+    for i in 0..=8 {
+      let t = i as f64 / 8.0;
+      almost_eq(left.sample(t).to_vec().magnitude(), 1.0);
+      almost_eq(right.sample(t).to_vec().magnitude(), 1.0);
+    }
+    almost_eq(left.length() + right.length(), spline.length());
+  }
+
+  #[test]
+  fn transform_rational_spline() {
+    let mut spline = BasisSpline::from(&Circle::new(Point3::origin(), 1.0));
+    spline.transform(&Matrix4::from_translation(Vec3::new(0.0, 0.0, 2.0)));
+    for i in 0..=8 {
+      let p = spline.sample(i as f64 / 8.0);
+      almost_eq(p.z, 2.0);
+      almost_eq(p.to_vec().truncate().magnitude(), 1.0);
+    }
+  }
+
+  #[test]
+  fn serialize_spline() {
+    let spline = BasisSpline::from(&Arc::new(Point3::origin(), 1.0, 0.0, 0.3));
+    let dump = ron::to_string(&spline).unwrap();
+    assert_eq!(ron::from_str::<BasisSpline>(&dump).unwrap(), spline);
+    // Splines stored without weights are non-rational
+    let mut legacy = test_data::s_curve();
+    legacy.weights = vec![];
+    let legacy: BasisSpline = ron::from_str(&ron::to_string(&legacy).unwrap().replace(",weights:[]", "")).unwrap();
+    almost_eq(legacy.sample(0.3), test_data::s_curve().sample(0.3));
+  }
+
   #[test]
   fn flip_trimmed_curve() {
     let line = Line::new(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)).into_enum();
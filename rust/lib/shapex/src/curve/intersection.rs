@@ -1,5 +1,6 @@
 use crate::curve::*;
 use crate::geom2d::cross_2d;
+use crate::surface::intersection::{plane_plane, SurfaceIntersectionType};
 
 
 #[derive(Debug, PartialEq)]
@@ -148,59 +149,264 @@ pub fn line_line(own: &Line, other: &Line) -> Option<CurveIntersectionType> {
 }
 
 
-pub fn line_spline(line: &Line, spline: &Spline) -> Vec<CurveIntersectionType> {
-  let spline_end_points = spline.endpoints();
-  let mut intersections = vec![];
-  // Curves touch at endpoints
-  if line.points.0.almost(spline_end_points.0) {
-    intersections.push(CurveIntersectionType::Touch(CurveIntersection::new(line.points.0, 0.0, 0.0)))
-
-  } else if line.points.0.almost(spline_end_points.1) {
-    intersections.push(CurveIntersectionType::Touch(CurveIntersection::new(line.points.0, 0.0, 1.0)))
+pub fn line_circle(line: &Line, circle: &Circle) -> Vec<CurveIntersectionType> {
+  line_circle_points(line, &circle.plane, circle.radius).into_iter().map(|(p, t)| {
+    classify(p, t, circle.unsample(&p), false, true)
+  }).collect()
+}
 
-  } else if line.points.1.almost(spline_end_points.0) {
-    intersections.push(CurveIntersectionType::Touch(CurveIntersection::new(line.points.1, 1.0, 0.0)))
 
-  } else if line.points.1.almost(spline_end_points.1) {
-    intersections.push(CurveIntersectionType::Touch(CurveIntersection::new(line.points.1, 1.0, 1.0)))
-  }
-  intersections
+pub fn line_arc(line: &Line, arc: &Arc) -> Vec<CurveIntersectionType> {
+  line_circle_points(line, &arc.plane, arc.radius).into_iter().map(|(p, t)| {
+    classify(p, t, arc.unsample(&p), false, arc.is_closed())
+  }).collect()
 }
 
 
-pub fn line_circle(line: &Line, circle: &Circle) -> Vec<CurveIntersectionType> {
+pub(crate) fn line_circle_points(line: &Line, plane: &Plane, radius: f64) -> Vec<(Point3, f64)> {
   let direction = line.points.1 - line.points.0;
-  let f = line.points.0 - circle.plane.origin;
+  let f = line.points.0 - plane.origin;
   let a = direction.dot(direction);
   let b = f.dot(direction) * 2.0;
-  let c = f.dot(f) - (circle.radius.powf(2.0));
-  let mut intersections = vec![];
+  let c = f.dot(f) - (radius.powf(2.0));
   let discriminant = b * b - 4.0 * a * c;
-  if discriminant >= 0.0 { // No intersection for negative discriminant
-    let discriminant = discriminant.sqrt();
+  if discriminant < 0.0 { return vec![] } // No intersection for negative discriminant
+  let discriminant = discriminant.sqrt();
+  let t1 = (-b - discriminant) / (2.0 * a);
+  let t2 = (-b + discriminant) / (2.0 * a);
+  // Tangent lines touch the circle only once
+  let params = if t1.almost(t2) { vec![t1] } else { vec![t1, t2] };
+  params.into_iter().map(|t| (line.sample(t), t) ).collect()
+}
 
-    let t1 = (-b - discriminant) / (2.0 * a);
-    let t2 = (-b + discriminant) / (2.0 * a);
 
-    let crossed = (t1 >= 0.0 && t1 <= 1.0, t2 >= 0.0 && t2 <= 1.0);
+pub fn circle_circle(own: &Circle, other: &Circle) -> Vec<CurveIntersectionType> {
+  match circle_circle_points(&own.plane, own.radius, &other.plane, other.radius) {
+    Some(points) => points.into_iter().map(|p| {
+      classify(p, own.unsample(&p), other.unsample(&p), true, true)
+    }).collect(),
+    None => vec![CurveIntersectionType::Contained],
+  }
+}
 
-    let p1 = line.sample(t1);
-    let p2 = line.sample(t2);
-    let isect1 = CurveIntersection::new(p1, t1, circle.unsample(&p1));
-    let isect2 = CurveIntersection::new(p2, t2, circle.unsample(&p2));
 
-    intersections.push(if crossed.0 {
-      CurveIntersectionType::Cross(isect1)
-    } else {
-      CurveIntersectionType::Extended(isect1)
-    });
-    intersections.push(if crossed.1 {
-      CurveIntersectionType::Cross(isect2)
-    } else {
-      CurveIntersectionType::Extended(isect2)
-    });
+pub fn arc_circle(arc: &Arc, circle: &Circle) -> Vec<CurveIntersectionType> {
+  match circle_circle_points(&arc.plane, arc.radius, &circle.plane, circle.radius) {
+    Some(points) => points.into_iter().map(|p| {
+      classify(p, arc.unsample(&p), circle.unsample(&p), arc.is_closed(), true)
+    }).collect(),
+    None => vec![CurveIntersectionType::Contained],
+  }
+}
+
+
+pub fn arc_arc(own: &Arc, other: &Arc) -> Vec<CurveIntersectionType> {
+  match circle_circle_points(&own.plane, own.radius, &other.plane, other.radius) {
+    Some(points) => points.into_iter().map(|p| {
+      classify(p, own.unsample(&p), other.unsample(&p), own.is_closed(), other.is_closed())
+    }).collect(),
+    None => {
+      // Arcs on the same circle either overlap or touch at their endpoints
+      let is_inside = |arc: &Arc, p: Point3| {
+        let t = arc.unsample(&p);
+        t > EPSILON && t < 1.0 - EPSILON
+      };
+      if is_inside(own, other.sample(0.0)) || is_inside(own, other.sample(1.0)) ||
+        is_inside(own, other.sample(0.5)) || is_inside(other, own.sample(0.5))
+      {
+        return vec![CurveIntersectionType::Contained]
+      }
+      let mut intersections = vec![];
+      for t1 in [0.0, 1.0] {
+        for t2 in [0.0, 1.0] {
+          let p = own.sample(t1);
+          if p.almost(other.sample(t2)) {
+            intersections.push(CurveIntersectionType::Touch(CurveIntersection::new(p, t1, t2)));
+          }
+        }
+      }
+      intersections
+    },
+  }
+}
+
+
+// Intersection points of two circles
+// Returns None when both circles are identical
+pub(crate) fn circle_circle_points(own: &Plane, own_radius: f64, other: &Plane, other_radius: f64) -> Option<Vec<Point3>> {
+  let normal = own.normal().normalize();
+  // Circles in different planes only meet where the other circle pierces this one's plane
+  if !normal.cross(other.normal().normalize()).magnitude().almost(0.0) || !own.contains_point(other.origin) {
+    return Some(match plane_plane(own, other) {
+      Some(SurfaceIntersectionType::Cross(CurveType::Line(line))) => line_circle_points(&line, other, other_radius).into_iter()
+      .map(|(p, _)| p )
+      .filter(|p| p.distance(own.origin).almost(own_radius) )
+      .collect(),
+      _ => vec![],
+    })
+  }
+  let delta = other.origin - own.origin;
+  let delta = delta - normal * delta.dot(normal);
+  let distance = delta.magnitude();
+  if distance.almost(0.0) {
+    return if own_radius.almost(other_radius) { None } else { Some(vec![]) }
+  }
+  if distance > own_radius + other_radius + EPSILON || distance < (own_radius - other_radius).abs() - EPSILON {
+    return Some(vec![])
+  }
+  let a = (distance.powf(2.0) + own_radius.powf(2.0) - other_radius.powf(2.0)) / (2.0 * distance);
+  let h2 = own_radius.powf(2.0) - a.powf(2.0);
+  let direction = delta / distance;
+  let center = own.origin + direction * a;
+  // Tangent circles touch in a single point
+  if h2.almost(0.0) { return Some(vec![center]) }
+  let offset = normal.cross(direction) * h2.sqrt();
+  Some(vec![center + offset, center - offset])
+}
+
+
+pub fn line_spline(line: &Line, spline: &Spline) -> Vec<CurveIntersectionType> {
+  curve_curve(line, spline)
+}
+
+
+pub fn arc_spline(arc: &Arc, spline: &Spline) -> Vec<CurveIntersectionType> {
+  curve_curve(arc, spline)
+}
+
+
+pub fn circle_spline(circle: &Circle, spline: &Spline) -> Vec<CurveIntersectionType> {
+  curve_curve(circle, spline)
+}
+
+
+pub fn spline_spline(own: &Spline, other: &Spline) -> Vec<CurveIntersectionType> {
+  let mut reversed = other.controls.clone();
+  reversed.reverse();
+  if own.controls == other.controls || own.controls == reversed {
+    return vec![CurveIntersectionType::Contained]
+  }
+  curve_curve(own, other)
+}
+
+
+// Classify an intersection by the position of its parameters on both curves
+// Closed curves have no endpoints and can only be crossed
+fn classify(point: Point3, t1: f64, t2: f64, own_closed: bool, other_closed: bool) -> CurveIntersectionType {
+  let first_at_end = !own_closed && (t1.almost(0.0) || t1.almost(1.0));
+  let second_at_end = !other_closed && (t2.almost(0.0) || t2.almost(1.0));
+  let first_inside = first_at_end || (0.0..=1.0).contains(&t1);
+  let second_inside = second_at_end || (0.0..=1.0).contains(&t2);
+  let mut isect = CurveIntersection::new(point, t1, t2);
+  if !first_inside || !second_inside {
+    isect.direction = second_inside;
+    CurveIntersectionType::Extended(isect)
+  } else if first_at_end && second_at_end {
+    CurveIntersectionType::Touch(isect)
+  } else if first_at_end || second_at_end {
+    isect.direction = first_at_end;
+    CurveIntersectionType::Pierce(isect)
+  } else {
+    CurveIntersectionType::Cross(isect)
+  }
+}
+
+
+// Find intersections of arbitrary curves by recursive subdivision
+// Candidates are refined with Newton's method
+fn curve_curve(own: &dyn Curve, other: &dyn Curve) -> Vec<CurveIntersectionType> {
+  let size = |curve: &dyn Curve| {
+    let bbox = sample_box(curve, (0.0, 1.0));
+    bbox.0.distance(bbox.1)
+  };
+  let tolerance = size(own).max(size(other)) * 1.0e-3;
+  let mut candidates = vec![];
+  subdivide(own, other, (0.0, 1.0), (0.0, 1.0), tolerance, 0, &mut candidates);
+  // Make sure touching endpoints are found
+  for t in [0.0, 1.0] {
+    candidates.push((t, other.unsample(&own.sample(t))));
+    candidates.push((own.unsample(&other.sample(t)), t));
+  }
+  let mut params: Vec<(f64, f64)> = vec![];
+  for (t1, t2) in candidates {
+    if let Some((t1, t2)) = refine(own, other, t1, t2) {
+      if params.iter().all(|other| (other.0 - t1).abs() > 1.0e-7 || (other.1 - t2).abs() > 1.0e-7 ) {
+        params.push((t1, t2));
+      }
+    }
+  }
+  params.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap() );
+  params.into_iter().map(|(t1, t2)| {
+    classify(own.sample(t1), t1, t2, own.is_closed(), other.is_closed())
+  }).collect()
+}
+
+fn subdivide(own: &dyn Curve, other: &dyn Curve, range1: (f64, f64), range2: (f64, f64), tolerance: f64, depth: usize, candidates: &mut Vec<(f64, f64)>) {
+  let box1 = sample_box(own, range1);
+  let box2 = sample_box(other, range2);
+  let overlap = box1.0.x <= box2.1.x && box2.0.x <= box1.1.x &&
+    box1.0.y <= box2.1.y && box2.0.y <= box1.1.y &&
+    box1.0.z <= box2.1.z && box2.0.z <= box1.1.z;
+  if !overlap { return }
+  let size1 = box1.0.distance(box1.1);
+  let size2 = box2.0.distance(box2.1);
+  if depth >= 32 || size1.max(size2) <= tolerance {
+    candidates.push(((range1.0 + range1.1) / 2.0, (range2.0 + range2.1) / 2.0));
+  } else if size1 >= size2 {
+    let center = (range1.0 + range1.1) / 2.0;
+    subdivide(own, other, (range1.0, center), range2, tolerance, depth + 1, candidates);
+    subdivide(own, other, (center, range1.1), range2, tolerance, depth + 1, candidates);
+  } else {
+    let center = (range2.0 + range2.1) / 2.0;
+    subdivide(own, other, range1, (range2.0, center), tolerance, depth + 1, candidates);
+    subdivide(own, other, range1, (center, range2.1), tolerance, depth + 1, candidates);
+  }
+}
+
+// Bounding box of a curve section, padded to account for bulges between samples
+fn sample_box(curve: &dyn Curve, range: (f64, f64)) -> (Point3, Point3) {
+  let samples: Vec<Point3> = (0..=4).map(|i| curve.sample(range.0 + (range.1 - range.0) * i as f64 / 4.0) ).collect();
+  let mut min = samples[0];
+  let mut max = samples[0];
+  for p in &samples {
+    min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+    max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+  }
+  let padding = max.distance(min) * 0.25 + EPSILON * 10.0;
+  let padding = Vec3::new(padding, padding, padding);
+  (min - padding, max + padding)
+}
+
+fn refine(own: &dyn Curve, other: &dyn Curve, mut t1: f64, mut t2: f64) -> Option<(f64, f64)> {
+  t1 = t1.clamp(0.0, 1.0);
+  t2 = t2.clamp(0.0, 1.0);
+  for _ in 0..32 {
+    let f = own.sample(t1) - other.sample(t2);
+    if f.magnitude() <= EPSILON { break }
+    let d1 = derivative(own, t1);
+    let d2 = derivative(other, t2);
+    // Solve the normal equations of [d1 -d2] * [dt1 dt2] = -f
+    let a11 = d1.dot(d1);
+    let a12 = -d1.dot(d2);
+    let a22 = d2.dot(d2);
+    let b1 = -d1.dot(f);
+    let b2 = d2.dot(f);
+    let determinant = a11 * a22 - a12 * a12;
+    if determinant.abs() <= EPSILON * a11 * a22 { break }
+    t1 = (t1 + (b1 * a22 - a12 * b2) / determinant).clamp(0.0, 1.0);
+    t2 = (t2 + (a11 * b2 - a12 * b1) / determinant).clamp(0.0, 1.0);
+  }
+  if own.sample(t1).distance(other.sample(t2)) <= EPSILON * 10.0 {
+    Some((t1, t2))
+  } else {
+    None
   }
-  intersections
+}
+
+fn derivative(curve: &dyn Curve, t: f64) -> Vec3 {
+  let t0 = (t - 1.0e-6).max(0.0);
+  let t1 = (t + 1.0e-6).min(1.0);
+  (curve.sample(t1) - curve.sample(t0)) / (t1 - t0)
 }
 
 
@@ -208,6 +414,7 @@ pub fn line_circle(line: &Line, circle: &Circle) -> Vec<CurveIntersectionType> {
 mod tests {
   use super::*;
   use crate::test_data;
+  use crate::internal::almost_eq;
 
   #[test]
   fn crossing_lines() {
@@ -259,4 +466,104 @@ mod tests {
     let hit = line_circle(&line, &circle);
     assert_eq!(hit, vec![]);
   }
+
+  fn points(hits: &Vec<CurveIntersectionType>) -> Vec<Point3> {
+    hits.iter().filter_map(|hit| hit.get_point(false) ).collect()
+  }
+
+  #[test]
+  fn line_arc_cross() {
+    let arc = Arc::new(Point3::origin(), 1.0, 0.0, 0.5);
+    let line = Line::new(Point3::new(-2.0, 0.0, 0.0), Point3::new(2.0, 0.0, 0.0));
+    let hits = line_arc(&line, &arc);
+    assert_eq!(hits.len(), 2);
+    // Only the right half of the circle is covered by the arc
+    assert!(matches!(hits[0], CurveIntersectionType::Extended(_)));
+    assert!(matches!(hits[1], CurveIntersectionType::Cross(_)));
+    almost_eq(points(&hits)[0], Point3::new(1.0, 0.0, 0.0));
+  }
+
+  #[test]
+  fn crossing_circles() {
+    let circle = Circle::new(Point3::origin(), 1.0);
+    let other = Circle::new(Point3::new(1.0, 0.0, 0.0), 1.0);
+    let hits = circle_circle(&circle, &other);
+    assert_eq!(hits.len(), 2);
+    for hit in &hits {
+      let isect = hit.get_intersection(false).unwrap();
+      almost_eq(isect.point.x, 0.5);
+      almost_eq(circle.sample(isect.t1), isect.point);
+      almost_eq(other.sample(isect.t2), isect.point);
+    }
+  }
+
+  #[test]
+  fn coincident_circles() {
+    let circle = Circle::new(Point3::origin(), 1.0);
+    assert_eq!(circle_circle(&circle, &circle.clone()), vec![CurveIntersectionType::Contained]);
+    assert_eq!(circle_circle(&circle, &Circle::new(Point3::origin(), 2.0)), vec![]);
+  }
+
+  #[test]
+  fn skew_circles() {
+    let circle = Circle::new(Point3::origin(), 1.0);
+    let upright = Circle::from_plane(Plane::from_normal(Point3::origin(), Vec3::unit_y()), 1.0);
+    let hits = circle_circle(&circle, &upright);
+    assert_eq!(hits.len(), 2);
+    for hit in &hits {
+      let isect = hit.get_intersection(false).unwrap();
+      almost_eq(isect.point.x.abs(), 1.0);
+      almost_eq(circle.sample(isect.t1), isect.point);
+      almost_eq(upright.sample(isect.t2), isect.point);
+    }
+    // Parallel circles stay apart
+    assert_eq!(circle_circle(&circle, &Circle::new(Point3::new(0.0, 0.0, 1.0), 1.0)), vec![]);
+  }
+
+  #[test]
+  fn touching_arcs() {
+    let arc = Arc::new(Point3::origin(), 1.0, 0.0, 0.25);
+    let other = Arc::new(Point3::origin(), 1.0, 0.25, 0.5);
+    let hits = arc_arc(&arc, &other);
+    assert_eq!(hits.len(), 1);
+    assert!(matches!(hits[0], CurveIntersectionType::Touch(_)));
+    let overlapping = Arc::new(Point3::origin(), 1.0, 0.125, 0.5);
+    assert_eq!(arc_arc(&arc, &overlapping), vec![CurveIntersectionType::Contained]);
+  }
+
+  #[test]
+  fn line_spline_cross() {
+    let spline = test_data::s_curve();
+    let line = Line::new(Point3::new(0.0, -2.0, 0.0), Point3::new(0.0, 2.0, 0.0));
+    let hits = line_spline(&line, &spline);
+    assert_eq!(hits.len(), 1);
+    assert!(matches!(hits[0], CurveIntersectionType::Cross(_)));
+    let isect = hits[0].get_intersection(false).unwrap();
+    almost_eq(isect.point, Point3::origin());
+    almost_eq(isect.t1, 0.5);
+    almost_eq(isect.t2, 0.5);
+  }
+
+  #[test]
+  fn spline_spline_cross() {
+    let spline = test_data::s_curve();
+    let mut other = test_data::s_curve();
+    other.controls.iter_mut().for_each(|p| p.y = -p.y );
+    let hits = spline_spline(&spline, &other);
+    let crossings: Vec<&CurveIntersectionType> = hits.iter().filter(|hit| matches!(hit, CurveIntersectionType::Cross(_)) ).collect();
+    assert_eq!(crossings.len(), 1);
+    almost_eq(crossings[0].get_point(false).unwrap(), Point3::origin());
+    assert_eq!(spline_spline(&spline, &spline.clone()), vec![CurveIntersectionType::Contained]);
+  }
+
+  #[test]
+  fn circle_spline_cross() {
+    let spline = test_data::s_curve();
+    let circle = Circle::new(Point3::origin(), 0.5);
+    let hits = circle_spline(&circle, &spline);
+    assert_eq!(hits.len(), 2);
+    for point in points(&hits) {
+      almost_eq(point.distance(Point3::origin()), 0.5);
+    }
+  }
 }
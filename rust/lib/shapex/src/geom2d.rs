@@ -35,7 +35,7 @@ pub fn polygon_area(closed_loop: &PolyLine) -> f64 {
   signed_polygon_area(&closed_loop).abs()
 }
 
-fn signed_polygon_area(closed_loop: &PolyLine) -> f64 {
+pub fn signed_polygon_area(closed_loop: &PolyLine) -> f64 {
   let mut signed_area = 0.0;
   let len = closed_loop.len();
   for i in 0..len {
@@ -47,14 +47,47 @@ fn signed_polygon_area(closed_loop: &PolyLine) -> f64 {
   signed_area / 2.0
 }
 
+// Even-odd test against a closed polygon
+// Points exactly on the boundary may be reported either way
+pub fn point_in_polygon(p: Point3, closed_loop: &PolyLine) -> bool {
+  let mut inside = false;
+  let len = closed_loop.len();
+  for i in 0..len {
+    let a = closed_loop[i];
+    let b = closed_loop[(i + 1) % len];
+    if (a.y > p.y) != (b.y > p.y) {
+      let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+      if p.x < x { inside = !inside }
+    }
+  }
+  inside
+}
+
+pub fn distance_to_segment(p: Point3, a: Point3, b: Point3) -> f64 {
+  let vec = b - a;
+  let len2 = vec.magnitude2();
+  if len2 == 0.0 { return p.distance(a) }
+  let t = ((p - a).dot(vec) / len2).max(0.0).min(1.0);
+  p.distance(a + vec * t)
+}
+
 pub fn point_in_region(p: Point3, region: &Region) -> bool {
   let ray = TrimmedCurve::new(Line::new(p, p + Vec3::unit_x() * 9999999.0).into_enum());
   let num_hits: usize = parallel!(region).flat_map(|elem| {
     let intersections = ray.intersect(&elem);
     intersections.iter().map(|isect| match isect {
-      CurveIntersectionType::Pierce(_)
-      | CurveIntersectionType::Cross(_)
-        => 1,
+      CurveIntersectionType::Pierce(isect)
+      | CurveIntersectionType::Cross(isect)
+      => if isect.t1 < 0.0 || isect.t2 < -EPSILON || isect.t2 > 1.0 + EPSILON {
+        0
+      } else if isect.t2.almost(0.0) || isect.t2.almost(1.0) {
+        // Vertices on the ray are only counted for elements that continue above it,
+        // so that grazing a vertex counts twice or not at all
+        let t = if isect.t2.almost(0.0) { 0.01 } else { 0.99 };
+        usize::from(elem.sample(t).y > p.y)
+      } else {
+        1
+      },
       _ => 0,
     }).collect::<Vec<usize>>()
   }).sum();
@@ -116,7 +149,9 @@ pub fn poly_from_wirebounds(wire: &Wire) -> PolyLine {
 pub fn wire_from_region(region: &mut Region) {
   let bounds = region[0].bounds;
   let next_bounds = region[1].bounds;
-  let mut point = if bounds.0.almost(next_bounds.0) || bounds.0.almost(next_bounds.1) {
+  // Keep the direction of the first element, if it connects to the next one at both ends
+  let connects_at_end = bounds.1.almost(next_bounds.0) || bounds.1.almost(next_bounds.1);
+  let mut point = if !connects_at_end && (bounds.0.almost(next_bounds.0) || bounds.0.almost(next_bounds.1)) {
     bounds.1
   } else {
     bounds.0
@@ -173,6 +208,20 @@ mod tests {
     assert!(!super::point_in_region(Point3::new(10.0, 0.0, 0.0), &rect));
   }
 
+  #[test]
+  fn point_in_region_through_vertex() {
+    let diamond = test_data::polygon_wire(&[
+      Point3::new(0.0, -1.0, 0.0),
+      Point3::new(1.0, 0.0, 0.0),
+      Point3::new(0.0, 1.0, 0.0),
+      Point3::new(-1.0, 0.0, 0.0),
+    ]);
+    // Ray passes through the vertex at (1, 0)
+    assert!(super::point_in_region(Point3::origin(), &diamond));
+    // Ray grazes the vertex at (0, 1)
+    assert!(!super::point_in_region(Point3::new(-2.0, 1.0, 0.0), &diamond));
+  }
+
   #[test]
   fn angle_clockwise() {
     let angle = test_data::angle_right();
@@ -228,6 +277,19 @@ mod tests {
     assert!(!super::region_in_region(&rect, &inner_rect));
   }
 
+  #[test]
+  fn point_in_polygon() {
+    let square = vec![
+      Point3::new(0.0, 0.0, 0.0),
+      Point3::new(1.0, 0.0, 0.0),
+      Point3::new(1.0, 1.0, 0.0),
+      Point3::new(0.0, 1.0, 0.0),
+    ];
+    assert!(super::point_in_polygon(Point3::new(0.5, 0.5, 0.0), &square));
+    assert!(!super::point_in_polygon(Point3::new(1.5, 0.5, 0.0), &square));
+    assert_eq!(distance_to_segment(Point3::new(0.5, 2.0, 0.0), square[2], square[3]), 1.0);
+  }
+
   #[test]
   fn point_in_triangle() {
     let tri = make_region(make_generic(test_data::triangle()));
@@ -1,3 +1,63 @@
+use crate::internal::*;
+use crate::transform::*;
+use crate::mesh::Mesh;
+
 pub mod step;
 pub mod stl;
 pub mod threemf;
+pub mod obj;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+  pub name: String,
+  pub color: [u8; 4],
+}
+
+
+// Bodies reference materials of the exported file by index
+#[derive(Debug, Clone)]
+pub struct Object {
+  pub name: String,
+  pub mesh: Mesh,
+  pub material: Option<usize>,
+}
+
+
+// Component tree, with each part placed relative to its parent
+#[derive(Debug, Clone)]
+pub struct Part {
+  pub name: String,
+  pub transform: Matrix4,
+  pub objects: Vec<Object>,
+  pub children: Vec<Part>,
+}
+
+impl Part {
+  pub fn new(name: &str, objects: Vec<Object>) -> Self {
+    Self {
+      name: name.to_string(),
+      transform: Matrix4::identity(),
+      objects,
+      children: vec![],
+    }
+  }
+
+  // Merge all objects into a single mesh, placing children with their transforms
+  pub fn flatten(&self) -> Mesh {
+    let mut mesh = Mesh::default();
+    for object in &self.objects {
+      mesh.append(object.mesh.clone());
+    }
+    for child in &self.children {
+      let mut child_mesh = child.flatten();
+      child_mesh.transform(&child.transform);
+      mesh.append(child_mesh);
+    }
+    mesh
+  }
+
+  fn is_empty(&self) -> bool {
+    self.objects.is_empty() && self.children.iter().all(|child| child.is_empty() )
+  }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use crate::internal::*;
+use crate::transform::*;
+use crate::mesh::Mesh;
+use super::{Material, Part};
+
+
+// Objects are placed in world space, as OBJ has no notion of transforms.
+// Materials are only referenced when a material library is given.
+pub fn export(root: &Part, materials: &[Material], mtllib: Option<&str>) -> String {
+  let mut writer = ObjWriter {
+    file: String::from("# Alchemy\n"),
+    materials: if mtllib.is_some() { materials } else { &[] },
+    num_vertices: 0,
+    num_normals: 0,
+  };
+  if let Some(mtllib) = mtllib {
+    writer.file.push_str(&format!("mtllib {}\n", mtllib));
+  }
+  writer.part(root, &root.transform, &mut vec![]);
+  writer.file
+}
+
+pub fn export_mtl(materials: &[Material]) -> String {
+  let mut file = String::from("# Alchemy\n");
+  for material in materials {
+    let [r, g, b, a] = material.color.map(|channel| channel as f64 / 255.0 );
+    file.push_str(&format!("\nnewmtl {}\nKd {:.4} {:.4} {:.4}\nd {:.4}\n", name(&material.name), r, g, b, a));
+  }
+  file
+}
+
+
+struct ObjWriter<'a> {
+  file: String,
+  materials: &'a [Material],
+  num_vertices: usize,
+  num_normals: usize,
+}
+
+impl ObjWriter<'_> {
+  // Groups list the names of all enclosing parts, outermost first
+  fn part(&mut self, part: &Part, transform: &Matrix4, path: &mut Vec<String>) {
+    path.push(name(&part.name));
+    for object in &part.objects {
+      let mut mesh = object.mesh.clone();
+      mesh.transform(transform);
+      self.file.push_str(&format!("o {}\ng {}\n", name(&object.name), path.join(" ")));
+      if let Some(material) = object.material.and_then(|index| self.materials.get(index) ) {
+        self.file.push_str(&format!("usemtl {}\n", name(&material.name)));
+      }
+      self.mesh(&mesh);
+    }
+    for child in &part.children {
+      self.part(child, &(transform * child.transform), path);
+    }
+    path.pop();
+  }
+
+  // Indices are one-based and count from the start of the file
+  fn mesh(&mut self, mesh: &Mesh) {
+    for vertex in &mesh.vertices {
+      self.file.push_str(&format!("v {} {} {}\n", vertex.x, vertex.y, vertex.z));
+    }
+    let has_normals = mesh.normals.len() == mesh.faces.len();
+    if has_normals {
+      for normal in &mesh.normals {
+        self.file.push_str(&format!("vn {} {} {}\n", normal.x, normal.y, normal.z));
+      }
+    }
+    for (i, tri) in mesh.faces.chunks_exact(3).enumerate() {
+      self.file.push('f');
+      for (j, vertex) in tri.iter().enumerate() {
+        if has_normals {
+          self.file.push_str(&format!(" {}//{}", self.num_vertices + vertex + 1, self.num_normals + i * 3 + j + 1));
+        } else {
+          self.file.push_str(&format!(" {}", self.num_vertices + vertex + 1));
+        }
+      }
+      self.file.push('\n');
+    }
+    self.num_vertices += mesh.vertices.len();
+    if has_normals { self.num_normals += mesh.normals.len() }
+  }
+}
+
+// Names must not contain whitespace in OBJ and MTL files
+fn name(text: &str) -> String {
+  text.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+
+// Mesh of a single group, which uses a subset of the shared vertices
+#[derive(Default)]
+struct Group {
+  mesh: Mesh,
+  indices: HashMap<usize, usize>,
+  normals: Vec<Option<Vec3>>,
+}
+
+impl Group {
+  fn add(&mut self, vertex: usize, normal: Option<Vec3>, vertices: &[Point3]) {
+    let num_vertices = self.mesh.vertices.len();
+    let index = *self.indices.entry(vertex).or_insert(num_vertices);
+    if index == num_vertices { self.mesh.vertices.push(vertices[vertex]) }
+    self.mesh.faces.push(index);
+    self.normals.push(normal);
+  }
+}
+
+// Read one mesh per group, named after the path of the group.
+// Faces without normals get flat shaded.
+pub fn import(text: &str) -> Result<Vec<(String, Mesh)>, String> {
+  let mut vertices = vec![];
+  let mut normals = vec![];
+  let mut groups: Vec<(String, Group)> = vec![];
+  let mut group_indices: HashMap<String, usize> = HashMap::new();
+  let mut current = "default".to_string();
+  for (i, line) in text.lines().enumerate() {
+    let line = line.split('#').next().unwrap();
+    let mut words = line.split_whitespace();
+    match words.next() {
+      Some("v") => vertices.push(Point3::from_vec(vector(words, i)?)),
+      Some("vn") => normals.push(vector(words, i)?),
+      Some("o") | Some("g") => {
+        let names: Vec<&str> = words.collect();
+        current = if names.is_empty() { "default".to_string() } else { names.join("/") };
+      },
+      Some("f") => {
+        let corners = words.map(|word| corner(word, vertices.len(), &normals, i) ).collect::<Result<Vec<_>, _>>()?;
+        if corners.len() < 3 { return Err(format!("Face with less than three vertices on line {} of OBJ file", i + 1)) }
+        let index = *group_indices.entry(current.clone()).or_insert_with(|| {
+          groups.push((current.clone(), Group::default()));
+          groups.len() - 1
+        });
+        let group = &mut groups[index].1;
+        // Polygons are split into a fan of triangles
+        for pair in corners[1..].windows(2) {
+          for &(vertex, normal) in &[corners[0], pair[0], pair[1]] {
+            group.add(vertex, normal, &vertices);
+          }
+        }
+      },
+      _ => {},
+    }
+  }
+  Ok(groups.into_iter().map(|(name, group)| {
+    let mut mesh = group.mesh;
+    match group.normals.into_iter().collect::<Option<Vec<Vec3>>>() {
+      Some(normals) => mesh.normals = normals,
+      None => mesh.compute_normals(),
+    }
+    (name, mesh)
+  }).collect())
+}
+
+fn vector<'a>(words: impl Iterator<Item = &'a str>, line: usize) -> Result<Vec3, String> {
+  let coords: Vec<f64> = words.map(|word| word.parse::<f64>() ).collect::<Result<_, _>>()
+  .map_err(|_| format!("Invalid vector on line {} of OBJ file", line + 1) )?;
+  // Vertices may carry an additional weight or color
+  if coords.len() < 3 { return Err(format!("Invalid vector on line {} of OBJ file", line + 1)) }
+  Ok(Vec3::new(coords[0], coords[1], coords[2]))
+}
+
+// Corners are given as v, v/vt, v//vn or v/vt/vn
+fn corner(word: &str, num_vertices: usize, normals: &[Vec3], line: usize) -> Result<(usize, Option<Vec3>), String> {
+  let mut parts = word.split('/');
+  let vertex = index(parts.next().unwrap(), num_vertices, line)?;
+  let normal = match parts.nth(1) {
+    Some(part) if !part.is_empty() => Some(normals[index(part, normals.len(), line)?]),
+    _ => None,
+  };
+  Ok((vertex, normal))
+}
+
+// Negative indices count backwards from the latest element
+fn index(text: &str, len: usize, line: usize) -> Result<usize, String> {
+  let error = || format!("Invalid index {} on line {} of OBJ file", text, line + 1);
+  let index: i64 = text.parse().map_err(|_| error() )?;
+  let index = if index < 0 { len as i64 + index } else { index - 1 };
+  if index < 0 || index >= len as i64 { return Err(error()) }
+  Ok(index as usize)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mesh::Meshable;
+  use crate::features::make_cube;
+  use crate::io::Object;
+
+  fn root() -> Part {
+    let mesh = make_cube(1.0, 2.0, 3.0).unwrap().solids[0].tesselate();
+    let mut child = Part::new("Front Wheel", vec![Object { name: "Tire".into(), mesh: mesh.clone(), material: Some(1) }]);
+    child.transform = Matrix4::from_translation(Vec3::new(10.0, 0.0, 0.0));
+    let mut root = Part::new("Car", vec![Object { name: "Body".into(), mesh, material: Some(0) }]);
+    root.children.push(child);
+    root
+  }
+
+  fn materials() -> Vec<Material> {
+    vec![
+      Material { name: "Green".into(), color: [0x21, 0xBB, 0x4C, 0xFF] },
+      Material { name: "Black Rubber".into(), color: [0, 0, 0, 0x80] },
+    ]
+  }
+
+  #[test]
+  fn groups() {
+    let obj = export(&root(), &materials(), Some("car.mtl"));
+    assert!(obj.contains("mtllib car.mtl\n"));
+    assert!(obj.contains("o Body\ng Car\nusemtl Green\n"));
+    assert!(obj.contains("o Tire\ng Car Front_Wheel\nusemtl Black_Rubber\n"));
+    assert!(!export(&root(), &materials(), None).contains("usemtl"));
+  }
+
+  #[test]
+  fn mtl() {
+    let mtl = export_mtl(&materials());
+    assert!(mtl.contains("newmtl Black_Rubber\nKd 0.0000 0.0000 0.0000\nd 0.5020\n"));
+  }
+
+  #[test]
+  fn round_trip() {
+    let root = root();
+    let groups = import(&export(&root, &[], None)).unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].0, "Car");
+    assert_eq!(groups[1].0, "Car/Front_Wheel");
+    let original = &root.objects[0].mesh;
+    for (_, mesh) in &groups {
+      assert_eq!(mesh.faces.len(), original.faces.len());
+      for (normal, expected) in mesh.normals.iter().zip(&original.normals) {
+        assert!(normal.almost(*expected));
+      }
+    }
+    let min_x = |mesh: &Mesh| mesh.vertices.iter().map(|vertex| vertex.x ).fold(f64::MAX, f64::min);
+    assert!((min_x(&groups[1].1) - min_x(original) - 10.0).abs() < EPSILON);
+  }
+
+  #[test]
+  fn foreign() {
+    let groups = import("
+      # Quad without normals
+      v 0 0 0
+      v 1 0 0
+      v 1 1 0
+      v 0 1 0 1.0
+      vt 0 0
+      f 1/1 2/1 3/1 -1/1
+      g
+      f -4 -3 -2
+    ").unwrap();
+    assert_eq!(groups.len(), 1);
+    let mesh = &groups[0].1;
+    assert_eq!(groups[0].0, "default");
+    assert_eq!(mesh.faces, vec![0, 1, 2, 0, 2, 3, 0, 1, 2]);
+    assert_eq!(mesh.normals.len(), 9);
+    assert!(mesh.normals.iter().all(|&normal| normal == Vec3::unit_z() ));
+  }
+
+  #[test]
+  fn invalid() {
+    assert!(import("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n").is_err());
+    assert!(import("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1//1 2//1 3//1\n").is_err());
+    assert!(import("v 0 0\n").is_err());
+    assert!(import("v 0 0 0\nf 1 1\n").is_err());
+  }
+}
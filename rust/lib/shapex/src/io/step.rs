@@ -1,268 +1,544 @@
-use crate::Solid;
-
-pub fn export(
-  _solid: &Solid,
-  name: &str,
-  description: &str,
-  _geom_only: bool
-) -> String {
-  let file = format!(r###"
-    ISO-10303-21;
-    HEADER;
-    FILE_DESCRIPTION(
-    /* description */ ('{}'),
-    /* implementation_level */ '2;1');
-
-    FILE_NAME(
-    /* name */ '{}.step',
-    /* time_stamp */ '2021-01-16T22:35:27+01:00',
-    /* author */ (''),
-    /* organization */ (''),
-    /* preprocessor_version */ 'Shapex',
-    /* originating_system */ 'Shapex Geometric Modeling Kernel',
-    /* authorisation */ '');
-
-    FILE_SCHEMA (('AUTOMOTIVE_DESIGN {{ 1 0 10303 214 3 1 1 }}'));
-    ENDSEC;
-
-    DATA;
-    #10=MECHANICAL_DESIGN_GEOMETRIC_PRESENTATION_REPRESENTATION('',(#13),#183);
-    #11=SHAPE_REPRESENTATION_RELATIONSHIP('SRR','None',#190,#12);
-    #12=ADVANCED_BREP_SHAPE_REPRESENTATION('',(#14),#182);
-    #13=STYLED_ITEM('',(#199),#14);
-    #14=MANIFOLD_SOLID_BREP('Box',#107);
-    #15=FACE_OUTER_BOUND('',#21,.T.);
-    #16=FACE_OUTER_BOUND('',#22,.T.);
-    #17=FACE_OUTER_BOUND('',#23,.T.);
-    #18=FACE_OUTER_BOUND('',#24,.T.);
-    #19=FACE_OUTER_BOUND('',#25,.T.);
-    #20=FACE_OUTER_BOUND('',#26,.T.);
-    #21=EDGE_LOOP('',(#71,#72,#73,#74));
-    #22=EDGE_LOOP('',(#75,#76,#77,#78));
-    #23=EDGE_LOOP('',(#79,#80,#81,#82));
-    #24=EDGE_LOOP('',(#83,#84,#85,#86));
-    #25=EDGE_LOOP('',(#87,#88,#89,#90));
-    #26=EDGE_LOOP('',(#91,#92,#93,#94));
-    #27=LINE('',#157,#39);
-    #28=LINE('',#159,#40);
-    #29=LINE('',#161,#41);
-    #30=LINE('',#162,#42);
-    #31=LINE('',#165,#43);
-    #32=LINE('',#167,#44);
-    #33=LINE('',#168,#45);
-    #34=LINE('',#171,#46);
-    #35=LINE('',#173,#47);
-    #36=LINE('',#174,#48);
-    #37=LINE('',#176,#49);
-    #38=LINE('',#177,#50);
-    #39=VECTOR('',#131,10.);
-    #40=VECTOR('',#132,10.);
-    #41=VECTOR('',#133,10.);
-    #42=VECTOR('',#134,10.);
-    #43=VECTOR('',#137,10.);
-    #44=VECTOR('',#138,10.);
-    #45=VECTOR('',#139,10.);
-    #46=VECTOR('',#142,10.);
-    #47=VECTOR('',#143,10.);
-    #48=VECTOR('',#144,10.);
-    #49=VECTOR('',#147,10.);
-    #50=VECTOR('',#148,10.);
-    #51=VERTEX_POINT('',#155);
-    #52=VERTEX_POINT('',#156);
-    #53=VERTEX_POINT('',#158);
-    #54=VERTEX_POINT('',#160);
-    #55=VERTEX_POINT('',#164);
-    #56=VERTEX_POINT('',#166);
-    #57=VERTEX_POINT('',#170);
-    #58=VERTEX_POINT('',#172);
-    #59=EDGE_CURVE('',#51,#52,#27,.T.);
-    #60=EDGE_CURVE('',#51,#53,#28,.T.);
-    #61=EDGE_CURVE('',#54,#53,#29,.T.);
-    #62=EDGE_CURVE('',#52,#54,#30,.T.);
-    #63=EDGE_CURVE('',#52,#55,#31,.T.);
-    #64=EDGE_CURVE('',#56,#54,#32,.T.);
-    #65=EDGE_CURVE('',#55,#56,#33,.T.);
-    #66=EDGE_CURVE('',#55,#57,#34,.T.);
-    #67=EDGE_CURVE('',#58,#56,#35,.T.);
-    #68=EDGE_CURVE('',#57,#58,#36,.T.);
-    #69=EDGE_CURVE('',#57,#51,#37,.T.);
-    #70=EDGE_CURVE('',#53,#58,#38,.T.);
-    #71=ORIENTED_EDGE('',*,*,#59,.F.);
-    #72=ORIENTED_EDGE('',*,*,#60,.T.);
-    #73=ORIENTED_EDGE('',*,*,#61,.F.);
-    #74=ORIENTED_EDGE('',*,*,#62,.F.);
-    #75=ORIENTED_EDGE('',*,*,#63,.F.);
-    #76=ORIENTED_EDGE('',*,*,#62,.T.);
-    #77=ORIENTED_EDGE('',*,*,#64,.F.);
-    #78=ORIENTED_EDGE('',*,*,#65,.F.);
-    #79=ORIENTED_EDGE('',*,*,#66,.F.);
-    #80=ORIENTED_EDGE('',*,*,#65,.T.);
-    #81=ORIENTED_EDGE('',*,*,#67,.F.);
-    #82=ORIENTED_EDGE('',*,*,#68,.F.);
-    #83=ORIENTED_EDGE('',*,*,#69,.F.);
-    #84=ORIENTED_EDGE('',*,*,#68,.T.);
-    #85=ORIENTED_EDGE('',*,*,#70,.F.);
-    #86=ORIENTED_EDGE('',*,*,#60,.F.);
-    #87=ORIENTED_EDGE('',*,*,#70,.T.);
-    #88=ORIENTED_EDGE('',*,*,#67,.T.);
-    #89=ORIENTED_EDGE('',*,*,#64,.T.);
-    #90=ORIENTED_EDGE('',*,*,#61,.T.);
-    #91=ORIENTED_EDGE('',*,*,#69,.T.);
-    #92=ORIENTED_EDGE('',*,*,#59,.T.);
-    #93=ORIENTED_EDGE('',*,*,#63,.T.);
-    #94=ORIENTED_EDGE('',*,*,#66,.T.);
-    #95=PLANE('',#121);
-    #96=PLANE('',#122);
-    #97=PLANE('',#123);
-    #98=PLANE('',#124);
-    #99=PLANE('',#125);
-    #100=PLANE('',#126);
-    #101=ADVANCED_FACE('',(#15),#95,.T.);
-    #102=ADVANCED_FACE('',(#16),#96,.T.);
-    #103=ADVANCED_FACE('',(#17),#97,.T.);
-    #104=ADVANCED_FACE('',(#18),#98,.T.);
-    #105=ADVANCED_FACE('',(#19),#99,.T.);
-    #106=ADVANCED_FACE('',(#20),#100,.F.);
-    #107=CLOSED_SHELL('',(#101,#102,#103,#104,#105,#106));
-    #108=DERIVED_UNIT_ELEMENT(#110,1.);
-    #109=DERIVED_UNIT_ELEMENT(#185,-3.);
-    #110=(
-    MASS_UNIT()
-    NAMED_UNIT(*)
-    SI_UNIT(.KILO.,.GRAM.)
-    );
-    #111=DERIVED_UNIT((#108,#109));
-    #112=MEASURE_REPRESENTATION_ITEM('density measure',
-    POSITIVE_RATIO_MEASURE(7850.),#111);
-    #113=PROPERTY_DEFINITION_REPRESENTATION(#118,#115);
-    #114=PROPERTY_DEFINITION_REPRESENTATION(#119,#116);
-    #115=REPRESENTATION('material name',(#117),#182);
-    #116=REPRESENTATION('density',(#112),#182);
-    #117=DESCRIPTIVE_REPRESENTATION_ITEM('Steel','Steel');
-    #118=PROPERTY_DEFINITION('material property','material name',#192);
-    #119=PROPERTY_DEFINITION('material property','density of part',#192);
-    #120=AXIS2_PLACEMENT_3D('placement',#153,#127,#128);
-    #121=AXIS2_PLACEMENT_3D('',#154,#129,#130);
-    #122=AXIS2_PLACEMENT_3D('',#163,#135,#136);
-    #123=AXIS2_PLACEMENT_3D('',#169,#140,#141);
-    #124=AXIS2_PLACEMENT_3D('',#175,#145,#146);
-    #125=AXIS2_PLACEMENT_3D('',#178,#149,#150);
-    #126=AXIS2_PLACEMENT_3D('',#179,#151,#152);
-    #127=DIRECTION('axis',(0.,0.,1.));
-    #128=DIRECTION('refdir',(1.,0.,0.));
-    #129=DIRECTION('center_axis',(1.,0.,0.));
-    #130=DIRECTION('ref_axis',(0.,0.,-1.));
-    #131=DIRECTION('',(0.,0.,1.));
-    #132=DIRECTION('',(0.,1.,0.));
-    #133=DIRECTION('',(0.,0.,-1.));
-    #134=DIRECTION('',(0.,1.,0.));
-    #135=DIRECTION('center_axis',(0.,0.,1.));
-    #136=DIRECTION('ref_axis',(1.,0.,0.));
-    #137=DIRECTION('',(-1.,0.,0.));
-    #138=DIRECTION('',(1.,0.,0.));
-    #139=DIRECTION('',(0.,1.,0.));
-    #140=DIRECTION('center_axis',(-1.,0.,0.));
-    #141=DIRECTION('ref_axis',(0.,0.,1.));
-    #142=DIRECTION('',(0.,0.,-1.));
-    #143=DIRECTION('',(0.,0.,1.));
-    #144=DIRECTION('',(0.,1.,0.));
-    #145=DIRECTION('center_axis',(-3.70074341541719E-17,0.,-1.));
-    #146=DIRECTION('ref_axis',(-1.,0.,3.70074341541719E-17));
-    #147=DIRECTION('',(1.,0.,-3.70074341541719E-17));
-    #148=DIRECTION('',(-1.,0.,3.70074341541719E-17));
-    #149=DIRECTION('center_axis',(0.,1.,0.));
-    #150=DIRECTION('ref_axis',(0.,0.,1.));
-    #151=DIRECTION('center_axis',(0.,1.,0.));
-    #152=DIRECTION('ref_axis',(1.,0.,0.));
-    #153=CARTESIAN_POINT('',(0.,0.,0.));
-    #154=CARTESIAN_POINT('Origin',(30.,0.,20.));
-    #155=CARTESIAN_POINT('',(30.,0.,-20.));
-    #156=CARTESIAN_POINT('',(30.,0.,20.));
-    #157=CARTESIAN_POINT('',(30.,0.,-20.));
-    #158=CARTESIAN_POINT('',(30.,140.,-20.));
-    #159=CARTESIAN_POINT('',(30.,0.,-20.));
-    #160=CARTESIAN_POINT('',(30.,140.,20.));
-    #161=CARTESIAN_POINT('',(30.,140.,-20.));
-    #162=CARTESIAN_POINT('',(30.,0.,20.));
-    #163=CARTESIAN_POINT('Origin',(-30.,0.,20.));
-    #164=CARTESIAN_POINT('',(-30.,0.,20.));
-    #165=CARTESIAN_POINT('',(30.,0.,20.));
-    #166=CARTESIAN_POINT('',(-30.,140.,20.));
-    #167=CARTESIAN_POINT('',(30.,140.,20.));
-    #168=CARTESIAN_POINT('',(-30.,0.,20.));
-    #169=CARTESIAN_POINT('Origin',(-30.,0.,-20.));
-    #170=CARTESIAN_POINT('',(-30.,0.,-20.));
-    #171=CARTESIAN_POINT('',(-30.,0.,20.));
-    #172=CARTESIAN_POINT('',(-30.,140.,-20.));
-    #173=CARTESIAN_POINT('',(-30.,140.,20.));
-    #174=CARTESIAN_POINT('',(-30.,0.,-20.));
-    #175=CARTESIAN_POINT('Origin',(30.,0.,-20.));
-    #176=CARTESIAN_POINT('',(-30.,0.,-20.));
-    #177=CARTESIAN_POINT('',(-30.,140.,-20.));
-    #178=CARTESIAN_POINT('Origin',(0.,140.,0.));
-    #179=CARTESIAN_POINT('Origin',(0.,0.,0.));
-    #180=UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(0.01),#184,
-    'DISTANCE_ACCURACY_VALUE',
-    'Maximum model space distance between geometric entities at asserted c
-    onnectivities');
-    #181=UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(0.01),#184,
-    'DISTANCE_ACCURACY_VALUE',
-    'Maximum model space distance between geometric entities at asserted c
-    onnectivities');
-    #182=(
-    GEOMETRIC_REPRESENTATION_CONTEXT(3)
-    GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT((#180))
-    GLOBAL_UNIT_ASSIGNED_CONTEXT((#184,#186,#187))
-    REPRESENTATION_CONTEXT('','3D')
-    );
-    #183=(
-    GEOMETRIC_REPRESENTATION_CONTEXT(3)
-    GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT((#181))
-    GLOBAL_UNIT_ASSIGNED_CONTEXT((#184,#186,#187))
-    REPRESENTATION_CONTEXT('','3D')
-    );
-    #184=(
-    LENGTH_UNIT()
-    NAMED_UNIT(*)
-    SI_UNIT(.MILLI.,.METRE.)
-    );
-    #185=(
-    LENGTH_UNIT()
-    NAMED_UNIT(*)
-    SI_UNIT($,.METRE.)
-    );
-    #186=(
-    NAMED_UNIT(*)
-    PLANE_ANGLE_UNIT()
-    SI_UNIT($,.RADIAN.)
-    );
-    #187=(
-    NAMED_UNIT(*)
-    SI_UNIT($,.STERADIAN.)
-    SOLID_ANGLE_UNIT()
-    );
-    #188=SHAPE_DEFINITION_REPRESENTATION(#189,#190);
-    #189=PRODUCT_DEFINITION_SHAPE('',$,#192);
-    #190=SHAPE_REPRESENTATION('',(#120),#182);
-    #191=PRODUCT_DEFINITION_CONTEXT('part definition',#196,'design');
-    #192=PRODUCT_DEFINITION('(Unsaved)','(Unsaved)',#193,#191);
-    #193=PRODUCT_DEFINITION_FORMATION('',$,#198);
-    #194=PRODUCT_RELATED_PRODUCT_CATEGORY('(Unsaved)','(Unsaved)',(#198));
-    #195=APPLICATION_PROTOCOL_DEFINITION('international standard',
-    'automotive_design',2009,#196);
-    #196=APPLICATION_CONTEXT(
-    'Core Data for Automotive Mechanical Design Process');
-    #197=PRODUCT_CONTEXT('part definition',#196,'mechanical');
-    #198=PRODUCT('(Unsaved)','(Unsaved)',$,(#197));
-    #199=PRESENTATION_STYLE_ASSIGNMENT((#200));
-    #200=SURFACE_STYLE_USAGE(.BOTH.,#201);
-    #201=SURFACE_SIDE_STYLE('',(#202));
-    #202=SURFACE_STYLE_FILL_AREA(#203);
-    #203=FILL_AREA_STYLE('Steel - Satin',(#204));
-    #204=FILL_AREA_STYLE_COLOUR('Steel - Satin',#205);
-    #205=COLOUR_RGB('Steel - Satin',0.627450980392157,0.627450980392157,0.627450980392157);
-    ENDSEC;
-    END-ISO-10303-21;
-  "###, description, name);
+use std::collections::HashMap;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::internal::*;
+use crate::curve::*;
+use crate::surface::*;
+use crate::solid::*;
+
+mod parser;
+mod import;
+
+pub use import::import;
+pub use import::Import;
+pub use import::Assembly;
+
+
+// Product tree of an assembly, with each part placed relative to its parent
+pub struct Part<'a> {
+  pub name: String,
+  pub transform: Matrix4,
+  pub solids: Vec<&'a Solid>,
+  pub children: Vec<Part<'a>>,
+}
+
+impl<'a> Part<'a> {
+  pub fn new(name: &str, solids: Vec<&'a Solid>) -> Self {
+    Self {
+      name: name.to_string(),
+      transform: Matrix4::identity(),
+      solids,
+      children: vec![],
+    }
+  }
+}
+
+
+pub fn export(solid: &Solid, name: &str, description: &str, time_stamp: &str, geom_only: bool) -> String {
+  export_assembly(&Part::new(name, vec![solid]), description, time_stamp, geom_only)
+}
+
+// Write an AP214 file with lengths in millimeters.
+// Presentation styles are left out when geom_only is set.
+pub fn export_assembly(part: &Part, description: &str, time_stamp: &str, geom_only: bool) -> String {
+  let mut writer = StepWriter::new(geom_only);
+  writer.write_part(part);
+  let mut file = format!(r###"ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION(('{}'),'2;1');
+FILE_NAME('{}.step','{}',(''),(''),'Shapex','Shapex Geometric Modeling Kernel','');
+FILE_SCHEMA(('AUTOMOTIVE_DESIGN {{ 1 0 10303 214 3 1 1 }}'));
+ENDSEC;
+DATA;
+"###, string(description), string(&part.name), string(time_stamp));
+  for (i, entity) in writer.entities.iter().enumerate() {
+    file.push_str(&format!("#{}={};\n", i + 1, entity));
+  }
+  file.push_str("ENDSEC;\nEND-ISO-10303-21;\n");
   file
 }
+
+
+struct Contexts {
+  product: usize,
+  product_definition: usize,
+  geometry: usize,
+}
+
+struct StepWriter {
+  entities: Vec<String>,
+  geom_only: bool,
+  contexts: Option<Contexts>,
+  vertices: HashMap<*const RefCell<Vertex>, usize>,
+  edges: HashMap<*const RefCell<Edge>, usize>,
+}
+
+impl StepWriter {
+  fn new(geom_only: bool) -> Self {
+    Self {
+      entities: vec![],
+      geom_only,
+      contexts: None,
+      vertices: HashMap::new(),
+      edges: HashMap::new(),
+    }
+  }
+
+  // Add an entity and return its id
+  fn add(&mut self, entity: String) -> usize {
+    self.entities.push(entity);
+    self.entities.len()
+  }
+
+  fn contexts(&mut self) -> &Contexts {
+    if self.contexts.is_none() {
+      let application = self.add("APPLICATION_CONTEXT('core data for automotive mechanical design processes')".into());
+      self.add(format!("APPLICATION_PROTOCOL_DEFINITION('international standard','automotive_design',2000,#{})", application));
+      let product = self.add(format!("PRODUCT_CONTEXT('',#{},'mechanical')", application));
+      let product_definition = self.add(format!("PRODUCT_DEFINITION_CONTEXT('part definition',#{},'design')", application));
+      let length = self.add("(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.))".into());
+      let angle = self.add("(NAMED_UNIT(*)PLANE_ANGLE_UNIT()SI_UNIT($,.RADIAN.))".into());
+      let solid_angle = self.add("(NAMED_UNIT(*)SI_UNIT($,.STERADIAN.)SOLID_ANGLE_UNIT())".into());
+      let uncertainty = self.add(format!(
+        "UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE({}),#{},'distance_accuracy_value','confusion accuracy')",
+        real(1.0e-7), length,
+      ));
+      let geometry = self.add(format!(
+        "(GEOMETRIC_REPRESENTATION_CONTEXT(3)GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT((#{}))GLOBAL_UNIT_ASSIGNED_CONTEXT((#{},#{},#{}))REPRESENTATION_CONTEXT('',''))",
+        uncertainty, length, angle, solid_angle,
+      ));
+      self.contexts = Some(Contexts { product, product_definition, geometry });
+    }
+    self.contexts.as_ref().unwrap()
+  }
+
+  // Returns the product definition, shape representation and its origin
+  fn write_part(&mut self, part: &Part) -> (usize, usize, usize) {
+    let contexts = self.contexts();
+    let (product_context, definition_context, geometry_context) = (contexts.product, contexts.product_definition, contexts.geometry);
+    let name = string(&part.name);
+    let product = self.add(format!("PRODUCT('{}','{}','',(#{}))", name, name, product_context));
+    self.add(format!("PRODUCT_RELATED_PRODUCT_CATEGORY('part',$,(#{}))", product));
+    let formation = self.add(format!("PRODUCT_DEFINITION_FORMATION('','',#{})", product));
+    let definition = self.add(format!("PRODUCT_DEFINITION('design','',#{},#{})", formation, definition_context));
+    let definition_shape = self.add(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", definition));
+    let origin = self.placement(Point3::origin(), Vec3::unit_z(), Vec3::unit_x());
+    // Children are placed by axis placements among the items of the parent shape
+    let placements: Vec<usize> = part.children.iter().map(|child| {
+      let transform = child.transform;
+      let location = Point3::from_vec(transform.w.truncate());
+      self.placement(location, transform.z.truncate(), transform.x.truncate())
+    }).collect();
+    let items = [vec![origin], placements.clone()].concat();
+    let shape = self.add(format!("SHAPE_REPRESENTATION('{}',({}),#{})", name, refs(&items), geometry_context));
+    self.add(format!("SHAPE_DEFINITION_REPRESENTATION(#{},#{})", definition_shape, shape));
+
+    // Geometry
+    if !part.solids.is_empty() {
+      let breps: Vec<usize> = part.solids.iter().map(|solid| self.write_solid(solid) ).collect();
+      let brep_origin = self.placement(Point3::origin(), Vec3::unit_z(), Vec3::unit_x());
+      let items = [breps.clone(), vec![brep_origin]].concat();
+      let brep_shape = self.add(format!("ADVANCED_BREP_SHAPE_REPRESENTATION('',({}),#{})", refs(&items), geometry_context));
+      self.add(format!("SHAPE_REPRESENTATION_RELATIONSHIP('','',#{},#{})", shape, brep_shape));
+      if !self.geom_only { self.write_styles(&breps, geometry_context) }
+    }
+
+    // Assembly structure
+    for (i, (child, placement)) in part.children.iter().zip(placements).enumerate() {
+      let (child_definition, child_shape, child_origin) = self.write_part(child);
+      let transformation = self.add(format!("ITEM_DEFINED_TRANSFORMATION('','',#{},#{})", child_origin, placement));
+      let relationship = self.add(format!(
+        "(REPRESENTATION_RELATIONSHIP('','',#{},#{})REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION(#{})SHAPE_REPRESENTATION_RELATIONSHIP())",
+        child_shape, shape, transformation,
+      ));
+      let usage = self.add(format!(
+        "NEXT_ASSEMBLY_USAGE_OCCURRENCE('{}','{}','',#{},#{},$)",
+        i + 1, string(&child.name), definition, child_definition,
+      ));
+      let usage_shape = self.add(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", usage));
+      self.add(format!("CONTEXT_DEPENDENT_SHAPE_REPRESENTATION(#{},#{})", relationship, usage_shape));
+    }
+    (definition, shape, origin)
+  }
+
+  fn write_styles(&mut self, breps: &[usize], geometry_context: usize) {
+    let colour = self.add(format!("COLOUR_RGB('',{},{},{})", real(0.63), real(0.63), real(0.63)));
+    let fill_colour = self.add(format!("FILL_AREA_STYLE_COLOUR('',#{})", colour));
+    let fill = self.add(format!("FILL_AREA_STYLE('',(#{}))", fill_colour));
+    let fill_area = self.add(format!("SURFACE_STYLE_FILL_AREA(#{})", fill));
+    let side = self.add(format!("SURFACE_SIDE_STYLE('',(#{}))", fill_area));
+    let usage = self.add(format!("SURFACE_STYLE_USAGE(.BOTH.,#{})", side));
+    let assignment = self.add(format!("PRESENTATION_STYLE_ASSIGNMENT((#{}))", usage));
+    let styled: Vec<usize> = breps.iter().map(|brep| self.add(format!("STYLED_ITEM('',(#{}),#{})", assignment, brep)) ).collect();
+    self.add(format!("MECHANICAL_DESIGN_GEOMETRIC_PRESENTATION_REPRESENTATION('',({}),#{})", refs(&styled), geometry_context));
+  }
+
+  // Topology
+
+  fn write_solid(&mut self, solid: &Solid) -> usize {
+    let shells: Vec<usize> = solid.shells.iter().enumerate().map(|(i, shell)| self.write_shell(shell, i > 0) ).collect();
+    if shells.len() == 1 {
+      self.add(format!("MANIFOLD_SOLID_BREP('',#{})", shells[0]))
+    } else {
+      // Void shells are written like outer shells and reversed
+      let voids: Vec<usize> = shells[1..].iter().map(|shell| self.add(format!("ORIENTED_CLOSED_SHELL('',*,#{},.F.)", shell)) ).collect();
+      self.add(format!("BREP_WITH_VOIDS('',#{},({}))", shells[0], refs(&voids)))
+    }
+  }
+
+  // Faces of voids point out of the material into the void, so they are written reversed
+  fn write_shell(&mut self, shell: &Shell, is_void: bool) -> usize {
+    let faces: Vec<usize> = shell.faces.iter().map(|face| {
+      let face = face.borrow();
+      self.write_face(&face, face.flip_normal == is_void)
+    }).collect();
+    self.add(format!("CLOSED_SHELL('',({}))", refs(&faces)))
+  }
+
+  fn write_face(&mut self, face: &Face, outward: bool) -> usize {
+    let bounds: Vec<usize> = face.rings.iter().map(|ring| {
+      let oriented_edges: Vec<usize> = ring.borrow().iter().map(|he| {
+        let edge = he.borrow().get_edge();
+        let edge_curve = self.write_edge(&edge);
+        let sense = Rc::ptr_eq(&he, &edge.borrow().left_half);
+        self.add(format!("ORIENTED_EDGE('',*,*,#{},{})", edge_curve, logical(sense)))
+      }).collect();
+      let edge_loop = self.add(format!("EDGE_LOOP('',({}))", refs(&oriented_edges)));
+      let kind = if Rc::ptr_eq(ring, &face.outer_ring) { "FACE_OUTER_BOUND" } else { "FACE_BOUND" };
+      self.add(format!("{}('',#{},.T.)", kind, edge_loop))
+    }).collect();
+    let (surface, normal) = self.write_surface(&face.surface);
+    // Faces point out of the shell
+    let surface_normal = face.surface.as_surface().normal_at(0.5, 0.5);
+    let same_sense = (surface_normal.dot(normal) > 0.0) == outward;
+    self.add(format!("ADVANCED_FACE('',({}),#{},{})", refs(&bounds), surface, logical(same_sense)))
+  }
+
+  // Edge curves run along their left half edge
+  fn write_edge(&mut self, edge: &Ref<Edge>) -> usize {
+    if let Some(&id) = self.edges.get(&Rc::as_ptr(edge)) { return id }
+    let half_edge = edge.borrow().left_half.clone();
+    let half_edge = half_edge.borrow();
+    let start = self.write_vertex(&half_edge.origin);
+    let end = self.write_vertex(&half_edge.end_vertex());
+    let curve = self.write_curve(&edge.borrow().curve);
+    let same_sense = half_edge.make_curve().is_forward;
+    let id = self.add(format!("EDGE_CURVE('',#{},#{},#{},{})", start, end, curve, logical(same_sense)));
+    self.edges.insert(Rc::as_ptr(edge), id);
+    id
+  }
+
+  fn write_vertex(&mut self, vertex: &Ref<Vertex>) -> usize {
+    if let Some(&id) = self.vertices.get(&Rc::as_ptr(vertex)) { return id }
+    let point = self.point(vertex.borrow().point);
+    let id = self.add(format!("VERTEX_POINT('',#{})", point));
+    self.vertices.insert(Rc::as_ptr(vertex), id);
+    id
+  }
+
+  // Geometry
+
+  fn write_curve(&mut self, curve: &CurveType) -> usize {
+    match curve {
+      CurveType::Line(line) => {
+        let (start, end) = line.points;
+        let point = self.point(start);
+        let direction = self.direction(end - start);
+        let vector = self.add(format!("VECTOR('',#{},{})", direction, real(start.distance(end))));
+        self.add(format!("LINE('',#{},#{})", point, vector))
+      },
+      // Our circles start at their v axis and run towards u
+      CurveType::Arc(Arc { plane, radius, .. })
+      | CurveType::Circle(Circle { plane, radius, .. })
+      => {
+        let placement = self.placement(plane.origin, -plane.normal(), plane.v);
+        self.add(format!("CIRCLE('',#{},{})", placement, real(*radius)))
+      },
+      CurveType::Spline(spline) => {
+        let controls: Vec<usize> = spline.controls.iter().map(|&p| self.point(p) ).collect();
+        let (multiplicities, knots) = knot_multiplicities(&spline.knots);
+        let is_rational = spline.weights.iter().any(|&w| !w.almost(1.0) );
+        if is_rational {
+          self.add(format!(
+            "(BOUNDED_CURVE()B_SPLINE_CURVE({},({}),.UNSPECIFIED.,.F.,.F.)B_SPLINE_CURVE_WITH_KNOTS(({}),({}),.UNSPECIFIED.)CURVE()GEOMETRIC_REPRESENTATION_ITEM()RATIONAL_B_SPLINE_CURVE(({}))REPRESENTATION_ITEM(''))",
+            spline.degree, refs(&controls), multiplicities, knots, reals(&spline.weights),
+          ))
+        } else {
+          self.add(format!(
+            "B_SPLINE_CURVE_WITH_KNOTS('',{},({}),.UNSPECIFIED.,.F.,.F.,({}),({}),.UNSPECIFIED.)",
+            spline.degree, refs(&controls), multiplicities, knots,
+          ))
+        }
+      },
+    }
+  }
+
+  // Returns the surface along with its normal at the center of our parameter space, as defined by STEP
+  fn write_surface(&mut self, surface: &SurfaceType) -> (usize, Vec3) {
+    let center = surface.as_surface().sample(0.5, 0.5);
+    match surface {
+      SurfaceType::Planar(planar) => {
+        let normal = planar.plane.normal();
+        let placement = self.placement(planar.plane.origin, normal, planar.plane.u);
+        (self.add(format!("PLANE('',#{})", placement)), normal)
+      },
+      SurfaceType::Revolution(revolution) => {
+        let axis = &revolution.axis;
+        let frame = axis.as_transform();
+        let reference = frame.x.truncate();
+        let direction = axis.direction.normalize();
+        let on_axis = |height: f64| axis.origin + direction * height;
+        let radial = {
+          let offset = center - axis.closest_point(center);
+          if offset.magnitude() < EPSILON { reference } else { offset.normalize() }
+        };
+        match revolution.shape() {
+          RevolutionShape::Cylinder { radius } => {
+            let placement = self.placement(axis.origin, direction, reference);
+            (self.add(format!("CYLINDRICAL_SURFACE('',#{},{})", placement, real(radius))), radial)
+          },
+          RevolutionShape::Cone { height, radius, semi_angle } => {
+            // Cones have to widen along their axis
+            let axis_direction = direction * semi_angle.signum();
+            let placement = self.placement(on_axis(height), axis_direction, reference);
+            let entity = format!("CONICAL_SURFACE('',#{},{},{})", placement, real(radius), real(semi_angle.abs()));
+            (self.add(entity), radial - axis_direction * semi_angle.abs().tan())
+          },
+          RevolutionShape::Sphere { height, radius } => {
+            let placement = self.placement(on_axis(height), direction, reference);
+            (self.add(format!("SPHERICAL_SURFACE('',#{},{})", placement, real(radius))), center - on_axis(height))
+          },
+          RevolutionShape::Torus { height, major_radius, minor_radius } => {
+            let placement = self.placement(on_axis(height), direction, reference);
+            let tube_center = on_axis(height) + radial * major_radius;
+            let entity = format!("TOROIDAL_SURFACE('',#{},{},{})", placement, real(major_radius), real(minor_radius));
+            (self.add(entity), center - tube_center)
+          },
+          RevolutionShape::Other => {
+            let mut curve = revolution.curve.clone();
+            curve.as_curve_mut().transform(&frame);
+            let curve = self.write_curve(&curve);
+            let location = self.point(axis.origin);
+            let axis_direction = self.direction(direction);
+            let axis_placement = self.add(format!("AXIS1_PLACEMENT('',#{},#{})", location, axis_direction));
+            let entity = format!("SURFACE_OF_REVOLUTION('',#{},#{})", curve, axis_placement);
+            (self.add(entity), parametric_normal(revolution))
+          },
+        }
+      },
+      SurfaceType::Spline(spline) => {
+        // Control points are listed by u first
+        let columns: Vec<String> = (0..spline.controls[0].len()).map(|i| {
+          let column: Vec<usize> = spline.controls.iter().map(|row| self.point(row[i]) ).collect();
+          format!("({})", refs(&column))
+        }).collect();
+        let (u_multiplicities, u_knots) = knot_multiplicities(&spline.knots.0);
+        let (v_multiplicities, v_knots) = knot_multiplicities(&spline.knots.1);
+        let entity = format!(
+          "B_SPLINE_SURFACE_WITH_KNOTS('',{},{},({}),.UNSPECIFIED.,.F.,.F.,.F.,({}),({}),({}),({}),.UNSPECIFIED.)",
+          spline.degree.0, spline.degree.1, columns.join(","), u_multiplicities, v_multiplicities, u_knots, v_knots,
+        );
+        (self.add(entity), parametric_normal(spline))
+      },
+    }
+  }
+
+  fn point(&mut self, p: Point3) -> usize {
+    self.add(format!("CARTESIAN_POINT('',({},{},{}))", real(p.x), real(p.y), real(p.z)))
+  }
+
+  fn direction(&mut self, vec: Vec3) -> usize {
+    let vec = vec.normalize();
+    self.add(format!("DIRECTION('',({},{},{}))", real(vec.x), real(vec.y), real(vec.z)))
+  }
+
+  fn placement(&mut self, origin: Point3, axis: Vec3, reference: Vec3) -> usize {
+    let location = self.point(origin);
+    let axis = self.direction(axis);
+    let reference = self.direction(reference);
+    self.add(format!("AXIS2_PLACEMENT_3D('',#{},#{},#{})", location, axis, reference))
+  }
+}
+
+
+// Cross product of the partial derivatives at the center of the parameter space
+fn parametric_normal(surface: &dyn Surface) -> Vec3 {
+  let delta = 1.0e-5;
+  let su = surface.sample(0.5 + delta, 0.5) - surface.sample(0.5 - delta, 0.5);
+  let sv = surface.sample(0.5, 0.5 + delta) - surface.sample(0.5, 0.5 - delta);
+  su.cross(sv)
+}
+
+// Split a knot vector into distinct knots and their multiplicities
+fn knot_multiplicities(knots: &[f64]) -> (String, String) {
+  let mut distinct: Vec<(f64, usize)> = vec![];
+  for &knot in knots {
+    match distinct.last_mut() {
+      Some((last, count)) if last.almost(knot) => *count += 1,
+      _ => distinct.push((knot, 1)),
+    }
+  }
+  let multiplicities: Vec<String> = distinct.iter().map(|(_, count)| count.to_string() ).collect();
+  let knots: Vec<f64> = distinct.iter().map(|&(knot, _)| knot ).collect();
+  (multiplicities.join(","), reals(&knots))
+}
+
+fn refs(ids: &[usize]) -> String {
+  ids.iter().map(|id| format!("#{}", id) ).collect::<Vec<String>>().join(",")
+}
+
+fn reals(values: &[f64]) -> String {
+  values.iter().map(|&value| real(value) ).collect::<Vec<String>>().join(",")
+}
+
+// Reals always need a decimal point
+fn real(value: f64) -> String {
+  let value = if value.abs() < 1.0e-15 { 0.0 } else { value };
+  let formatted = format!("{:?}", value);
+  match formatted.split_once('e') {
+    Some((mantissa, exponent)) if !mantissa.contains('.') => format!("{}.E{}", mantissa, exponent),
+    Some((mantissa, exponent)) => format!("{}E{}", mantissa, exponent),
+    None => formatted,
+  }
+}
+
+fn logical(value: bool) -> &'static str {
+  if value { ".T." } else { ".F." }
+}
+
+// Quotes are escaped by doubling them
+fn string(text: &str) -> String {
+  text.replace('\'', "''")
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::features::*;
+
+  fn count(file: &str, entity: &str) -> usize {
+    file.matches(&format!("={}(", entity)).count()
+  }
+
+  // Every referenced entity has to be defined
+  fn assert_references(file: &str) {
+    let num_entities = file.lines().filter(|line| line.starts_with('#') ).count();
+    for line in file.lines().filter(|line| line.starts_with('#') ) {
+      let body = &line[line.find('=').unwrap()..];
+      for reference in body.split('#').skip(1) {
+        let id: usize = reference.chars().take_while(|c| c.is_ascii_digit() ).collect::<String>().parse().unwrap();
+        assert!((1..=num_entities).contains(&id), "Dangling reference #{} in {}", id, line);
+      }
+    }
+  }
+
+  #[test]
+  fn cube() {
+    let compound = make_cube(1.0, 2.0, 3.0).unwrap();
+    let file = export(&compound.solids[0], "Cube", "Test", "2024-01-01T00:00:00", false);
+    assert!(file.starts_with("ISO-10303-21;"));
+    assert!(file.contains("'2024-01-01T00:00:00'"));
+    assert!(file.contains("SI_UNIT(.MILLI.,.METRE.)"));
+    assert_eq!(count(&file, "MANIFOLD_SOLID_BREP"), 1);
+    assert_eq!(count(&file, "CLOSED_SHELL"), 1);
+    assert_eq!(count(&file, "ADVANCED_FACE"), 6);
+    assert_eq!(count(&file, "PLANE"), 6);
+    assert_eq!(count(&file, "EDGE_CURVE"), 12);
+    assert_eq!(count(&file, "ORIENTED_EDGE"), 24);
+    assert_eq!(count(&file, "VERTEX_POINT"), 8);
+    assert_eq!(count(&file, "LINE"), 12);
+    assert_eq!(count(&file, "STYLED_ITEM"), 1);
+    assert_references(&file);
+  }
+
+  #[test]
+  fn geom_only() {
+    let compound = make_cube(1.0, 1.0, 1.0).unwrap();
+    let file = export(&compound.solids[0], "Cube", "", "", true);
+    assert_eq!(count(&file, "STYLED_ITEM"), 0);
+    assert_references(&file);
+  }
+
+  #[test]
+  fn analytic_surfaces() {
+    let cylinder = make_cylinder(1.0, 2.0).unwrap();
+    let file = export(&cylinder.solids[0], "Cylinder", "", "", true);
+    assert_eq!(count(&file, "CYLINDRICAL_SURFACE"), 1);
+    assert_eq!(count(&file, "CIRCLE"), 2);
+    assert_references(&file);
+    let sphere = make_sphere(1.0).unwrap();
+    assert_eq!(count(&export(&sphere.solids[0], "Sphere", "", "", true), "SPHERICAL_SURFACE"), 1);
+    let cone = make_cone(1.0, 2.0).unwrap();
+    assert_eq!(count(&export(&cone.solids[0], "Cone", "", "", true), "CONICAL_SURFACE"), 1);
+    let torus = make_torus(2.0, 0.5).unwrap();
+    assert_eq!(count(&export(&torus.solids[0], "Torus", "", "", true), "TOROIDAL_SURFACE"), 1);
+  }
+
+  #[test]
+  fn spline_surfaces() {
+    let spline = Spline::fit(&[
+      Point3::new(1.0, 0.0, 0.0),
+      Point3::new(0.0, 1.0, 0.0),
+      Point3::new(-1.0, 0.0, 0.0),
+      Point3::new(0.0, -1.2, 0.0),
+      Point3::new(1.0, 0.0, 0.0),
+    ]);
+    let profile = vec![vec![TrimmedCurve::new(spline.into_enum())]];
+    let compound = extrude(&profile, 1.0).unwrap();
+    let file = export(&compound.solids[0], "Extrusion", "", "", true);
+    assert_eq!(count(&file, "B_SPLINE_SURFACE_WITH_KNOTS"), 1);
+    assert_eq!(count(&file, "B_SPLINE_CURVE_WITH_KNOTS"), 2);
+    assert_references(&file);
+  }
+
+  #[test]
+  fn voids() {
+    let mut compound = make_cube(2.0, 2.0, 2.0).unwrap();
+    shell(&mut compound, &[], 0.2).unwrap();
+    let file = export(&compound.solids[0], "Box", "", "", true);
+    assert_eq!(count(&file, "BREP_WITH_VOIDS"), 1);
+    assert_eq!(count(&file, "ORIENTED_CLOSED_SHELL"), 1);
+    assert_eq!(count(&file, "CLOSED_SHELL"), 2);
+    assert_references(&file);
+  }
+
+  #[test]
+  fn assembly() {
+    let cube = make_cube(1.0, 1.0, 1.0).unwrap();
+    let cylinder = make_cylinder(1.0, 2.0).unwrap();
+    let mut child = Part::new("Cylinder", vec![&cylinder.solids[0]]);
+    child.transform = Matrix4::from_translation(Vec3::new(5.0, 0.0, 0.0));
+    let mut root = Part::new("Assembly", vec![&cube.solids[0]]);
+    root.children.push(child);
+    let file = export_assembly(&root, "", "", true);
+    assert_eq!(count(&file, "PRODUCT"), 2);
+    assert_eq!(count(&file, "NEXT_ASSEMBLY_USAGE_OCCURRENCE"), 1);
+    assert_eq!(count(&file, "CONTEXT_DEPENDENT_SHAPE_REPRESENTATION"), 1);
+    assert!(file.contains("CARTESIAN_POINT('',(5.0,0.0,0.0))"));
+    assert_references(&file);
+  }
+
+  // Loops run counter-clockwise around the outside of faces
+  #[test]
+  fn loop_orientation() {
+    let compound = make_cube(1.0, 2.0, 3.0).unwrap();
+    let shell = &compound.solids[0].shells[0];
+    for face in &shell.faces {
+      let face = face.borrow();
+      let points: Vec<Point3> = face.outer_ring.borrow().iter().map(|he| he.borrow().origin.borrow().point ).collect();
+      let normal = points.iter().zip(points.iter().cycle().skip(1)).fold(Vec3::new(0.0, 0.0, 0.0), |acc, (a, b)| acc + a.to_vec().cross(b.to_vec()) );
+      let outward = face.surface.as_surface().normal_at(0.0, 0.0) * if face.flip_normal { -1.0 } else { 1.0 };
+      assert!(normal.dot(outward) > 0.0);
+    }
+  }
+
+  #[test]
+  fn reals() {
+    assert_eq!(real(1.0), "1.0");
+    assert_eq!(real(-2.5), "-2.5");
+    assert_eq!(real(1.0e-7), "1.E-7");
+    assert_eq!(real(1.5e20), "1.5E20");
+  }
+}
@@ -0,0 +1,888 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+use crate::internal::*;
+use crate::curve::*;
+use crate::surface::*;
+use crate::solid::*;
+use crate::solid::serde as dump;
+use crate::transform::*;
+use super::parser::*;
+
+
+// Product tree of an imported file.
+// Solids are given by their index into the compound and are placed in the coordinate system of their part.
+#[derive(Debug)]
+pub struct Assembly {
+  pub name: String,
+  pub transform: Matrix4,
+  pub solids: Vec<usize>,
+  pub children: Vec<Assembly>,
+}
+
+#[derive(Debug)]
+pub struct Import {
+  pub compound: Compound,
+  pub assembly: Assembly,
+  pub warnings: Vec<String>,
+}
+
+
+// Read the B-rep solids and product structure of an AP203/AP214 file, converting lengths to millimeters
+pub fn import(text: &str) -> Result<Import, String> {
+  let mut importer = Importer::new(parse(text)?);
+  importer.read_units()?;
+  let assembly = importer.read_assembly()?;
+  if importer.compound.solids.is_empty() {
+    let mut message = "No supported solids found in STEP file".to_string();
+    for warning in &importer.warnings {
+      message.push_str(&format!("\n{}", warning));
+    }
+    return Err(message)
+  }
+  Ok(Import {
+    compound: importer.compound,
+    assembly,
+    warnings: importer.warnings,
+  })
+}
+
+
+struct Importer {
+  entities: HashMap<usize, Vec<Record>>,
+  ids: HashMap<String, Vec<usize>>, // Entities by the names of their records, in order of their ids
+  length_factor: f64,
+  angle_factor: f64,
+  warnings: Vec<String>,
+  solids: HashMap<usize, Option<usize>>, // Index of the solid imported for each B-rep entity
+  compound: Compound,
+}
+
+impl Importer {
+  fn new(entities: HashMap<usize, Vec<Record>>) -> Self {
+    let mut ids: HashMap<String, Vec<usize>> = HashMap::new();
+    for (&id, records) in &entities {
+      for record in records {
+        let list = ids.entry(record.name.clone()).or_default();
+        if list.last() != Some(&id) { list.push(id) }
+      }
+    }
+    for list in ids.values_mut() { list.sort() }
+    Self {
+      entities,
+      ids,
+      length_factor: 1.0,
+      angle_factor: 1.0,
+      warnings: vec![],
+      solids: HashMap::new(),
+      compound: Compound::default(),
+    }
+  }
+
+  // Entities
+
+  fn entity(&self, id: usize) -> Result<&[Record], String> {
+    self.entities.get(&id).map(|records| records.as_slice() ).ok_or(format!("Missing entity #{}", id))
+  }
+
+  // Name of a simple entity, or of the first record of a complex one
+  fn name(&self, id: usize) -> Result<&str, String> {
+    Ok(&self.entity(id)?[0].name)
+  }
+
+  fn find(&self, id: usize, name: &str) -> Option<&[Param]> {
+    self.entities.get(&id)?.iter().find(|record| record.name == name ).map(|record| record.params.as_slice() )
+  }
+
+  fn params(&self, id: usize, name: &str) -> Result<&[Param], String> {
+    self.find(id, name).ok_or(format!("Expected {} for #{}, found {}", name, id, self.name(id)?))
+  }
+
+  fn ids_of(&self, name: &str) -> &[usize] {
+    self.ids.get(name).map(|ids| ids.as_slice() ).unwrap_or(&[])
+  }
+
+  // Units
+
+  fn read_units(&mut self) -> Result<(), String> {
+    let context = match self.ids_of("GLOBAL_UNIT_ASSIGNED_CONTEXT").first() {
+      Some(&context) => context,
+      None => return Ok(()),
+    };
+    let units = list(param(self.params(context, "GLOBAL_UNIT_ASSIGNED_CONTEXT")?, context, 0)?)?.to_vec();
+    for unit in units {
+      let unit = reference(&unit)?;
+      if self.find(unit, "LENGTH_UNIT").is_some() {
+        self.length_factor = self.unit_factor(unit)? * 1000.0;
+      } else if self.find(unit, "PLANE_ANGLE_UNIT").is_some() {
+        self.angle_factor = self.unit_factor(unit)?;
+      }
+    }
+    Ok(())
+  }
+
+  // Size of a unit in meters or radians
+  fn unit_factor(&self, unit: usize) -> Result<f64, String> {
+    if let Some(params) = self.find(unit, "SI_UNIT") {
+      let prefix = match params.first() {
+        Some(Param::Enum(prefix)) => prefix.as_str(),
+        _ => "",
+      };
+      Ok(match prefix {
+        "KILO" => 1.0e3,
+        "CENTI" => 1.0e-2,
+        "MILLI" => 1.0e-3,
+        "MICRO" => 1.0e-6,
+        "NANO" => 1.0e-9,
+        _ => 1.0,
+      })
+    } else if let Some(params) = self.find(unit, "CONVERSION_BASED_UNIT") {
+      let measure = reference(param(params, unit, 1)?)?;
+      let record = &self.entity(measure)?[0];
+      let value = real(param(&record.params, measure, 0)?)?;
+      Ok(value * self.unit_factor(reference(param(&record.params, measure, 1)?)?)?)
+    } else {
+      Err(format!("Unsupported unit #{}", unit))
+    }
+  }
+
+  // Product structure
+
+  fn read_assembly(&mut self) -> Result<Assembly, String> {
+    let definitions = self.ids_of("PRODUCT_DEFINITION").to_vec();
+    // Parent and child definitions of each usage, with the placement of the child
+    let mut usages = vec![];
+    for &usage in self.ids_of("NEXT_ASSEMBLY_USAGE_OCCURRENCE") {
+      let params = self.params(usage, "NEXT_ASSEMBLY_USAGE_OCCURRENCE")?;
+      let (parent, child) = (reference(param(params, usage, 3)?)?, reference(param(params, usage, 4)?)?);
+      let transform = self.usage_transform(usage, parent)?;
+      usages.push((parent, child, transform));
+    }
+    let children: HashSet<usize> = usages.iter().map(|&(_, child, _)| child ).collect();
+    let roots: Vec<usize> = definitions.iter().filter(|definition| !children.contains(definition) ).cloned().collect();
+    let mut assemblies = vec![];
+    for root in roots {
+      assemblies.push(self.read_part(root, Matrix4::identity(), &usages, 0)?);
+    }
+    let mut assembly = if assemblies.len() == 1 {
+      assemblies.remove(0)
+    } else {
+      Assembly { name: String::new(), transform: Matrix4::identity(), solids: vec![], children: assemblies }
+    };
+    // Solids that are not part of any product
+    for brep in [self.ids_of("MANIFOLD_SOLID_BREP"), self.ids_of("BREP_WITH_VOIDS")].concat() {
+      if !self.solids.contains_key(&brep) {
+        if let Some(index) = self.read_solid(brep) { assembly.solids.push(index) }
+      }
+    }
+    Ok(assembly)
+  }
+
+  fn read_part(&mut self, definition: usize, transform: Matrix4, usages: &[(usize, usize, Matrix4)], depth: usize) -> Result<Assembly, String> {
+    if depth > 100 { return Err("Cyclic assembly structure".into()) }
+    let mut solids = vec![];
+    for representation in self.representations(definition)? {
+      for brep in self.breps(representation)? {
+        let index = match self.solids.get(&brep) {
+          Some(&index) => index,
+          None => self.read_solid(brep),
+        };
+        if let Some(index) = index { solids.push(index) }
+      }
+    }
+    let mut children = vec![];
+    for &(parent, child, child_transform) in usages {
+      if parent == definition {
+        children.push(self.read_part(child, child_transform, usages, depth + 1)?);
+      }
+    }
+    Ok(Assembly {
+      name: self.product_name(definition)?,
+      transform,
+      solids,
+      children,
+    })
+  }
+
+  fn product_name(&self, definition: usize) -> Result<String, String> {
+    let formation = reference(param(self.params(definition, "PRODUCT_DEFINITION")?, definition, 2)?)?;
+    let formation_params = &self.entity(formation)?[0].params;
+    let product = reference(param(formation_params, formation, 2)?)?;
+    Ok(string(param(self.params(product, "PRODUCT")?, product, 1)?)?.to_string())
+  }
+
+  // Shape representations of a product definition
+  fn representations(&self, definition: usize) -> Result<Vec<usize>, String> {
+    let mut representations = vec![];
+    for &definition_shape in self.ids_of("PRODUCT_DEFINITION_SHAPE") {
+      if *param(self.params(definition_shape, "PRODUCT_DEFINITION_SHAPE")?, definition_shape, 2)? != Param::Ref(definition) { continue }
+      for &shape_definition in self.ids_of("SHAPE_DEFINITION_REPRESENTATION") {
+        let params = self.params(shape_definition, "SHAPE_DEFINITION_REPRESENTATION")?;
+        if *param(params, shape_definition, 0)? == Param::Ref(definition_shape) { representations.push(reference(param(params, shape_definition, 1)?)?) }
+      }
+    }
+    Ok(representations)
+  }
+
+  // B-rep items of a representation, including those of representations related to it without a transformation
+  fn breps(&mut self, representation: usize) -> Result<Vec<usize>, String> {
+    let mut breps = vec![];
+    let mut warnings = vec![];
+    let mut visited = HashSet::new();
+    let mut stack = vec![representation];
+    while let Some(representation) = stack.pop() {
+      if !visited.insert(representation) { continue }
+      let items = list(param(&self.entity(representation)?[0].params, representation, 1)?)?;
+      for item in items {
+        let item = reference(item)?;
+        match self.name(item)? {
+          "MANIFOLD_SOLID_BREP" | "BREP_WITH_VOIDS" => breps.push(item),
+          "AXIS2_PLACEMENT_3D" | "MAPPED_ITEM" | "STYLED_ITEM" => {},
+          name => warnings.push(format!("Unsupported representation item {} in #{}", name, item)),
+        }
+      }
+      for &relationship in self.ids_of("SHAPE_REPRESENTATION_RELATIONSHIP") {
+        if self.find(relationship, "REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION").is_some() { continue }
+        // Complex instances list the related representations in their supertype
+        let params = match self.find(relationship, "SHAPE_REPRESENTATION_RELATIONSHIP") {
+          Some(params) if params.len() == 4 => params,
+          _ => self.params(relationship, "REPRESENTATION_RELATIONSHIP")?,
+        };
+        let (first, second) = (reference(param(params, relationship, 2)?)?, reference(param(params, relationship, 3)?)?);
+        if first == representation { stack.push(second) }
+        if second == representation { stack.push(first) }
+      }
+    }
+    for warning in warnings {
+      if !self.warnings.contains(&warning) { self.warnings.push(warning) }
+    }
+    Ok(breps)
+  }
+
+  // Placement of the child of an assembly usage in the coordinate system of its parent
+  fn usage_transform(&self, usage: usize, parent: usize) -> Result<Matrix4, String> {
+    let parent_representations = self.representations(parent)?;
+    for &definition_shape in self.ids_of("PRODUCT_DEFINITION_SHAPE") {
+      if *param(self.params(definition_shape, "PRODUCT_DEFINITION_SHAPE")?, definition_shape, 2)? != Param::Ref(usage) { continue }
+      for &dependent in self.ids_of("CONTEXT_DEPENDENT_SHAPE_REPRESENTATION") {
+        let params = self.params(dependent, "CONTEXT_DEPENDENT_SHAPE_REPRESENTATION")?;
+        if *param(params, dependent, 1)? != Param::Ref(definition_shape) { continue }
+        let relationship = reference(param(params, dependent, 0)?)?;
+        let representations = self.params(relationship, "REPRESENTATION_RELATIONSHIP")?;
+        let transformation = reference(param(self.params(relationship, "REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION")?, relationship, 0)?)?;
+        let items = self.params(transformation, "ITEM_DEFINED_TRANSFORMATION")?;
+        let from = self.placement_transform(reference(param(items, transformation, 2)?)?)?;
+        let to = self.placement_transform(reference(param(items, transformation, 3)?)?)?;
+        let transform = to * from.invert().ok_or("Invalid placement in assembly")?;
+        // The first representation is usually the one of the child
+        let is_reversed = parent_representations.contains(&reference(param(representations, relationship, 2)?)?);
+        return Ok(if is_reversed { transform.invert().unwrap() } else { transform })
+      }
+    }
+    Ok(Matrix4::identity())
+  }
+
+  // Topology
+
+  fn read_solid(&mut self, brep: usize) -> Option<usize> {
+    let result = self.convert_solid(brep);
+    let index = match result {
+      Ok(solid) => {
+        self.compound.solids.push(solid);
+        Some(self.compound.solids.len() - 1)
+      },
+      Err(error) => {
+        self.warnings.push(format!("Skipped solid #{}: {}", brep, error));
+        None
+      },
+    };
+    self.solids.insert(brep, index);
+    index
+  }
+
+  fn convert_solid(&self, brep: usize) -> Result<Solid, String> {
+    // Shells along with whether they are reversed
+    let mut shells = vec![];
+    if let Some(params) = self.find(brep, "MANIFOLD_SOLID_BREP") {
+      shells.push((reference(param(params, brep, 1)?)?, false));
+    } else {
+      let params = self.params(brep, "BREP_WITH_VOIDS")?;
+      shells.push((reference(param(params, brep, 1)?)?, false));
+      // Voids are listed as reversed closed shells, which we keep facing into the void
+      for void in list(param(params, brep, 2)?)? {
+        let void = reference(void)?;
+        shells.push(match self.find(void, "ORIENTED_CLOSED_SHELL") {
+          Some(params) => (reference(param(params, void, 2)?)?, !boolean(param(params, void, 3)?)?),
+          None => (void, true),
+        });
+      }
+    }
+    let mut unoriented = vec![];
+    let mut dump_shells = vec![];
+    for (shell, reversed) in shells {
+      let (dump_shell, faces) = self.convert_shell(shell, reversed)?;
+      dump_shells.push(dump_shell);
+      unoriented.push(faces);
+    }
+    let solid = dump::undump_solid(dump::Solid { shells: dump_shells });
+    // Orient faces without a sense by their position in the shell
+    for (i, (shell, faces)) in solid.shells.iter().zip(unoriented).enumerate() {
+      for face in faces.into_iter().map(|j| &shell.faces[j] ) {
+        let orientation = shell.face_orientation(&face.borrow());
+        face.borrow_mut().flip_normal = (orientation < 0.0) == (i == 0);
+      }
+    }
+    Ok(solid)
+  }
+
+  // Returns the shell along with the indices of faces that don't specify their sense
+  fn convert_shell(&self, shell: usize, reversed: bool) -> Result<(dump::Shell, Vec<usize>), String> {
+    let params = self.params(shell, "CLOSED_SHELL")
+    .map_err(|_| format!("Unsupported shell type {} in #{}", self.name(shell).unwrap_or(""), shell) )?;
+    let mut vertices = vec![];
+    let mut vertex_indices = HashMap::new();
+    let mut edges = vec![];
+    let mut edge_indices = HashMap::new();
+    let mut edge_uses = vec![];
+    let mut faces = vec![];
+    let mut unoriented = vec![];
+    for face in list(param(params, shell, 1)?)? {
+      let face = reference(face)?;
+      let face_params = self.find(face, "ADVANCED_FACE").or_else(|| self.find(face, "FACE_SURFACE") )
+      .ok_or(format!("Unsupported face type {} in #{}", self.name(face)?, face))?;
+      let mut rings = vec![];
+      let mut is_outer = vec![];
+      let mut curves = vec![];
+      for bound in list(param(face_params, face, 1)?)? {
+        let bound = reference(bound)?;
+        let bound_name = self.name(bound)?;
+        let bound_params = &self.entity(bound)?[0].params;
+        let edge_loop = reference(param(bound_params, bound, 1)?)?;
+        // Loops around single vertices mark the poles of spheres and cones
+        if self.find(edge_loop, "VERTEX_LOOP").is_some() { continue }
+        let mut ring = vec![];
+        for oriented_edge in list(param(self.params(edge_loop, "EDGE_LOOP")?, edge_loop, 1)?)? {
+          let oriented_edge = reference(oriented_edge)?;
+          let oriented_params = self.params(oriented_edge, "ORIENTED_EDGE")?;
+          let edge = reference(param(oriented_params, oriented_edge, 3)?)?;
+          let edge_params = self.params(edge, "EDGE_CURVE")?;
+          let start = reference(param(edge_params, edge, 1)?)?;
+          let end = reference(param(edge_params, edge, 2)?)?;
+          let vertex = if boolean(param(oriented_params, oriented_edge, 4)?)? { start } else { end };
+          // Vertices and edges are shared between faces
+          let origin = match vertex_indices.get(&vertex) {
+            Some(&index) => index,
+            None => {
+              vertices.push(dump::Vertex { point: self.vertex(vertex)? });
+              vertex_indices.insert(vertex, vertices.len() - 1);
+              vertices.len() - 1
+            },
+          };
+          let index = match edge_indices.get(&edge) {
+            Some(&index) => index,
+            None => {
+              edges.push(dump::Edge { id: Uuid::new_v4(), curve: self.edge_curve(edge)? });
+              edge_uses.push(0);
+              edge_indices.insert(edge, edges.len() - 1);
+              edges.len() - 1
+            },
+          };
+          edge_uses[index] += 1;
+          curves.push(edges[index].curve.clone());
+          ring.push(dump::HalfEdge { origin, edge: index });
+        }
+        if !boolean(param(bound_params, bound, 2)?)? {
+          // Reversed half edges start where the original ones end
+          let n = ring.len();
+          ring = (0..n).rev().map(|i| dump::HalfEdge { origin: ring[(i + 1) % n].origin, edge: ring[i].edge } ).collect();
+        }
+        is_outer.push(bound_name == "FACE_OUTER_BOUND");
+        rings.push(ring);
+      }
+      if rings.is_empty() { return Err(format!("Face #{} has no edges", face)) }
+      // Outer ring comes first
+      if let Some(outer) = is_outer.iter().position(|&outer| outer ) { rings.swap(0, outer) }
+      let surface_id = reference(param(face_params, face, 2)?)?;
+      let surface = self.surface(surface_id, &curves)?;
+      // Faces point along the normal of their STEP surface if their sense is true.
+      // Spline and revolution normals depend on a parametrization we don't preserve,
+      // so these faces get ray cast like faces without a sense.
+      let same_sense = face_params.get(3).and_then(|same_sense| boolean(same_sense).ok() );
+      let flip_normal = match (same_sense, self.surface_normal(surface_id, &surface)?) {
+        (Some(same_sense), Some(normal)) => {
+          let agrees = normal.dot(surface.as_surface().normal_at(0.5, 0.5)) > 0.0;
+          agrees != (same_sense != reversed)
+        },
+        _ => {
+          unoriented.push(faces.len());
+          false
+        },
+      };
+      faces.push(dump::Face { id: Uuid::new_v4(), rings, surface, flip_normal });
+    }
+    if let Some(index) = edge_uses.iter().position(|&uses| uses != 2 ) {
+      let id = edge_indices.iter().find(|(_, &i)| i == index ).map(|(&id, _)| id ).unwrap();
+      return Err(format!("Edge #{} is not shared by exactly two faces", id))
+    }
+    Ok((dump::Shell { faces, edges, vertices }, unoriented))
+  }
+
+  fn vertex(&self, vertex: usize) -> Result<Point3, String> {
+    self.point(reference(param(self.params(vertex, "VERTEX_POINT")?, vertex, 1)?)?)
+  }
+
+  // Edge curves are bounded by their vertices
+  fn edge_curve(&self, edge: usize) -> Result<CurveType, String> {
+    let params = self.params(edge, "EDGE_CURVE")?;
+    let start = self.vertex(reference(param(params, edge, 1)?)?)?;
+    let end = self.vertex(reference(param(params, edge, 2)?)?)?;
+    let same_sense = boolean(param(params, edge, 4)?)?;
+    Ok(match self.curve(reference(param(params, edge, 3)?)?)? {
+      CurveType::Line(_) => {
+        if start.almost(end) { return Err(format!("Degenerate line in #{}", edge)) }
+        Line::new(start, end).into_enum()
+      },
+      CurveType::Circle(circle) => {
+        if start.almost(end) {
+          circle.into_enum()
+        } else {
+          // Arcs run from start to end in the direction of the circle or against it
+          let t_start = circle.unsample(&start);
+          let mut t_end = circle.unsample(&end);
+          if same_sense && t_end <= t_start { t_end += 1.0 }
+          if !same_sense && t_end >= t_start { t_end -= 1.0 }
+          Arc::from_plane(circle.plane, circle.radius, t_start, t_end).into_enum()
+        }
+      },
+      curve => curve,
+    })
+  }
+
+  // Geometry
+
+  fn point(&self, point: usize) -> Result<Point3, String> {
+    let coords = list(param(self.params(point, "CARTESIAN_POINT")?, point, 1)?)?;
+    if coords.len() != 3 { return Err(format!("Expected 3D point in #{}", point)) }
+    Ok(Point3::new(real(&coords[0])?, real(&coords[1])?, real(&coords[2])?) * self.length_factor)
+  }
+
+  fn direction(&self, direction: usize) -> Result<Vec3, String> {
+    let ratios = list(param(self.params(direction, "DIRECTION")?, direction, 1)?)?;
+    if ratios.len() != 3 { return Err(format!("Expected 3D direction in #{}", direction)) }
+    let vec = Vec3::new(real(&ratios[0])?, real(&ratios[1])?, real(&ratios[2])?);
+    if vec.magnitude() < EPSILON { return Err(format!("Zero direction in #{}", direction)) }
+    Ok(vec.normalize())
+  }
+
+  // Origin, axis and reference direction, which is made perpendicular to the axis
+  fn placement(&self, placement: usize) -> Result<(Point3, Vec3, Vec3), String> {
+    let params = self.params(placement, "AXIS2_PLACEMENT_3D")?;
+    let origin = self.point(reference(param(params, placement, 1)?)?)?;
+    let axis = match param(params, placement, 2)? {
+      Param::Ref(id) => self.direction(*id)?,
+      _ => Vec3::unit_z(),
+    };
+    let reference = match param(params, placement, 3)? {
+      Param::Ref(id) => self.direction(*id)?,
+      _ => if axis.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_y() },
+    };
+    let reference = reference - axis * reference.dot(axis);
+    if reference.magnitude() < EPSILON { return Err(format!("Reference direction parallel to axis in #{}", placement)) }
+    Ok((origin, axis, reference.normalize()))
+  }
+
+  fn vector_direction(&self, vector: usize) -> Result<Vec3, String> {
+    self.direction(reference(param(self.params(vector, "VECTOR")?, vector, 1)?)?)
+  }
+
+  fn placement_transform(&self, placement: usize) -> Result<Matrix4, String> {
+    let (origin, axis, reference) = self.placement(placement)?;
+    Ok(Matrix4::from_cols(
+      reference.extend(0.0),
+      axis.cross(reference).extend(0.0),
+      axis.extend(0.0),
+      origin.to_vec().extend(1.0),
+    ))
+  }
+
+  // Our circles start at their v axis and run towards u
+  fn circle_plane(&self, placement: usize) -> Result<Plane, String> {
+    let (origin, axis, reference) = self.placement(placement)?;
+    Ok(Plane { origin, u: axis.cross(reference), v: reference })
+  }
+
+  fn curve(&self, curve: usize) -> Result<CurveType, String> {
+    let name = self.name(curve)?;
+    let params = &self.entity(curve)?[0].params;
+    match name {
+      "LINE" => {
+        let point = self.point(reference(param(params, curve, 1)?)?)?;
+        let direction = self.vector_direction(reference(param(params, curve, 2)?)?)?;
+        Ok(Line::new(point, point + direction).into_enum())
+      },
+      "CIRCLE" => {
+        let plane = self.circle_plane(reference(param(params, curve, 1)?)?)?;
+        Ok(Circle::from_plane(plane, real(param(params, curve, 2)?)? * self.length_factor).into_enum())
+      },
+      "POLYLINE" => {
+        let points = list(param(params, curve, 1)?)?.iter().map(|point| self.point(reference(point)?) ).collect::<Result<_, _>>()?;
+        Ok(BasisSpline::polyline(points).into_enum())
+      },
+      "TRIMMED_CURVE" | "SURFACE_CURVE" | "SEAM_CURVE" => self.curve(reference(param(params, curve, 1)?)?),
+      _ if self.find(curve, "B_SPLINE_CURVE_WITH_KNOTS").is_some() => self.spline_curve(curve),
+      _ => Err(format!("Unsupported curve type {} in #{}", name, curve)),
+    }
+  }
+
+  fn spline_curve(&self, curve: usize) -> Result<CurveType, String> {
+    // Simple instances carry the parameters of all supertypes
+    let (spline, knots) = match self.find(curve, "B_SPLINE_CURVE") {
+      Some(spline) => (spline, self.params(curve, "B_SPLINE_CURVE_WITH_KNOTS")?),
+      None => {
+        let params = self.params(curve, "B_SPLINE_CURVE_WITH_KNOTS")?;
+        params.get(1..6).zip(params.get(6..)).ok_or(format!("#{} has too few parameters", curve))?
+      },
+    };
+    let controls: Vec<Point3> = list(param(spline, curve, 1)?)?.iter().map(|point| self.point(reference(point)?) ).collect::<Result<_, _>>()?;
+    let weights = match self.find(curve, "RATIONAL_B_SPLINE_CURVE") {
+      Some(params) => reals(param(params, curve, 0)?)?,
+      None => vec![1.0; controls.len()],
+    };
+    let degree = integer(param(spline, curve, 0)?)?;
+    let knots = expand_knots(param(knots, curve, 0)?, param(knots, curve, 1)?)?;
+    if knots.len() != controls.len() + degree + 1 || weights.len() != controls.len() {
+      return Err(format!("Inconsistent spline #{}", curve))
+    }
+    Ok(BasisSpline { id: Uuid::new_v4(), degree, controls, knots, weights }.into_enum())
+  }
+
+  // Unbounded surfaces get trimmed to the extent of their edges
+  fn surface(&self, surface: usize, edges: &[CurveType]) -> Result<SurfaceType, String> {
+    let name = self.name(surface)?;
+    let params = &self.entity(surface)?[0].params;
+    let points: Vec<Point3> = edges.iter().flat_map(|curve| curve.as_curve().tesselate() ).collect();
+    // Range of the edges along an axis
+    let extent = |origin: Point3, direction: Vec3| {
+      points.iter().fold((f64::MAX, f64::MIN), |(min, max), p| {
+        let height = (p - origin).dot(direction);
+        (min.min(height), max.max(height))
+      })
+    };
+    match name {
+      "PLANE" => {
+        let (origin, axis, reference) = self.placement(reference(param(params, surface, 1)?)?)?;
+        Ok(PlanarSurface::new(Plane { origin, u: reference, v: axis.cross(reference) }).into_enum())
+      },
+      "CYLINDRICAL_SURFACE" => {
+        let (origin, axis, _) = self.placement(reference(param(params, surface, 1)?)?)?;
+        let radius = real(param(params, surface, 2)?)? * self.length_factor;
+        let (min, max) = extent(origin, axis);
+        Ok(RevolutionSurface::cylinder(Axis::new(origin + axis * min, axis), radius, max - min).into_enum())
+      },
+      "CONICAL_SURFACE" => {
+        let (origin, axis, _) = self.placement(reference(param(params, surface, 1)?)?)?;
+        let radius = real(param(params, surface, 2)?)? * self.length_factor;
+        let slope = (real(param(params, surface, 3)?)? * self.angle_factor).tan();
+        let (min, max) = extent(origin, axis);
+        let profile = Line::new(
+          Point3::new((radius + min * slope).max(0.0), 0.0, min),
+          Point3::new((radius + max * slope).max(0.0), 0.0, max),
+        );
+        Ok(RevolutionSurface { axis: Axis::new(origin, axis), curve: profile.into_enum(), u_bounds: (0.0, 1.0) }.into_enum())
+      },
+      "SPHERICAL_SURFACE" => {
+        let (origin, axis, _) = self.placement(reference(param(params, surface, 1)?)?)?;
+        let radius = real(param(params, surface, 2)?)? * self.length_factor;
+        // Meridian from south to north pole
+        let plane = Plane { origin: Point3::origin(), u: Vec3::unit_x(), v: Vec3::unit_z() };
+        let profile = Arc::from_plane(plane, radius, 0.5, 0.0);
+        Ok(RevolutionSurface { axis: Axis::new(origin, axis), curve: profile.into_enum(), u_bounds: (0.0, 1.0) }.into_enum())
+      },
+      "TOROIDAL_SURFACE" => {
+        let (origin, axis, _) = self.placement(reference(param(params, surface, 1)?)?)?;
+        let major_radius = real(param(params, surface, 2)?)? * self.length_factor;
+        let minor_radius = real(param(params, surface, 3)?)? * self.length_factor;
+        Ok(RevolutionSurface::torus(Axis::new(origin, axis), major_radius, minor_radius).into_enum())
+      },
+      "SURFACE_OF_REVOLUTION" => {
+        let axis = reference(param(params, surface, 2)?)?;
+        let axis_params = self.params(axis, "AXIS1_PLACEMENT")?;
+        let origin = self.point(reference(param(axis_params, axis, 1)?)?)?;
+        let direction = match param(axis_params, axis, 2)? {
+          Param::Ref(id) => self.direction(*id)?,
+          _ => Vec3::unit_z(),
+        };
+        let profile = match self.curve(reference(param(params, surface, 1)?)?)? {
+          CurveType::Line(line) => {
+            let direction = (line.points.1 - line.points.0).normalize();
+            let (min, max) = extent(line.points.0, direction);
+            Line::new(line.points.0 + direction * min, line.points.0 + direction * max).into_enum()
+          },
+          curve => curve,
+        };
+        Ok(RevolutionSurface::new(Axis::new(origin, direction), profile).into_enum())
+      },
+      "SURFACE_OF_LINEAR_EXTRUSION" => {
+        let direction = self.vector_direction(reference(param(params, surface, 2)?)?)?;
+        match self.curve(reference(param(params, surface, 1)?)?)? {
+          CurveType::Spline(mut spline) => {
+            let (min, max) = extent(spline.controls[0], direction);
+            spline.translate(direction * min);
+            Ok(SplineSurface::tabulated(&spline, direction * (max - min)).into_enum())
+          },
+          CurveType::Line(line) => {
+            let u = (line.points.1 - line.points.0).normalize();
+            let v = direction - u * direction.dot(u);
+            Ok(PlanarSurface::new(Plane { origin: line.points.0, u, v: v.normalize() }).into_enum())
+          },
+          _ => Err(format!("Unsupported extrusion profile in #{}", surface)),
+        }
+      },
+      _ if self.find(surface, "B_SPLINE_SURFACE_WITH_KNOTS").is_some() => self.spline_surface(surface),
+      _ => Err(format!("Unsupported surface type {} in #{}", name, surface)),
+    }
+  }
+
+  // Normal of an elementary STEP surface at the center of the surface converted from it
+  fn surface_normal(&self, surface: usize, converted: &SurfaceType) -> Result<Option<Vec3>, String> {
+    let converted = converted.as_surface();
+    let name = self.name(surface)?;
+    if !["PLANE", "CYLINDRICAL_SURFACE", "CONICAL_SURFACE", "SPHERICAL_SURFACE", "TOROIDAL_SURFACE"].contains(&name) {
+      return Ok(None)
+    }
+    let params = &self.entity(surface)?[0].params;
+    let (origin, axis, _) = self.placement(reference(param(params, surface, 1)?)?)?;
+    let p = converted.sample(0.5, 0.5);
+    let offset = p - origin;
+    let radial = (offset - axis * offset.dot(axis)).normalize();
+    Ok(Some(match name {
+      "PLANE" => axis,
+      "CYLINDRICAL_SURFACE" => radial,
+      "CONICAL_SURFACE" => radial - axis * (real(param(params, surface, 3)?)? * self.angle_factor).tan(),
+      "SPHERICAL_SURFACE" => offset,
+      _ => p - (origin + radial * real(param(params, surface, 2)?)? * self.length_factor),
+    }))
+  }
+
+  fn spline_surface(&self, surface: usize) -> Result<SurfaceType, String> {
+    let (spline, knots) = match self.find(surface, "B_SPLINE_SURFACE") {
+      Some(spline) => (spline, self.params(surface, "B_SPLINE_SURFACE_WITH_KNOTS")?),
+      None => {
+        let params = self.params(surface, "B_SPLINE_SURFACE_WITH_KNOTS")?;
+        params.get(1..8).zip(params.get(8..)).ok_or(format!("#{} has too few parameters", surface))?
+      },
+    };
+    if self.find(surface, "RATIONAL_B_SPLINE_SURFACE").is_some() {
+      return Err(format!("Rational spline surfaces are not supported in #{}", surface))
+    }
+    let degree = (integer(param(spline, surface, 0)?)?, integer(param(spline, surface, 1)?)?);
+    // Control points are listed by u first, while we store rows along u
+    let columns: Vec<Vec<Point3>> = list(param(spline, surface, 2)?)?.iter().map(|column|
+      list(column)?.iter().map(|point| self.point(reference(point)?) ).collect::<Result<_, _>>()
+    ).collect::<Result<_, _>>()?;
+    if columns.is_empty() || columns[0].is_empty() { return Err(format!("Empty spline surface #{}", surface)) }
+    if columns.iter().any(|column| column.len() != columns[0].len() ) { return Err(format!("Inconsistent spline surface #{}", surface)) }
+    let controls: Vec<Vec<Point3>> = (0..columns[0].len()).map(|j| columns.iter().map(|column| column[j] ).collect() ).collect();
+    let knots = (
+      expand_knots(param(knots, surface, 0)?, param(knots, surface, 2)?)?,
+      expand_knots(param(knots, surface, 1)?, param(knots, surface, 3)?)?,
+    );
+    if knots.0.len() != columns.len() + degree.0 + 1 || knots.1.len() != controls.len() + degree.1 + 1 {
+      return Err(format!("Inconsistent spline surface #{}", surface))
+    }
+    Ok(SplineSurface { degree, controls, knots }.into_enum())
+  }
+}
+
+
+// Parameter of an entity, which malformed files may leave out
+fn param(params: &[Param], id: usize, i: usize) -> Result<&Param, String> {
+  params.get(i).ok_or(format!("#{} has too few parameters", id))
+}
+
+fn reference(param: &Param) -> Result<usize, String> {
+  match param {
+    Param::Ref(id) => Ok(*id),
+    _ => Err(format!("Expected entity reference, found {:?}", param)),
+  }
+}
+
+fn list(param: &Param) -> Result<&[Param], String> {
+  match param {
+    Param::List(params) => Ok(params),
+    _ => Err(format!("Expected list, found {:?}", param)),
+  }
+}
+
+fn real(param: &Param) -> Result<f64, String> {
+  match param {
+    Param::Real(value) => Ok(*value),
+    Param::Integer(value) => Ok(*value as f64),
+    Param::Typed(_, value) => real(value),
+    _ => Err(format!("Expected number, found {:?}", param)),
+  }
+}
+
+fn reals(param: &Param) -> Result<Vec<f64>, String> {
+  list(param)?.iter().map(real).collect()
+}
+
+fn integer(param: &Param) -> Result<usize, String> {
+  match param {
+    Param::Integer(value) if *value >= 0 => Ok(*value as usize),
+    _ => Err(format!("Expected positive integer, found {:?}", param)),
+  }
+}
+
+fn boolean(param: &Param) -> Result<bool, String> {
+  match param {
+    Param::Enum(value) if value == "T" => Ok(true),
+    Param::Enum(value) if value == "F" => Ok(false),
+    _ => Err(format!("Expected boolean, found {:?}", param)),
+  }
+}
+
+fn string(param: &Param) -> Result<&str, String> {
+  match param {
+    Param::String(text) => Ok(text),
+    _ => Err(format!("Expected string, found {:?}", param)),
+  }
+}
+
+// Repeat distinct knots by their multiplicities
+fn expand_knots(multiplicities: &Param, knots: &Param) -> Result<Vec<f64>, String> {
+  let multiplicities = list(multiplicities)?.iter().map(integer).collect::<Result<Vec<_>, _>>()?;
+  let knots = reals(knots)?;
+  if multiplicities.len() != knots.len() { return Err("Knots don't match their multiplicities".into()) }
+  Ok(knots.iter().zip(multiplicities).flat_map(|(&knot, count)| vec![knot; count] ).collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::features::*;
+  use crate::io::step::*;
+
+  fn round_trip(compound: &Compound) -> Solid {
+    let original = &compound.solids[0];
+    let file = export(original, "Part", "", "", false);
+    let mut import = import(&file).unwrap();
+    assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+    assert_eq!(import.compound.solids.len(), 1);
+    assert_eq!(import.assembly.solids, vec![0]);
+    let solid = import.compound.solids.remove(0);
+    let report = solid.validate();
+    assert!(report.is_valid(), "{}", report);
+    assert_eq!(solid.shells.len(), original.shells.len());
+    for (i, (shell, original_shell)) in solid.shells.iter().zip(&original.shells).enumerate() {
+      assert_eq!(shell.faces.len(), original_shell.faces.len());
+      assert_eq!(shell.edges.len(), original_shell.edges.len());
+      assert_eq!(shell.vertices.len(), original_shell.vertices.len());
+      // Face senses agree with the position of faces in their shell
+      for face in &shell.faces {
+        let orientation = shell.face_orientation(&face.borrow());
+        assert_eq!(face.borrow().flip_normal, (orientation < 0.0) == (i == 0));
+      }
+    }
+    let (volume, original_volume) = (solid.volume(), original.volume());
+    assert!((volume - original_volume).abs() < original_volume * 1.0e-3, "{} != {}", volume, original_volume);
+    solid
+  }
+
+  #[test]
+  fn cube() {
+    round_trip(&make_cube(1.0, 2.0, 3.0).unwrap());
+  }
+
+  #[test]
+  fn cylinder() {
+    round_trip(&make_cylinder(1.0, 2.0).unwrap());
+  }
+
+  #[test]
+  fn sphere() {
+    round_trip(&make_sphere(2.0).unwrap());
+  }
+
+  #[test]
+  fn cone() {
+    round_trip(&make_cone(1.0, 2.0).unwrap());
+  }
+
+  #[test]
+  fn torus() {
+    round_trip(&make_torus(2.0, 0.5).unwrap());
+  }
+
+  #[test]
+  fn spline_extrusion() {
+    let spline = Spline::fit(&[
+      Point3::new(1.0, 0.0, 0.0),
+      Point3::new(0.0, 1.0, 0.0),
+      Point3::new(-1.0, 0.0, 0.0),
+      Point3::new(0.0, -1.2, 0.0),
+      Point3::new(1.0, 0.0, 0.0),
+    ]);
+    let profile = vec![vec![TrimmedCurve::new(spline.into_enum())]];
+    round_trip(&extrude(&profile, 1.0).unwrap());
+  }
+
+  #[test]
+  fn hollow_box() {
+    let mut compound = make_cube(2.0, 2.0, 2.0).unwrap();
+    shell(&mut compound, &[], 0.2).unwrap();
+    round_trip(&compound);
+  }
+
+  #[test]
+  fn assembly() {
+    let cube = make_cube(1.0, 1.0, 1.0).unwrap();
+    let cylinder = make_cylinder(1.0, 2.0).unwrap();
+    let transform = Matrix4::from_translation(Vec3::new(5.0, 0.0, 0.0)) * Matrix4::from_angle_x(Deg(90.0));
+    let mut root = Part::new("Assembly", vec![&cube.solids[0]]);
+    for _ in 0..2 {
+      let mut child = Part::new("Cylinder", vec![&cylinder.solids[0]]);
+      child.transform = transform;
+      root.children.push(child);
+    }
+    let import = import(&export_assembly(&root, "", "", false)).unwrap();
+    assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+    let assembly = import.assembly;
+    assert_eq!(assembly.name, "Assembly");
+    assert_eq!(assembly.solids.len(), 1);
+    assert_eq!(assembly.children.len(), 2);
+    let child = &assembly.children[0];
+    assert_eq!(child.name, "Cylinder");
+    assert_eq!(child.solids.len(), 1);
+    assert_eq!(import.compound.solids.len(), 3);
+    let p = Point3::new(1.0, 2.0, 3.0);
+    assert!(child.transform.transform_point(p).almost(transform.transform_point(p)));
+  }
+
+  #[test]
+  fn units() {
+    let file = export(&make_cube(1.0, 1.0, 1.0).unwrap().solids[0], "Cube", "", "", true)
+    .replace("SI_UNIT(.MILLI.,.METRE.)", "SI_UNIT($,.METRE.)");
+    let import = import(&file).unwrap();
+    assert!((import.compound.solids[0].volume() - 1.0e9).abs() < 1.0);
+  }
+
+  #[test]
+  fn unsupported() {
+    let file = export(&make_cylinder(1.0, 2.0).unwrap().solids[0], "Cylinder", "", "", true)
+    .replace("CYLINDRICAL_SURFACE(", "ELLIPTIC_CYLINDER(");
+    let error = import(&file).unwrap_err();
+    assert!(error.contains("Unsupported surface type ELLIPTIC_CYLINDER"), "{}", error);
+  }
+
+  #[test]
+  fn missing_parameters() {
+    let file = export(&make_cube(1.0, 1.0, 1.0).unwrap().solids[0], "Cube", "", "", true)
+    .replace("PRODUCT_DEFINITION_SHAPE('','',#", "PRODUCT_DEFINITION_SHAPE('',#");
+    let error = import(&file).unwrap_err();
+    assert!(error.ends_with("has too few parameters"), "{}", error);
+  }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Param {
+  Ref(usize),
+  Integer(i64),
+  Real(f64),
+  String(String),
+  Enum(String),
+  List(Vec<Param>),
+  Typed(String, Box<Param>),
+  Unset,
+  Derived,
+}
+
+
+// Simple entities consist of a single record, complex ones of several
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+  pub name: String,
+  pub params: Vec<Param>,
+}
+
+
+// Parse the data section of an ISO-10303-21 file into its entities by id
+pub fn parse(text: &str) -> Result<HashMap<usize, Vec<Record>>, String> {
+  let mut parser = Parser { chars: text.chars().collect(), pos: 0 };
+  let mut entities = HashMap::new();
+  loop {
+    parser.skip_whitespace();
+    if parser.pos >= parser.chars.len() { break }
+    match parser.peek() {
+      Some('#') => {
+        let id = parser.reference()?;
+        parser.expect('=')?;
+        let records = parser.records()?;
+        parser.expect(';')?;
+        entities.insert(id, records);
+      },
+      // Keywords outside of entity instances, like section markers and header entries
+      _ => parser.statement()?,
+    }
+  }
+  if entities.is_empty() { return Err("No entities found in STEP file".into()) }
+  Ok(entities)
+}
+
+
+struct Parser {
+  chars: Vec<char>,
+  pos: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.pos).cloned()
+  }
+
+  fn skip_whitespace(&mut self) {
+    while let Some(c) = self.peek() {
+      if c.is_whitespace() {
+        self.pos += 1;
+      } else if c == '/' && self.chars.get(self.pos + 1) == Some(&'*') {
+        self.pos += 2;
+        while self.pos < self.chars.len() && !(self.chars[self.pos] == '*' && self.chars.get(self.pos + 1) == Some(&'/')) {
+          self.pos += 1;
+        }
+        self.pos += 2;
+      } else {
+        break
+      }
+    }
+  }
+
+  fn error(&self, message: &str) -> String {
+    let line = self.chars[..self.pos.min(self.chars.len())].iter().filter(|&&c| c == '\n' ).count() + 1;
+    format!("{} on line {} of STEP file", message, line)
+  }
+
+  fn expect(&mut self, expected: char) -> Result<(), String> {
+    self.skip_whitespace();
+    if self.peek() == Some(expected) {
+      self.pos += 1;
+      Ok(())
+    } else {
+      Err(self.error(&format!("Expected '{}'", expected)))
+    }
+  }
+
+  // Skip anything up to the next semicolon outside of strings
+  fn statement(&mut self) -> Result<(), String> {
+    while let Some(c) = self.peek() {
+      match c {
+        ';' => { self.pos += 1; return Ok(()) },
+        '\'' => { self.string()?; },
+        _ => self.pos += 1,
+      }
+    }
+    Ok(())
+  }
+
+  fn keyword(&mut self) -> Result<String, String> {
+    self.skip_whitespace();
+    let start = self.pos;
+    while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '!' ) {
+      self.pos += 1;
+    }
+    if self.pos == start { return Err(self.error("Expected keyword")) }
+    Ok(self.chars[start..self.pos].iter().collect::<String>().to_uppercase())
+  }
+
+  fn reference(&mut self) -> Result<usize, String> {
+    self.expect('#')?;
+    let start = self.pos;
+    while self.peek().is_some_and(|c| c.is_ascii_digit() ) {
+      self.pos += 1;
+    }
+    self.chars[start..self.pos].iter().collect::<String>().parse().map_err(|_| self.error("Invalid entity reference") )
+  }
+
+  fn records(&mut self) -> Result<Vec<Record>, String> {
+    self.skip_whitespace();
+    if self.peek() == Some('(') {
+      self.pos += 1;
+      let mut records = vec![];
+      loop {
+        self.skip_whitespace();
+        if self.peek() == Some(')') {
+          self.pos += 1;
+          return Ok(records)
+        }
+        records.push(self.record()?);
+      }
+    } else {
+      Ok(vec![self.record()?])
+    }
+  }
+
+  fn record(&mut self) -> Result<Record, String> {
+    let name = self.keyword()?;
+    let params = self.list()?;
+    Ok(Record { name, params })
+  }
+
+  fn list(&mut self) -> Result<Vec<Param>, String> {
+    self.expect('(')?;
+    let mut params = vec![];
+    self.skip_whitespace();
+    if self.peek() == Some(')') {
+      self.pos += 1;
+      return Ok(params)
+    }
+    loop {
+      params.push(self.param()?);
+      self.skip_whitespace();
+      match self.peek() {
+        Some(',') => self.pos += 1,
+        Some(')') => { self.pos += 1; return Ok(params) },
+        _ => return Err(self.error("Expected ',' or ')'")),
+      }
+    }
+  }
+
+  fn param(&mut self) -> Result<Param, String> {
+    self.skip_whitespace();
+    match self.peek() {
+      Some('#') => Ok(Param::Ref(self.reference()?)),
+      Some('\'') => Ok(Param::String(self.string()?)),
+      Some('"') => {
+        self.pos += 1;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c != '"' ) { self.pos += 1 }
+        let binary = self.chars[start..self.pos].iter().collect();
+        self.expect('"')?;
+        Ok(Param::String(binary))
+      },
+      Some('.') => {
+        self.pos += 1;
+        let value = self.keyword()?;
+        self.expect('.')?;
+        Ok(Param::Enum(value))
+      },
+      Some('$') => { self.pos += 1; Ok(Param::Unset) },
+      Some('*') => { self.pos += 1; Ok(Param::Derived) },
+      Some('(') => Ok(Param::List(self.list()?)),
+      Some(c) if c == '-' || c == '+' || c.is_ascii_digit() => self.number(),
+      Some(c) if c.is_ascii_alphabetic() => {
+        let name = self.keyword()?;
+        let mut params = self.list()?;
+        if params.len() != 1 { return Err(self.error("Typed parameters need exactly one value")) }
+        Ok(Param::Typed(name, Box::new(params.remove(0))))
+      },
+      _ => Err(self.error("Unexpected character")),
+    }
+  }
+
+  fn string(&mut self) -> Result<String, String> {
+    self.expect('\'')?;
+    let mut text = String::new();
+    loop {
+      match self.peek() {
+        // Quotes are escaped by doubling them
+        Some('\'') if self.chars.get(self.pos + 1) == Some(&'\'') => {
+          text.push('\'');
+          self.pos += 2;
+        },
+        Some('\'') => {
+          self.pos += 1;
+          return Ok(text)
+        },
+        Some(c) => {
+          text.push(c);
+          self.pos += 1;
+        },
+        None => return Err(self.error("Unterminated string")),
+      }
+    }
+  }
+
+  fn number(&mut self) -> Result<Param, String> {
+    let start = self.pos;
+    let mut is_real = false;
+    while let Some(c) = self.peek() {
+      match c {
+        '0'..='9' | '+' | '-' => {},
+        '.' | 'E' | 'e' => is_real = true,
+        _ => break,
+      }
+      self.pos += 1;
+    }
+    let text: String = self.chars[start..self.pos].iter().collect();
+    if is_real {
+      text.parse().map(Param::Real).map_err(|_| self.error(&format!("Invalid real {}", text)) )
+    } else {
+      text.parse().map(Param::Integer).map_err(|_| self.error(&format!("Invalid integer {}", text)) )
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn entities() {
+    let entities = parse("ISO-10303-21;
+      HEADER;
+      FILE_NAME('a;b.step','',(''),(''),'','','');
+      ENDSEC;
+      DATA;
+      /* Comment */
+      #1=CARTESIAN_POINT('It''s',(1.,-2.5E-1,3));
+      #2=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.));
+      #3=UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(1.E-07),#2,'',$);
+      ENDSEC;
+      END-ISO-10303-21;
+    ").unwrap();
+    assert_eq!(entities.len(), 3);
+    assert_eq!(entities[&1][0], Record {
+      name: "CARTESIAN_POINT".into(),
+      params: vec![
+        Param::String("It's".into()),
+        Param::List(vec![Param::Real(1.0), Param::Real(-0.25), Param::Integer(3)]),
+      ],
+    });
+    let names: Vec<&str> = entities[&2].iter().map(|record| record.name.as_str() ).collect();
+    assert_eq!(names, vec!["LENGTH_UNIT", "NAMED_UNIT", "SI_UNIT"]);
+    assert_eq!(entities[&2][1].params, vec![Param::Derived]);
+    assert_eq!(entities[&3][0].params[0], Param::Typed("LENGTH_MEASURE".into(), Box::new(Param::Real(1.0e-7))));
+    assert_eq!(entities[&3][0].params[3], Param::Unset);
+  }
+
+  #[test]
+  fn errors() {
+    assert!(parse("DATA;\n#1=LINE('',#2,#3;\nENDSEC;").unwrap_err().contains("line 2"));
+    assert!(parse("").is_err());
+  }
+}
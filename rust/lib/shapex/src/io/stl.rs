@@ -1,13 +1,13 @@
+use crate::internal::*;
 use crate::Mesh;
 
 
 pub fn export(mesh: &Mesh, name: &str) -> String {
   let mut file = format!("solid {}", name);
-  for i in (0..mesh.faces.len() - 2).step_by(3) {
-    file.push_str(&format!("\nfacet normal {} {} {}", 0.0, 0.0, 0.0));
+  for (normal, vertices) in facets(mesh) {
+    file.push_str(&format!("\nfacet normal {} {} {}", normal.x, normal.y, normal.z));
     file.push_str("\nouter loop");
-    for j in i ..= i + 2 {
-      let vertex = mesh.vertices[mesh.faces[j]];
+    for vertex in vertices {
       file.push_str(&format!("\nvertex {} {} {}", vertex.x, vertex.y, vertex.z));
     }
     file.push_str("\nendloop");
@@ -17,16 +17,157 @@ pub fn export(mesh: &Mesh, name: &str) -> String {
   file
 }
 
+pub fn export_binary(mesh: &Mesh, name: &str) -> Vec<u8> {
+  let num_facets = mesh.faces.len() / 3;
+  let mut data = Vec::with_capacity(84 + num_facets * 50);
+  // Header must not start with "solid", as readers would take the file for ASCII
+  let mut header = format!("Binary STL {}", name).into_bytes();
+  header.resize(80, 0);
+  data.extend(header);
+  data.extend((num_facets as u32).to_le_bytes());
+  for (normal, vertices) in facets(mesh) {
+    for vec in [normal, vertices[0].to_vec(), vertices[1].to_vec(), vertices[2].to_vec()] {
+      for coord in [vec.x, vec.y, vec.z] {
+        data.extend((coord as f32).to_le_bytes());
+      }
+    }
+    data.extend(0_u16.to_le_bytes());
+  }
+  data
+}
+
+// Facet normals follow the winding of triangles
+fn facets(mesh: &Mesh) -> impl Iterator<Item = (Vec3, [Point3; 3])> + '_ {
+  mesh.faces.chunks_exact(3).map(|tri| {
+    let vertices = [mesh.vertices[tri[0]], mesh.vertices[tri[1]], mesh.vertices[tri[2]]];
+    (triangle_normal(&vertices), vertices)
+  })
+}
+
+fn triangle_normal(vertices: &[Point3; 3]) -> Vec3 {
+  let normal = (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]);
+  if normal.magnitude() > EPSILON * EPSILON { normal.normalize() } else { Vec3::new(0.0, 0.0, 0.0) }
+}
+
+
+// Read ASCII or binary STL into a mesh with shared vertices
+pub fn import(data: &[u8]) -> Result<Mesh, String> {
+  let triangles = if is_binary(data) { read_binary(data)? } else { read_ascii(data)? };
+  let mut mesh = Mesh::default();
+  for vertices in triangles {
+    let normal = triangle_normal(&vertices);
+    for vertex in vertices {
+      mesh.faces.push(mesh.vertices.len());
+      mesh.vertices.push(vertex);
+      mesh.normals.push(normal);
+    }
+  }
+  mesh.weld(EPSILON);
+  Ok(mesh)
+}
+
+// Binary files may start with "solid" as well, so their size is checked instead
+fn is_binary(data: &[u8]) -> bool {
+  if data.len() < 84 { return false }
+  let num_facets = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+  // Facet counts of corrupt headers could overflow on 32 bit targets
+  num_facets.checked_mul(50).and_then(|size| size.checked_add(84) ) == Some(data.len())
+}
+
+fn read_binary(data: &[u8]) -> Result<Vec<[Point3; 3]>, String> {
+  let float = |bytes: &[u8]| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64;
+  Ok(data[84..].chunks_exact(50).map(|facet| {
+    // Skip the stored normal
+    let point = |i: usize| {
+      let coords = &facet[12 + i * 12..];
+      Point3::new(float(coords), float(&coords[4..]), float(&coords[8..]))
+    };
+    [point(0), point(1), point(2)]
+  }).collect())
+}
+
+fn read_ascii(data: &[u8]) -> Result<Vec<[Point3; 3]>, String> {
+  let text = std::str::from_utf8(data).map_err(|_| "STL file is neither binary nor ASCII".to_string() )?;
+  if !text.trim_start().starts_with("solid") { return Err("STL file is neither binary nor ASCII".into()) }
+  let mut triangles = vec![];
+  let mut vertices = vec![];
+  for (i, line) in text.lines().enumerate() {
+    let mut words = line.split_whitespace();
+    match words.next() {
+      Some("vertex") => {
+        let coords: Vec<f64> = words.map(|word| word.parse::<f64>() ).collect::<Result<_, _>>()
+        .map_err(|_| format!("Invalid vertex on line {} of STL file", i + 1) )?;
+        if coords.len() != 3 { return Err(format!("Invalid vertex on line {} of STL file", i + 1)) }
+        vertices.push(Point3::new(coords[0], coords[1], coords[2]));
+      },
+      Some("endloop") => {
+        if vertices.len() != 3 { return Err(format!("Facet without three vertices on line {} of STL file", i + 1)) }
+        triangles.push([vertices[0], vertices[1], vertices[2]]);
+        vertices.clear();
+      },
+      _ => {},
+    }
+  }
+  Ok(triangles)
+}
+
 
 #[cfg(test)]
 mod tests {
-use crate::mesh::Meshable;
-use crate::features::make_cube;
+  use super::*;
+  use crate::mesh::Meshable;
+  use crate::features::make_cube;
+
+  fn cube() -> Mesh {
+    make_cube(1.5, 1.5, 1.5).unwrap().solids[0].tesselate()
+  }
 
   #[test]
-  #[ignore]
   fn stl() {
-    let cube = &make_cube(1.5, 1.5, 1.5).unwrap().solids[0];
-    let _stl = super::export(&cube.tesselate(), "Cube");
+    let stl = export(&cube(), "Cube");
+    assert!(stl.starts_with("solid Cube"));
+    assert_eq!(stl.matches("facet normal").count(), 12);
+    assert!(!stl.contains("facet normal 0 0 0"));
+  }
+
+  #[test]
+  fn empty() {
+    assert_eq!(export(&Mesh::default(), "Empty"), "solid Empty\nendsolid Empty\n");
+    assert_eq!(export_binary(&Mesh::default(), "Empty").len(), 84);
+  }
+
+  #[test]
+  fn binary() {
+    let mesh = cube();
+    let data = export_binary(&mesh, "Cube");
+    assert_eq!(data.len(), 84 + 12 * 50);
+    // First facet normal
+    let normal = Vec3::new(
+      f32::from_le_bytes([data[84], data[85], data[86], data[87]]) as f64,
+      f32::from_le_bytes([data[88], data[89], data[90], data[91]]) as f64,
+      f32::from_le_bytes([data[92], data[93], data[94], data[95]]) as f64,
+    );
+    assert!((normal.magnitude() - 1.0).abs() < 1.0e-6);
+  }
+
+  #[test]
+  fn round_trip() {
+    let mesh = cube();
+    for imported in [import(export(&mesh, "Cube").as_bytes()).unwrap(), import(&export_binary(&mesh, "Cube")).unwrap()] {
+      assert_eq!(imported.faces.len(), mesh.faces.len());
+      assert_eq!(imported.normals.len(), mesh.faces.len());
+      // Welded into the eight corners of the cube
+      assert_eq!(imported.vertices.len(), 8);
+    }
+  }
+
+  #[test]
+  fn invalid() {
+    assert!(import(b"solid broken\nfacet normal 0 0 1\nouter loop\nvertex 0 0\nendloop\n").is_err());
+    assert!(import(&[0xff, 0xfe, 0x00]).is_err());
+    // Huge facet counts in binary headers
+    let mut data = vec![0; 84];
+    data[80..84].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(import(&data).is_err());
   }
 }
@@ -1,55 +1,504 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::internal::*;
 use crate::mesh::Mesh;
+use super::{Material, Object, Part};
 
+const MODEL_PATH: &str = "3D/3dmodel.model";
+const THUMBNAIL_PATH: &str = "Metadata/thumbnail.png";
+const MODEL_TYPE: &str = "http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel";
+const THUMBNAIL_TYPE: &str = "http://schemas.openxmlformats.org/package/2006/relationships/metadata/thumbnail";
 
-pub fn export(meshes: &Vec<Mesh>, unit: &str) -> String {
-  let mut file = format!(r##"<?xml version="1.0" encoding="UTF-8"?>
-<model unit="{}"
-  xml:lang="en-US"
-  xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
-  <metadata name="Application">Alchemy</metadata>
-  <resources>
-  <basematerials id="1">
-    <base name="Green" displaycolor="#21BB4CFF" />
-  </basematerials>"##, unit);
-  for (id, mesh) in meshes.iter().enumerate() {
-    file.push_str(&format!(r##"
-    <object id="{}" type="model" pid="1" pindex="0">
-      <mesh>
-        <vertices>"##, id + 2));
-    for vertex in &mesh.vertices {
-      file.push_str(&format!(
-        "\n<vertex x=\"{}\" y=\"{}\" z=\"{}\" />",
-        vertex.x, vertex.y, vertex.z,
-      ));
-    }
-    file.push_str(r##"
-        </vertices>
-        <triangles>"##);
-    for i in (0..mesh.faces.len() - 2).step_by(3) {
-      file.push_str(&format!(
-        "\n<triangle v1=\"{}\" v2=\"{}\" v3=\"{}\" />",
-        mesh.faces[i], mesh.faces[i + 1], mesh.faces[i + 2],
+
+#[derive(Debug, Clone)]
+pub struct Package {
+  pub unit: String,
+  pub materials: Vec<Material>,
+  pub root: Part,
+  pub thumbnail: Option<Vec<u8>>,
+}
+
+
+// Write a complete OPC package, ready to be saved as .3mf
+pub fn export(package: &Package) -> Result<Vec<u8>, String> {
+  let mut types = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml" />
+  <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml" />"#);
+  let mut rels = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/{}" Id="rel0" Type="{}" />"#, MODEL_PATH, MODEL_TYPE);
+  if package.thumbnail.is_some() {
+    types.push_str("\n  <Default Extension=\"png\" ContentType=\"image/png\" />");
+    rels.push_str(&format!("\n  <Relationship Target=\"/{}\" Id=\"rel1\" Type=\"{}\" />", THUMBNAIL_PATH, THUMBNAIL_TYPE));
+  }
+  types.push_str("\n</Types>\n");
+  rels.push_str("\n</Relationships>\n");
+  let model = export_model(package);
+  let mut files: Vec<(&str, &[u8])> = vec![
+    ("[Content_Types].xml", types.as_bytes()),
+    ("_rels/.rels", rels.as_bytes()),
+    (MODEL_PATH, model.as_bytes()),
+  ];
+  if let Some(thumbnail) = &package.thumbnail {
+    files.push((THUMBNAIL_PATH, thumbnail));
+  }
+  let mut writer = ZipWriter::new(Cursor::new(vec![]));
+  let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+  for (path, content) in files {
+    writer.start_file(path, options).map_err(|e| e.to_string() )?;
+    writer.write_all(content).map_err(|e| e.to_string() )?;
+  }
+  Ok(writer.finish().map_err(|e| e.to_string() )?.into_inner())
+}
+
+pub fn export_model(package: &Package) -> String {
+  let mut writer = ModelWriter { xml: String::new(), next_id: 1 };
+  if !package.materials.is_empty() {
+    writer.xml.push_str("\n    <basematerials id=\"1\">");
+    for material in &package.materials {
+      let [r, g, b, a] = material.color;
+      writer.xml.push_str(&format!(
+        "\n      <base name=\"{}\" displaycolor=\"#{:02X}{:02X}{:02X}{:02X}\" />",
+        escape(&material.name), r, g, b, a,
       ));
     }
-    file.push_str(r##"
-        </triangles>
-      </mesh>
-    </object>"##);
-  }
-  file.push_str(r##"
-    <object id="99" type="model">
-      <components>
-        <component objectid="2" />
-      </components>
-    </object>
+    writer.xml.push_str("\n    </basematerials>");
+    writer.next_id = 2;
+  }
+  let root_id = writer.part(&package.root);
+  let mut file = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<model unit="{}" xml:lang="en-US" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
+  <metadata name="Application">Alchemy</metadata>
+  <resources>{}
   </resources>
-  <build>"##);
-  for (id, _) in meshes.iter().enumerate() {
-    file.push_str(&format!("\n    <item objectid=\"{}\" printable=\"1\" />", id + 2));
-  }
-  file.push_str(r##"
-  </build>
-</model>
-"##);
+  <build>"#, package.unit, writer.xml);
+  if let Some(id) = root_id {
+    file.push_str(&format!("\n    <item objectid=\"{}\" transform=\"{}\" />", id, transform_string(&package.root.transform)));
+  }
+  file.push_str("\n  </build>\n</model>\n");
   file
 }
+
+
+struct ModelWriter {
+  xml: String,
+  next_id: usize,
+}
+
+impl ModelWriter {
+  // Referenced objects have to be defined first, so children are written before their parents
+  fn part(&mut self, part: &Part) -> Option<usize> {
+    if part.is_empty() { return None }
+    let mut components = vec![];
+    for object in &part.objects {
+      components.push((self.object(object), Matrix4::identity()));
+    }
+    for child in &part.children {
+      if let Some(id) = self.part(child) {
+        components.push((id, child.transform));
+      }
+    }
+    let id = self.id();
+    self.xml.push_str(&format!("\n    <object id=\"{}\" type=\"model\" name=\"{}\">\n      <components>", id, escape(&part.name)));
+    for (object_id, transform) in components {
+      self.xml.push_str(&format!("\n        <component objectid=\"{}\" transform=\"{}\" />", object_id, transform_string(&transform)));
+    }
+    self.xml.push_str("\n      </components>\n    </object>");
+    Some(id)
+  }
+
+  fn object(&mut self, object: &Object) -> usize {
+    let id = self.id();
+    let material = match object.material {
+      Some(index) => format!(" pid=\"1\" pindex=\"{}\"", index),
+      None => String::new(),
+    };
+    self.xml.push_str(&format!(
+      "\n    <object id=\"{}\" type=\"model\" name=\"{}\"{}>\n      <mesh>\n        <vertices>",
+      id, escape(&object.name), material,
+    ));
+    for vertex in &object.mesh.vertices {
+      self.xml.push_str(&format!("\n          <vertex x=\"{}\" y=\"{}\" z=\"{}\" />", vertex.x, vertex.y, vertex.z));
+    }
+    self.xml.push_str("\n        </vertices>\n        <triangles>");
+    for tri in object.mesh.faces.chunks_exact(3) {
+      self.xml.push_str(&format!("\n          <triangle v1=\"{}\" v2=\"{}\" v3=\"{}\" />", tri[0], tri[1], tri[2]));
+    }
+    self.xml.push_str("\n        </triangles>\n      </mesh>\n    </object>");
+    id
+  }
+
+  fn id(&mut self) -> usize {
+    self.next_id += 1;
+    self.next_id - 1
+  }
+}
+
+
+// 3MF matrices are written row by row for row vectors,
+// which is the column major layout of our matrices
+fn transform_string(transform: &Matrix4) -> String {
+  [transform.x, transform.y, transform.z, transform.w].iter()
+  .flat_map(|column| [column.x, column.y, column.z] )
+  .map(|value| value.to_string() )
+  .collect::<Vec<String>>().join(" ")
+}
+
+fn parse_transform(text: &str) -> Result<Matrix4, String> {
+  let m: Vec<f64> = text.split_whitespace().map(|value| value.parse::<f64>() ).collect::<Result<_, _>>()
+  .map_err(|_| format!("Invalid transform {}", text) )?;
+  if m.len() != 12 { return Err(format!("Invalid transform {}", text)) }
+  Ok(Matrix4::new(
+    m[0], m[1], m[2], 0.0,
+    m[3], m[4], m[5], 0.0,
+    m[6], m[7], m[8], 0.0,
+    m[9], m[10], m[11], 1.0,
+  ))
+}
+
+fn parse_color(text: &str) -> Result<[u8; 4], String> {
+  let hex = text.trim_start_matches('#');
+  let channel = |i: usize| u8::from_str_radix(hex.get(i * 2..i * 2 + 2).unwrap_or("x"), 16).map_err(|_| format!("Invalid color {}", text) );
+  match hex.len() {
+    6 => Ok([channel(0)?, channel(1)?, channel(2)?, 255]),
+    8 => Ok([channel(0)?, channel(1)?, channel(2)?, channel(3)?]),
+    _ => Err(format!("Invalid color {}", text)),
+  }
+}
+
+fn escape(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+  text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+
+// Opening, closing or self-closing XML tag
+struct Tag {
+  name: String,
+  attributes: HashMap<String, String>,
+  closing: bool,
+  empty: bool,
+}
+
+impl Tag {
+  fn get(&self, attribute: &str) -> Result<&str, String> {
+    self.attributes.get(attribute).map(|value| value.as_str() ).ok_or_else(|| format!("Missing attribute {} on {}", attribute, self.name) )
+  }
+
+  fn index(&self, attribute: &str) -> Result<usize, String> {
+    self.get(attribute)?.parse().map_err(|_| format!("Invalid attribute {} on {}", attribute, self.name) )
+  }
+
+  fn number(&self, attribute: &str) -> Result<f64, String> {
+    self.get(attribute)?.parse().map_err(|_| format!("Invalid attribute {} on {}", attribute, self.name) )
+  }
+}
+
+// Text content is of no interest for 3MF, so only tags are returned
+fn tags(xml: &str) -> Result<Vec<Tag>, String> {
+  let mut tags = vec![];
+  let mut rest = xml;
+  while let Some(start) = rest.find('<') {
+    rest = &rest[start + 1..];
+    if rest.starts_with("!--") {
+      let end = rest.find("-->").ok_or("Unterminated comment in XML")?;
+      rest = &rest[end + 3..];
+      continue
+    }
+    let end = rest.find('>').ok_or("Unterminated tag in XML")?;
+    let content = &rest[..end];
+    rest = &rest[end + 1..];
+    if content.starts_with('?') || content.starts_with('!') { continue }
+    let closing = content.starts_with('/');
+    let empty = content.ends_with('/');
+    let content = content.trim_start_matches('/').trim_end_matches('/');
+    let name_end = content.find(char::is_whitespace).unwrap_or(content.len());
+    let mut attributes = HashMap::new();
+    let mut attribute_text = &content[name_end..];
+    while let Some(equals) = attribute_text.find('=') {
+      let key = attribute_text[..equals].trim().to_string();
+      let value_text = attribute_text[equals + 1..].trim_start();
+      let quote = value_text.chars().next().ok_or("Invalid attribute in XML")?;
+      if quote != '"' && quote != '\'' { return Err("Invalid attribute in XML".into()) }
+      let value_end = value_text[1..].find(quote).ok_or("Invalid attribute in XML")?;
+      attributes.insert(key, unescape(&value_text[1..value_end + 1]));
+      attribute_text = &value_text[value_end + 2..];
+    }
+    tags.push(Tag { name: content[..name_end].to_string(), attributes, closing, empty });
+  }
+  Ok(tags)
+}
+
+
+fn read_archive(data: &[u8]) -> Result<HashMap<String, Vec<u8>>, String> {
+  let mut archive = ZipArchive::new(Cursor::new(data)).map_err(|e| e.to_string() )?;
+  let mut files = HashMap::new();
+  for i in 0..archive.len() {
+    let mut file = archive.by_index(i).map_err(|e| e.to_string() )?;
+    if file.is_dir() { continue }
+    let mut content = vec![];
+    file.read_to_end(&mut content).map_err(|e| e.to_string() )?;
+    files.insert(file.name().to_string(), content);
+  }
+  Ok(files)
+}
+
+pub fn import(data: &[u8]) -> Result<Package, String> {
+  let files = read_archive(data)?;
+  let text = |path: &str| files.get(path).map(|content| String::from_utf8_lossy(content).to_string() );
+  // Locate parts through the package relationships and fall back to the usual paths
+  let mut model_path = MODEL_PATH.to_string();
+  let mut thumbnail_path = THUMBNAIL_PATH.to_string();
+  if let Some(rels) = text("_rels/.rels") {
+    for tag in tags(&rels)?.iter().filter(|tag| tag.name == "Relationship" ) {
+      let target = tag.get("Target")?.trim_start_matches('/').to_string();
+      match tag.get("Type")? {
+        MODEL_TYPE => model_path = target,
+        THUMBNAIL_TYPE => thumbnail_path = target,
+        _ => {},
+      }
+    }
+  }
+  let model = text(&model_path).ok_or("3MF package contains no model")?;
+  let mut package = import_model(&model)?;
+  package.thumbnail = files.get(&thumbnail_path).cloned();
+  Ok(package)
+}
+
+// Resource objects, which either hold a mesh or components
+#[derive(Default)]
+struct Resource {
+  name: String,
+  material: Option<usize>,
+  mesh: Option<Mesh>,
+  components: Vec<(usize, Matrix4)>,
+}
+
+pub fn import_model(xml: &str) -> Result<Package, String> {
+  let mut unit = "millimeter".to_string();
+  let mut materials = vec![];
+  // Base material groups by id, mapped to indices into materials
+  let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+  let mut group = None;
+  let mut resources: HashMap<usize, Resource> = HashMap::new();
+  let mut current: Option<(usize, Resource)> = None;
+  let mut items = vec![];
+  for tag in tags(xml)? {
+    if tag.closing {
+      match tag.name.as_str() {
+        "object" => if let Some((id, resource)) = current.take() { resources.insert(id, resource); },
+        "basematerials" => group = None,
+        _ => {},
+      }
+      continue
+    }
+    match tag.name.as_str() {
+      "model" => if let Some(value) = tag.attributes.get("unit") { unit = value.clone() },
+      "basematerials" => {
+        let id = tag.index("id")?;
+        groups.insert(id, vec![]);
+        if !tag.empty { group = Some(id) }
+      },
+      "base" => if let Some(id) = group {
+        groups.get_mut(&id).unwrap().push(materials.len());
+        materials.push(Material {
+          name: tag.attributes.get("name").cloned().unwrap_or_default(),
+          color: parse_color(tag.get("displaycolor")?)?,
+        });
+      },
+      "object" => {
+        let id = tag.index("id")?;
+        let name = tag.attributes.get("name").cloned().unwrap_or_else(|| format!("Object {}", id) );
+        current = Some((id, Resource { name, ..Default::default() }));
+        if tag.attributes.contains_key("pid") {
+          let (pid, pindex) = (tag.index("pid")?, tag.index("pindex")?);
+          // Property groups other than base materials are ignored
+          let material = groups.get(&pid).and_then(|group| group.get(pindex) ).cloned();
+          current.as_mut().unwrap().1.material = material;
+        }
+      },
+      "mesh" => if let Some((_, resource)) = &mut current { resource.mesh = Some(Mesh::default()) },
+      "vertex" => if let Some(mesh) = current.as_mut().and_then(|(_, resource)| resource.mesh.as_mut() ) {
+        mesh.vertices.push(Point3::new(tag.number("x")?, tag.number("y")?, tag.number("z")?));
+      },
+      "triangle" => if let Some(mesh) = current.as_mut().and_then(|(_, resource)| resource.mesh.as_mut() ) {
+        let indices = [tag.index("v1")?, tag.index("v2")?, tag.index("v3")?];
+        if indices.iter().any(|&i| i >= mesh.vertices.len() ) { return Err("Triangle references missing vertex".into()) }
+        mesh.faces.extend(indices);
+      },
+      "component" => if let Some((_, resource)) = &mut current {
+        resource.components.push((tag.index("objectid")?, transform_attribute(&tag)?));
+      },
+      "item" => items.push((tag.index("objectid")?, transform_attribute(&tag)?)),
+      _ => {},
+    }
+  }
+  if items.is_empty() { return Err("3MF model contains no build items".into()) }
+  let root = if items.len() == 1 {
+    let mut root = build_part(items[0].0, &resources, 0)?;
+    root.transform = items[0].1;
+    root
+  } else {
+    let mut root = Part::new("Build", vec![]);
+    for (id, transform) in items {
+      let mut child = build_part(id, &resources, 0)?;
+      child.transform = transform;
+      root.children.push(child);
+    }
+    root
+  };
+  Ok(Package { unit, materials, root, thumbnail: None })
+}
+
+fn transform_attribute(tag: &Tag) -> Result<Matrix4, String> {
+  match tag.attributes.get("transform") {
+    Some(text) => parse_transform(text),
+    None => Ok(Matrix4::identity()),
+  }
+}
+
+// Meshes placed without a transform become objects of the part, everything else child parts
+fn build_part(id: usize, resources: &HashMap<usize, Resource>, depth: usize) -> Result<Part, String> {
+  if depth > 32 { return Err("Cyclic components in 3MF model".into()) }
+  let resource = resources.get(&id).ok_or_else(|| format!("Missing object {} in 3MF model", id) )?;
+  if let Some(mesh) = &resource.mesh {
+    return Ok(Part::new(&resource.name, vec![object(resource, mesh)]))
+  }
+  let mut part = Part::new(&resource.name, vec![]);
+  for &(component_id, transform) in &resource.components {
+    let component = resources.get(&component_id).ok_or_else(|| format!("Missing object {} in 3MF model", component_id) )?;
+    match &component.mesh {
+      Some(mesh) if transform == Matrix4::identity() => part.objects.push(object(component, mesh)),
+      _ => {
+        let mut child = build_part(component_id, resources, depth + 1)?;
+        child.transform = transform;
+        part.children.push(child);
+      },
+    }
+  }
+  Ok(part)
+}
+
+fn object(resource: &Resource, mesh: &Mesh) -> Object {
+  let mut mesh = mesh.clone();
+  mesh.compute_normals();
+  Object {
+    name: resource.name.clone(),
+    mesh,
+    material: resource.material,
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mesh::Meshable;
+  use crate::features::make_cube;
+
+  fn package() -> Package {
+    let mesh = make_cube(1.0, 2.0, 3.0).unwrap().solids[0].tesselate();
+    let mut child = Part::new("Wheel", vec![Object { name: "Tire".into(), mesh: mesh.clone(), material: Some(1) }]);
+    child.transform = Matrix4::from_translation(Vec3::new(10.0, 0.0, -5.0)) * Matrix4::from_angle_z(Deg(90.0));
+    let mut root = Part::new("Car & Co", vec![Object { name: "Body".into(), mesh, material: Some(0) }]);
+    root.children.push(child);
+    Package {
+      unit: "millimeter".into(),
+      materials: vec![
+        Material { name: "Green".into(), color: [0x21, 0xBB, 0x4C, 0xFF] },
+        Material { name: "Rubber".into(), color: [0x10, 0x10, 0x10, 0x80] },
+      ],
+      root,
+      thumbnail: Some(vec![0x89, b'P', b'N', b'G']),
+    }
+  }
+
+  #[test]
+  fn model() {
+    let xml = export_model(&package());
+    assert!(xml.contains("displaycolor=\"#10101080\""));
+    assert!(xml.contains("name=\"Car &amp; Co\""));
+    assert_eq!(xml.matches("<item ").count(), 1);
+    assert_eq!(xml.matches("<component ").count(), 3);
+  }
+
+  #[test]
+  fn round_trip() {
+    let original = package();
+    let package = import(&export(&original).unwrap()).unwrap();
+    assert_eq!(package.unit, "millimeter");
+    assert_eq!(package.materials, original.materials);
+    assert_eq!(package.thumbnail, original.thumbnail);
+    assert_eq!(package.root.name, "Car & Co");
+    assert_eq!(package.root.objects.len(), 1);
+    assert_eq!(package.root.objects[0].material, Some(0));
+    assert_eq!(package.root.objects[0].mesh.faces, original.root.objects[0].mesh.faces);
+    assert_eq!(package.root.objects[0].mesh.normals.len(), original.root.objects[0].mesh.faces.len());
+    let child = &package.root.children[0];
+    assert_eq!(child.name, "Wheel");
+    assert_eq!(child.objects[0].material, Some(1));
+    let p = Point3::new(1.0, 2.0, 3.0);
+    assert!(child.transform.transform_point(p).almost(original.root.children[0].transform.transform_point(p)));
+  }
+
+  #[test]
+  fn flatten() {
+    let package = package();
+    let mesh = package.root.flatten();
+    assert_eq!(mesh.faces.len(), package.root.objects[0].mesh.faces.len() * 2);
+    assert!(mesh.vertices.iter().any(|vertex| vertex.x > 9.0 ));
+  }
+
+  #[test]
+  fn empty() {
+    let package = Package {
+      unit: "millimeter".into(),
+      materials: vec![],
+      root: Part::new("Empty", vec![]),
+      thumbnail: None,
+    };
+    let xml = export_model(&package);
+    assert!(!xml.contains("<object"));
+    assert!(!xml.contains("<item"));
+    assert!(import(&export(&package).unwrap()).is_err());
+    assert!(import(b"Not a zip archive at all").is_err());
+  }
+
+  #[test]
+  fn foreign_model() {
+    let package = import_model(r##"<?xml version="1.0" encoding="UTF-8"?>
+      <model unit="inch" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
+        <!-- <item objectid="9" /> -->
+        <resources>
+          <basematerials id="5"><base name="Red" displaycolor="#FF0000" /></basematerials>
+          <object id="1" type="model" pid="5" pindex="0">
+            <mesh>
+              <vertices>
+                <vertex x="0" y="0" z="0" /><vertex x="1" y="0" z="0" /><vertex x='0' y='1' z='0' />
+              </vertices>
+              <triangles><triangle v1="0" v2="1" v3="2" /></triangles>
+            </mesh>
+          </object>
+        </resources>
+        <build>
+          <item objectid="1" />
+          <item objectid="1" transform="1 0 0 0 1 0 0 0 1 5 0 0" />
+        </build>
+      </model>
+    "##).unwrap();
+    assert_eq!(package.unit, "inch");
+    assert_eq!(package.materials[0].color, [255, 0, 0, 255]);
+    assert_eq!(package.root.children.len(), 2);
+    let object = &package.root.children[1].objects[0];
+    assert_eq!(object.material, Some(0));
+    assert_eq!(object.mesh.normals[0], Vec3::unit_z());
+    assert_eq!(package.root.children[1].transform.w.x, 5.0);
+  }
+}
@@ -1,13 +1,19 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
 use crate::internal::*;
 use crate::transform::*;
 
+// Vertices closer than this are considered identical when healing
+const HEAL_TOLERANCE: f64 = 1.0e-6;
+
 
 pub trait Meshable {
   fn tesselate(&self) -> Mesh;
 }
 
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct Mesh {
   pub vertices: Vec<Point3>,
   pub faces: Vec<usize>,
@@ -26,32 +32,185 @@ impl Mesh {
     (positions, normals)
   }
 
+  // Coincident vertices are kept apart, use heal or weld to merge them
   pub fn append(&mut self, mut other: Self) {
-    let offset = self.vertices.len() as i32;
-    let other_faces: Vec<i32> = other.faces.iter().map(|&f| f as i32 ).collect();
-    // for (i, vertex) in self.vertices.iter().enumerate() {
-    //   for (j, other_vertex) in other.vertices.iter().enumerate() {
-    //     if vertex == other_vertex {
-    //       // Duplicate found
-    //       // -> Update new faces to point to existing vertex
-    //       for face in &mut other_faces {
-    //         if *face == j as i32 {
-    //           *face = i as i32 - offset;
-    //         }
-    //       }
-    //       break
-    //     }
-    //   }
-    // }
-    //XXX remove duplicate vertices from other
+    let offset = self.vertices.len();
     self.vertices.append(&mut other.vertices);
-    self.faces.append(&mut other_faces.iter().map(|&f| (f + offset) as usize ).collect());
+    self.faces.extend(other.faces.into_iter().map(|i| i + offset ));
     self.normals.append(&mut other.normals);
   }
 
-  pub fn invert_normals(&mut self) { todo!() }
+  // Merge vertices closer to each other than tolerance
+  pub fn weld(&mut self, tolerance: f64) {
+    let mut hash = SpatialHash::new(tolerance);
+    let mut vertices = vec![];
+    let indices: Vec<usize> = self.vertices.iter().map(|&vertex| hash.add(vertex, &mut vertices) ).collect();
+    self.vertices = vertices;
+    for face in &mut self.faces {
+      *face = indices[*face];
+    }
+  }
+
+  pub fn invert_normals(&mut self) {
+    for i in 0..self.faces.len() / 3 {
+      self.flip_triangle(i);
+    }
+    for normal in &mut self.normals {
+      *normal = -*normal;
+    }
+  }
+
+  // Flat shading for meshes that come without normals
+  pub fn compute_normals(&mut self) {
+    self.normals = (0..self.faces.len() / 3).flat_map(|i| {
+      let normal = self.triangle_normal(i);
+      [if normal.magnitude() > 0.0 { normal.normalize() } else { normal }; 3]
+    }).collect();
+  }
+
+  // Prepare meshes from arbitrary sources for export
+  pub fn heal(&mut self) {
+    self.weld(HEAL_TOLERANCE);
+    self.remove_degenerate_triangles(HEAL_TOLERANCE);
+    self.orient_consistently();
+    self.fill_holes();
+    self.orient_consistently();
+    self.align_normals();
+  }
+
+  pub fn remove_degenerate_triangles(&mut self, tolerance: f64) {
+    let keep: Vec<bool> = (0..self.faces.len() / 3).map(|i| {
+      let [a, b, c] = self.triangle(i);
+      a != b && b != c && c != a && self.triangle_normal(i).magnitude() > tolerance * tolerance
+    }).collect();
+    let mut i = 0;
+    self.faces.retain(|_| { i += 1; keep[(i - 1) / 3] });
+    let mut i = 0;
+    self.normals.retain(|_| { i += 1; keep[(i - 1) / 3] });
+  }
+
+  // Flip triangles to agree with their neighbours in the winding of shared edges.
+  // Closed parts of the mesh get turned outwards.
+  pub fn orient_consistently(&mut self) {
+    let num_triangles = self.faces.len() / 3;
+    let mut adjacency: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for i in 0..num_triangles {
+      for (a, b) in self.triangle_edges(i) {
+        adjacency.entry((a.min(b), a.max(b))).or_default().push(i);
+      }
+    }
+    let mut visited = vec![false; num_triangles];
+    for seed in 0..num_triangles {
+      if visited[seed] { continue }
+      visited[seed] = true;
+      let mut component = vec![seed];
+      let mut stack = vec![seed];
+      let mut is_closed = true;
+      while let Some(i) = stack.pop() {
+        for (a, b) in self.triangle_edges(i) {
+          let neighbours = &adjacency[&(a.min(b), a.max(b))];
+          // Don't propagate across non-manifold edges
+          if neighbours.len() != 2 {
+            is_closed = false;
+            continue
+          }
+          let other = if neighbours[0] == i { neighbours[1] } else { neighbours[0] };
+          if visited[other] { continue }
+          visited[other] = true;
+          if self.triangle_edges(other).contains(&(a, b)) { self.flip_triangle(other) }
+          component.push(other);
+          stack.push(other);
+        }
+      }
+      if is_closed && self.signed_volume(&component) < 0.0 {
+        for &i in &component { self.flip_triangle(i) }
+      }
+    }
+  }
+
+  // Close open boundaries with triangulated polygons
+  pub fn fill_holes(&mut self) {
+    let edges: HashSet<(usize, usize)> = (0..self.faces.len() / 3).flat_map(|i| self.triangle_edges(i) ).collect();
+    // Fill polygons run against the open edges of the triangles around them
+    let mut next: HashMap<usize, usize> = edges.iter()
+    .filter(|&&(a, b)| !edges.contains(&(b, a)) )
+    .map(|&(a, b)| (b, a) )
+    .collect();
+    while let Some(&start) = next.keys().next() {
+      let mut ring = vec![start];
+      let mut current = next.remove(&start).unwrap();
+      while current != start {
+        ring.push(current);
+        match next.remove(&current) {
+          Some(vertex) => current = vertex,
+          None => break,
+        }
+      }
+      if current == start && ring.len() >= 3 { self.fill_polygon(&ring) }
+    }
+  }
+
+  fn fill_polygon(&mut self, ring: &[usize]) {
+    let points: Vec<Point3> = ring.iter().map(|&i| self.vertices[i] ).collect();
+    // Newell's method
+    let normal = points.iter().zip(points.iter().cycle().skip(1)).fold(Vec3::new(0.0, 0.0, 0.0), |acc, (a, b)| {
+      acc + Vec3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y))
+    });
+    if normal.magnitude() < EPSILON { return }
+    let normal = normal.normalize();
+    let helper = if normal.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_y() };
+    let u = normal.cross(helper).normalize();
+    let v = normal.cross(u);
+    let flat_vertices: Vec<f64> = points.iter().flat_map(|p| vec![p.to_vec().dot(u), p.to_vec().dot(v)] ).collect();
+    for tri in earcutr::earcut(&flat_vertices, &[], 2).chunks(3) {
+      let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| ring[i] );
+      let (a, b, c) = if (self.vertices[b] - self.vertices[a]).cross(self.vertices[c] - self.vertices[a]).dot(normal) < 0.0 {
+        (a, c, b)
+      } else {
+        (a, b, c)
+      };
+      self.faces.extend([a, b, c]);
+      self.normals.extend([normal; 3]);
+    }
+  }
+
+  // Make vertex normals point to the same side as their triangle
+  fn align_normals(&mut self) {
+    for i in 0..self.faces.len() / 3 {
+      let normal = self.triangle_normal(i);
+      for normal_of_vertex in &mut self.normals[i * 3..i * 3 + 3] {
+        if normal_of_vertex.dot(normal) < 0.0 { *normal_of_vertex = -*normal_of_vertex }
+      }
+    }
+  }
+
+  fn triangle(&self, i: usize) -> [usize; 3] {
+    [self.faces[i * 3], self.faces[i * 3 + 1], self.faces[i * 3 + 2]]
+  }
+
+  fn triangle_edges(&self, i: usize) -> [(usize, usize); 3] {
+    let [a, b, c] = self.triangle(i);
+    [(a, b), (b, c), (c, a)]
+  }
 
-  pub fn heal(&mut self) { todo!() }
+  fn triangle_normal(&self, i: usize) -> Vec3 {
+    let [a, b, c] = self.triangle(i).map(|i| self.vertices[i] );
+    (b - a).cross(c - a)
+  }
+
+  fn flip_triangle(&mut self, i: usize) {
+    self.faces.swap(i * 3 + 1, i * 3 + 2);
+    if self.normals.len() == self.faces.len() {
+      self.normals.swap(i * 3 + 1, i * 3 + 2);
+    }
+  }
+
+  fn signed_volume(&self, triangles: &[usize]) -> f64 {
+    triangles.iter().map(|&i| {
+      let [a, b, c] = self.triangle(i).map(|i| self.vertices[i].to_vec() );
+      a.dot(b.cross(c)) / 6.0
+    }).sum()
+  }
 }
 
 impl Transformable for Mesh {
@@ -59,8 +218,182 @@ impl Transformable for Mesh {
     for vertex in &mut self.vertices {
       *vertex = transform.transform_point(*vertex);
     }
-    // for normal in &mut self.normals {
-    //   *normal = transform.apply_vec(*normal);
-    // }
+    // Normals transform with the inverse transpose
+    if let Some(inverse) = transform.invert() {
+      let normal_transform = inverse.transpose();
+      for normal in &mut self.normals {
+        // Degenerate triangles carry zero normals, which must not turn into NaN
+        let transformed = normal_transform.transform_vector(*normal);
+        *normal = if transformed.magnitude() > 0.0 { transformed.normalize() } else { transformed };
+      }
+    }
+    // Mirroring reverses the winding of triangles
+    if transform.determinant() < 0.0 {
+      for i in 0..self.faces.len() / 3 {
+        self.flip_triangle(i);
+      }
+    }
+  }
+}
+
+
+// Sorts points into cubic cells, so that coincident points are found by looking at neighbouring cells only
+#[derive(Debug)]
+pub(crate) struct SpatialHash {
+  cell_size: f64,
+  cells: HashMap<(i64, i64, i64), Vec<usize>>,
+}
+
+impl SpatialHash {
+  pub(crate) fn new(tolerance: f64) -> Self {
+    Self {
+      cell_size: tolerance.max(EPSILON),
+      cells: HashMap::new(),
+    }
+  }
+
+  fn cell(&self, p: Point3) -> (i64, i64, i64) {
+    ((p.x / self.cell_size).floor() as i64, (p.y / self.cell_size).floor() as i64, (p.z / self.cell_size).floor() as i64)
+  }
+
+  fn insert(&mut self, p: Point3, index: usize) {
+    let cell = self.cell(p);
+    self.cells.entry(cell).or_default().push(index);
+  }
+
+  fn find(&self, p: Point3, vertices: &[Point3]) -> Option<usize> {
+    let (x, y, z) = self.cell(p);
+    for i in x - 1..=x + 1 {
+      for j in y - 1..=y + 1 {
+        for k in z - 1..=z + 1 {
+          let found = self.cells.get(&(i, j, k)).and_then(|indices| {
+            indices.iter().find(|&&index| vertices[index].distance(p) <= self.cell_size ).cloned()
+          });
+          if found.is_some() { return found }
+        }
+      }
+    }
+    None
+  }
+
+  // Index of an existing vertex close to p, or of p appended to vertices
+  pub(crate) fn add(&mut self, p: Point3, vertices: &mut Vec<Point3>) -> usize {
+    self.find(p, vertices).unwrap_or_else(|| {
+      vertices.push(p);
+      self.insert(p, vertices.len() - 1);
+      vertices.len() - 1
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::features::make_cube;
+
+  fn cube_mesh() -> Mesh {
+    make_cube(1.0, 2.0, 3.0).unwrap().solids[0].tesselate()
+  }
+
+  fn is_watertight(mesh: &Mesh) -> bool {
+    let edges: HashSet<(usize, usize)> = (0..mesh.faces.len() / 3).flat_map(|i| mesh.triangle_edges(i) ).collect();
+    edges.len() == mesh.faces.len() && edges.iter().all(|&(a, b)| edges.contains(&(b, a)) )
+  }
+
+  fn volume(mesh: &Mesh) -> f64 {
+    mesh.signed_volume(&(0..mesh.faces.len() / 3).collect::<Vec<_>>())
+  }
+
+  #[test]
+  fn weld() {
+    let mut mesh = Mesh {
+      vertices: vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0), Point3::new(1.0, 0.0, 1.0e-9)],
+      faces: vec![0, 1, 2, 3, 2, 1],
+      normals: vec![Vec3::unit_z(); 6],
+    };
+    mesh.weld(1.0e-6);
+    assert_eq!(mesh.vertices.len(), 3);
+    assert_eq!(mesh.faces, vec![0, 1, 2, 1, 2, 1]);
+  }
+
+  #[test]
+  fn append() {
+    let mut mesh = cube_mesh();
+    let num_vertices = mesh.vertices.len();
+    mesh.append(cube_mesh());
+    assert_eq!(mesh.vertices.len(), num_vertices * 2);
+    assert_eq!(mesh.normals.len(), mesh.faces.len());
+    assert!(mesh.faces[mesh.faces.len() / 2..].iter().all(|&i| i >= num_vertices ));
+    mesh.heal();
+    assert_eq!(mesh.vertices.len(), num_vertices);
+  }
+
+  #[test]
+  fn invert_normals() {
+    let mut mesh = cube_mesh();
+    let normal = mesh.normals[0];
+    mesh.invert_normals();
+    assert!(volume(&mesh) < 0.0);
+    assert_eq!(mesh.normals[0], -normal);
+    assert!(is_watertight(&mesh));
+  }
+
+  #[test]
+  fn heal() {
+    let mut mesh = cube_mesh();
+    // Remove two triangles, flip another and add a degenerate one
+    mesh.faces.drain(0..6);
+    mesh.normals.drain(0..6);
+    mesh.flip_triangle(3);
+    mesh.faces.extend([0, 0, 1]);
+    mesh.normals.extend([Vec3::unit_z(); 3]);
+    assert!(!is_watertight(&mesh));
+    mesh.heal();
+    assert!(is_watertight(&mesh));
+    assert!((volume(&mesh) - 6.0).abs() < 1.0e-9);
+    assert_eq!(mesh.normals.len(), mesh.faces.len());
+    for i in 0..mesh.faces.len() / 3 {
+      assert!(mesh.normals[i * 3].dot(mesh.triangle_normal(i)) > 0.0);
+    }
+  }
+
+  #[test]
+  fn heal_inverted() {
+    let mut mesh = cube_mesh();
+    mesh.invert_normals();
+    mesh.heal();
+    assert!((volume(&mesh) - 6.0).abs() < 1.0e-9);
+  }
+
+  #[test]
+  fn transform_normals() {
+    let mut mesh = cube_mesh();
+    mesh.transform(&Matrix4::from_nonuniform_scale(-2.0, 1.0, 1.0));
+    assert!((volume(&mesh) - 12.0).abs() < 1.0e-9);
+    for i in 0..mesh.faces.len() / 3 {
+      let normal = mesh.triangle_normal(i).normalize();
+      assert!(mesh.normals[i * 3].dot(normal) > 1.0 - 1.0e-9);
+    }
+  }
+
+  #[test]
+  fn transform_zero_normals() {
+    let mut mesh = cube_mesh();
+    mesh.normals[0] = Vec3::new(0.0, 0.0, 0.0);
+    mesh.transform(&Matrix4::from_scale(2.0));
+    assert_eq!(mesh.normals[0], Vec3::new(0.0, 0.0, 0.0));
+  }
+
+  #[test]
+  fn compute_normals() {
+    let mut mesh = cube_mesh();
+    let normals = mesh.normals.clone();
+    mesh.normals.clear();
+    mesh.compute_normals();
+    assert_eq!(mesh.normals.len(), mesh.faces.len());
+    for (normal, expected) in mesh.normals.iter().zip(normals) {
+      assert!(normal.dot(expected) > 1.0 - 1.0e-9);
+    }
   }
 }
@@ -5,20 +5,28 @@ use std::collections::HashSet;
 use uuid::Uuid;
 
 use crate::internal::*;
+use crate::transform::*;
 use crate::curve::*;
 use crate::surface::*;
+use crate::geom2d;
 
 mod volume;
 mod boolean;
 mod tesselation;
-mod serde;
+pub(crate) mod serde;
 mod repair;
+mod euler;
+mod validation;
 
 pub mod features;
 
 pub use boolean::Boolean;
 pub use boolean::BooleanType;
 pub use volume::Volume;
+pub use volume::MassProperties;
+pub use validation::ValidationReport;
+pub use validation::ValidationProblem;
+pub use validation::ProblemType;
 
 // use crate::log;
 
@@ -99,6 +107,10 @@ impl Compound {
     None
   }
 
+  pub fn find_edge(&self, id: Uuid) -> Option<&Ref<Edge>> {
+    self.solids.iter().find_map(|solid| solid.find_edge(id) )
+  }
+
   pub fn find_face_from_bounds(&self, ids: &HashSet<Uuid>) -> Option<&Ref<Face>> {
     self.faces_iter().find(|face| {
       let hashset = face.borrow().get_edge_ids();
@@ -116,6 +128,15 @@ impl Compound {
 }
 
 
+impl Transformable for Compound {
+  fn transform(&mut self, transform: &Matrix4) {
+    for solid in &mut self.solids {
+      solid.transform(transform);
+    }
+  }
+}
+
+
 impl Solid {
   pub fn new() -> Self {
     Self {
@@ -124,13 +145,15 @@ impl Solid {
     }
   }
 
-  pub fn new_lamina(wire: Wire, top_surface: SurfaceType) -> Self {
+  // The first wire of the profile becomes the outer ring of the lamina, all others become holes
+  pub fn new_lamina(profile: &Profile, top_surface: SurfaceType) -> Self {
     println!("Creating Lamina:");
+    let wire = &profile[0];
     let mut bottom = top_surface.clone();
     bottom.as_surface_mut().flip();
     let mut this = Self::new();
     // Create shell from bottom face with empty ring
-    this.mvfs(wire[0].bounds.0, bottom);
+    this.lmvfs(wire[0].bounds.0, bottom);
     let shell = &mut this.shells[0];
     // Complete ring of bottom face
     let mut he = shell.vertices.last().unwrap().borrow().get_half_edge();
@@ -145,7 +168,22 @@ impl Solid {
     // let he2 = shell.edges.last().unwrap().borrow().left_half.clone();
     let he1 = shell.vertices[0].borrow().get_half_edge();
     let he2 = shell.vertices.last().unwrap().borrow().get_half_edge();
-    shell.lmef(&he1, &he2, wire.last().unwrap().base.clone(), top_surface);
+    let (_, top) = shell.lmef(&he1, &he2, wire.last().unwrap().base.clone(), top_surface.clone());
+    // Make holes
+    let bottom = shell.faces[0].clone();
+    for hole in profile.iter().skip(1) {
+      // Inner rings run opposite to the outer ring
+      let hole = if area_vector(hole).dot(area_vector(wire)) < 0.0 {
+        hole.iter().rev().map(|tcurve| {
+          let mut tcurve = tcurve.clone();
+          tcurve.flip();
+          tcurve
+        }).collect()
+      } else {
+        hole.clone()
+      };
+      shell.make_hole(&top, &bottom, &hole, top_surface.clone());
+    }
     this
   }
 
@@ -163,12 +201,11 @@ impl Solid {
     None
   }
 
-  pub fn validate(&self) -> Result<(), String> {
-    for shell in &self.shells { shell.validate()? }
-    Ok(())
+  pub fn find_edge(&self, id: Uuid) -> Option<&Ref<Edge>> {
+    self.shells.iter().find_map(|shell| shell.find_edge(id) )
   }
 
-  pub fn mvfs(&mut self, p: Point3, surface: SurfaceType) -> (Ref<Vertex>, Ref<Face>, &mut Shell) {
+  pub fn lmvfs(&mut self, p: Point3, surface: SurfaceType) -> (Ref<Vertex>, Ref<Face>, &mut Shell) {
     let mut shell = Shell {
       faces: vec![],
       edges: vec![],
@@ -221,6 +258,15 @@ impl Solid {
 }
 
 
+impl Transformable for Solid {
+  fn transform(&mut self, transform: &Matrix4) {
+    for shell in &mut self.shells {
+      shell.transform(transform);
+    }
+  }
+}
+
+
 impl Shell {
   pub fn euler_characteristics(&self) -> i32 {
     let num_faces = self.faces.len() as i32;
@@ -249,12 +295,9 @@ impl Shell {
     None
   }
 
-  pub fn validate(&self) -> Result<(), String> {
-    // Closed shells have odd connectivity
-    if self.connectivity() % 2 == 0 {
-      return Err("Open shell".into())
-    }
-    Ok(())
+  // Edges are found by the id of their curve, which stays the same when features get regenerated
+  pub fn find_edge(&self, id: Uuid) -> Option<&Ref<Edge>> {
+    self.edges.iter().find(|edge| edge.borrow().curve.get_id() == id )
   }
 
   pub fn lmev(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>, curve: CurveType, p: Point3) -> (Ref<Edge>, Ref<Vertex>) {
@@ -264,13 +307,9 @@ impl Shell {
     });
     let mut he = he1.clone();
     while !Rc::ptr_eq(&he, he2) {
-      he = {
-        let mut heb = he.borrow_mut();
-        heb.origin = vertex.clone();
-        let mate = heb.mate();
-        let next = &mate.borrow().next;
-        next.upgrade().unwrap().clone()
-      }
+      he.borrow_mut().origin = vertex.clone();
+      let next = he.borrow().mate().borrow().get_next();
+      he = next;
     }
     let origin = he2.borrow().origin.clone();
     let right_half = if he1.borrow().edge.upgrade().is_some() {
@@ -359,7 +398,7 @@ impl Shell {
       let temp = nhe1b.previous.clone();
       nhe1b.previous = nhe2b.previous.clone();
       nhe2b.previous = temp;
-      // nhe1b.ring = Rc::downgrade(&ring);
+      nhe1b.ring = Rc::downgrade(&ring);
     }
     he2.borrow().ring.upgrade().unwrap().borrow_mut().half_edge = nhe2;
     self.edges.push(edge.clone());
@@ -367,56 +406,128 @@ impl Shell {
     (edge, face)
   }
 
+  // Cut a hole through a lamina. The hole's ring gets connected to the outer ring of the top face
+  // by a bridge edge, which is killed again once the ring is closed. The face inside the hole
+  // then turns into an inner ring of the bottom face.
+  fn make_hole(&mut self, top: &Ref<Face>, bottom: &Ref<Face>, wire: &Wire, surface: SurfaceType) {
+    let he = top.borrow().outer_ring.borrow().half_edge.clone();
+    let start = he.borrow().origin.borrow().point;
+    let p = wire[0].bounds.0;
+    let (bridge, _) = self.lmev(&he, &he, Line::new(start, p).into_enum(), p);
+    let mut tip = bridge.borrow().left_half.clone();
+    let mut first = None;
+    for elem in wire.iter().take(wire.len() - 1) {
+      let (edge, _) = self.lmev(&tip, &tip, elem.base.clone(), elem.bounds.1);
+      first.get_or_insert_with(|| edge.borrow().right_half.clone() );
+      tip = edge.borrow().left_half.clone();
+    }
+    let first = first.unwrap_or_else(|| tip.clone() );
+    let (_, inside) = self.lmef(&first, &tip, wire.last().unwrap().base.clone(), surface);
+    let (he1, he2) = {
+      let bridge = bridge.borrow();
+      (bridge.right_half.clone(), bridge.left_half.clone())
+    };
+    self.lkemr(&he1, &he2);
+    self.lkfmrh(bottom, &inside);
+  }
+
   pub fn sweep<C,S>(&mut self, face: &Ref<Face>, transform: &Matrix4, make_curve: C, make_surface: S)
   where
     C: Fn(Point3) -> CurveType,
     S: Fn(&TrimmedCurve) -> SurfaceType,
   {
-    for ring in &face.borrow().rings {
+    let mut surface = face.borrow().surface.clone();
+    surface.as_surface_mut().transform(transform);
+    self.sweep_with(
+      face,
+      |point| transform.transform_point(point),
+      |tcurve| {
+        let mut curve = tcurve.base.clone();
+        curve.as_curve_mut().transform(transform);
+        curve
+      },
+      make_curve,
+      make_surface,
+      surface,
+    );
+  }
+
+  // Sweep a face, placing its vertices and edges at the far end by the given functions
+  pub fn sweep_with<P,M,C,S>(&mut self, face: &Ref<Face>, map_point: P, map_curve: M, make_curve: C, make_surface: S, surface: SurfaceType)
+  where
+    P: Fn(Point3) -> Point3,
+    M: Fn(&TrimmedCurve) -> CurveType,
+    C: Fn(Point3) -> CurveType,
+    S: Fn(&TrimmedCurve) -> SurfaceType,
+  {
+    let rings = face.borrow().rings.clone();
+    for ring in &rings {
       let first = ring.borrow().half_edge.clone();
       let mut scan = first.borrow().get_next();
-      self.sweep_mev(&scan, transform, &make_curve);
+      self.sweep_mev(&scan, &map_point, &make_curve);
       while !Rc::ptr_eq(&scan, &first) {
         scan = {
           let scan_next = scan.borrow().get_next();
-          self.sweep_mev(&scan_next, transform, &make_curve);
-          self.sweep_mef(&scan, transform, &make_surface);
+          self.sweep_mev(&scan_next, &map_point, &make_curve);
+          self.sweep_mef(&scan, &map_curve, &make_surface);
           let scanb = scan.borrow();
           scanb.get_next().borrow().mate().borrow().get_next()
         }
       }
-      self.sweep_mef(&scan, transform, &make_surface);
+      self.sweep_mef(&scan, &map_curve, &make_surface);
     }
-    face.borrow_mut().surface.as_surface_mut().transform(transform);
+    face.borrow_mut().surface = surface;
   }
 
-  fn sweep_mev<C: Fn(Point3) -> CurveType>(&mut self, scan: &Ref<HalfEdge>, transform: &Matrix4, make_curve: C) {
+  fn sweep_mev<P: Fn(Point3) -> Point3, C: Fn(Point3) -> CurveType>(&mut self, scan: &Ref<HalfEdge>, map_point: P, make_curve: C) {
     let point = scan.borrow().origin.borrow().point;
     let curve = make_curve(point);
-    self.lmev(scan, scan, curve, transform.transform_point(point));
+    self.lmev(scan, scan, curve, map_point(point));
   }
 
-  fn sweep_mef<S: Fn(&TrimmedCurve) -> SurfaceType>(&mut self, scan: &Ref<HalfEdge>, transform: &Matrix4, make_surface: S) {
+  fn sweep_mef<M: Fn(&TrimmedCurve) -> CurveType, S: Fn(&TrimmedCurve) -> SurfaceType>(&mut self, scan: &Ref<HalfEdge>, map_curve: M, make_surface: S) {
     let scan_previous = scan.borrow().get_previous();
     let next = scan.borrow().get_next();
     let next_next = next.borrow().get_next();
-    let mut curve = scan.borrow().get_edge().borrow().curve.clone();
-    curve.as_curve_mut().transform(transform);
+    let tcurve = scan.borrow().make_curve();
+    let mut curve = map_curve(&tcurve);
     // Create new stable id for cloned curve
-    let curve_id = curve.get_id();
+    let curve_id = tcurve.base.get_id();
     let fields = curve_id.as_fields();
     curve.set_id(Uuid::from_fields(fields.0, fields.1 + 1, fields.2, fields.3).unwrap());
     // Sweep actual surface
-    let surface = make_surface(&scan.borrow().make_curve());
+    let surface = make_surface(&tcurve);
     // let p1 = scan_previous.borrow().origin.borrow().point;
     // let p2 = next_next.borrow().origin.borrow().point;
-    self.lmef(
+    let (_, new_face) = self.lmef(
       // New edge is oriented from..
       &scan_previous, // ..this half edge's vertex..
       &next_next, // ..to this half edge's vertex
       curve,
       surface,
     );
+    // Rings made of a single edge get split the other way around,
+    // leaving the swept face with the ring of the new side face
+    if Rc::ptr_eq(&scan_previous, &next_next) {
+      let wall_ring = scan.borrow().ring.upgrade().unwrap();
+      let cap_ring = new_face.borrow().outer_ring.clone();
+      Self::exchange_rings(&wall_ring, &cap_ring);
+    }
+  }
+
+  // Move two rings over to each other's face
+  fn exchange_rings(ring: &Ref<Ring>, other: &Ref<Ring>) {
+    let face = ring.borrow().face.upgrade().unwrap();
+    let other_face = other.borrow().face.upgrade().unwrap();
+    for (face, old, new) in [(&face, ring, other), (&other_face, other, ring)] {
+      let mut faceb = face.borrow_mut();
+      if Rc::ptr_eq(&faceb.outer_ring, old) { faceb.outer_ring = new.clone() }
+      for ring in &mut faceb.rings {
+        if Rc::ptr_eq(ring, old) { *ring = new.clone() }
+      }
+    }
+    ring.borrow_mut().face = Rc::downgrade(&other_face);
+    other.borrow_mut().face = Rc::downgrade(&face);
   }
 
   pub fn print(&self) {
@@ -433,10 +544,45 @@ impl Shell {
 }
 
 
+impl Transformable for Shell {
+  fn transform(&mut self, transform: &Matrix4) {
+    for vertex in &self.vertices {
+      let mut vertex = vertex.borrow_mut();
+      vertex.point = transform.transform_point(vertex.point);
+    }
+    for edge in &self.edges {
+      edge.borrow_mut().curve.as_curve_mut().transform(transform);
+    }
+    // Mirroring turns the cross product of surface derivatives around
+    let mirror = transform.determinant() < 0.0;
+    for face in &self.faces {
+      let mut face = face.borrow_mut();
+      face.surface.as_surface_mut().transform(transform);
+      if mirror { face.flip_normal = !face.flip_normal }
+    }
+  }
+}
+
+
+// Normal of a closed wire, scaled by its enclosed area
+fn area_vector(wire: &Wire) -> Vec3 {
+  let poly = geom2d::tesselate_wire(wire);
+  poly.iter().zip(poly.iter().cycle().skip(1)).fold(Vec3::new(0.0, 0.0, 0.0), |acc, (p, next)| {
+    acc + p.to_vec().cross(next.to_vec())
+  }) / 2.0
+}
+
+
 impl Face {
   pub fn make_surface(&self) -> TrimmedSurface {
     let wire = self.outer_ring.borrow().make_wire();
-    TrimmedSurface::new(self.surface.clone(), wire)
+    let mut surface = TrimmedSurface::new(self.surface.clone(), wire);
+    for ring in &self.rings {
+      if !Rc::ptr_eq(ring, &self.outer_ring) {
+        surface.profile.push(ring.borrow().make_wire());
+      }
+    }
+    surface
   }
 
   pub fn get_edge_ids(&self) -> HashSet<Uuid> {
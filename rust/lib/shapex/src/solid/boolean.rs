@@ -1,4 +1,21 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::f64::consts::PI;
+
+use uuid::Uuid;
+
 use crate::solid::*;
+use crate::solid::serde as dump;
+use crate::geom2d;
+use crate::geom2d::cross_2d;
+use crate::surface::intersection;
+use crate::mesh::SpatialHash;
+
+// use crate::log;
+
+
+// Points closer than this are merged into a single vertex
+const SNAP_DISTANCE: f64 = 1.0e-7;
 
 
 #[derive(Debug, Clone, Copy)]
@@ -11,33 +28,990 @@ pub enum BooleanType {
 }
 
 pub trait Boolean {
-  fn create(&mut self, tool: Self);
-  fn join(&mut self, tool: Self);
-  fn cut(&mut self, tool: Self);
-  fn intersect(&mut self, tool: Self);
-  fn difference(&mut self, tool: Self);
-  fn boolean(&mut self, tool: Self, op: BooleanType);
+  fn create(&mut self, tool: Self) -> Result<(), String>;
+  fn join(&mut self, tool: Self) -> Result<(), String>;
+  fn cut(&mut self, tool: Self) -> Result<(), String>;
+  fn intersect(&mut self, tool: Self) -> Result<(), String>;
+  fn difference(&mut self, tool: Self) -> Result<(), String>;
+  fn boolean(&mut self, tool: Self, op: BooleanType) -> Result<(), String>;
 }
 
 
 impl Boolean for Compound {
-  fn create(&mut self, mut tool: Self) { self.solids.append(&mut tool.solids) }
-  fn join(&mut self, mut tool: Self) { self.solids.append(&mut tool.solids) }
-  fn cut(&mut self, _tool: Self) { todo!() }
-  fn intersect(&mut self, _tool: Self) { todo!() }
-  fn difference(&mut self, _tool: Self) { todo!() }
+  fn create(&mut self, mut tool: Self) -> Result<(), String> {
+    self.solids.append(&mut tool.solids);
+    Ok(())
+  }
+
+  // Overlapping solids with curved faces or edges cannot be fused yet and fail the join
+  fn join(&mut self, tool: Self) -> Result<(), String> {
+    self.solids = boolean_solids(self.solids.clone(), tool.solids, BooleanType::Join)?;
+    Ok(())
+  }
+
+  // Operands are only read, so solids stay untouched when an operation fails
+  fn cut(&mut self, tool: Self) -> Result<(), String> {
+    self.solids = boolean_solids(self.solids.clone(), tool.solids, BooleanType::Cut)?;
+    Ok(())
+  }
+
+  fn intersect(&mut self, tool: Self) -> Result<(), String> {
+    self.solids = boolean_solids(self.solids.clone(), tool.solids, BooleanType::Intersection)?;
+    Ok(())
+  }
 
-  fn boolean(&mut self, tool: Self, op: BooleanType) {
+  // Symmetric difference, keeping everything that is covered by exactly one operand
+  fn difference(&mut self, tool: Self) -> Result<(), String> {
+    let mut own_part = boolean_solids(self.solids.clone(), tool.solids.clone(), BooleanType::Cut)?;
+    let mut tool_part = boolean_solids(tool.solids, self.solids.clone(), BooleanType::Cut)?;
+    own_part.append(&mut tool_part);
+    self.solids = own_part;
+    Ok(())
+  }
+
+  fn boolean(&mut self, tool: Self, op: BooleanType) -> Result<(), String> {
     match op {
       BooleanType::Create => self.create(tool),
       BooleanType::Join => self.join(tool),
-      _ => todo!()
+      BooleanType::Cut => self.cut(tool),
+      BooleanType::Intersection => self.intersect(tool),
+      BooleanType::Difference => self.difference(tool),
+    }
+  }
+}
+
+
+// Boundary evaluation for polyhedral solids
+// 1. Faces of both operands are split along their mutual intersections
+// 2. Each fragment is classified as inside, outside or on the other operand
+// 3. Fragments are selected according to the operation and stitched into new shells
+fn boolean_solids(solids: Vec<Solid>, tools: Vec<Solid>, op: BooleanType) -> Result<Vec<Solid>, String> {
+  // Solids that don't touch the other operand are not rebuilt
+  let tool_boxes: Vec<(Point3, Point3)> = tools.iter().map(bounding_box).collect();
+  let (mut result, solids): (Vec<Solid>, Vec<Solid>) = solids.into_iter().partition(|solid| {
+    let bbox = bounding_box(solid);
+    !tool_boxes.iter().any(|other| boxes_overlap(&bbox, other) )
+  });
+  let own_boxes: Vec<(Point3, Point3)> = solids.iter().map(bounding_box).collect();
+  let (mut loose_tools, tools): (Vec<Solid>, Vec<Solid>) = tools.into_iter().partition(|tool| {
+    let bbox = bounding_box(tool);
+    !own_boxes.iter().any(|other| boxes_overlap(&bbox, other) )
+  });
+  match op {
+    BooleanType::Intersection => result.clear(),
+    BooleanType::Join => result.append(&mut loose_tools),
+    _ => {},
+  }
+  if solids.is_empty() { return Ok(result) }
+
+  // Split faces
+  let mut pool = PointPool::default();
+  let own_faces = poly_faces(&solids, &mut pool)?;
+  let tool_faces = poly_faces(&tools, &mut pool)?;
+  let mut own_cuts = vec![vec![]; own_faces.len()];
+  let mut tool_cuts = vec![vec![]; tool_faces.len()];
+  for (i, face) in own_faces.iter().enumerate() {
+    let bbox = face.bounding_box(&pool);
+    for (j, other) in tool_faces.iter().enumerate() {
+      if !boxes_overlap(&bbox, &other.bounding_box(&pool)) { continue }
+      let (mut face_cuts, mut other_cuts) = face.intersect(other, &mut pool);
+      own_cuts[i].append(&mut face_cuts);
+      tool_cuts[j].append(&mut other_cuts);
+    }
+  }
+  let own_fragments: Vec<PolyFace> = own_faces.iter().zip(own_cuts.iter())
+    .flat_map(|(face, cuts)| face.split(cuts, &mut pool) ).collect();
+  let tool_fragments: Vec<PolyFace> = tool_faces.iter().zip(tool_cuts.iter())
+    .flat_map(|(face, cuts)| face.split(cuts, &mut pool) ).collect();
+
+  // Classify and select fragments
+  let own_triangles: Vec<[Point3; 3]> = own_faces.iter().flat_map(|face| face.triangles(&pool) ).collect();
+  let tool_triangles: Vec<[Point3; 3]> = tool_faces.iter().flat_map(|face| face.triangles(&pool) ).collect();
+  let mut selected = vec![];
+  for fragment in own_fragments {
+    let class = fragment.classify(&tool_faces, &tool_triangles, &pool);
+    if let Some(flip) = select_fragment(op, class, false) {
+      selected.push(if flip { fragment.flipped() } else { fragment });
     }
   }
+  for fragment in tool_fragments {
+    let class = fragment.classify(&own_faces, &own_triangles, &pool);
+    if let Some(flip) = select_fragment(op, class, true) {
+      selected.push(if flip { fragment.flipped() } else { fragment });
+    }
+  }
+  resolve_junctions(&mut selected, &pool);
+  let mut selected = merge_fragments(selected, &pool);
+  remove_colinear(&mut selected, &pool);
+  result.append(&mut build_solids(selected, &pool)?);
+  Ok(result)
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Classification {
+  Inside,
+  Outside,
+  OnSame, // Coplanar with a face of the other operand, same orientation
+  OnOpposite, // Coplanar with a face of the other operand, opposite orientation
+}
+
+
+// Decide if a fragment is part of the result and if it has to be flipped
+fn select_fragment(op: BooleanType, class: Classification, is_tool: bool) -> Option<bool> {
+  use Classification::*;
+  match (op, is_tool, class) {
+    (BooleanType::Join, false, Outside | OnSame) => Some(false),
+    (BooleanType::Join, true, Outside) => Some(false),
+    (BooleanType::Intersection, false, Inside | OnSame) => Some(false),
+    (BooleanType::Intersection, true, Inside) => Some(false),
+    (BooleanType::Cut, false, Outside | OnOpposite) => Some(false),
+    (BooleanType::Cut, true, Inside) => Some(true),
+    _ => None,
+  }
+}
+
+
+// Snaps nearby points onto shared vertices, so that faces which are split independently stay connected
+#[derive(Debug)]
+struct PointPool {
+  points: Vec<Point3>,
+  hash: SpatialHash,
+}
+
+impl Default for PointPool {
+  fn default() -> Self {
+    Self {
+      points: vec![],
+      hash: SpatialHash::new(SNAP_DISTANCE),
+    }
+  }
+}
+
+impl PointPool {
+  fn insert(&mut self, p: Point3) -> usize {
+    self.hash.add(p, &mut self.points)
+  }
+}
+
+
+// A line segment between two pooled vertices, that is used to split a face
+type Cut = (usize, usize, CurveType);
+
+
+// Planar face with straight edges, whose rings reference pooled vertices
+// Every ring element stores the vertex an edge starts at, together with the edge's curve
+#[derive(Debug, Clone)]
+struct PolyFace {
+  surface: SurfaceType,
+  plane: Plane,
+  to_local: Matrix4,
+  to_world: Matrix4,
+  rings: Vec<Vec<(usize, CurveType)>>,
+}
+
+impl PolyFace {
+  fn new(surface: SurfaceType, rings: Vec<Vec<(usize, CurveType)>>) -> Self {
+    let plane = match &surface {
+      SurfaceType::Planar(surface) => surface.plane.clone(),
+      _ => unreachable!(),
+    };
+    let to_world = plane.as_transform();
+    Self {
+      surface,
+      plane,
+      to_local: to_world.invert().unwrap(),
+      to_world,
+      rings,
+    }
+  }
+
+  fn from_face(face: &Face, pool: &mut PointPool) -> Result<Self, String> {
+    let mut surface = face.surface.clone();
+    if let SurfaceType::Planar(_) = surface {} else {
+      return Err("Boolean operations on curved faces are not supported yet".into())
+    }
+    if face.flip_normal { surface.as_surface_mut().flip() }
+    let mut rings = vec![];
+    for ring in &face.rings {
+      let mut elems = vec![];
+      for he in ring.borrow().iter() {
+        let he = he.borrow();
+        let curve = he.get_edge().borrow().curve.clone();
+        if let CurveType::Line(_) = curve {} else {
+          return Err("Boolean operations on curved edges are not supported yet".into())
+        }
+        elems.push((pool.insert(he.origin.borrow().point), curve));
+      }
+      rings.push(elems);
+    }
+    Ok(Self::new(surface, rings))
+  }
+
+  fn with_rings(&self, rings: Vec<Vec<(usize, CurveType)>>) -> Self {
+    Self {
+      rings,
+      ..self.clone()
+    }
+  }
+
+  fn flipped(&self) -> Self {
+    let mut surface = self.surface.clone();
+    surface.as_surface_mut().flip();
+    let rings = self.rings.iter().map(|ring| {
+      let n = ring.len();
+      (0..n).map(|k| {
+        let i = (n - k) % n;
+        (ring[i].0, ring[(i + n - 1) % n].1.clone())
+      }).collect()
+    }).collect();
+    Self::new(surface, rings)
+  }
+
+  fn is_coplanar(&self, other: &Self) -> bool {
+    let normal = self.plane.normal();
+    normal.dot(other.plane.normal()) >= 1.0 - 1.0e-9 &&
+      (other.plane.origin - self.plane.origin).dot(normal).abs() <= SNAP_DISTANCE
+  }
+
+  fn local(&self, p: Point3) -> Point3 {
+    let mut p = self.to_local.transform_point(p);
+    p.z = 0.0;
+    p
+  }
+
+  fn edges(&self) -> impl Iterator<Item = (usize, usize, &CurveType)> {
+    self.rings.iter().flat_map(|ring| {
+      ring.iter().enumerate().map(move |(i, elem)| (elem.0, ring[(i + 1) % ring.len()].0, &elem.1) )
+    })
+  }
+
+  fn polygons(&self, pool: &PointPool) -> Vec<PolyLine> {
+    self.rings.iter().map(|ring|
+      ring.iter().map(|elem| self.local(pool.points[elem.0]) ).collect()
+    ).collect()
+  }
+
+  fn bounding_box(&self, pool: &PointPool) -> (Point3, Point3) {
+    points_bounding_box(self.rings.iter().flatten().map(|elem| pool.points[elem.0] ))
+  }
+
+  // Check if a local point lies inside or on the boundary of this face
+  fn contains_local(&self, p: Point3, polygons: &[PolyLine]) -> bool {
+    let on_boundary = polygons.iter().any(|polygon| {
+      (0..polygon.len()).any(|i| geom2d::distance_to_segment(p, polygon[i], polygon[(i + 1) % polygon.len()]) <= SNAP_DISTANCE )
+    });
+    on_boundary || (
+      geom2d::point_in_polygon(p, &polygons[0]) &&
+      !polygons.iter().skip(1).any(|hole| geom2d::point_in_polygon(p, hole) )
+    )
+  }
+
+  // Parameter intervals of a line, for which it runs inside this face
+  fn line_intervals(&self, origin: Point3, direction: Vec3, pool: &PointPool) -> Vec<(f64, f64)> {
+    let polygons = self.polygons(pool);
+    let a = self.local(origin);
+    let mut d = self.to_local.transform_vector(direction);
+    d.z = 0.0;
+    let len = d.magnitude();
+    if len <= SNAP_DISTANCE { return vec![] }
+    let mut params = vec![];
+    for polygon in &polygons {
+      for i in 0..polygon.len() {
+        let c = polygon[i];
+        let edge = polygon[(i + 1) % polygon.len()] - c;
+        let edge_len = edge.magnitude();
+        let denominator = cross_2d(d, edge);
+        if denominator.abs() <= 1.0e-12 * len * edge_len {
+          // Edge is parallel to line and contributes its endpoints when colinear
+          if cross_2d(c - a, d).abs() / len <= SNAP_DISTANCE {
+            params.push((c - a).dot(d) / (len * len));
+            params.push((c + edge - a).dot(d) / (len * len));
+          }
+        } else {
+          let t = cross_2d(c - a, edge) / denominator;
+          let s = cross_2d(c - a, d) / denominator;
+          let tolerance = SNAP_DISTANCE / edge_len;
+          if s >= -tolerance && s <= 1.0 + tolerance {
+            params.push(t);
+          }
+        }
+      }
+    }
+    params.sort_by(|a, b| a.partial_cmp(b).unwrap() );
+    params.dedup_by(|t, last| (*t - *last) * len <= SNAP_DISTANCE );
+    let mut intervals: Vec<(f64, f64)> = vec![];
+    for pair in params.windows(2) {
+      let center = a + d * ((pair[0] + pair[1]) / 2.0);
+      if !self.contains_local(center, &polygons) { continue }
+      match intervals.last_mut() {
+        Some(last) if last.1 == pair[0] => last.1 = pair[1],
+        _ => intervals.push((pair[0], pair[1])),
+      }
+    }
+    intervals
+  }
+
+  // Pieces of a segment that lie inside or on the boundary of this face
+  fn clip_segment(&self, a: usize, b: usize, curve: &CurveType, pool: &mut PointPool) -> Vec<Cut> {
+    let p = pool.points[a];
+    let q = pool.points[b];
+    let len = p.distance(q);
+    let intervals = self.line_intervals(p, q - p, pool);
+    intervals.into_iter().filter_map(|(t0, t1)| {
+      let t0 = t0.max(0.0);
+      let t1 = t1.min(1.0);
+      if (t1 - t0) * len <= SNAP_DISTANCE { return None }
+      let start = if t0 == 0.0 { a } else { pool.insert(p + (q - p) * t0) };
+      let end = if t1 == 1.0 { b } else { pool.insert(p + (q - p) * t1) };
+      if start == end { None } else { Some((start, end, curve.clone())) }
+    }).collect()
+  }
+
+  // Find segments along which this face and the other one have to be split
+  fn intersect(&self, other: &Self, pool: &mut PointPool) -> (Vec<Cut>, Vec<Cut>) {
+    match intersection::plane_plane(&self.plane, &other.plane) {
+      Some(SurfaceIntersectionType::Contained) => {
+        // Coplanar faces are split along each other's boundaries
+        let own_cuts = other.edges().flat_map(|(a, b, curve)| self.clip_segment(a, b, curve, pool) ).collect();
+        let other_cuts = self.edges().flat_map(|(a, b, curve)| other.clip_segment(a, b, curve, pool) ).collect();
+        (own_cuts, other_cuts)
+      },
+      Some(isect) => if let Some(line) = isect.get_line() {
+        let origin = line.points.0;
+        let direction = line.points.1 - line.points.0;
+        let own_intervals = self.line_intervals(origin, direction, pool);
+        let other_intervals = other.line_intervals(origin, direction, pool);
+        let mut cuts = vec![];
+        for own in &own_intervals {
+          for theirs in &other_intervals {
+            let t0 = own.0.max(theirs.0);
+            let t1 = own.1.min(theirs.1);
+            if (t1 - t0) * direction.magnitude() <= SNAP_DISTANCE { continue }
+            let a = pool.insert(origin + direction * t0);
+            let b = pool.insert(origin + direction * t1);
+            if a == b { continue }
+            cuts.push((a, b, Line::new(pool.points[a], pool.points[b]).into_enum()));
+          }
+        }
+        (cuts.clone(), cuts)
+      } else {
+        (vec![], vec![])
+      },
+      None => (vec![], vec![]),
+    }
+  }
+
+  // Split face into fragments along the given cuts
+  fn split(&self, cuts: &[Cut], pool: &mut PointPool) -> Vec<Self> {
+    if cuts.is_empty() { return vec![self.clone()] }
+    // Boundary segments are directed, cuts are not
+    let segments: Vec<(usize, usize, CurveType, bool)> = self.edges()
+      .map(|(a, b, curve)| (a, b, curve.clone(), true) )
+      .chain(cuts.iter().map(|cut| (cut.0, cut.1, cut.2.clone(), false) ))
+      .collect();
+
+    // Find points where segments touch or cross each other
+    let mut splits: Vec<Vec<(f64, Option<usize>, Point3)>> = vec![vec![]; segments.len()];
+    for (i, own) in segments.iter().enumerate() {
+      let pa = self.local(pool.points[own.0]);
+      let pb = self.local(pool.points[own.1]);
+      let vec = pb - pa;
+      let len = vec.magnitude();
+      if len <= SNAP_DISTANCE { continue }
+      let tolerance = SNAP_DISTANCE / len;
+      for (j, other) in segments.iter().enumerate() {
+        if i == j { continue }
+        let pc = self.local(pool.points[other.0]);
+        let pd = self.local(pool.points[other.1]);
+        for (index, p) in [(other.0, pc), (other.1, pd)] {
+          if index == own.0 || index == own.1 { continue }
+          let t = (p - pa).dot(vec) / (len * len);
+          if t > tolerance && t < 1.0 - tolerance && geom2d::distance_to_segment(p, pa, pb) <= SNAP_DISTANCE {
+            splits[i].push((t, Some(index), p));
+          }
+        }
+        let other_vec = pd - pc;
+        let other_len = other_vec.magnitude();
+        let denominator = cross_2d(vec, other_vec);
+        if other_len <= SNAP_DISTANCE || denominator.abs() <= 1.0e-12 * len * other_len { continue }
+        let t = cross_2d(pc - pa, other_vec) / denominator;
+        let u = cross_2d(pc - pa, vec) / denominator;
+        let other_tolerance = SNAP_DISTANCE / other_len;
+        if t > tolerance && t < 1.0 - tolerance && u > other_tolerance && u < 1.0 - other_tolerance {
+          splits[i].push((t, None, pa + vec * t));
+        }
+      }
+    }
+
+    // Split segments into pieces
+    let mut boundary = vec![];
+    let mut boundary_keys = HashSet::new();
+    let mut inner = vec![];
+    let mut inner_keys = HashSet::new();
+    for (segment, mut params) in segments.into_iter().zip(splits) {
+      params.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap() );
+      let mut indices = vec![segment.0];
+      for (_, index, p) in params {
+        indices.push(index.unwrap_or_else(|| pool.insert(self.to_world.transform_point(p)) ));
+      }
+      indices.push(segment.1);
+      indices.dedup();
+      for pair in indices.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if a == b { continue }
+        if segment.3 {
+          if boundary_keys.insert((a, b)) {
+            boundary.push((a, b, segment.2.clone()));
+          }
+        } else if inner_keys.insert((a.min(b), a.max(b))) {
+          inner.push((a, b, segment.2.clone()));
+        }
+      }
+    }
+    // Cuts running along the boundary are already covered
+    inner.retain(|(a, b, _)| !boundary_keys.contains(&(*a, *b)) && !boundary_keys.contains(&(*b, *a)) );
+
+    // Remove dangling cuts
+    loop {
+      let mut degrees: HashMap<usize, usize> = HashMap::new();
+      for (a, b, _) in boundary.iter().chain(inner.iter()) {
+        *degrees.entry(*a).or_insert(0) += 1;
+        *degrees.entry(*b).or_insert(0) += 1;
+      }
+      let len = inner.len();
+      inner.retain(|(a, b, _)| degrees[a] > 1 && degrees[b] > 1 );
+      if inner.len() == len { break }
+    }
+
+    let mut edges: Vec<(usize, usize, CurveType)> = boundary;
+    for (a, b, curve) in inner {
+      edges.push((b, a, curve.clone()));
+      edges.push((a, b, curve));
+    }
+    self.trace_faces(edges, pool)
+  }
+
+  // Build faces from a set of directed edges, which enclose face regions to their left
+  fn trace_faces(&self, edges: Vec<(usize, usize, CurveType)>, pool: &PointPool) -> Vec<Self> {
+    // Trace cycles, always taking the sharpest left turn
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut locals: HashMap<usize, Point3> = HashMap::new();
+    for (i, edge) in edges.iter().enumerate() {
+      outgoing.entry(edge.0).or_insert(vec![]).push(i);
+      locals.entry(edge.0).or_insert_with(|| self.local(pool.points[edge.0]) );
+      locals.entry(edge.1).or_insert_with(|| self.local(pool.points[edge.1]) );
+    }
+    let mut used = vec![false; edges.len()];
+    let mut cycles: Vec<Vec<usize>> = vec![];
+    for start in 0..edges.len() {
+      if used[start] { continue }
+      let mut cycle = vec![];
+      let mut current = start;
+      let is_closed = loop {
+        used[current] = true;
+        cycle.push(current);
+        let (a, b, _) = &edges[current];
+        let reverse = locals[a] - locals[b];
+        let reverse_angle = reverse.y.atan2(reverse.x);
+        let next = outgoing.get(b).and_then(|candidates| candidates.iter().min_by(|x, y| {
+          let angle = |e: usize| {
+            let dir = locals[&edges[e].1] - locals[b];
+            let mut angle = reverse_angle - dir.y.atan2(dir.x);
+            while angle <= 1.0e-12 { angle += 2.0 * PI }
+            while angle > 2.0 * PI { angle -= 2.0 * PI }
+            angle
+          };
+          angle(**x).partial_cmp(&angle(**y)).unwrap()
+        }));
+        match next {
+          Some(&next) if next == start => break true,
+          Some(&next) if !used[next] => current = next,
+          _ => break false,
+        }
+      };
+      if is_closed { cycles.push(cycle) }
+    }
+
+    // Sort cycles into outer rings and holes
+    let mut outers = vec![];
+    let mut holes = vec![];
+    for cycle in cycles {
+      let polygon: PolyLine = cycle.iter().map(|&e| locals[&edges[e].0] ).collect();
+      let area = geom2d::signed_polygon_area(&polygon);
+      let ring: Vec<(usize, CurveType)> = cycle.iter().map(|&e| (edges[e].0, edges[e].2.clone()) ).collect();
+      if area > SNAP_DISTANCE * SNAP_DISTANCE {
+        outers.push((area, polygon, vec![ring]));
+      } else if area < -SNAP_DISTANCE * SNAP_DISTANCE {
+        holes.push((polygon, ring));
+      }
+    }
+    for (polygon, ring) in holes {
+      // The surrounding face lies to the left of a hole's edges
+      let edge = polygon[1] - polygon[0];
+      let test_point = polygon[0] + edge / 2.0 + Vec3::new(-edge.y, edge.x, 0.0).normalize() * SNAP_DISTANCE;
+      let container = outers.iter_mut()
+        .filter(|outer| geom2d::point_in_polygon(test_point, &outer.1) )
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap() );
+      if let Some(outer) = container {
+        outer.2.push(ring);
+      }
+    }
+    outers.into_iter().map(|(_, _, rings)| self.with_rings(rings) ).collect()
+  }
+
+  // Triangulation in world space, with triangles oriented like the face
+  fn triangles(&self, pool: &PointPool) -> Vec<[Point3; 3]> {
+    let polygons = self.polygons(pool);
+    let mut holes = vec![];
+    let mut i = 0;
+    for polygon in &polygons {
+      i += polygon.len();
+      holes.push(i);
+    }
+    holes.pop();
+    let mesh = geom2d::tesselate_polygon(polygons.concat(), holes, Vec3::unit_z());
+    mesh.faces.chunks(3).map(|tri| {
+      let mut points = [mesh.vertices[tri[0]], mesh.vertices[tri[1]], mesh.vertices[tri[2]]];
+      if cross_2d(points[1] - points[0], points[2] - points[0]) < 0.0 { points.swap(1, 2) }
+      points.map(|p| self.to_world.transform_point(p) )
+    }).collect()
+  }
+
+  // A point well inside the face
+  fn inner_point(&self, pool: &PointPool) -> Point3 {
+    let triangles = self.triangles(pool);
+    let largest = triangles.iter().max_by(|a, b| {
+      let area_a = (a[1] - a[0]).cross(a[2] - a[0]).magnitude2();
+      let area_b = (b[1] - b[0]).cross(b[2] - b[0]).magnitude2();
+      area_a.partial_cmp(&area_b).unwrap()
+    }).unwrap();
+    Point3::from_vec((largest[0].to_vec() + largest[1].to_vec() + largest[2].to_vec()) / 3.0)
+  }
+
+  fn classify(&self, others: &[Self], other_triangles: &[[Point3; 3]], pool: &PointPool) -> Classification {
+    let p = self.inner_point(pool);
+    let normal = self.plane.normal();
+    for other in others {
+      let other_normal = other.plane.normal();
+      if normal.cross(other_normal).magnitude() > 1.0e-9 { continue }
+      if (p - other.plane.origin).dot(other_normal).abs() > SNAP_DISTANCE { continue }
+      if other.contains_local(other.local(p), &other.polygons(pool)) {
+        return if normal.dot(other_normal) > 0.0 {
+          Classification::OnSame
+        } else {
+          Classification::OnOpposite
+        }
+      }
+    }
+    if winding_number(p, other_triangles) > 0.5 {
+      Classification::Inside
+    } else {
+      Classification::Outside
+    }
+  }
+}
+
+
+fn poly_faces(solids: &Vec<Solid>, pool: &mut PointPool) -> Result<Vec<PolyFace>, String> {
+  let mut faces = vec![];
+  for solid in solids {
+    for shell in &solid.shells {
+      for face in &shell.faces {
+        faces.push(PolyFace::from_face(&face.borrow(), pool)?);
+      }
+    }
+  }
+  Ok(faces)
+}
+
+
+// Sum of solid angles spanned by a closed triangle mesh, divided by 4 PI
+// https://en.wikipedia.org/wiki/Solid_angle#Tetrahedron
+fn winding_number(p: Point3, triangles: &[[Point3; 3]]) -> f64 {
+  triangles.iter().fold(0.0, |acc, tri| {
+    let a = tri[0] - p;
+    let b = tri[1] - p;
+    let c = tri[2] - p;
+    let (la, lb, lc) = (a.magnitude(), b.magnitude(), c.magnitude());
+    let numerator = a.dot(b.cross(c));
+    let denominator = la * lb * lc + a.dot(b) * lc + b.dot(c) * la + c.dot(a) * lb;
+    acc + 2.0 * numerator.atan2(denominator)
+  }) / (4.0 * PI)
+}
+
+
+fn signed_volume(triangles: &[[Point3; 3]]) -> f64 {
+  triangles.iter().fold(0.0, |acc, tri| {
+    acc + tri[0].to_vec().dot(tri[1].to_vec().cross(tri[2].to_vec())) / 6.0
+  })
+}
+
+
+pub(super) fn points_bounding_box(points: impl Iterator<Item = Point3>) -> (Point3, Point3) {
+  points.fold((Point3::new(MAX_FLOAT, MAX_FLOAT, MAX_FLOAT), Point3::new(-MAX_FLOAT, -MAX_FLOAT, -MAX_FLOAT)), |(min, max), p| (
+    Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+    Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+  ))
+}
+
+// Edges are sampled, so that curved solids are not reduced to their vertices
+fn bounding_box(solid: &Solid) -> (Point3, Point3) {
+  points_bounding_box(solid.shells.iter().flat_map(|shell|
+    shell.edges.iter().flat_map(|edge| edge.borrow().curve.as_curve().tesselate() )
+  ))
+}
+
+fn boxes_overlap(a: &(Point3, Point3), b: &(Point3, Point3)) -> bool {
+  a.0.x <= b.1.x + SNAP_DISTANCE && b.0.x <= a.1.x + SNAP_DISTANCE &&
+  a.0.y <= b.1.y + SNAP_DISTANCE && b.0.y <= a.1.y + SNAP_DISTANCE &&
+  a.0.z <= b.1.z + SNAP_DISTANCE && b.0.z <= a.1.z + SNAP_DISTANCE
+}
+
+
+// Insert vertices that split the edges of neighbouring fragments
+fn resolve_junctions(fragments: &mut [PolyFace], pool: &PointPool) {
+  let used: HashSet<usize> = fragments.iter().flat_map(|fragment| fragment.rings.iter().flatten().map(|elem| elem.0 ) ).collect();
+  for fragment in fragments.iter_mut() {
+    for ring in &mut fragment.rings {
+      let n = ring.len();
+      let mut new_ring = vec![];
+      for i in 0..n {
+        let (a, curve) = ring[i].clone();
+        let b = ring[(i + 1) % n].0;
+        let (pa, pb) = (pool.points[a], pool.points[b]);
+        let len2 = pa.distance2(pb);
+        let mut inserts: Vec<(f64, usize)> = used.iter().filter_map(|&v| {
+          if v == a || v == b { return None }
+          let p = pool.points[v];
+          let t = (p - pa).dot(pb - pa) / len2;
+          if t > 0.0 && t < 1.0 && geom2d::distance_to_segment(p, pa, pb) <= SNAP_DISTANCE {
+            Some((t, v))
+          } else { None }
+        }).collect();
+        inserts.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap() );
+        new_ring.push((a, curve.clone()));
+        for (_, v) in inserts {
+          new_ring.push((v, curve.clone()));
+        }
+      }
+      *ring = new_ring;
+    }
+  }
+}
+
+
+// Unify adjacent fragments that lie in the same plane
+fn merge_fragments(fragments: Vec<PolyFace>, pool: &PointPool) -> Vec<PolyFace> {
+  let mut owners: HashMap<(usize, usize), usize> = HashMap::new();
+  for (i, fragment) in fragments.iter().enumerate() {
+    for (a, b, _) in fragment.edges() {
+      owners.insert((a, b), i);
+    }
+  }
+  let mut group_ids: Vec<usize> = (0..fragments.len()).collect();
+  for (i, fragment) in fragments.iter().enumerate() {
+    for (a, b, _) in fragment.edges() {
+      if let Some(&j) = owners.get(&(b, a)) {
+        if i != j && fragment.is_coplanar(&fragments[j]) {
+          let (root_i, root_j) = (find_root(&mut group_ids, i), find_root(&mut group_ids, j));
+          group_ids[root_i] = root_j;
+        }
+      }
+    }
+  }
+  let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+  for i in 0..fragments.len() {
+    let root = find_root(&mut group_ids, i);
+    groups.entry(root).or_insert(vec![]).push(i);
+  }
+  let mut groups: Vec<Vec<usize>> = groups.into_values().collect();
+  groups.sort();
+  groups.into_iter().flat_map(|group| {
+    if group.len() == 1 { return vec![fragments[group[0]].clone()] }
+    // Drop edges between members of the group and retrace the remaining boundary
+    let members: HashSet<usize> = group.iter().cloned().collect();
+    let edges = group.iter().flat_map(|&i| {
+      fragments[i].edges()
+        .filter(|(a, b, _)| !owners.get(&(*b, *a)).is_some_and(|j| members.contains(j) ) )
+        .map(|(a, b, curve)| (a, b, curve.clone()) )
+        .collect::<Vec<_>>()
+    }).collect();
+    fragments[group[0]].trace_faces(edges, pool)
+  }).collect()
+}
+
+
+// Remove vertices that only connect two colinear edges
+fn remove_colinear(fragments: &mut [PolyFace], pool: &PointPool) {
+  let mut neighbours: HashMap<usize, HashSet<usize>> = HashMap::new();
+  for fragment in fragments.iter() {
+    for (a, b, _) in fragment.edges() {
+      neighbours.entry(a).or_default().insert(b);
+      neighbours.entry(b).or_default().insert(a);
+    }
+  }
+  let removable: HashSet<usize> = neighbours.iter().filter(|(&v, others)| {
+    if others.len() != 2 { return false }
+    let ends: Vec<Point3> = others.iter().map(|&i| pool.points[i] ).collect();
+    geom2d::distance_to_segment(pool.points[v], ends[0], ends[1]) <= SNAP_DISTANCE
+  }).map(|(&v, _)| v ).collect();
+  for fragment in fragments.iter_mut() {
+    for ring in &mut fragment.rings {
+      let n = ring.len();
+      let start = match (0..n).find(|&i| !removable.contains(&ring[i].0) ) {
+        Some(start) => start,
+        None => continue,
+      };
+      let mut new_ring: Vec<(usize, CurveType)> = vec![];
+      let mut merged: Vec<bool> = vec![];
+      for k in 0..n {
+        let (v, curve) = &ring[(start + k) % n];
+        if removable.contains(v) {
+          let last = new_ring.last().unwrap();
+          if last.1.get_id() != curve.get_id() { *merged.last_mut().unwrap() = true }
+        } else {
+          new_ring.push((*v, curve.clone()));
+          merged.push(false);
+        }
+      }
+      if new_ring.len() < 3 { continue }
+      // Edges that combine different curves receive a new line
+      for i in 0..new_ring.len() {
+        if !merged[i] { continue }
+        let end = new_ring[(i + 1) % new_ring.len()].0;
+        new_ring[i].1 = Line::new(pool.points[new_ring[i].0], pool.points[end]).into_enum();
+      }
+      *ring = new_ring;
+    }
+  }
+}
+
+
+fn find_root(ids: &mut Vec<usize>, i: usize) -> usize {
+  if ids[i] == i { i } else {
+    let root = find_root(ids, ids[i]);
+    ids[i] = root;
+    root
+  }
+}
+
+
+// Stitch fragments into closed shells and group them into solids
+fn build_solids(fragments: Vec<PolyFace>, pool: &PointPool) -> Result<Vec<Solid>, String> {
+  // Match half edges with their mates
+  let mut half_edges: HashMap<(usize, usize), usize> = HashMap::new();
+  for fragment in &fragments {
+    for (a, b, _) in fragment.edges() {
+      *half_edges.entry((a, b)).or_insert(0) += 1;
+    }
+  }
+  if half_edges.iter().any(|(&(a, b), &count)| count != 1 || half_edges.get(&(b, a)) != Some(&1) ) {
+    return Err("Boolean operation produced a non-manifold result".into())
+  }
+  let mut edges: Vec<CurveType> = vec![];
+  let mut edge_indices: HashMap<(usize, usize), usize> = HashMap::new();
+  let mut edge_faces: Vec<Vec<usize>> = vec![];
+  for (i, fragment) in fragments.iter().enumerate() {
+    for (a, b, curve) in fragment.edges() {
+      let index = *edge_indices.entry((a.min(b), a.max(b))).or_insert_with(|| {
+        edges.push(curve.clone());
+        edge_faces.push(vec![]);
+        edges.len() - 1
+      });
+      edge_faces[index].push(i);
+    }
+  }
+
+  // Group connected fragments into shells
+  let mut shell_ids: Vec<usize> = (0..fragments.len()).collect();
+  for faces in &edge_faces {
+    let (a, b) = (find_root(&mut shell_ids, faces[0]), find_root(&mut shell_ids, faces[1]));
+    shell_ids[a] = b;
+  }
+  let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+  for i in 0..fragments.len() {
+    let root = find_root(&mut shell_ids, i);
+    groups.entry(root).or_insert(vec![]).push(i);
+  }
+  let mut groups: Vec<Vec<usize>> = groups.into_values().collect();
+  groups.sort();
+
+  // Sort shells into outer shells and voids
+  let mut outer_shells = vec![];
+  let mut voids = vec![];
+  for group in groups {
+    let triangles: Vec<[Point3; 3]> = group.iter().flat_map(|&i| fragments[i].triangles(pool) ).collect();
+    let volume = signed_volume(&triangles);
+    if volume > 0.0 {
+      outer_shells.push((volume, triangles, vec![group]));
+    } else {
+      voids.push(group);
+    }
+  }
+  for group in voids {
+    let p = pool.points[fragments[group[0]].rings[0][0].0];
+    if let Some(outer) = outer_shells.iter_mut()
+      .filter(|outer| winding_number(p, &outer.1) > 0.5 )
+      .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap() )
+    {
+      outer.2.push(group);
+    }
+  }
+
+  // Build half edge structures
+  Ok(outer_shells.into_iter().map(|(_, _, shells)| {
+    let shells = shells.into_iter().map(|group| {
+      let mut vertex_map: HashMap<usize, usize> = HashMap::new();
+      let mut edge_map: HashMap<usize, usize> = HashMap::new();
+      let mut vertices = vec![];
+      let mut shell_edges = vec![];
+      let faces = group.iter().map(|&i| {
+        let fragment = &fragments[i];
+        dump::Face {
+          id: Uuid::new_v4(),
+          rings: fragment.rings.iter().map(|ring| {
+            let n = ring.len();
+            (0..n).map(|k| {
+              let a = ring[k].0;
+              let b = ring[(k + 1) % n].0;
+              let origin = *vertex_map.entry(a).or_insert_with(|| {
+                vertices.push(dump::Vertex { point: pool.points[a] });
+                vertices.len() - 1
+              });
+              let global_edge = edge_indices[&(a.min(b), a.max(b))];
+              let edge = *edge_map.entry(global_edge).or_insert_with(|| {
+                shell_edges.push(dump::Edge { id: Uuid::new_v4(), curve: edges[global_edge].clone() });
+                shell_edges.len() - 1
+              });
+              dump::HalfEdge { origin, edge }
+            }).collect()
+          }).collect(),
+          surface: fragment.surface.clone(),
+          flip_normal: false,
+        }
+      }).collect();
+      dump::Shell {
+        faces,
+        edges: shell_edges,
+        vertices,
+      }
+    }).collect();
+    dump::undump_solid(dump::Solid { shells })
+  }).collect())
 }
 
 
 #[cfg(test)]
 mod tests {
+  use super::*;
+  use crate::solid::features;
+  use crate::test_data::{cube_between, shell_counts};
 
+  #[test]
+  fn cut_corner() {
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 2.0, 2.0));
+    compound.cut(cube_between(Point3::new(1.0, 1.0, 1.0), Point3::new(3.0, 3.0, 3.0))).unwrap();
+    assert_eq!(compound.solids.len(), 1);
+    assert_eq!(compound.solids[0].shells.len(), 1);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!(shell_counts(shell), (14, 21, 9, 2));
+    assert!(!compound.solids[0].contains_point(Point3::new(1.5, 1.5, 1.5)));
+  }
+
+  #[test]
+  fn intersect_corner() {
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 2.0, 2.0));
+    compound.intersect(cube_between(Point3::new(1.0, 1.0, 1.0), Point3::new(3.0, 3.0, 3.0))).unwrap();
+    assert_eq!(compound.solids.len(), 1);
+    assert_eq!(shell_counts(&compound.solids[0].shells[0]), (8, 12, 6, 2));
+  }
+
+  #[test]
+  fn cut_void() {
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(3.0, 3.0, 3.0));
+    compound.cut(cube_between(Point3::new(1.0, 1.0, 1.0), Point3::new(2.0, 2.0, 2.0))).unwrap();
+    assert_eq!(compound.solids.len(), 1);
+    assert_eq!(compound.solids[0].shells.len(), 2);
+  }
+
+  #[test]
+  fn cut_in_two() {
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(3.0, 1.0, 1.0));
+    compound.cut(cube_between(Point3::new(1.0, -1.0, -1.0), Point3::new(2.0, 2.0, 2.0))).unwrap();
+    assert_eq!(compound.solids.len(), 2);
+    for solid in &compound.solids {
+      assert_eq!(shell_counts(&solid.shells[0]), (8, 12, 6, 2));
+    }
+  }
+
+  #[test]
+  fn cut_flush() {
+    // Tool shares the top face of the target
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(3.0, 3.0, 1.0));
+    compound.cut(cube_between(Point3::new(1.0, 1.0, 0.5), Point3::new(2.0, 2.0, 1.0))).unwrap();
+    assert_eq!(compound.solids.len(), 1);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!(shell_counts(shell), (16, 24, 11, 2));
+  }
+
+  #[test]
+  fn cut_disjoint() {
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+    compound.cut(cube_between(Point3::new(2.0, 2.0, 2.0), Point3::new(3.0, 3.0, 3.0))).unwrap();
+    assert_eq!(compound.solids.len(), 1);
+    assert_eq!(shell_counts(&compound.solids[0].shells[0]), (8, 12, 6, 2));
+  }
+
+  #[test]
+  fn join_overlapping() {
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 2.0, 1.0));
+    compound.join(cube_between(Point3::new(1.0, 1.0, 0.0), Point3::new(3.0, 3.0, 1.0))).unwrap();
+    assert_eq!(compound.solids.len(), 1);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!(shell_counts(shell), (16, 24, 10, 2));
+  }
+
+  #[test]
+  fn join_stacked() {
+    // Side faces of both boxes are unified
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+    compound.join(cube_between(Point3::new(0.0, 0.0, 1.0), Point3::new(1.0, 1.0, 2.0))).unwrap();
+    assert_eq!(compound.solids.len(), 1);
+    assert_eq!(shell_counts(&compound.solids[0].shells[0]), (8, 12, 6, 2));
+  }
+
+  #[test]
+  fn join_disjoint() {
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+    compound.join(cube_between(Point3::new(2.0, 2.0, 2.0), Point3::new(3.0, 3.0, 3.0))).unwrap();
+    assert_eq!(compound.solids.len(), 2);
+  }
+
+  #[test]
+  fn join_cylinders() {
+    let mut compound = features::make_cylinder(1.0, 2.0).unwrap();
+    let result = compound.join(features::make_cylinder(0.5, 3.0).unwrap());
+    assert!(result.is_err());
+    assert_eq!(compound.solids.len(), 1);
+  }
+
+  #[test]
+  fn join_cylinder_to_boxes() {
+    // Boxes are fused, while the cylinder away from them is left as it is
+    let mut compound = cube_between(Point3::new(2.0, 2.0, 0.0), Point3::new(4.0, 4.0, 1.0));
+    compound.create(features::make_cylinder(0.5, 2.0).unwrap()).unwrap();
+    compound.join(cube_between(Point3::new(3.0, 3.0, 0.0), Point3::new(5.0, 5.0, 1.0))).unwrap();
+    assert_eq!(compound.solids.len(), 2);
+    assert_eq!(shell_counts(&compound.solids[1].shells[0]), (16, 24, 10, 2));
+  }
+
+  #[test]
+  fn cut_cylinder() {
+    let mut compound = cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+    let result = compound.cut(features::make_cylinder(0.5, 2.0).unwrap());
+    assert!(result.is_err());
+    assert_eq!(compound.solids.len(), 1);
+    assert_eq!(shell_counts(&compound.solids[0].shells[0]), (8, 12, 6, 2));
+  }
 }
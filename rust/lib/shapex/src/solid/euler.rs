@@ -0,0 +1,407 @@
+use crate::solid::*;
+use crate::solid::validation::VERTEX_TOLERANCE;
+
+// use crate::log;
+
+
+// Checked Euler operators
+// Each one verifies its arguments and confirms that the Euler-Poincaré formula
+// will still hold, before the low level operator touches the topology.
+// Every operator is undone by its counterpart (mev/kev, mef/kef, kemr/mekr, kfmrh/mfkrh, mvfs/kvfs).
+impl Solid {
+  pub fn mvfs(&mut self, p: Point3, surface: SurfaceType) -> Result<(Ref<Vertex>, Ref<Face>, &mut Shell), String> {
+    if surface.as_surface().distance(p) > VERTEX_TOLERANCE {
+      return Err("Vertex must lie on the surface".into())
+    }
+    Ok(self.lmvfs(p, surface))
+  }
+
+  pub fn kvfs(&mut self, face: &Ref<Face>) -> Result<(), String> {
+    let index = self.shells.iter().position(|shell|
+      shell.faces.iter().any(|f| Rc::ptr_eq(f, face) )
+    ).ok_or("Face does not belong to solid")?;
+    let shell = &self.shells[index];
+    if shell.faces.len() != 1 || !shell.edges.is_empty() || shell.vertices.len() != 1 {
+      return Err("Shell must consist of a single vertex and face".into())
+    }
+    self.shells.remove(index);
+    Ok(())
+  }
+}
+
+
+impl Shell {
+  pub fn mev(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>, curve: CurveType, p: Point3) -> Result<(Ref<Edge>, Ref<Vertex>), String> {
+    if !Rc::ptr_eq(&he1.borrow().origin, &he2.borrow().origin) {
+      return Err("Half edges must start at the same vertex".into())
+    }
+    self.check_euler(self.genus(), 1, 1, 0, 0)?;
+    Ok(self.lmev(he1, he2, curve, p))
+  }
+
+  pub fn kev(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>) -> Result<(), String> {
+    Self::check_mates(he1, he2)?;
+    if Rc::ptr_eq(&he1.borrow().origin, &he2.borrow().origin) {
+      return Err("Cannot kill vertex of a closed edge".into())
+    }
+    self.check_euler(self.genus(), -1, -1, 0, 0)?;
+    self.lkev(he1, he2);
+    Ok(())
+  }
+
+  pub fn mef(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>, curve: CurveType, surface: SurfaceType) -> Result<(Ref<Edge>, Ref<Face>), String> {
+    if !Rc::ptr_eq(&he1.borrow().ring.upgrade().unwrap(), &he2.borrow().ring.upgrade().unwrap()) {
+      return Err("Half edges must belong to the same ring".into())
+    }
+    self.check_euler(self.genus(), 0, 1, 1, 1)?;
+    Ok(self.lmef(he1, he2, curve, surface))
+  }
+
+  pub fn kef(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>) -> Result<(), String> {
+    Self::check_mates(he1, he2)?;
+    let face = he2.borrow().get_face();
+    if Rc::ptr_eq(&he1.borrow().get_face(), &face) {
+      return Err("Edge must separate two different faces".into())
+    }
+    if face.borrow().rings.len() != 1 {
+      return Err("Killed face must not have inner rings".into())
+    }
+    self.check_euler(self.genus(), 0, -1, -1, -1)?;
+    self.lkef(he1, he2);
+    Ok(())
+  }
+
+  pub fn kemr(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>) -> Result<Ref<Ring>, String> {
+    Self::check_mates(he1, he2)?;
+    if !Rc::ptr_eq(&he1.borrow().ring.upgrade().unwrap(), &he2.borrow().ring.upgrade().unwrap()) {
+      return Err("Edge must have the same ring on both sides".into())
+    }
+    self.check_euler(self.genus(), 0, -1, 0, 1)?;
+    Ok(self.lkemr(he1, he2))
+  }
+
+  pub fn mekr(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>, curve: CurveType) -> Result<Ref<Edge>, String> {
+    let ring1 = he1.borrow().ring.upgrade().unwrap();
+    let ring2 = he2.borrow().ring.upgrade().unwrap();
+    if Rc::ptr_eq(&ring1, &ring2) {
+      return Err("Half edges must belong to different rings".into())
+    }
+    if !Rc::ptr_eq(&he1.borrow().get_face(), &he2.borrow().get_face()) {
+      return Err("Half edges must belong to the same face".into())
+    }
+    self.check_euler(self.genus(), 0, 1, 0, -1)?;
+    Ok(self.lmekr(he1, he2, curve))
+  }
+
+  // Split an edge by inserting a new vertex
+  // The half edge keeps its edge, which starts at the new vertex afterwards.
+  // The returned edge connects its former origin to the new vertex.
+  pub fn split_edge(&mut self, he: &Ref<HalfEdge>, p: Point3) -> Result<(Ref<Edge>, Ref<Vertex>), String> {
+    let he2 = he.borrow().mate().borrow().get_next();
+    let curve = he.borrow().get_edge().borrow().curve.clone();
+    self.mev(he, &he2, curve, p)
+  }
+
+  pub fn kfmrh(&mut self, face: &Ref<Face>, other: &Ref<Face>) -> Result<(), String> {
+    if Rc::ptr_eq(face, other) {
+      return Err("Cannot merge face with itself".into())
+    }
+    if other.borrow().rings.len() != 1 {
+      return Err("Killed face must not have inner rings".into())
+    }
+    self.check_euler(self.genus() + 1, 0, 0, -1, 0)?;
+    self.lkfmrh(face, other);
+    Ok(())
+  }
+
+  pub fn mfkrh(&mut self, ring: &Ref<Ring>, surface: SurfaceType) -> Result<Ref<Face>, String> {
+    let face = ring.borrow().face.upgrade().unwrap();
+    if Rc::ptr_eq(&face.borrow().outer_ring, ring) {
+      return Err("Cannot make face from outer ring".into())
+    }
+    self.check_euler(self.genus() - 1, 0, 0, 1, 0)?;
+    Ok(self.lmfkrh(ring, surface))
+  }
+
+  // Evaluated for the counts after adding the given numbers of vertices, edges, faces and rings
+  fn check_euler(&self, handles: i32, vertices: i32, edges: i32, faces: i32, rings: i32) -> Result<(), String> {
+    let characteristic = self.euler_characteristics() + vertices - edges + 2 * faces - rings;
+    Self::euler_poincare(characteristic, handles)
+  }
+
+  fn check_mates(he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>) -> Result<(), String> {
+    if he1.borrow().edge.upgrade().is_none() || !Rc::ptr_eq(&he1.borrow().mate(), he2) {
+      return Err("Half edges must belong to the same edge".into())
+    }
+    Ok(())
+  }
+}
+
+
+// Low level Euler operators
+impl Shell {
+  // Kill edge and the vertex at the origin of he1, moving its other edges over to he2's origin
+  pub fn lkev(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>) {
+    let edge = he1.borrow().get_edge();
+    let vertex = he1.borrow().origin.clone();
+    let origin = he2.borrow().origin.clone();
+    let mut he = he2.borrow().get_next();
+    while !Rc::ptr_eq(&he, he1) {
+      he.borrow_mut().origin = origin.clone();
+      let next = he.borrow().mate().borrow().get_next();
+      he = next;
+    }
+    let ring = he1.borrow().ring.upgrade().unwrap();
+    ring.borrow_mut().half_edge = Self::delete_half_edge(he1);
+    let ring = he2.borrow().ring.upgrade().unwrap();
+    ring.borrow_mut().half_edge = Self::delete_half_edge(he2);
+    origin.borrow_mut().half_edge = Rc::downgrade(&he2.borrow().get_next());
+    self.edges.retain(|e| !Rc::ptr_eq(e, &edge) );
+    self.vertices.retain(|v| !Rc::ptr_eq(v, &vertex) );
+  }
+
+  // Kill edge and the face of he2, merging its ring into the ring of he1
+  pub fn lkef(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>) {
+    let edge = he1.borrow().get_edge();
+    let ring = he1.borrow().ring.upgrade().unwrap();
+    let face = he2.borrow().get_face();
+    let iter = he2.borrow().ring_iter();
+    for he in iter {
+      he.borrow_mut().ring = Rc::downgrade(&ring);
+    }
+    let next1 = he1.borrow().get_next();
+    let next2 = he2.borrow().get_next();
+    let previous1 = he1.borrow().get_previous();
+    let previous2 = he2.borrow().get_previous();
+    previous1.borrow_mut().next = Rc::downgrade(he2);
+    previous2.borrow_mut().next = Rc::downgrade(he1);
+    he1.borrow_mut().previous = Rc::downgrade(&previous2);
+    he2.borrow_mut().previous = Rc::downgrade(&previous1);
+    Self::delete_half_edge(he1);
+    let remaining = Self::delete_half_edge(he2);
+    ring.borrow_mut().half_edge = remaining.clone();
+    // Closed edges may have been their own successor
+    let is_killed = |he: &Ref<HalfEdge>| Rc::ptr_eq(he, he1) || Rc::ptr_eq(he, he2);
+    let next1 = if is_killed(&next1) { remaining.clone() } else { next1 };
+    let next2 = if is_killed(&next2) { remaining } else { next2 };
+    he2.borrow().origin.borrow_mut().half_edge = Rc::downgrade(&next1);
+    he1.borrow().origin.borrow_mut().half_edge = Rc::downgrade(&next2);
+    self.edges.retain(|e| !Rc::ptr_eq(e, &edge) );
+    self.faces.retain(|f| !Rc::ptr_eq(f, &face) );
+  }
+
+  // Kill the edge of two mating half edges in the same ring,
+  // splitting off the part that starts behind he2 into a new ring of the same face
+  pub fn lkemr(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>) -> Ref<Ring> {
+    let old_ring = he1.borrow().ring.upgrade().unwrap();
+    let face = old_ring.borrow().face.upgrade().unwrap();
+    let edge = he1.borrow().get_edge();
+    let he3 = he1.borrow().get_next();
+    let he4 = he2.borrow().get_next();
+    he1.borrow_mut().next = Rc::downgrade(&he4);
+    he4.borrow_mut().previous = Rc::downgrade(he1);
+    he2.borrow_mut().next = Rc::downgrade(&he3);
+    he3.borrow_mut().previous = Rc::downgrade(he2);
+    let ring = rc(Ring {
+      half_edge: he2.clone(),
+      face: Rc::downgrade(&face),
+    });
+    for he in RingIterator::new(he2.clone()) {
+      he.borrow_mut().ring = Rc::downgrade(&ring);
+    }
+    old_ring.borrow_mut().half_edge = Self::delete_half_edge(he1);
+    ring.borrow_mut().half_edge = Self::delete_half_edge(he2);
+    he1.borrow().origin.borrow_mut().half_edge = Rc::downgrade(&he4);
+    he2.borrow().origin.borrow_mut().half_edge = Rc::downgrade(&he3);
+    self.edges.retain(|e| !Rc::ptr_eq(e, &edge) );
+    face.borrow_mut().rings.push(ring.clone());
+    ring
+  }
+
+  // Make edge from he1's origin to he2's origin, merging the ring of he2 into the ring of he1
+  // lkemr(edge.left_half, edge.right_half) restores both rings
+  pub fn lmekr(&mut self, he1: &Ref<HalfEdge>, he2: &Ref<HalfEdge>, curve: CurveType) -> Ref<Edge> {
+    let ring = he1.borrow().ring.upgrade().unwrap();
+    let other = he2.borrow().ring.upgrade().unwrap();
+    let face = ring.borrow().face.upgrade().unwrap();
+    let iter = he2.borrow().ring_iter();
+    for he in iter {
+      he.borrow_mut().ring = Rc::downgrade(&ring);
+    }
+    // Half edges of rings that consist of a single vertex get reused
+    let origin1 = he1.borrow().origin.clone();
+    let origin2 = he2.borrow().origin.clone();
+    let nhe1 = if he1.borrow().edge.upgrade().is_some() {
+      HalfEdge::new_at(&origin1, he1)
+    } else {
+      he1.clone()
+    };
+    let nhe2 = if he2.borrow().edge.upgrade().is_some() {
+      HalfEdge::new_at(&origin2, he2)
+    } else {
+      he2.clone()
+    };
+    nhe1.borrow_mut().next = Rc::downgrade(he2);
+    he2.borrow_mut().previous = Rc::downgrade(&nhe1);
+    nhe2.borrow_mut().next = Rc::downgrade(he1);
+    he1.borrow_mut().previous = Rc::downgrade(&nhe2);
+    let edge = rc(Edge {
+      id: Uuid::new_v4(),
+      left_half: nhe1.clone(),
+      right_half: nhe2.clone(),
+      curve,
+    });
+    nhe1.borrow_mut().edge = Rc::downgrade(&edge);
+    nhe2.borrow_mut().edge = Rc::downgrade(&edge);
+    ring.borrow_mut().half_edge = nhe1;
+    {
+      let mut faceb = face.borrow_mut();
+      faceb.rings.retain(|r| !Rc::ptr_eq(r, &other) );
+      if Rc::ptr_eq(&faceb.outer_ring, &other) { faceb.outer_ring = ring.clone() }
+    }
+    self.edges.push(edge.clone());
+    edge
+  }
+
+  // Kill a face, turning its rings into inner rings of another face
+  pub fn lkfmrh(&mut self, face: &Ref<Face>, other: &Ref<Face>) {
+    for ring in &other.borrow().rings {
+      ring.borrow_mut().face = Rc::downgrade(face);
+      face.borrow_mut().rings.push(ring.clone());
+    }
+    self.faces.retain(|f| !Rc::ptr_eq(f, other) );
+  }
+
+  // Make a new face from an inner ring
+  pub fn lmfkrh(&mut self, ring: &Ref<Ring>, surface: SurfaceType) -> Ref<Face> {
+    let old_face = ring.borrow().face.upgrade().unwrap();
+    old_face.borrow_mut().rings.retain(|r| !Rc::ptr_eq(r, ring) );
+    let face = rc(Face {
+      id: Uuid::new_v4(),
+      outer_ring: ring.clone(),
+      rings: vec![ring.clone()],
+      surface,
+      flip_normal: false,
+    });
+    ring.borrow_mut().face = Rc::downgrade(&face);
+    self.faces.push(face.clone());
+    face
+  }
+
+  // Remove half edge from its ring and return its predecessor
+  // Half edges that are alone in their ring remain as an empty ring
+  pub(super) fn delete_half_edge(he: &Ref<HalfEdge>) -> Ref<HalfEdge> {
+    if Rc::ptr_eq(&he.borrow().get_next(), he) {
+      he.borrow_mut().edge = Weak::new();
+      he.clone()
+    } else {
+      let previous = he.borrow_mut().remove();
+      previous.upgrade().unwrap()
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_data::{square_wire, shell_counts};
+
+  fn plane() -> SurfaceType {
+    PlanarSurface::new(Plane::new()).into_enum()
+  }
+
+  #[test]
+  fn mev_kev() {
+    let mut solid = Solid::new_lamina(&vec![square_wire(2.0, 0.0)], plane());
+    let shell = &mut solid.shells[0];
+    let before = shell_counts(shell);
+    let he = shell.faces[1].borrow().outer_ring.borrow().half_edge.clone();
+    let p = Point3::new(1.0, 1.0, 0.0);
+    let start = he.borrow().origin.borrow().point;
+    let (edge, vertex) = shell.mev(&he, &he, Line::new(start, p).into_enum(), p).unwrap();
+    assert_eq!(shell_counts(shell), (5, 5, 2, 2));
+    assert!(Rc::ptr_eq(&edge.borrow().left_half.borrow().origin, &vertex));
+    let (he1, he2) = (edge.borrow().left_half.clone(), edge.borrow().right_half.clone());
+    shell.kev(&he1, &he2).unwrap();
+    assert_eq!(shell_counts(shell), before);
+    assert_eq!(shell.faces[1].borrow().outer_ring.borrow().iter().count(), 4);
+  }
+
+  #[test]
+  fn mef_kef() {
+    let mut solid = Solid::new_lamina(&vec![square_wire(2.0, 0.0)], plane());
+    let shell = &mut solid.shells[0];
+    let before = shell_counts(shell);
+    let he1 = shell.faces[1].borrow().outer_ring.borrow().half_edge.clone();
+    let he2 = he1.borrow().get_next().borrow().get_next();
+    let line = Line::new(he1.borrow().origin.borrow().point, he2.borrow().origin.borrow().point);
+    let (edge, face) = shell.mef(&he1, &he2, line.into_enum(), plane()).unwrap();
+    assert_eq!(shell_counts(shell), (4, 5, 3, 2));
+    assert_eq!(face.borrow().outer_ring.borrow().iter().count(), 3);
+    let (he1, he2) = (edge.borrow().left_half.clone(), edge.borrow().right_half.clone());
+    assert!(shell.kef(&he1, &he1).is_err());
+    shell.kef(&he1, &he2).unwrap();
+    assert_eq!(shell_counts(shell), before);
+    assert_eq!(shell.faces[1].borrow().outer_ring.borrow().iter().count(), 4);
+  }
+
+  #[test]
+  fn kemr_mekr() {
+    let mut solid = Solid::new_lamina(&vec![square_wire(2.0, 0.0)], plane());
+    let shell = &mut solid.shells[0];
+    let face = shell.faces[1].clone();
+    let he = face.borrow().outer_ring.borrow().half_edge.clone();
+    let start = he.borrow().origin.borrow().point;
+    let p = Point3::new(1.0, 1.0, 0.0);
+    let (edge, _) = shell.mev(&he, &he, Line::new(start, p).into_enum(), p).unwrap();
+    let before = shell_counts(shell);
+    let (he1, he2) = (edge.borrow().right_half.clone(), edge.borrow().left_half.clone());
+    let ring = shell.kemr(&he1, &he2).unwrap();
+    assert_eq!(shell_counts(shell), (5, 4, 2, 2));
+    assert_eq!(face.borrow().rings.len(), 2);
+    assert_eq!(face.borrow().outer_ring.borrow().iter().count(), 4);
+    let he1 = face.borrow().outer_ring.borrow().half_edge.clone();
+    let he2 = ring.borrow().half_edge.clone();
+    assert!(shell.mekr(&he1, &he1, Line::new(start, p).into_enum()).is_err());
+    shell.mekr(&he1, &he2, Line::new(start, p).into_enum()).unwrap();
+    assert_eq!(shell_counts(shell), before);
+    assert_eq!(face.borrow().rings.len(), 1);
+    assert_eq!(face.borrow().outer_ring.borrow().iter().count(), 6);
+  }
+
+  #[test]
+  fn kfmrh_mfkrh() {
+    let mut profile = vec![square_wire(2.0, 0.0)];
+    profile.push(vec![
+      TrimmedCurve::new(Circle::new(Point3::new(1.0, 1.0, 0.0), 0.5).into_enum())
+    ]);
+    let mut solid = Solid::new_lamina(&profile, plane());
+    let shell = &mut solid.shells[0];
+    let before = shell_counts(shell);
+    assert_eq!(shell.genus(), 1);
+    let bottom = shell.faces[0].clone();
+    let ring = bottom.borrow().rings[1].clone();
+    assert!(shell.mfkrh(&bottom.borrow().outer_ring.clone(), plane()).is_err());
+    let face = shell.mfkrh(&ring, plane()).unwrap();
+    assert_eq!(shell.genus(), 0);
+    assert_eq!(bottom.borrow().rings.len(), 1);
+    shell.kfmrh(&bottom, &face).unwrap();
+    assert_eq!(shell_counts(shell), before);
+    assert_eq!(bottom.borrow().rings.len(), 2);
+  }
+
+  #[test]
+  fn mvfs_kvfs() {
+    let mut solid = Solid::new();
+    assert!(solid.mvfs(Point3::new(0.0, 0.0, 1.0), plane()).is_err());
+    assert!(solid.shells.is_empty());
+    let (_, face, _) = solid.mvfs(Point3::origin(), plane()).unwrap();
+    assert_eq!(solid.shells[0].euler_characteristics(), 2);
+    solid.kvfs(&face).unwrap();
+    assert!(solid.shells.is_empty());
+    let mut solid = Solid::new_lamina(&vec![square_wire(2.0, 0.0)], plane());
+    let face = solid.shells[0].faces[0].clone();
+    assert!(solid.kvfs(&face).is_err());
+  }
+}
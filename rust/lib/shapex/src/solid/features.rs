@@ -14,7 +14,7 @@ pub fn extrude(profile: &Profile, distance: f64) -> Result<Compound, String> {
   let poly = geom2d::tesselate_wire(&profile[0]);
   let plane = geom3d::plane_from_points(&poly)?;
   let vec = plane.normal() * distance;
-  let mut solid = Solid::new_lamina(profile[0].clone(), PlanarSurface::new(plane).into_enum());
+  let mut solid = Solid::new_lamina(profile, PlanarSurface::new(plane).into_enum());
   let shell = &mut solid.shells[0];
   let face = if distance >= 0.0 {
     shell.faces.last()
@@ -63,7 +63,7 @@ pub fn extrude(profile: &Profile, distance: f64) -> Result<Compound, String> {
 pub fn revolve(profile: &Profile, mut axis: geom3d::Axis, angle: Deg<f64>) -> Result<Compound, String> {
   let poly = geom2d::tesselate_wire(&profile[0]);
   let plane = geom3d::plane_from_points(&poly)?;
-  let mut solid = Solid::new_lamina(profile[0].clone(), PlanarSurface::new(plane.clone()).into_enum());
+  let mut solid = Solid::new_lamina(profile, PlanarSurface::new(plane.clone()).into_enum());
   let shell = &mut solid.shells[0];
   if axis.direction.dot(plane.u).signum() < 0.0 {
     axis.flip();
@@ -97,26 +97,926 @@ pub fn revolve(profile: &Profile, mut axis: geom3d::Axis, angle: Deg<f64>) -> Re
 }
 
 
+/// Orientation of the profile along the rail of a sweep
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+  Frenet, // Profile turns with the curvature of the rail
+  FixedNormal(Vec3), // Profile keeps facing the same direction
+}
+
+// Largest angle the rail may turn by between neighbouring sections.
+// Cubic spans through sections 15° apart deviate from circular rails by roughly 1e-5 of their radius.
+const SWEEP_STATION_ANGLE: f64 = 15.0;
+
+// Least number of spans along curved rails, to follow changes in curvature
+const SWEEP_MIN_SPANS: usize = 8;
+
+// Number of spans fitted to curved edges of swept profiles
+const SECTION_SAMPLES: u32 = 8;
+
+
+pub fn sweep(profile: &Profile, rail: &CurveType, bounds: (f64, f64), framing: Framing) -> Result<Compound, String> {
+  if bounds.0.almost(bounds.1) { return Err("Sweep bounds must not be empty".into()) }
+  let poly = geom2d::tesselate_wire(&profile[0]);
+  let plane = geom3d::plane_from_points(&poly)?;
+  let direction = (bounds.1 - bounds.0).signum();
+  let tangent = rail.as_curve().tangent_at(bounds.0).normalize() * direction;
+  if tangent.dot(plane.normal()).abs() < 1.0e-6 {
+    return Err("Profile must not be tangent to rail".into())
+  }
+  // Circular rails turn the profile about their axis
+  if let Some((mut axis, mut angle)) = rail_rotation(rail, bounds, framing) {
+    // Revolve flips axes to suit the profile plane, which would reverse the rotation
+    if axis.direction.dot(plane.u) < 0.0 {
+      axis.flip();
+      angle = -angle;
+    }
+    return revolve(profile, axis, angle)
+  }
+  let frames = rail_frames(rail, bounds, framing)?;
+  let start = frames[0].invert().ok_or("Rail frame is degenerate")?;
+  let transforms: Vec<Matrix4> = frames.iter().map(|frame| frame * start ).collect();
+  let params: Vec<f64> = (0..transforms.len()).map(|j| j as f64 / (transforms.len() - 1) as f64 ).collect();
+  let mut solid = Solid::new_lamina(profile, PlanarSurface::new(plane.clone()).into_enum());
+  let shell = &mut solid.shells[0];
+  let face = if tangent.dot(plane.normal()) >= 0.0 {
+    shell.faces.last()
+  } else {
+    shell.faces.first()
+  }.unwrap().clone();
+  shell.sweep(
+    &face,
+    transforms.last().unwrap(),
+    |point| {
+      let points: Vec<Point3> = transforms.iter().map(|transform| transform.transform_point(point) ).collect();
+      Spline::fit_with_params(&points, &params).into_enum()
+    },
+    |tcurve| {
+      let section = section_spline(tcurve);
+      let sections: Vec<Spline> = transforms.iter().map(|transform| {
+        let mut section = section.clone();
+        section.transform(transform);
+        section
+      }).collect();
+      SplineSurface::skinned(&sections, &params).into_enum()
+    }
+  );
+  Ok(solid.into_compound())
+}
+
+// Sweeping along circles amounts to revolving about their axis
+fn rail_rotation(rail: &CurveType, bounds: (f64, f64), framing: Framing) -> Option<(Axis, Deg<f64>)> {
+  let (plane, range) = match rail {
+    CurveType::Circle(circle) => (&circle.plane, 1.0),
+    CurveType::Arc(arc) => (&arc.plane, arc.range()),
+    _ => return None,
+  };
+  if let Framing::FixedNormal(normal) = framing {
+    if !normal.normalize().dot(plane.normal()).abs().almost(1.0) { return None }
+  }
+  let curve = rail.as_curve();
+  let radius = curve.sample(bounds.0) - plane.origin;
+  let tangent = curve.tangent_at(bounds.0) * (bounds.1 - bounds.0).signum();
+  let axis = Axis::new(plane.origin, radius.cross(tangent).normalize());
+  Some((axis, Deg(360.0 * range.abs() * (bounds.1 - bounds.0).abs())))
+}
+
+// Coordinate systems along the rail with z pointing along the tangent
+fn rail_frames(rail: &CurveType, bounds: (f64, f64), framing: Framing) -> Result<Vec<Matrix4>, String> {
+  let curve = rail.as_curve();
+  let stations = if let CurveType::Line(_) = rail { 2 } else { rail_spans(curve, bounds) + 1 };
+  let mut frames: Vec<Matrix4> = vec![];
+  for j in 0..stations {
+    let t = bounds.0 + (bounds.1 - bounds.0) * j as f64 / (stations - 1) as f64;
+    let p = curve.sample(t);
+    let tangent = curve.tangent_at(t).normalize();
+    let normal = match framing {
+      Framing::Frenet => frenet_normal(curve, t, tangent)
+        // Keep the previous frame across straight sections
+        .or_else(|| frames.last().map(|frame| frame.x.truncate() ) )
+        .map(|normal| normal - tangent * normal.dot(tangent) )
+        .filter(|normal| normal.magnitude() > 1.0e-9 )
+        .unwrap_or_else(|| geom3d::transform_from_location_and_normal(p, tangent).x.truncate() ),
+      Framing::FixedNormal(normal) => {
+        let normal = normal - tangent * normal.dot(tangent);
+        if normal.magnitude() < 1.0e-9 {
+          return Err("Fixed normal must not be parallel to rail".into())
+        }
+        normal
+      },
+    }.normalize();
+    frames.push(Matrix4::from_cols(
+      normal.extend(0.0),
+      tangent.cross(normal).extend(0.0),
+      tangent.extend(0.0),
+      p.to_vec().extend(1.0),
+    ));
+  }
+  Ok(frames)
+}
+
+// Spans needed to keep the turning of the rail below the station angle
+fn rail_spans(curve: &dyn Curve, bounds: (f64, f64)) -> usize {
+  let samples = SWEEP_MIN_SPANS * 8;
+  let tangents: Vec<Vec3> = (0..=samples).map(|j| {
+    curve.tangent_at(bounds.0 + (bounds.1 - bounds.0) * j as f64 / samples as f64).normalize()
+  }).collect();
+  let turning: f64 = tangents.windows(2).map(|pair| pair[0].angle(pair[1]).0 ).sum();
+  let spans = (turning.to_degrees() / SWEEP_STATION_ANGLE).ceil() as usize;
+  spans.max(SWEEP_MIN_SPANS)
+}
+
+// Direction towards the center of curvature
+fn frenet_normal(curve: &dyn Curve, t: f64, tangent: Vec3) -> Option<Vec3> {
+  let delta = 1.0e-4;
+  let bend = curve.tangent_at((t + delta).min(1.0)).normalize() - curve.tangent_at((t - delta).max(0.0)).normalize();
+  let normal = bend - tangent * bend.dot(tangent);
+  if normal.magnitude() > 1.0e-9 { Some(normal.normalize()) } else { None }
+}
+
+// Non-rational spline along a trimmed curve, as spline surfaces carry no weights
+fn section_spline(tcurve: &TrimmedCurve) -> Spline {
+  match &tcurve.base {
+    CurveType::Line(_) => Spline::new(vec![tcurve.bounds.0, tcurve.bounds.1]),
+    _ => Spline::fit(&tcurve.tesselate_fixed(SECTION_SAMPLES)),
+  }
+}
+
+
+/// How the side faces of a loft pass through its profiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoftMode {
+  Ruled, // Straight between neighbouring profiles
+  Tangent, // Smooth across profiles
+}
+
+// Number of spans inserted between neighbouring profiles to follow guide rails
+const GUIDE_SPANS: usize = 4;
+
+
+pub fn loft(profiles: &[Profile], guides: &[CurveType], mode: LoftMode) -> Result<Compound, String> {
+  if profiles.len() < 2 { return Err("Lofts need at least two profiles".into()) }
+  if mode == LoftMode::Ruled && !guides.is_empty() {
+    return Err("Ruled lofts cannot follow guide rails".into())
+  }
+  let planes = profiles.iter()
+    .map(|profile| geom3d::plane_from_points(&geom2d::tesselate_wire(&profile[0])) )
+    .collect::<Result<Vec<Plane>, _>>()?;
+  // Corresponding curves of all profiles, with their wires laid end to end
+  let mut wires: Vec<Wire> = vec![profiles[0].concat()];
+  for profile in profiles.iter().skip(1) {
+    if profile.len() != profiles[0].len() {
+      return Err("Profiles must have the same number of holes".into())
+    }
+    let aligned = profile.iter().zip(&profiles[0]).map(|(wire, reference)| align_wire(wire, reference) ).collect::<Result<Vec<Wire>, String>>()?;
+    wires.push(aligned.concat());
+  }
+  let sections = compatible_sections(&wires);
+  let centers: Vec<Point3> = wires.iter().map(|wire| Point3::centroid(&wire.iter().map(|tcurve| tcurve.bounds.0 ).collect::<Vec<_>>()) ).collect();
+  let params: Vec<f64> = match mode {
+    LoftMode::Ruled => (0..wires.len()).map(|k| k as f64 / (wires.len() - 1) as f64 ).collect(),
+    LoftMode::Tangent => {
+      let mut params = vec![0.0];
+      for pair in centers.windows(2) {
+        params.push(params.last().unwrap() + pair[0].distance(pair[1]));
+      }
+      let length = *params.last().unwrap();
+      if length.almost(0.0) { return Err("Profiles must not lie on top of each other".into()) }
+      params.iter().map(|param| param / length ).collect()
+    },
+  };
+  let (stations, params) = guide_stations(&wires, sections, &params, guides)?;
+  let mut solid = Solid::new_lamina(&profiles[0], PlanarSurface::new(planes[0].clone()).into_enum());
+  let shell = &mut solid.shells[0];
+  let face = if (centers[1] - centers[0]).dot(planes[0].normal()) >= 0.0 {
+    shell.faces.last()
+  } else {
+    shell.faces.first()
+  }.unwrap().clone();
+  let mut cap = planes.last().unwrap().clone();
+  if let SurfaceType::Planar(surface) = &face.borrow().surface {
+    if surface.plane.normal().dot(cap.normal()) < 0.0 { cap.flip() }
+  }
+  let vertex_index = |point: Point3| wires[0].iter().position(|tcurve| tcurve.bounds.0.almost(point) ).unwrap();
+  let make_column = |points: Vec<Point3>| match mode {
+    LoftMode::Ruled => Spline::polyline(points),
+    LoftMode::Tangent => Spline::fit_with_params(&points, &params),
+  };
+  shell.sweep_with(
+    &face,
+    |point| stations.last().unwrap()[vertex_index(point)].controls[0],
+    |tcurve| wires.last().unwrap()[curve_index(&wires[0], tcurve)].base.clone(),
+    |point| {
+      let j = vertex_index(point);
+      make_column(stations.iter().map(|station| station[j].controls[0] ).collect()).into_enum()
+    },
+    |tcurve| {
+      let i = curve_index(&wires[0], tcurve);
+      let sections: Vec<Spline> = stations.iter().map(|station| station[i].clone() ).collect();
+      match mode {
+        LoftMode::Ruled => SplineSurface::ruled(&sections),
+        LoftMode::Tangent => SplineSurface::skinned(&sections, &params),
+      }.into_enum()
+    },
+    PlanarSurface::new(cap).into_enum(),
+  );
+  Ok(solid.into_compound())
+}
+
+// Orient a wire like its reference and start it at the matching vertex
+fn align_wire(wire: &Wire, reference: &Wire) -> Result<Wire, String> {
+  let n = wire.len();
+  if n != reference.len() { return Err("Profiles must consist of the same number of curves".into()) }
+  let mut wire = wire.clone();
+  if area_vector(&wire).dot(area_vector(reference)) < 0.0 {
+    wire.reverse();
+    for tcurve in &mut wire { tcurve.flip() }
+  }
+  let starts = |wire: &Wire| wire.iter().map(|tcurve| tcurve.bounds.0 ).collect::<Vec<Point3>>();
+  let (points, reference_points) = (starts(&wire), starts(reference));
+  let (center, reference_center) = (Point3::centroid(&points), Point3::centroid(&reference_points));
+  // Compare vertices relative to the centers of their profiles
+  let cost = |shift: usize| (0..n).map(|j| {
+    (points[(j + shift) % n] - center).distance(reference_points[j] - reference_center)
+  }).sum::<f64>();
+  let shift = (0..n).min_by(|&a, &b| cost(a).partial_cmp(&cost(b)).unwrap() ).unwrap();
+  wire.rotate_left(shift);
+  Ok(wire)
+}
+
+// Splines of corresponding curves share degree and knots,
+// so straight curves stay straight only if they are straight in all profiles
+fn compatible_sections(wires: &[Wire]) -> Vec<Vec<Spline>> {
+  let params: Vec<f64> = (0..=SECTION_SAMPLES).map(|i| i as f64 / SECTION_SAMPLES as f64 ).collect();
+  let straight: Vec<bool> = (0..wires[0].len()).map(|i| {
+    wires.iter().all(|wire| matches!(wire[i].base, CurveType::Line(_)) )
+  }).collect();
+  wires.iter().map(|wire| {
+    wire.iter().zip(&straight).map(|(tcurve, &straight)| if straight {
+      Spline::new(vec![tcurve.bounds.0, tcurve.bounds.1])
+    } else {
+      Spline::fit_with_params(&tcurve.tesselate_fixed(SECTION_SAMPLES), &params)
+    }).collect()
+  }).collect()
+}
+
+// Insert sections between profiles, that are pulled onto the guide rails
+fn guide_stations(wires: &[Wire], sections: Vec<Vec<Spline>>, params: &[f64], guides: &[CurveType]) -> Result<(Vec<Vec<Spline>>, Vec<f64>), String> {
+  if guides.is_empty() { return Ok((sections, params.to_vec())) }
+  // Vertex index and parameters at each profile for every guide
+  let guided = guides.iter().map(|guide| {
+    let curve = guide.as_curve();
+    let touches = |p: &Point3| curve.sample(curve.unsample(p)).distance(*p) < 1.0e-6;
+    let j = wires[0].iter().position(|tcurve| touches(&tcurve.bounds.0) )
+      .ok_or("Guide rails must pass through a vertex of the first profile")?;
+    if !wires.iter().all(|wire| touches(&wire[j].bounds.0) ) {
+      return Err("Guide rails must pass through corresponding vertices of all profiles".into())
+    }
+    Ok((j, wires.iter().map(|wire| curve.unsample(&wire[j].bounds.0) ).collect::<Vec<f64>>()))
+  }).collect::<Result<Vec<(usize, Vec<f64>)>, String>>()?;
+  let mut stations = vec![sections[0].clone()];
+  let mut station_params = vec![params[0]];
+  for k in 0..sections.len() - 1 {
+    for step in 1..GUIDE_SPANS {
+      let s = step as f64 / GUIDE_SPANS as f64;
+      let mut station: Vec<Spline> = sections[k].iter().zip(&sections[k + 1]).map(|(from, to)| {
+        let mut spline = from.clone();
+        spline.controls = from.controls.iter().zip(&to.controls).map(|(a, b)| a + (b - a) * s ).collect();
+        spline
+      }).collect();
+      let offsets: Vec<(Point3, Vec3)> = guides.iter().zip(&guided).map(|(guide, (j, guide_params))| {
+        let p = station[*j].controls[0];
+        let t = guide_params[k] + (guide_params[k + 1] - guide_params[k]) * s;
+        (p, guide.as_curve().sample(t) - p)
+      }).collect();
+      for spline in &mut station {
+        for control in &mut spline.controls {
+          *control += shepard_offset(*control, &offsets);
+        }
+      }
+      stations.push(station);
+      station_params.push(params[k] + (params[k + 1] - params[k]) * s);
+    }
+    stations.push(sections[k + 1].clone());
+    station_params.push(params[k + 1]);
+  }
+  Ok((stations, station_params))
+}
+
+// Inverse distance weighted offset, that is exact at the given points
+fn shepard_offset(p: Point3, offsets: &[(Point3, Vec3)]) -> Vec3 {
+  let mut sum = Vec3::new(0.0, 0.0, 0.0);
+  let mut total = 0.0;
+  for (q, offset) in offsets {
+    let distance2 = p.distance2(*q);
+    if distance2 < 1.0e-18 { return *offset }
+    sum += offset / distance2;
+    total += 1.0 / distance2;
+  }
+  sum / total
+}
+
+// Index of the curve with the same bounds and course in a wire
+fn curve_index(wire: &Wire, tcurve: &TrimmedCurve) -> usize {
+  let middle = tcurve.sample(0.5);
+  wire.iter().position(|other| {
+    let same_bounds = (other.bounds.0.almost(tcurve.bounds.0) && other.bounds.1.almost(tcurve.bounds.1))
+      || (other.bounds.0.almost(tcurve.bounds.1) && other.bounds.1.almost(tcurve.bounds.0));
+    same_bounds && other.sample(0.5).almost(middle)
+  }).unwrap()
+}
+
+
 pub fn draft(faces: &Vec<Ref<Face>>, fixed_plane: &Plane, angle: Deg<f64>) -> Result<(), String> {
   for face in faces {
     let mut face = face.borrow_mut();
-    match &face.surface {
+    match &mut face.surface {
       SurfaceType::Planar(plane) => {
         if let Some(intersection) = intersection::plane_plane(&plane.plane, fixed_plane) {
           if let Some(line) = intersection.get_line() {
             let axis = Axis::from_points(line.endpoints());
-            face.surface.as_surface_mut().rotate_about_axis(&axis, angle);
+            plane.rotate_about_axis(&axis, angle);
           }
         }
       },
-      SurfaceType::Revolution(_) => todo!(),
-      SurfaceType::Spline(_) => todo!(),
+      // Cylinders turn into cones
+      SurfaceType::Revolution(surface) => surface.map_profile(&|p, normal| draft_point(p, normal, fixed_plane, angle) ),
+      SurfaceType::Spline(surface) => surface.map_points(&|p, normal| draft_point(p, normal, fixed_plane, angle) ),
+    }
+  }
+  for face in faces {
+    face.borrow().repair_edges()?;
+  }
+  Ok(())
+}
+
+
+// Move a point against the sideways part of its surface normal,
+// proportionally to its height above the fixed plane
+fn draft_point(p: Point3, normal: Vec3, fixed_plane: &Plane, angle: Deg<f64>) -> Point3 {
+  let pull = fixed_plane.normal().normalize();
+  let side = normal - pull * normal.dot(pull);
+  if side.magnitude().almost(0.0) { return p }
+  let height = (p - fixed_plane.origin).dot(pull);
+  p - side.normalize() * height * Rad::from(angle).0.tan()
+}
+
+
+// Number of samples used to approximate offset edges
+const OFFSET_EDGE_SAMPLES: usize = 8;
+
+/// Hollow solids by moving their faces inward by a wall thickness.
+/// Selected faces are removed to open up the solid, leaving only a rim.
+/// Solids are hollowed into a closed void when no faces are given.
+pub fn shell(compound: &mut Compound, faces: &[Ref<Face>], thickness: f64) -> Result<(), String> {
+  if thickness <= 0.0 { return Err("Wall thickness must be positive".into()) }
+  let num_found = compound.solids.iter()
+    .flat_map(|solid| &solid.shells[0].faces )
+    .filter(|face| faces.iter().any(|f| Rc::ptr_eq(f, face) ) )
+    .count();
+  if num_found != faces.len() { return Err("Face does not belong to outer shell of compound".into()) }
+  // Solids are hollowed on copies, so the compound stays untouched on failure
+  let mut solids = vec![];
+  for solid in &compound.solids {
+    let removed: Vec<usize> = solid.shells[0].faces.iter().enumerate()
+      .filter(|(_, face)| faces.iter().any(|f| Rc::ptr_eq(f, face) ) )
+      .map(|(i, _)| i )
+      .collect();
+    if !faces.is_empty() && removed.is_empty() {
+      solids.push(solid.clone());
+      continue
+    }
+    let mut hollowed = solid.deep_clone();
+    hollowed.id = solid.id;
+    hollow(&mut hollowed, &removed, thickness)?;
+    solids.push(hollowed);
+  }
+  compound.solids = solids;
+  Ok(())
+}
+
+
+fn hollow(solid: &mut Solid, removed: &[usize], thickness: f64) -> Result<(), String> {
+  let outer = &solid.shells[0];
+  if removed.iter().any(|&i| outer.faces[i].borrow().rings.len() > 1 ) {
+    return Err("Removed faces must not have holes".into())
+  }
+  // Faces, edges and vertices of the copy keep the order of the original
+  let copy = Solid { id: Uuid::new_v4(), shells: vec![outer.clone()] };
+  let mut inner = copy.deep_clone().shells.remove(0);
+  // Move faces inward, leaving removed faces in place to form the rims
+  for (i, (face, inner_face)) in outer.faces.iter().zip(&inner.faces).enumerate() {
+    let face = face.borrow();
+    let mut inner_face = inner_face.borrow_mut();
+    inner_face.id = Uuid::new_v4();
+    inner_face.flip_normal = !face.flip_normal;
+    if removed.contains(&i) { continue }
+    let orientation = outer.face_orientation(&face);
+    inner_face.surface.as_surface_mut().offset(-thickness * orientation);
+  }
+  // Re-intersect neighbouring offset faces
+  for vertex in &inner.vertices {
+    let surfaces = incident_surfaces(&inner, vertex);
+    let p = vertex.borrow().point;
+    vertex.borrow_mut().point = intersect_surfaces(p, &surfaces)?;
+  }
+  for (edge, inner_edge) in outer.edges.iter().zip(&inner.edges) {
+    let mut inner_edge = inner_edge.borrow_mut();
+    let left_face = inner_edge.left_half.borrow().get_face();
+    let right_face = inner_edge.right_half.borrow().get_face();
+    let mut surfaces = vec![left_face.borrow().surface.clone()];
+    if !Rc::ptr_eq(&left_face, &right_face) { surfaces.push(right_face.borrow().surface.clone()) }
+    let edge = edge.borrow();
+    let ends = (
+      (edge.left_half.borrow().origin.borrow().point, inner_edge.left_half.borrow().origin.borrow().point),
+      (edge.right_half.borrow().origin.borrow().point, inner_edge.right_half.borrow().origin.borrow().point),
+    );
+    let mut curve = offset_edge_curve(&edge.curve, ends, &surfaces)?;
+    // Inner curves get stable ids derived from their outer counterparts
+    curve.set_id(Uuid::new_v5(&edge.curve.get_id(), b"hollow"));
+    inner_edge.id = Uuid::new_v4();
+    inner_edge.curve = curve;
+  }
+  for face in &inner.faces {
+    trim_profile(face);
+  }
+  // Inner faces face the other way
+  for face in &inner.faces {
+    let rings = face.borrow().rings.clone();
+    for ring in &rings {
+      let half_edges: Vec<Ref<HalfEdge>> = ring.borrow().iter().collect();
+      let origins: Vec<Ref<Vertex>> = half_edges.iter().map(|he| he.borrow().origin.clone() ).collect();
+      let len = half_edges.len();
+      for (i, he) in half_edges.iter().enumerate() {
+        let mut he = he.borrow_mut();
+        he.next = Rc::downgrade(&half_edges[(i + len - 1) % len]);
+        he.previous = Rc::downgrade(&half_edges[(i + 1) % len]);
+        he.origin = origins[(i + 1) % len].clone();
+      }
+      for he in &half_edges {
+        he.borrow().origin.borrow_mut().half_edge = Rc::downgrade(he);
+      }
+    }
+  }
+  if removed.is_empty() {
+    solid.shells.push(inner);
+  } else {
+    // Open up the solid by turning inner copies of removed faces into holes of the rims
+    let rims: Vec<(Ref<Face>, Ref<Face>)> = removed.iter().map(|&i| (outer.faces[i].clone(), inner.faces[i].clone()) ).collect();
+    let outer = &mut solid.shells[0];
+    outer.faces.append(&mut inner.faces);
+    outer.edges.append(&mut inner.edges);
+    outer.vertices.append(&mut inner.vertices);
+    for (face, inner_face) in rims {
+      outer.lkfmrh(&face, &inner_face);
+    }
+  }
+  Ok(())
+}
+
+
+// Surfaces of all faces around a vertex
+fn incident_surfaces(shell: &Shell, vertex: &Ref<Vertex>) -> Vec<SurfaceType> {
+  shell.faces.iter().filter(|face| {
+    face.borrow().rings.iter().any(|ring| ring.borrow().iter().any(|he| Rc::ptr_eq(&he.borrow().origin, vertex) ) )
+  }).map(|face| face.borrow().surface.clone() ).collect()
+}
+
+
+// Revolution surfaces are bounded by their profile, so offset lines
+// need to end where their boundary meets the neighbouring faces
+fn trim_profile(face: &Ref<Face>) {
+  let boundary: Vec<Point3> = face.borrow().rings.iter().flat_map(|ring| {
+    ring.borrow().iter().flat_map(|he| {
+      let p = he.borrow().origin.borrow().point;
+      let mid = he.borrow().get_edge().borrow().curve.as_curve().sample(0.5);
+      [p, mid]
+    }).collect::<Vec<Point3>>()
+  }).collect();
+  let mut face = face.borrow_mut();
+  if let SurfaceType::Revolution(surface) = &mut face.surface {
+    if let CurveType::Line(line) = &mut surface.curve {
+      let to_local = surface.axis.as_transform().invert().unwrap();
+      let params: Vec<f64> = boundary.iter().map(|&p| {
+        let q = to_local.transform_point(p);
+        line.unsample(&Point3::new(q.x.hypot(q.y), 0.0, q.z))
+      }).collect();
+      let min = params.iter().cloned().fold(f64::MAX, f64::min);
+      let max = params.iter().cloned().fold(f64::MIN, f64::max);
+      line.points = (line.sample(min), line.sample(max));
+    }
+  }
+}
+
+
+// Closest point to p that lies on all given surfaces, found with Gauss-Newton steps
+// Steps take the shortest way where the surfaces don't pin down a single point
+fn intersect_surfaces(p: Point3, surfaces: &[SurfaceType]) -> Result<Point3, String> {
+  let residuals = |x: Point3| surfaces.iter().map(|surface| {
+    let surface = surface.as_surface();
+    let (u, v) = surface.unsample(x);
+    let normal = surface.normal_at(u, v);
+    (normal, normal.dot(x - surface.sample(u, v)))
+  }).collect::<Vec<(Vec3, f64)>>();
+  let mut x = p;
+  for _ in 0..32 {
+    let (jtj, jtr) = residuals(x).into_iter().fold(
+      (Matrix3::from_value(0.0), Vec3::new(0.0, 0.0, 0.0)),
+      |(jtj, jtr), (normal, r)| (jtj + Matrix3::from_cols(normal * normal.x, normal * normal.y, normal * normal.z), jtr + normal * r)
+    );
+    let step = (jtj + Matrix3::identity() * 1.0e-6)
+      .invert().ok_or("Offset faces do not intersect")? * jtr;
+    x -= step;
+    if step.magnitude() < 1.0e-12 { break }
+  }
+  if residuals(x).iter().all(|(_, r)| r.almost(0.0) ) {
+    Ok(x)
+  } else {
+    Err("Offset faces do not intersect".into())
+  }
+}
+
+
+// Intersection curve of offset faces, given pairs of original and offset edge ends
+fn offset_edge_curve(curve: &CurveType, ends: ((Point3, Point3), (Point3, Point3)), surfaces: &[SurfaceType]) -> Result<CurveType, String> {
+  let project = |t: f64| intersect_surfaces(curve.as_curve().sample(t), surfaces);
+  Ok(match curve {
+    CurveType::Line(line) => {
+      let ((from, inner_from), (_, inner_to)) = ends;
+      if line.points.0.distance(from) < line.points.1.distance(from) {
+        Line::new(inner_from, inner_to).into_enum()
+      } else {
+        Line::new(inner_to, inner_from).into_enum()
+      }
+    },
+    CurveType::Circle(circle) => {
+      let (plane, radius) = plane_through(project(0.0)?, project(1.0 / 3.0)?, project(2.0 / 3.0)?, &circle.plane)?;
+      Circle::from_plane(plane, radius).into_enum()
+    },
+    CurveType::Arc(arc) => {
+      let (start, end) = (project(0.0)?, project(1.0)?);
+      let (plane, radius) = plane_through(start, project(0.5)?, end, &arc.plane)?;
+      let circle = Circle::from_plane(plane.clone(), radius);
+      let start = circle.unsample(&start);
+      let mut end = circle.unsample(&end);
+      // Keep direction and extent of original arc
+      let range = arc.range();
+      while end - start - range > 0.5 { end -= 1.0 }
+      while end - start - range < -0.5 { end += 1.0 }
+      Arc::from_plane(plane, radius, start, end).into_enum()
+    },
+    CurveType::Spline(_) => {
+      let points = (0..=OFFSET_EDGE_SAMPLES)
+        .map(|i| project(i as f64 / OFFSET_EDGE_SAMPLES as f64) )
+        .collect::<Result<Vec<Point3>, String>>()?;
+      Spline::fit(&points).into_enum()
+    },
+  })
+}
+
+
+// Plane centered on the circle through three points, with axes oriented like a reference plane
+fn plane_through(p1: Point3, p2: Point3, p3: Point3, reference: &Plane) -> Result<(Plane, f64), String> {
+  let triangle = Plane::from_triangle(p1, p2, p3);
+  let local = |p: Point3| {
+    let (u, v) = triangle.unsample(p);
+    Point3::new(u, v, 0.0)
+  };
+  let circle = Circle::from_points(local(p1), local(p2), local(p3))?;
+  let center = triangle.sample(circle.plane.origin.x, circle.plane.origin.y);
+  let mut normal = triangle.normal().normalize();
+  if normal.dot(reference.normal()) < 0.0 { normal = -normal }
+  let u = (reference.u - normal * reference.u.dot(normal)).normalize();
+  let plane = Plane { origin: center, u, v: normal.cross(u) };
+  Ok((plane, center.distance(p1)))
+}
+
+
+// Rolling ball blend or flat bevel between the faces of an edge
+#[derive(Debug, Clone, Copy)]
+enum Blend {
+  Fillet(f64),
+  Chamfer(f64),
+}
+
+impl Blend {
+  // Distance from the edge to where the blend meets a face,
+  // given the angle between the faces' directions away from the edge
+  fn setback(&self, angle: f64) -> f64 {
+    match self {
+      Self::Fillet(radius) => radius / (angle / 2.0).tan(),
+      Self::Chamfer(distance) => *distance,
+    }
+  }
+
+  // Center of the rolling ball
+  fn center(&self, p: Point3, direction: Vec3, other_direction: Vec3) -> Point3 {
+    let angle = direction.angle(other_direction).0;
+    let radius = match self {
+      Self::Fillet(radius) => *radius,
+      Self::Chamfer(distance) => distance * (angle / 2.0).tan(),
+    };
+    p + (direction + other_direction).normalize() * (radius / (angle / 2.0).sin())
+  }
+
+  // Profile of the blend between two contact points
+  fn section(&self, center: Point3, from: Point3, to: Point3) -> CurveType {
+    match self {
+      Self::Fillet(_) => short_arc(center, from, to).into_enum(),
+      Self::Chamfer(_) => Line::new(from, to).into_enum(),
+    }
+  }
+}
+
+
+pub fn fillet(compound: &mut Compound, edges: &[Ref<Edge>], radius: f64) -> Result<(), String> {
+  if radius <= 0.0 { return Err("Fillet radius must be positive".into()) }
+  blend(compound, edges, Blend::Fillet(radius))
+}
+
+
+pub fn chamfer(compound: &mut Compound, edges: &[Ref<Edge>], distance: f64) -> Result<(), String> {
+  if distance <= 0.0 { return Err("Chamfer distance must be positive".into()) }
+  blend(compound, edges, Blend::Chamfer(distance))
+}
+
+
+fn blend(compound: &mut Compound, edges: &[Ref<Edge>], blend: Blend) -> Result<(), String> {
+  // Blends meeting at a vertex would need a corner patch
+  let mut vertices: Vec<Ref<Vertex>> = vec![];
+  for edge in edges {
+    let edge = edge.borrow();
+    let left = edge.left_half.borrow().origin.clone();
+    let right = edge.right_half.borrow().origin.clone();
+    let ends = if Rc::ptr_eq(&left, &right) { vec![left] } else { vec![left, right] };
+    for vertex in ends {
+      if vertices.iter().any(|v| Rc::ptr_eq(v, &vertex) ) {
+        return Err("Blended edges must not share vertices".into())
+      }
+      vertices.push(vertex);
+    }
+  }
+  // Blend copies of the solids, so an edge failing halfway leaves the compound untouched
+  let mut solids: Vec<Solid> = compound.solids.iter().map(|solid| Solid {
+    id: solid.id,
+    ..solid.deep_clone()
+  }).collect();
+  for edge in edges {
+    if !compound.solids.iter().flat_map(|solid| &solid.shells ).any(|shell| shell.edges.iter().any(|e| Rc::ptr_eq(e, edge) ) ) {
+      return Err("Edge does not belong to compound".into())
+    }
+    // Copies keep the ids of their edges
+    let id = edge.borrow().id;
+    let shell = solids.iter_mut()
+      .flat_map(|solid| solid.shells.iter_mut() )
+      .find(|shell| shell.edges.iter().any(|e| e.borrow().id == id ) )
+      .ok_or("Edge does not belong to compound")?;
+    let edge = shell.edges.iter().find(|e| e.borrow().id == id ).unwrap().clone();
+    let is_circle = matches!(edge.borrow().curve, CurveType::Circle(_));
+    if is_circle {
+      blend_circle(shell, &edge, blend)?;
+    } else {
+      blend_line(shell, &edge, blend)?;
+    }
+  }
+  compound.solids = solids;
+  Ok(())
+}
+
+
+// Blend a straight edge between two planar faces
+fn blend_line(shell: &mut Shell, edge: &Ref<Edge>, blend: Blend) -> Result<(), String> {
+  let (he1, he2) = {
+    let edge = edge.borrow();
+    if !matches!(edge.curve, CurveType::Line(_)) {
+      return Err("Only straight and circular edges can be blended".into())
+    }
+    (edge.left_half.clone(), edge.right_half.clone())
+  };
+  let face1 = he1.borrow().get_face();
+  let face2 = he2.borrow().get_face();
+  if !matches!(face1.borrow().surface, SurfaceType::Planar(_)) || !matches!(face2.borrow().surface, SurfaceType::Planar(_)) {
+    return Err("Straight edges can only be blended between planar faces".into())
+  }
+  let a = he1.borrow().origin.clone();
+  let b = he2.borrow().origin.clone();
+  if a.borrow().edges_iter().count() != 3 || b.borrow().edges_iter().count() != 3 {
+    return Err("Blended edges must end in vertices shared by three edges".into())
+  }
+  let pa = a.borrow().point;
+  let pb = b.borrow().point;
+  let direction = (pb - pa).normalize();
+  // Neighbouring edges, given by their half edges leaving the blended edge
+  let neighbours = [
+    he1.borrow().get_previous().borrow().mate(), // At A in face 1
+    he2.borrow().get_next(), // At A in face 2
+    he1.borrow().get_next(), // At B in face 1
+    he2.borrow().get_previous().borrow().mate(), // At B in face 2
+  ];
+  let mut directions = vec![];
+  let mut lengths = vec![];
+  for he in &neighbours {
+    let he = he.borrow();
+    if !matches!(he.get_edge().borrow().curve, CurveType::Line(_)) {
+      return Err("Blended edges must meet straight edges".into())
+    }
+    let vec = he.end_vertex().borrow().point - he.origin.borrow().point;
+    if vec.normalize().dot(direction).abs() > 1.0e-6 {
+      return Err("Blended edges must meet their neighbours at right angles".into())
+    }
+    directions.push(vec.normalize());
+    lengths.push(vec.magnitude());
+  }
+  let angle = directions[0].angle(directions[1]).0;
+  let setback = blend.setback(angle);
+  if lengths.iter().any(|&length| setback >= length - 1.0e-6 ) {
+    return Err("Blend is too large for adjacent edges".into())
+  }
+  let points: Vec<Point3> = [pa, pa, pb, pb].iter().zip(&directions).map(|(&p, &d)| p + d * setback ).collect();
+  // Split neighbours at the contact points
+  let mut struts = vec![];
+  let mut contacts = vec![];
+  for (he, &p) in neighbours.iter().zip(&points) {
+    let (strut, vertex) = shell.split_edge(he, p)?;
+    struts.push(strut);
+    contacts.push(vertex);
+  }
+  // Cut off slivers along the edge in both faces
+  let surface1 = face1.borrow().surface.clone();
+  let surface2 = face2.borrow().surface.clone();
+  let (_, region) = shell.mef(&find_in_ring(&he1, &contacts[0]), &find_in_ring(&he1, &contacts[2]), Line::new(points[0], points[2]).into_enum(), surface1)?;
+  shell.mef(&find_in_ring(&he2, &contacts[3]), &find_in_ring(&he2, &contacts[1]), Line::new(points[3], points[1]).into_enum(), surface2)?;
+  // Cut off corners of the end faces
+  let center_a = blend.center(pa, directions[0], directions[1]);
+  let center_b = blend.center(pb, directions[2], directions[3]);
+  for (strut, center, from, to) in [
+    (&struts[0], center_a, 1, 0),
+    (&struts[2], center_b, 2, 3),
+  ] {
+    let he = outside_half(strut, &region);
+    let surface = he.borrow().get_face().borrow().surface.clone();
+    shell.mef(
+      &find_in_ring(&he, &contacts[from]),
+      &find_in_ring(&he, &contacts[to]),
+      blend.section(center, points[from], points[to]),
+      surface,
+    )?;
+  }
+  // Merge slivers and corners into a single blend face
+  shell.kef(&he1, &he2)?;
+  for strut in [&struts[0], &struts[2]] {
+    let he = outside_half(strut, &region);
+    let mate = he.borrow().mate();
+    shell.kef(&mate, &he)?;
+  }
+  for (strut, vertex) in [(&struts[1], &a), (&struts[3], &b)] {
+    let (he, mate) = {
+      let strut = strut.borrow();
+      (strut.left_half.clone(), strut.right_half.clone())
+    };
+    if Rc::ptr_eq(&he.borrow().origin, vertex) {
+      shell.kev(&he, &mate)?;
+    } else {
+      shell.kev(&mate, &he)?;
+    }
+  }
+  region.borrow_mut().surface = match blend {
+    Blend::Fillet(_) => {
+      // Roll from the contact line in face 1 towards face 2
+      let spin = (points[0] - center_a).cross(points[1] - center_a).dot(direction);
+      let axis = Axis::new(center_a, direction * spin.signum());
+      RevolutionSurface::with_bounds(
+        axis,
+        Line::new(points[0], points[2]).into_enum(),
+        (0.0, (std::f64::consts::PI - angle) / (std::f64::consts::PI * 2.0)),
+      ).into_enum()
+    },
+    Blend::Chamfer(_) => PlanarSurface::new(Plane::from_triangle(points[0], points[2], points[1])).into_enum(),
+  };
+  Ok(())
+}
+
+
+// Blend a circular edge between a planar cap and a cylinder
+fn blend_circle(shell: &mut Shell, edge: &Ref<Edge>, blend: Blend) -> Result<(), String> {
+  let (he1, he2, circle) = {
+    let edge = edge.borrow();
+    let circle = if let CurveType::Circle(circle) = &edge.curve { circle.clone() } else { unreachable!() };
+    (edge.left_half.clone(), edge.right_half.clone(), circle)
+  };
+  let (cap_he, side_he) = if matches!(he1.borrow().get_face().borrow().surface, SurfaceType::Planar(_)) {
+    (he1, he2)
+  } else {
+    (he2, he1)
+  };
+  let cap = cap_he.borrow().get_face();
+  let side = side_he.borrow().get_face();
+  let axis = match (&cap.borrow().surface, &side.borrow().surface) {
+    (SurfaceType::Planar(_), SurfaceType::Revolution(surface)) => surface.axis.clone(),
+    _ => return Err("Circular edges can only be blended between a planar face and a cylinder".into()),
+  };
+  let vertex = cap_he.borrow().origin.clone();
+  if vertex.borrow().edges_iter().count() != 3 {
+    return Err("Blended edges must end in vertices shared by three edges".into())
+  }
+  let seam = side_he.borrow().get_next();
+  if !matches!(seam.borrow().get_edge().borrow().curve, CurveType::Line(_)) {
+    return Err("Circular edges can only be blended between a planar face and a cylinder".into())
+  }
+  let p = vertex.borrow().point;
+  let center = circle.plane.origin;
+  let seam_end = seam.borrow().end_vertex().borrow().point;
+  // Material lies inside of outer rings
+  let is_outer = Rc::ptr_eq(&cap_he.borrow().ring.upgrade().unwrap(), &cap.borrow().outer_ring);
+  let cap_direction = if is_outer { (center - p).normalize() } else { (p - center).normalize() };
+  let side_direction = (seam_end - p).normalize();
+  if cap_direction.dot(side_direction).abs() > 1.0e-6 {
+    return Err("Circular edges can only be blended between a planar face and a cylinder".into())
+  }
+  let angle = cap_direction.angle(side_direction).0;
+  let setback = blend.setback(angle);
+  if setback >= seam_end.distance(p) - 1.0e-6 || (is_outer && setback >= circle.radius - 1.0e-6) {
+    return Err("Blend is too large for adjacent faces".into())
+  }
+  let p1 = p + cap_direction * setback;
+  let p2 = p + side_direction * setback;
+  // Cut a band off the cylinder
+  let (upper, _) = shell.split_edge(&seam, p2)?;
+  let mut lower_circle = edge.borrow().curve.clone();
+  lower_circle.as_curve_mut().transform(&Matrix4::from_translation(p2 - p));
+  let side_surface = side.borrow().surface.clone();
+  let upper_left = upper.borrow().left_half.clone();
+  let (_, band) = shell.mef(&seam, &upper_left, lower_circle, side_surface)?;
+  let side_ring = side_he.borrow().ring.upgrade().unwrap();
+  let band_ring = band.borrow().outer_ring.clone();
+  Shell::exchange_rings(&side_ring, &band_ring);
+  // Revolution surfaces are bounded by their curve, so the cylinder has to end at the band
+  if let SurfaceType::Revolution(surface) = &mut side.borrow_mut().surface {
+    let to_local = surface.axis.as_transform().invert().unwrap();
+    let (z, new_z) = (to_local.transform_point(p).z, to_local.transform_point(p2).z);
+    if let CurveType::Line(line) = &mut surface.curve {
+      if (line.points.0.z - z).abs() < (line.points.1.z - z).abs() {
+        line.points.0.z = new_z;
+      } else {
+        line.points.1.z = new_z;
+      }
     }
   }
+  // Cut an annulus off the cap
+  let (strut, _) = shell.mev(&cap_he, &cap_he, Line::new(p, p1).into_enum(), p1)?;
+  let tip = strut.borrow().left_half.clone();
+  let cap_circle = Circle::from_plane(circle.plane.clone(), p1.distance(center)).into_enum();
+  let cap_surface = cap.borrow().surface.clone();
+  let (_, annulus) = shell.mef(&tip, &tip, cap_circle, cap_surface)?;
+  let cap_ring = cap_he.borrow().ring.upgrade().unwrap();
+  let annulus_ring = annulus.borrow().outer_ring.clone();
+  Shell::exchange_rings(&cap_ring, &annulus_ring);
+  // Merge band and annulus into a single blend face
+  shell.kef(&side_he, &cap_he)?;
+  let (strut_right, strut_left) = {
+    let strut = strut.borrow();
+    (strut.right_half.clone(), strut.left_half.clone())
+  };
+  shell.kev(&strut_right, &strut_left)?;
+  let section = blend.section(blend.center(p, cap_direction, side_direction), p1, p2);
+  upper.borrow_mut().curve = section.clone();
+  band.borrow_mut().surface = RevolutionSurface::new(axis, section).into_enum();
   Ok(())
 }
 
 
+// Find the half edge starting at the given vertex in the ring of another half edge
+fn find_in_ring(he: &Ref<HalfEdge>, vertex: &Ref<Vertex>) -> Ref<HalfEdge> {
+  let ring = he.borrow().ring.upgrade().unwrap();
+  let ring = ring.borrow();
+  ring.iter().find(|he| Rc::ptr_eq(&he.borrow().origin, vertex) ).unwrap()
+}
+
+
+// Half of an edge that does not belong to the given face
+fn outside_half(edge: &Ref<Edge>, face: &Ref<Face>) -> Ref<HalfEdge> {
+  let edge = edge.borrow();
+  if Rc::ptr_eq(&edge.left_half.borrow().get_face(), face) {
+    edge.right_half.clone()
+  } else {
+    edge.left_half.clone()
+  }
+}
+
+
+// Arc around center from one point to another, taking the shorter way
+fn short_arc(center: Point3, from: Point3, to: Point3) -> Arc {
+  let plane = Plane::from_triangle(center, from, to);
+  let radius = from.distance(center);
+  let circle = Circle::from_plane(plane.clone(), radius);
+  let start = circle.unsample(&from);
+  let mut end = circle.unsample(&to);
+  if end - start > 0.5 { end -= 1.0 }
+  if end - start < -0.5 { end += 1.0 }
+  Arc::from_plane(plane, radius, start, end)
+}
+
+
 pub fn make_cube(dx: f64, dy: f64, dz: f64) -> Result<Compound, String> {
   let mut points = vec![
     Point3::new(0.0, 0.0, 0.0),
@@ -148,10 +1048,63 @@ pub fn make_cylinder(radius: f64, height: f64) -> Result<Compound, String> {
 }
 
 
+// Single face bounded by a seam from pole to pole
+pub fn make_sphere(radius: f64) -> Result<Compound, String> {
+  if radius <= 0.0 { return Err("Sphere radius must be positive".into()) }
+  let surface = RevolutionSurface::sphere(Point3::origin(), radius);
+  let seam = surface.profile_at(0.0);
+  let (south, north) = seam.as_curve().endpoints();
+  let mut solid = Solid::new();
+  let (vertex, _, shell) = solid.lmvfs(south, surface.into_enum());
+  let he = vertex.borrow().get_half_edge();
+  shell.lmev(&he, &he, seam, north);
+  Ok(solid.into_compound())
+}
+
+
+// Cone standing on its base with the apex on top
+pub fn make_cone(radius: f64, height: f64) -> Result<Compound, String> {
+  if radius <= 0.0 || height <= 0.0 { return Err("Cone dimensions must be positive".into()) }
+  let profile = vec![vec![
+    TrimmedCurve::new(Circle::new(Point3::origin(), radius).into_enum())
+  ]];
+  let mut solid = Solid::new_lamina(&profile, PlanarSurface::new(Plane::new()).into_enum());
+  let shell = &mut solid.shells[0];
+  let lateral = shell.faces[1].clone();
+  lateral.borrow_mut().surface = RevolutionSurface::cone(Axis::new(Point3::origin(), Vec3::unit_z()), radius, height).into_enum();
+  let he = lateral.borrow().outer_ring.borrow().half_edge.clone();
+  let base = he.borrow().origin.borrow().point;
+  let apex = Point3::new(0.0, 0.0, height);
+  shell.lmev(&he, &he, Line::new(base, apex).into_enum(), apex);
+  Ok(solid.into_compound())
+}
+
+
+// Single face bounded by a circle around the axis and a circle around the tube
+pub fn make_torus(major_radius: f64, minor_radius: f64) -> Result<Compound, String> {
+  if minor_radius <= 0.0 || major_radius <= minor_radius {
+    return Err("Torus needs a positive tube radius smaller than its major radius".into())
+  }
+  let surface = RevolutionSurface::torus(Axis::new(Point3::origin(), Vec3::unit_z()), major_radius, minor_radius);
+  let meridian = surface.profile_at(0.0);
+  let p = Point3::new(major_radius + minor_radius, 0.0, 0.0);
+  let parallel = Circle::new(Point3::origin(), major_radius + minor_radius).into_enum();
+  let mut solid = Solid::new();
+  let (vertex, face, shell) = solid.lmvfs(p, surface.clone().into_enum());
+  let he = vertex.borrow().get_half_edge();
+  let (_, other) = shell.lmef(&he, &he, parallel, surface.into_enum());
+  shell.lkfmrh(&face, &other);
+  let he1 = face.borrow().rings[0].borrow().half_edge.clone();
+  let he2 = face.borrow().rings[1].borrow().half_edge.clone();
+  shell.lmekr(&he1, &he2, meridian);
+  Ok(solid.into_compound())
+}
+
+
 #[cfg(test)]
 mod tests {
   use super::*;
-  // use crate::test_data;
+  use crate::test_data::{square_wire, shell_counts};
 
   #[test]
   fn cube() {
@@ -166,7 +1119,6 @@ mod tests {
   }
 
   #[test]
-  #[ignore]
   fn cylinder() {
     let cube = &make_cylinder(1.0, 1.0).unwrap().solids[0];
     let shell = &cube.shells[0];
@@ -180,4 +1132,374 @@ mod tests {
     assert_eq!(shell.faces[2].borrow().outer_ring.borrow().iter().count(), 4);
     // panic!("Test trap");
   }
+
+  #[test]
+  fn extrude_square_hole() {
+    let profile = vec![square_wire(3.0, 0.0), square_wire(1.0, 1.0)];
+    let compound = extrude(&profile, 1.0).unwrap();
+    let solid = &compound.solids[0];
+    let shell = &solid.shells[0];
+    assert_eq!(shell.vertices.len(), 16);
+    assert_eq!(shell.edges.len(), 24);
+    assert_eq!(shell.faces.len(), 10);
+    assert_eq!(shell.faces[0].borrow().rings.len(), 2);
+    assert_eq!(shell.faces[1].borrow().rings.len(), 2);
+    assert_eq!(shell.genus(), 1);
+    almost_eq(solid.volume(), 8.0);
+  }
+
+  #[test]
+  fn extrude_circular_hole() {
+    let profile = vec![
+      square_wire(4.0, 0.0),
+      vec![TrimmedCurve::new(Circle::new(Point3::new(2.0, 2.0, 0.0), 1.0).into_enum())],
+    ];
+    let solid = &extrude(&profile, 2.0).unwrap().solids[0];
+    let shell = &solid.shells[0];
+    assert_eq!(shell.faces.len(), 7);
+    assert!((solid.volume() - (32.0 - std::f64::consts::PI * 2.0)).abs() < 1e-6);
+  }
+
+  #[test]
+  fn sweep_line() {
+    let rail = Line::new(Point3::origin(), Point3::new(0.0, 0.0, 2.0)).into_enum();
+    let compound = sweep(&vec![square_wire(1.0, 0.0)], &rail, (0.0, 1.0), Framing::FixedNormal(Vec3::unit_x())).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    assert!((compound.solids[0].volume() - 2.0).abs() < 1e-6);
+  }
+
+  #[test]
+  fn sweep_arc() {
+    let profile = vec![vec![
+      TrimmedCurve::new(Line::new(Point3::new(2.0, 0.0, 0.0), Point3::new(3.0, 0.0, 0.0)).into_enum()),
+      TrimmedCurve::new(Line::new(Point3::new(3.0, 0.0, 0.0), Point3::new(3.0, 0.0, 1.0)).into_enum()),
+      TrimmedCurve::new(Line::new(Point3::new(3.0, 0.0, 1.0), Point3::new(2.0, 0.0, 1.0)).into_enum()),
+      TrimmedCurve::new(Line::new(Point3::new(2.0, 0.0, 1.0), Point3::new(2.0, 0.0, 0.0)).into_enum()),
+    ]];
+    let plane = Plane { origin: Point3::origin(), u: Vec3::unit_y(), v: Vec3::unit_x() };
+    let rail = Arc::from_plane(plane, 2.5, 0.0, 0.25).into_enum();
+    let compound = sweep(&profile, &rail, (0.0, 1.0), Framing::Frenet).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let properties = compound.solids[0].mass_properties(None);
+    assert!((properties.volume - std::f64::consts::PI / 2.0 * 2.5).abs() < 1e-3);
+    assert!(properties.center_of_mass.x > 0.0 && properties.center_of_mass.y > 0.0);
+  }
+
+  #[test]
+  fn sweep_spline() {
+    let rail = Spline::fit(&[
+      Point3::new(0.0, 0.0, 0.0),
+      Point3::new(1.0, 0.2, 0.0),
+      Point3::new(2.0, 0.8, 0.0),
+      Point3::new(2.6, 1.8, 0.0),
+    ]);
+    let tangent = rail.tangent_at(0.0);
+    let circle = Circle::from_plane(Plane::from_normal(Point3::origin(), tangent), 0.2);
+    let length = rail.length_between(0.0, 1.0);
+    let compound = sweep(&vec![vec![TrimmedCurve::new(circle.into_enum())]], &rail.into_enum(), (0.0, 1.0), Framing::Frenet).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!(shell.faces.len(), 3);
+    assert!(matches!(shell.faces[2].borrow().surface, SurfaceType::Spline(_)));
+    // Pappus' theorem holds for profiles moving perpendicular to planar rails
+    let volume = std::f64::consts::PI * 0.04 * length;
+    assert!((compound.solids[0].volume() - volume).abs() < volume * 1e-2);
+  }
+
+  fn lifted(mut wire: Wire, z: f64) -> Wire {
+    for tcurve in &mut wire {
+      tcurve.transform(&Matrix4::from_translation(Vec3::new(0.0, 0.0, z)));
+    }
+    wire
+  }
+
+  #[test]
+  fn loft_ruled() {
+    let profiles = vec![vec![square_wire(2.0, 0.0)], vec![lifted(square_wire(1.0, 0.5), 1.0)]];
+    let compound = loft(&profiles, &[], LoftMode::Ruled).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    assert_eq!(compound.solids[0].shells[0].faces.len(), 6);
+    // Frustum of a pyramid
+    assert!((compound.solids[0].volume() - 7.0 / 3.0).abs() < 1e-6);
+  }
+
+  #[test]
+  fn loft_tangent() {
+    let circle = |z, radius| vec![vec![TrimmedCurve::new(Circle::new(Point3::new(0.0, 0.0, z), radius).into_enum())]];
+    let profiles = vec![circle(0.0, 1.0), circle(1.0, 1.5), circle(2.0, 1.0)];
+    let compound = loft(&profiles, &[], LoftMode::Tangent).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!(shell.faces.len(), 3);
+    let volume = compound.solids[0].volume();
+    assert!(volume > std::f64::consts::PI * 2.0 && volume < std::f64::consts::PI * 2.25 * 2.0);
+    assert!(loft(&profiles, &[], LoftMode::Ruled).is_ok());
+  }
+
+  #[test]
+  fn loft_guide() {
+    let profiles = vec![vec![square_wire(1.0, 0.0)], vec![lifted(square_wire(1.0, 0.0), 2.0)]];
+    let guide = Spline::fit(&[
+      Point3::new(0.0, 0.0, 0.0),
+      Point3::new(-0.5, -0.5, 1.0),
+      Point3::new(0.0, 0.0, 2.0),
+    ]);
+    let middle = guide.sample(0.5);
+    let compound = loft(&profiles, &[guide.into_enum()], LoftMode::Tangent).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    // Sections are shifted along the guide, which keeps their area
+    assert!((compound.solids[0].volume() - 2.0).abs() < 1e-3);
+    let follows_guide = compound.solids[0].shells[0].edges.iter().any(|edge| {
+      let curve = &edge.borrow().curve;
+      curve.as_curve().sample(curve.as_curve().unsample(&middle)).distance(middle) < 1e-3
+    });
+    assert!(follows_guide);
+    assert!(loft(&profiles, &[], LoftMode::Ruled).is_ok());
+  }
+
+  fn vertical_edge(compound: &Compound) -> Ref<Edge> {
+    compound.solids[0].shells[0].edges.iter().find(|edge| {
+      let edge = edge.borrow();
+      let (p1, p2) = edge.curve.as_curve().endpoints();
+      matches!(edge.curve, CurveType::Line(_)) && p1.x.almost(p2.x) && p1.y.almost(p2.y)
+    }).unwrap().clone()
+  }
+
+  #[test]
+  fn fillet_box() {
+    let mut compound = extrude(&vec![square_wire(2.0, 0.0)], 2.0).unwrap();
+    let edge = vertical_edge(&compound);
+    fillet(&mut compound, &[edge], 0.5).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!(shell.faces.len(), 7);
+    assert_eq!(shell.edges.len(), 15);
+    assert_eq!(shell.vertices.len(), 10);
+    let removed = (1.0 - std::f64::consts::PI / 4.0) * 0.25 * 2.0;
+    assert!((compound.solids[0].volume() - (8.0 - removed)).abs() < 1e-3);
+  }
+
+  #[test]
+  fn chamfer_box() {
+    let mut compound = extrude(&vec![square_wire(2.0, 0.0)], 2.0).unwrap();
+    let edge = vertical_edge(&compound);
+    chamfer(&mut compound, &[edge], 0.5).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    assert_eq!(compound.solids[0].shells[0].faces.len(), 7);
+    assert!((compound.solids[0].volume() - (8.0 - 0.25)).abs() < 1e-6);
+  }
+
+  #[test]
+  fn fillet_cylinder() {
+    let mut compound = make_cylinder(1.0, 2.0).unwrap();
+    let edge = compound.solids[0].shells[0].edges.iter().find(|edge|
+      matches!(&edge.borrow().curve, CurveType::Circle(circle) if circle.plane.origin.z.almost(2.0))
+    ).unwrap().clone();
+    fillet(&mut compound, &[edge], 0.25).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!(shell.faces.len(), 4);
+    assert_eq!(shell.edges.len(), 5);
+    // Removed ring volume by Pappus' theorem
+    let pi = std::f64::consts::PI;
+    let area = (1.0 - pi / 4.0) * 0.0625;
+    let centroid = 1.0 - 0.25 * (10.0 - 3.0 * pi) / (12.0 - 3.0 * pi);
+    let removed = 2.0 * pi * centroid * area;
+    assert!((compound.solids[0].volume() - (2.0 * pi - removed)).abs() < 1e-2);
+  }
+
+  #[test]
+  fn blend_shared_vertex() {
+    let mut compound = make_cube(1.0, 1.0, 1.0).unwrap();
+    let shell = &compound.solids[0].shells[0];
+    let edge = shell.edges[0].clone();
+    let he = edge.borrow().left_half.clone();
+    let other = he.borrow().get_next().borrow().get_edge();
+    assert!(fillet(&mut compound, &[edge, other], 0.1).is_err());
+  }
+
+  #[test]
+  fn blend_failure_is_atomic() {
+    let mut compound = make_cube(1.0, 1.0, 1.0).unwrap();
+    let shell = &compound.solids[0].shells[0];
+    let edge = shell.edges[0].clone();
+    // Opposite edge of the same face shares no vertices
+    let he = edge.borrow().left_half.clone();
+    let other = he.borrow().get_next().borrow().get_next().borrow().get_edge();
+    let id = compound.solids[0].id;
+    // Blending the first edge succeeds, but leaves too little room for the second
+    assert!(fillet(&mut compound, &[edge.clone(), other], 0.6).is_err());
+    assert_eq!(compound.solids[0].id, id);
+    assert_eq!(shell_counts(&compound.solids[0].shells[0]), (8, 12, 6, 2));
+    assert!(compound.solids[0].shells[0].edges.iter().any(|e| Rc::ptr_eq(e, &edge) ));
+  }
+
+  fn top_face(compound: &Compound, height: f64) -> Ref<Face> {
+    compound.solids[0].shells[0].faces.iter().find(|face|
+      matches!(&face.borrow().surface, SurfaceType::Planar(plane) if plane.plane.origin.z.almost(height))
+    ).unwrap().clone()
+  }
+
+  #[test]
+  fn shell_open_box() {
+    let mut compound = extrude(&vec![square_wire(2.0, 0.0)], 2.0).unwrap();
+    let face = top_face(&compound, 2.0);
+    shell(&mut compound, &[face], 0.2).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!(shell.faces.len(), 11);
+    assert_eq!(shell.edges.len(), 24);
+    assert_eq!(shell.vertices.len(), 16);
+    assert!((compound.solids[0].volume() - (8.0 - 1.6 * 1.6 * 1.8)).abs() < 1e-6);
+  }
+
+  #[test]
+  fn shell_closed_box() {
+    let mut compound = extrude(&vec![square_wire(2.0, 0.0)], 2.0).unwrap();
+    shell(&mut compound, &[], 0.2).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    assert_eq!(compound.solids[0].shells.len(), 2);
+    assert!((compound.solids[0].volume() - (8.0 - 1.6 * 1.6 * 1.6)).abs() < 1e-6);
+    assert!(!compound.solids[0].contains_point(Point3::new(1.0, 1.0, 1.0)));
+    assert!(compound.solids[0].contains_point(Point3::new(0.1, 1.0, 1.0)));
+  }
+
+  #[test]
+  fn shell_cylinder() {
+    let mut compound = make_cylinder(1.0, 2.0).unwrap();
+    let face = top_face(&compound, 2.0);
+    shell(&mut compound, &[face], 0.1).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let pi = std::f64::consts::PI;
+    assert!((compound.solids[0].volume() - (pi * 2.0 - pi * 0.81 * 1.9)).abs() < 1e-3);
+  }
+
+  #[test]
+  fn shell_foreign_face() {
+    let mut compound = make_cube(1.0, 1.0, 1.0).unwrap();
+    let other = make_cube(1.0, 1.0, 1.0).unwrap();
+    let face = other.solids[0].shells[0].faces[0].clone();
+    assert!(shell(&mut compound, &[face], 0.1).is_err());
+    assert!(shell(&mut compound, &[], 0.0).is_err());
+  }
+
+  #[test]
+  fn shell_failure_keeps_compound() {
+    // The box is hollowed before the face with a hole turns out to be unsupported
+    let mut compound = extrude(&vec![square_wire(2.0, 0.0)], 2.0).unwrap();
+    let mut frame = extrude(&vec![square_wire(3.0, 5.0), square_wire(1.0, 6.0)], 2.0).unwrap();
+    compound.solids.append(&mut frame.solids);
+    let holed_face = compound.solids[1].shells[0].faces.iter()
+      .find(|face| face.borrow().rings.len() > 1 ).unwrap().clone();
+    let faces = [top_face(&compound, 2.0), holed_face];
+    assert!(shell(&mut compound, &faces, 0.2).is_err());
+    assert_eq!(shell_counts(&compound.solids[0].shells[0]), (8, 12, 6, 2));
+    assert_eq!(compound.solids.len(), 2);
+  }
+
+  fn side_face(compound: &Compound) -> Ref<Face> {
+    compound.solids[0].shells[0].faces.iter().find(|face|
+      !matches!(&face.borrow().surface, SurfaceType::Planar(plane) if plane.plane.normal().z.abs().almost(1.0))
+    ).unwrap().clone()
+  }
+
+  #[test]
+  fn draft_box() {
+    let compound = extrude(&vec![square_wire(2.0, 0.0)], 2.0).unwrap();
+    let face = side_face(&compound);
+    draft(&vec![face], &Plane::new(), Deg(10.0)).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    // Face tilts inward about its bottom edge, cutting off a triangular prism
+    let removed = 2.0 * 2.0 * 10.0_f64.to_radians().tan();
+    assert!((compound.solids[0].volume() - (8.0 - removed)).abs() < 1e-6);
+  }
+
+  #[test]
+  fn draft_cylinder() {
+    let compound = make_cylinder(1.0, 2.0).unwrap();
+    let face = side_face(&compound);
+    draft(&vec![face], &Plane::new(), Deg(10.0)).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let top_radius = 1.0 - 2.0 * 10.0_f64.to_radians().tan();
+    let shell = &compound.solids[0].shells[0];
+    assert!(shell.edges.iter().any(|edge|
+      matches!(&edge.borrow().curve, CurveType::Circle(circle) if circle.plane.origin.z.almost(2.0) && circle.radius.almost(top_radius))
+    ));
+    let frustum = std::f64::consts::PI * 2.0 / 3.0 * (1.0 + top_radius + top_radius.powi(2));
+    assert!((compound.solids[0].volume() - frustum).abs() < 1e-3);
+  }
+
+  #[test]
+  fn draft_spline() {
+    let spline = Spline::fit(&[
+      Point3::new(1.0, 0.0, 0.0),
+      Point3::new(0.0, 1.0, 0.0),
+      Point3::new(-1.0, 0.0, 0.0),
+      Point3::new(0.0, -1.2, 0.0),
+      Point3::new(1.0, 0.0, 0.0),
+    ]);
+    let compound = extrude(&vec![vec![TrimmedCurve::new(spline.into_enum())]], 1.0).unwrap();
+    let face = side_face(&compound);
+    let original = face.borrow().surface.clone();
+    draft(&vec![face.clone()], &Plane::new(), Deg(10.0)).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    // Surface stays in place on the fixed plane and leans by the draft angle above it
+    let surface = face.borrow().surface.clone();
+    let (surface, original) = (surface.as_surface(), original.as_surface());
+    for u in [0.1, 0.4, 0.7] {
+      let (bottom, top) = if original.sample(u, 0.0).z.almost(0.0) { (0.0, 1.0) } else { (1.0, 0.0) };
+      assert!(surface.distance(original.sample(u, bottom)) < 1e-3);
+      let shift = surface.distance(original.sample(u, top));
+      assert!((shift - 10.0_f64.to_radians().sin()).abs() < 1e-2);
+    }
+  }
+
+  #[test]
+  fn sphere() {
+    let compound = make_sphere(1.5).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!((shell.faces.len(), shell.edges.len(), shell.vertices.len()), (1, 1, 2));
+    let volume = 4.0 / 3.0 * std::f64::consts::PI * 1.5_f64.powi(3);
+    assert!((compound.solids[0].volume() - volume).abs() < 1e-3);
+  }
+
+  #[test]
+  fn cone() {
+    let compound = make_cone(1.0, 3.0).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!((shell.faces.len(), shell.edges.len(), shell.vertices.len()), (2, 2, 2));
+    assert!((compound.solids[0].volume() - std::f64::consts::PI).abs() < 1e-3);
+  }
+
+  #[test]
+  fn torus() {
+    let compound = make_torus(2.0, 0.5).unwrap();
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+    let shell = &compound.solids[0].shells[0];
+    assert_eq!((shell.faces.len(), shell.edges.len(), shell.vertices.len()), (1, 2, 1));
+    assert_eq!(shell.genus(), 1);
+    let volume = 2.0 * std::f64::consts::PI.powi(2) * 2.0 * 0.25;
+    assert!((compound.solids[0].volume() - volume).abs() < 1e-3);
+    assert!(make_torus(1.0, 1.0).is_err());
+  }
 }
@@ -1,19 +1,26 @@
 use crate::solid::*;
 
+
+// Number of spans used to approximate borders of spline patches
+const BORDER_SAMPLES: usize = 8;
+
 // use crate::log;
 
 
 impl Compound {
-  pub fn repair(&mut self) -> Result<(), String> {
-    for solid in &mut self.solids {
-      solid.validate()?;
-      // solid.repair()?;
+  // Overlapping solids are fused by joins rather than here,
+  // as bodies made with BooleanType::Create are meant to stay separate
+  pub fn repair(&mut self) -> Result<(), ValidationReport> {
+    // for solid in &mut self.solids {
+    //   solid.repair()?;
+    // }
+    let report = self.validate();
+    if report.is_valid() {
+      Ok(())
+    } else {
+      Err(report)
     }
-    self.join_solids();
-    Ok(())
   }
-
-  fn join_solids(&mut self) {} //XXX
 }
 
 
@@ -29,6 +36,15 @@ impl Solid {
 
 impl Shell {
   pub fn repair(&mut self) -> Result<(), String> {
+    // Join faces that ended up on the same surface
+    let joints: Vec<Ref<Edge>> = self.edges.iter().filter(|edge| edge.borrow().joins_surface() ).cloned().collect();
+    for edge in joints {
+      let (left_half, right_half) = {
+        let edge = edge.borrow();
+        (edge.left_half.clone(), edge.right_half.clone())
+      };
+      self.kef(&left_half, &right_half)?;
+    }
     for edge in &mut self.edges {
       edge.borrow_mut().repair()?;
     }
@@ -50,12 +66,20 @@ impl Face {
 
 impl Edge {
   pub fn repair(&mut self) -> Result<(), String> {
+    // Spline patches are bounded by their parameters, so their edges follow the border of the patch
+    let spline = [self.get_left_face(), self.get_right_face()].iter().find_map(|face| match &face.borrow().surface {
+      SurfaceType::Spline(surface) => Some(surface.clone()),
+      _ => None,
+    });
+    if let Some(surface) = spline { return self.repair_border(&surface) }
+    if Rc::ptr_eq(&self.get_left_face(), &self.get_right_face()) { return self.repair_seam() }
     let intersections = self.get_left_face().borrow().surface.intersect(&self.get_right_face().borrow().surface);
     if intersections.len() == 0 { return Err("Adjacent faces don't intersect".into()) }
+    if let CurveType::Circle(_) = self.curve { return self.repair_circle(intersections) }
     for intersection in intersections {
       match intersection {
         SurfaceIntersectionType::Contained
-        => todo!(), //XXX Faces should be joined into one
+        => return Err("Faces on the same surface need to be joined by their shell".into()),
 
         SurfaceIntersectionType::Touch(curve)
         | SurfaceIntersectionType::Cross(curve)
@@ -79,10 +103,109 @@ impl Edge {
     }
     Ok(())
   }
+
+  fn joins_surface(&self) -> bool {
+    let left_face = self.get_left_face();
+    let right_face = self.get_right_face();
+    !Rc::ptr_eq(&left_face, &right_face)
+    && left_face.borrow().surface.intersect(&right_face.borrow().surface).contains(&SurfaceIntersectionType::Contained)
+  }
+
+  // Closed edges have no ends to trim, so their vertex only gets moved onto the closest new circle
+  fn repair_circle(&mut self, intersections: Vec<SurfaceIntersectionType>) -> Result<(), String> {
+    let vertex = self.left_half.borrow().origin.clone();
+    let p = vertex.borrow().point;
+    let closest = |curve: &CurveType| {
+      let curve = curve.as_curve();
+      curve.sample(curve.unsample(&p))
+    };
+    let mut circle = intersections.into_iter().filter_map(|intersection| match intersection {
+      SurfaceIntersectionType::Touch(curve)
+      | SurfaceIntersectionType::Cross(curve)
+      | SurfaceIntersectionType::Extended(curve)
+        => if let CurveType::Circle(circle) = curve { Some(circle) } else { None },
+      SurfaceIntersectionType::Contained => None,
+    }).min_by(|a, b| {
+      let a = closest(&CurveType::Circle(a.clone())).distance(p);
+      let b = closest(&CurveType::Circle(b.clone())).distance(p);
+      a.partial_cmp(&b).unwrap()
+    }).ok_or("Adjacent faces don't intersect in a circle")?;
+    // Keep direction of the edge
+    if let CurveType::Circle(old) = &self.curve {
+      if circle.plane.normal().dot(old.plane.normal()) < 0.0 { circle.plane.flip() }
+      circle.id = old.id;
+    }
+    let curve = circle.into_enum();
+    vertex.borrow_mut().point = closest(&curve);
+    self.curve = curve;
+    Ok(())
+  }
+
+  fn repair_border(&mut self, surface: &SplineSurface) -> Result<(), String> {
+    let old_curve = self.curve.as_curve();
+    let (u, v) = surface.unsample(old_curve.sample(0.5));
+    let along_v = (u - u.round()).abs() < (v - v.round()).abs();
+    let border = |t: f64| if along_v { surface.sample(u.round(), t) } else { surface.sample(t, v.round()) };
+    let mut points: Vec<Point3> = (0..=BORDER_SAMPLES).map(|i| border(i as f64 / BORDER_SAMPLES as f64) ).collect();
+    // Keep direction of the edge
+    let p = old_curve.sample(0.25);
+    if p.distance(border(0.75)) < p.distance(border(0.25)) { points.reverse() }
+    let mut curve = Spline::fit(&points).into_enum();
+    let (start, end) = curve.as_curve().endpoints();
+    for half in [&self.left_half, &self.right_half] {
+      let vertex = half.borrow().origin.clone();
+      let q = vertex.borrow().point;
+      vertex.borrow_mut().point = if q.distance(start) < q.distance(end) { start } else { end };
+    }
+    curve.set_id(self.curve.get_id());
+    self.curve = curve;
+    Ok(())
+  }
+
+  // Seams of revolution surfaces follow the profile at the position of the seam
+  fn repair_seam(&mut self) -> Result<(), String> {
+    let face = self.get_left_face();
+    let face = face.borrow();
+    if let SurfaceType::Revolution(surface) = &face.surface {
+      let p = self.left_half.borrow().origin.borrow().point;
+      let (u, _) = surface.unsample(p);
+      let mut curve = surface.profile_at(u);
+      let (start, end) = curve.as_curve().endpoints();
+      for half in [&self.left_half, &self.right_half] {
+        let vertex = half.borrow().origin.clone();
+        let q = vertex.borrow().point;
+        vertex.borrow_mut().point = if q.distance(start) < q.distance(end) { start } else { end };
+      }
+      curve.set_id(self.curve.get_id());
+      self.curve = curve;
+      Ok(())
+    } else {
+      Err("Seams can only be repaired on revolution surfaces".into())
+    }
+  }
 }
 
 
 #[cfg(test)]
 mod tests {
-
+  use super::*;
+  use crate::solid::features;
+
+  #[test]
+  fn join_split_face() {
+    let mut compound = features::make_cube(1.0, 1.0, 1.0).unwrap();
+    let shell = &mut compound.solids[0].shells[0];
+    let face = shell.faces[0].clone();
+    let he1 = face.borrow().outer_ring.borrow().half_edge.clone();
+    let he2 = he1.borrow().get_next().borrow().get_next();
+    let line = Line::new(he1.borrow().origin.borrow().point, he2.borrow().origin.borrow().point);
+    let surface = face.borrow().surface.clone();
+    shell.mef(&he1, &he2, line.into_enum(), surface).unwrap();
+    assert_eq!(shell.faces.len(), 7);
+    shell.repair().unwrap();
+    assert_eq!(shell.faces.len(), 6);
+    assert_eq!(shell.edges.len(), 12);
+    let report = compound.validate();
+    assert!(report.is_valid(), "{}", report);
+  }
 }
@@ -30,7 +30,7 @@ impl solid::Solid {
 }
 
 
-fn undump_solid(solid: Solid) -> solid::Solid {
+pub(crate) fn undump_solid(solid: Solid) -> solid::Solid {
   solid::Solid {
     id: Uuid::new_v4(),
 
@@ -91,7 +91,7 @@ fn undump_solid(solid: Solid) -> solid::Solid {
           outer_ring: rings[0].clone(),
           rings: rings.clone(),
           surface: face.surface.clone(),
-          flip_normal: false,
+          flip_normal: face.flip_normal,
         });
 
         // Connect rings to face
@@ -188,13 +188,13 @@ fn get_vertex_index(vertex: &Ref<solid::Vertex>, vertices: &Vec<Ref<solid::Verte
 
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Solid {
+pub(crate) struct Solid {
   pub shells: Vec<Shell>,
 }
 
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Shell {
+pub(crate) struct Shell {
   pub faces: Vec<Face>,
   pub edges: Vec<Edge>,
   pub vertices: Vec<Vertex>,
@@ -202,7 +202,7 @@ struct Shell {
 
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Face {
+pub(crate) struct Face {
   pub id: Uuid,
   pub rings: Vec<Vec<HalfEdge>>,
   pub surface: surface::SurfaceType,
@@ -211,21 +211,21 @@ struct Face {
 
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Edge {
+pub(crate) struct Edge {
   pub id: Uuid,
   pub curve: curve::CurveType,
 }
 
 
 #[derive(Debug, Serialize, Deserialize)]
-struct HalfEdge {
+pub(crate) struct HalfEdge {
   pub origin: usize,
   pub edge: usize,
 }
 
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Vertex {
+pub(crate) struct Vertex {
   pub point: Point3,
 }
 
@@ -1,30 +1,213 @@
+use std::collections::HashMap;
+use std::cell::RefCell;
+
 use crate::solid::*;
 use crate::mesh::*;
+use crate::surface::tesselation;
+use crate::surface::tesselation::IndexRing;
 
 
-impl Meshable for Solid {
-  fn tesselate(&self) -> Mesh {
+impl Solid {
+  // Faces share the samples of their common edges, which makes the mesh of each shell watertight
+  pub fn tesselate_adaptive(&self, max_deviation: f64, max_angle: Deg<f64>) -> Mesh {
     let mut mesh = Mesh::default();
-    for shell in &self.shells {
+    for (i, shell) in self.shells.iter().enumerate() {
+      let mut sampler = EdgeSampler::new(max_deviation, max_angle);
+      // Voids face into their inside
+      let sign = if i == 0 { 1.0 } else { -1.0 };
       for face in &shell.faces {
-        let face_mesh = face.borrow().tesselate();
-        mesh.append(face_mesh);
+        let face = face.borrow();
+        let rings = sampler.face_rings(&face, &mut mesh);
+        let orientation = shell.face_orientation(&face) * sign;
+        face.tesselate_rings(&rings, max_deviation, max_angle, orientation, &mut mesh);
       }
     }
     mesh
   }
 }
 
-impl Meshable for Face {
+impl Meshable for Solid {
   fn tesselate(&self) -> Mesh {
-    let mut mesh = self.make_surface().tesselate();
-    if self.flip_normal { mesh.invert_normals() }
+    self.tesselate_adaptive(0.025, Deg(20.0))
+  }
+}
+
+
+impl Face {
+  pub fn tesselate_adaptive(&self, max_deviation: f64, max_angle: Deg<f64>) -> Mesh {
+    let mut mesh = Mesh::default();
+    let rings = EdgeSampler::new(max_deviation, max_angle).face_rings(self, &mut mesh);
+    let orientation = if self.flip_normal { -1.0 } else { 1.0 };
+    self.tesselate_rings(&rings, max_deviation, max_angle, orientation, &mut mesh);
     mesh
   }
+
+  fn tesselate_rings(&self, rings: &[IndexRing], max_deviation: f64, max_angle: Deg<f64>, orientation: f64, mesh: &mut Mesh) {
+    let surface = self.surface.as_surface();
+    let steps = tesselation::grid_steps(surface, max_deviation, max_angle);
+    tesselation::tesselate_rings(surface, rings, steps, orientation, mesh);
+  }
+}
+
+impl Meshable for Face {
+  fn tesselate(&self) -> Mesh {
+    self.tesselate_adaptive(0.025, Deg(20.0))
+  }
+}
+
+
+// Samples every edge only once, so that the faces on both of its sides use the same mesh vertices
+struct EdgeSampler {
+  max_deviation: f64,
+  max_angle: Deg<f64>,
+  vertices: HashMap<*const RefCell<Vertex>, usize>,
+  edges: HashMap<*const RefCell<Edge>, Vec<usize>>,
+}
+
+impl EdgeSampler {
+  fn new(max_deviation: f64, max_angle: Deg<f64>) -> Self {
+    Self {
+      max_deviation,
+      max_angle,
+      vertices: HashMap::new(),
+      edges: HashMap::new(),
+    }
+  }
+
+  fn face_rings(&mut self, face: &Face, mesh: &mut Mesh) -> Vec<IndexRing> {
+    face.rings.iter().map(|ring| {
+      ring.borrow().iter().map(|he| {
+        let edge = he.borrow().get_edge();
+        let mut indices = self.edge_indices(&edge, mesh);
+        // Edge samples run along the left half edge
+        if !Rc::ptr_eq(&he, &edge.borrow().left_half) { indices.reverse() }
+        indices
+      }).collect()
+    }).collect()
+  }
+
+  fn edge_indices(&mut self, edge: &Ref<Edge>, mesh: &mut Mesh) -> Vec<usize> {
+    if let Some(indices) = self.edges.get(&Rc::as_ptr(edge)) { return indices.clone() }
+    let half_edge = edge.borrow().left_half.clone();
+    let half_edge = half_edge.borrow();
+    let poly = half_edge.make_curve().tesselate_adaptive(self.max_deviation, self.max_angle, (0.0, 1.0));
+    let mut indices = vec![self.vertex_index(&half_edge.origin, mesh)];
+    for &p in &poly[1..poly.len() - 1] {
+      indices.push(mesh.vertices.len());
+      mesh.vertices.push(p);
+    }
+    indices.push(self.vertex_index(&half_edge.end_vertex(), mesh));
+    self.edges.insert(Rc::as_ptr(edge), indices.clone());
+    indices
+  }
+
+  fn vertex_index(&mut self, vertex: &Ref<Vertex>, mesh: &mut Mesh) -> usize {
+    *self.vertices.entry(Rc::as_ptr(vertex)).or_insert_with(|| {
+      mesh.vertices.push(vertex.borrow().point);
+      mesh.vertices.len() - 1
+    })
+  }
 }
 
 
 #[cfg(test)]
 mod tests {
+  use super::*;
+  use crate::features::*;
+
+  // Every edge of a closed, consistently wound mesh is used once in each direction
+  fn assert_watertight(mesh: &Mesh) {
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+    for tri in mesh.faces.chunks(3) {
+      for k in 0..3 {
+        *edges.entry((tri[k], tri[(k + 1) % 3])).or_insert(0) += 1;
+      }
+    }
+    for (&(a, b), &count) in &edges {
+      assert_eq!(count, 1, "Edge {:?} used {} times", (a, b), count);
+      assert!(edges.contains_key(&(b, a)), "Open edge {:?}", (a, b));
+    }
+    assert_eq!(mesh.normals.len(), mesh.faces.len());
+  }
+
+  fn mesh_volume(mesh: &Mesh) -> f64 {
+    mesh.faces.chunks(3).map(|tri| {
+      let (a, b, c) = (mesh.vertices[tri[0]], mesh.vertices[tri[1]], mesh.vertices[tri[2]]);
+      a.to_vec().dot(b.to_vec().cross(c.to_vec())) / 6.0
+    }).sum()
+  }
+
+  fn check_solid(compound: &Compound, volume: f64) {
+    let solid = &compound.solids[0];
+    assert_watertight(&solid.tesselate());
+    let mesh = solid.tesselate_adaptive(0.001, Deg(5.0));
+    assert_watertight(&mesh);
+    let mesh_volume = mesh_volume(&mesh);
+    assert!((mesh_volume - volume).abs() < volume * 0.002, "{} != {}", mesh_volume, volume);
+  }
+
+  #[test]
+  fn cube() {
+    check_solid(&make_cube(1.0, 2.0, 3.0).unwrap(), 6.0);
+  }
 
+  #[test]
+  fn cylinder() {
+    check_solid(&make_cylinder(1.0, 2.0).unwrap(), std::f64::consts::PI * 2.0);
+  }
+
+  #[test]
+  fn sphere() {
+    check_solid(&make_sphere(2.0).unwrap(), 4.0 / 3.0 * std::f64::consts::PI * 8.0);
+  }
+
+  #[test]
+  fn cone() {
+    check_solid(&make_cone(1.0, 2.0).unwrap(), std::f64::consts::PI * 2.0 / 3.0);
+  }
+
+  #[test]
+  fn torus() {
+    check_solid(&make_torus(2.0, 0.5).unwrap(), 2.0 * std::f64::consts::PI.powi(2) * 2.0 * 0.25);
+  }
+
+  #[test]
+  fn hollow_box() {
+    let mut compound = make_cube(2.0, 2.0, 2.0).unwrap();
+    shell(&mut compound, &[], 0.2).unwrap();
+    check_solid(&compound, 8.0 - 1.6 * 1.6 * 1.6);
+  }
+
+  #[test]
+  fn spline_extrusion() {
+    let spline = Spline::fit(&[
+      Point3::new(1.0, 0.0, 0.0),
+      Point3::new(0.0, 1.0, 0.0),
+      Point3::new(-1.0, 0.0, 0.0),
+      Point3::new(0.0, -1.2, 0.0),
+      Point3::new(1.0, 0.0, 0.0),
+    ]);
+    let profile = vec![vec![TrimmedCurve::new(spline.into_enum())]];
+    let compound = extrude(&profile, 1.0).unwrap();
+    let volume = compound.solids[0].volume();
+    check_solid(&compound, volume);
+  }
+
+  #[test]
+  fn face_with_hole() {
+    let surface = PlanarSurface::new(Plane::new());
+    let outer = vec![
+      TrimmedCurve::new(Line::new(Point3::new(0.0, 0.0, 0.0), Point3::new(4.0, 0.0, 0.0)).into_enum()),
+      TrimmedCurve::new(Line::new(Point3::new(4.0, 0.0, 0.0), Point3::new(4.0, 4.0, 0.0)).into_enum()),
+      TrimmedCurve::new(Line::new(Point3::new(4.0, 4.0, 0.0), Point3::new(0.0, 4.0, 0.0)).into_enum()),
+      TrimmedCurve::new(Line::new(Point3::new(0.0, 4.0, 0.0), Point3::new(0.0, 0.0, 0.0)).into_enum()),
+    ];
+    let hole = vec![TrimmedCurve::new(Circle::new(Point3::new(2.0, 2.0, 0.0), 1.0).into_enum())];
+    let mesh = surface.tesselate(&vec![outer, hole]);
+    let area: f64 = mesh.faces.chunks(3).map(|tri| {
+      let (a, b, c) = (mesh.vertices[tri[0]], mesh.vertices[tri[1]], mesh.vertices[tri[2]]);
+      (b - a).cross(c - a).z / 2.0
+    }).sum();
+    assert!((area - (16.0 - std::f64::consts::PI)).abs() < 0.1, "{}", area);
+  }
 }
@@ -0,0 +1,294 @@
+use crate::solid::*;
+
+// use crate::log;
+
+
+// Maximum distance of vertices from the curves and surfaces they lie on
+pub(super) const VERTEX_TOLERANCE: f64 = 1.0e-6;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemType {
+  BrokenLink, // next and previous pointers of half edges disagree
+  BrokenMate, // Half edges of an edge don't reference each other
+  DanglingReference, // Reference to an entity that was dropped or belongs to another shell
+  OpenShell, // Edge is not bounded by two faces
+  InconsistentOrientation, // Mating half edges run in the same direction
+  VertexMismatch, // Vertex lies off the curve of its edge
+  SelfIntersection, // Rings of a face intersect each other
+  EulerCharacteristic, // Euler-Poincaré formula does not hold
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationProblem {
+  pub problem_type: ProblemType,
+  pub ids: Vec<Uuid>, // Faces, edges and half edges involved
+  pub message: String,
+}
+
+
+/// All problems found while validating a B-rep
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+  pub problems: Vec<ValidationProblem>,
+}
+
+impl ValidationReport {
+  pub fn is_valid(&self) -> bool {
+    self.problems.is_empty()
+  }
+
+  pub fn of_type(&self, problem_type: ProblemType) -> impl Iterator<Item = &ValidationProblem> {
+    self.problems.iter().filter(move |problem| problem.problem_type == problem_type )
+  }
+
+  pub fn merge(&mut self, other: Self) {
+    self.problems.extend(other.problems);
+  }
+
+  fn add(&mut self, problem_type: ProblemType, ids: Vec<Uuid>, message: &str) {
+    self.problems.push(ValidationProblem {
+      problem_type,
+      ids,
+      message: message.to_string(),
+    });
+  }
+}
+
+impl std::fmt::Display for ValidationReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    for problem in &self.problems {
+      let ids: Vec<String> = problem.ids.iter().map(|id| id.to_string() ).collect();
+      writeln!(f, "{:?}: {} [{}]", problem.problem_type, problem.message, ids.join(", "))?;
+    }
+    Ok(())
+  }
+}
+
+
+impl Compound {
+  pub fn validate(&self) -> ValidationReport {
+    self.solids.iter().fold(ValidationReport::default(), |mut report, solid| {
+      report.merge(solid.validate());
+      report
+    })
+  }
+}
+
+
+impl Solid {
+  pub fn validate(&self) -> ValidationReport {
+    self.shells.iter().fold(ValidationReport::default(), |mut report, shell| {
+      report.merge(shell.validate());
+      report
+    })
+  }
+}
+
+
+impl Shell {
+  pub fn validate(&self) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    self.validate_rings(&mut report);
+    self.validate_edges(&mut report);
+    self.validate_intersections(&mut report);
+    if let Err(message) = Self::euler_poincare(self.euler_characteristics(), self.genus()) {
+      report.add(ProblemType::EulerCharacteristic, vec![], &message);
+    }
+    report
+  }
+
+  // Euler-Poincaré formula for a single shell: V - E + F - (L - F) = 2 * (1 - H)
+  pub(super) fn euler_poincare(characteristic: i32, handles: i32) -> Result<(), String> {
+    let expected = 2 * (1 - handles);
+    if characteristic == expected {
+      Ok(())
+    } else {
+      Err(format!("Euler characteristic is {} instead of {} for {} handles", characteristic, expected, handles))
+    }
+  }
+
+  fn validate_rings(&self, report: &mut ValidationReport) {
+    // Bound ring traversal in case next pointers form a cycle that misses the start
+    let max_steps = 2 * self.edges.len() + self.faces.len() + 1;
+    for face in &self.faces {
+      let faceb = face.borrow();
+      let face_id = faceb.id;
+      if !faceb.rings.iter().any(|ring| Rc::ptr_eq(ring, &faceb.outer_ring) ) {
+        report.add(ProblemType::DanglingReference, vec![face_id], "Outer ring is missing from rings");
+      }
+      for ring in &faceb.rings {
+        if !ring.borrow().face.upgrade().is_some_and(|f| Rc::ptr_eq(&f, face) ) {
+          report.add(ProblemType::DanglingReference, vec![face_id], "Ring does not reference its face");
+        }
+        let first = ring.borrow().half_edge.clone();
+        let mut he = first.clone();
+        for _ in 0..max_steps {
+          let heb = he.borrow();
+          if !heb.ring.upgrade().is_some_and(|r| Rc::ptr_eq(&r, ring) ) {
+            report.add(ProblemType::DanglingReference, vec![face_id, heb.id], "Half edge does not reference its ring");
+          }
+          if heb.origin.borrow().half_edge.upgrade().is_none() {
+            report.add(ProblemType::DanglingReference, vec![face_id, heb.id], "Vertex references dropped half edge");
+          }
+          let next = if let Some(next) = heb.next.upgrade() {
+            next
+          } else {
+            report.add(ProblemType::DanglingReference, vec![face_id, heb.id], "Half edge references dropped successor");
+            break
+          };
+          if !next.borrow().previous.upgrade().is_some_and(|previous| Rc::ptr_eq(&previous, &he) ) {
+            report.add(ProblemType::BrokenLink, vec![face_id, heb.id, next.borrow().id], "Successor does not link back to half edge");
+          }
+          match heb.edge.upgrade() {
+            Some(edge) => if !self.edges.iter().any(|e| Rc::ptr_eq(e, &edge) ) {
+              report.add(ProblemType::DanglingReference, vec![face_id, heb.id, edge.borrow().id], "Edge is not part of shell");
+            },
+            None => if !Rc::ptr_eq(&next, &he) {
+              report.add(ProblemType::DanglingReference, vec![face_id, heb.id], "Half edge references dropped edge");
+            },
+          }
+          drop(heb);
+          he = next;
+          if Rc::ptr_eq(&he, &first) { break }
+        }
+        if !Rc::ptr_eq(&he, &first) {
+          report.add(ProblemType::BrokenLink, vec![face_id, first.borrow().id], "Ring is not closed");
+        }
+      }
+    }
+  }
+
+  fn validate_edges(&self, report: &mut ValidationReport) {
+    for edge in &self.edges {
+      let edgeb = edge.borrow();
+      let id = edgeb.id;
+      let halves = [&edgeb.left_half, &edgeb.right_half];
+      if Rc::ptr_eq(halves[0], halves[1]) {
+        report.add(ProblemType::BrokenMate, vec![id], "Edge uses the same half edge on both sides");
+        continue
+      }
+      if halves.iter().any(|he| !he.borrow().edge.upgrade().is_some_and(|e| Rc::ptr_eq(&e, edge) ) ) {
+        report.add(ProblemType::BrokenMate, vec![id, halves[0].borrow().id, halves[1].borrow().id], "Half edge does not reference its edge");
+        continue
+      }
+      // Both halves must lie in rings of faces of this shell
+      let bounded = halves.iter().all(|he|
+        he.borrow().ring.upgrade()
+        .and_then(|ring| ring.borrow().face.upgrade() )
+        .is_some_and(|face| self.faces.iter().any(|f| Rc::ptr_eq(f, &face) ) )
+      );
+      if !bounded {
+        report.add(ProblemType::OpenShell, vec![id], "Edge is not bounded by two faces");
+        continue
+      }
+      // Mates run in opposite directions
+      for (he, mate) in [(halves[0], halves[1]), (halves[1], halves[0])] {
+        let end = he.borrow().next.upgrade().map(|next| next.borrow().origin.clone() );
+        if !end.is_some_and(|end| Rc::ptr_eq(&end, &mate.borrow().origin) ) {
+          report.add(ProblemType::InconsistentOrientation, vec![id, he.borrow().id, mate.borrow().id], "Mating half edges run in the same direction");
+        }
+      }
+      // Vertices lie on the edge's curve
+      let curve = edgeb.curve.as_curve();
+      for he in halves {
+        let p = he.borrow().origin.borrow().point;
+        if curve.sample(curve.unsample(&p)).distance(p) > VERTEX_TOLERANCE {
+          report.add(ProblemType::VertexMismatch, vec![id, he.borrow().id], &format!("Vertex {:?} lies off curve", p));
+        }
+      }
+    }
+  }
+
+  // Curve intersections are computed in the XY plane, so rings
+  // of planar faces are checked in the local system of their plane
+  fn validate_intersections(&self, report: &mut ValidationReport) {
+    for face in &self.faces {
+      let faceb = face.borrow();
+      let plane = if let SurfaceType::Planar(surface) = &faceb.surface {
+        &surface.plane
+      } else {
+        continue
+      };
+      let transform = if let Some(transform) = plane.as_transform().invert() {
+        transform
+      } else {
+        continue
+      };
+      let mut half_edges = vec![];
+      for ring in &faceb.rings {
+        for he in ring.borrow().iter() {
+          let edge = if let Some(edge) = he.borrow().edge.upgrade() { edge } else { continue };
+          let mut curve = he.borrow().make_curve();
+          curve.transform(&transform);
+          half_edges.push((edge, curve));
+        }
+      }
+      for (i, (edge, curve)) in half_edges.iter().enumerate() {
+        for (other_edge, other) in half_edges.iter().skip(i + 1) {
+          // Seams appear twice in the same face
+          if Rc::ptr_eq(edge, other_edge) { continue }
+          let crosses = curve.intersect(other).iter().any(|isect| matches!(isect,
+            CurveIntersectionType::Cross(_) | CurveIntersectionType::Contained
+          ));
+          if crosses {
+            report.add(ProblemType::SelfIntersection, vec![faceb.id, edge.borrow().id, other_edge.borrow().id], "Edges of face intersect");
+          }
+        }
+      }
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::solid::features;
+
+  #[test]
+  fn valid_solids() {
+    let cube = features::make_cube(1.5, 1.5, 1.5).unwrap();
+    let cylinder = features::make_cylinder(1.0, 2.0).unwrap();
+    for compound in [cube, cylinder] {
+      let report = compound.validate();
+      assert!(report.is_valid(), "{}", report);
+    }
+  }
+
+  #[test]
+  fn broken_links() {
+    let cube = features::make_cube(1.5, 1.5, 1.5).unwrap();
+    let shell = &cube.solids[0].shells[0];
+    let edge = shell.edges[0].clone();
+    let he = edge.borrow().left_half.clone();
+    let next = he.borrow().get_next();
+    let next_next = next.borrow().get_next();
+    next_next.borrow_mut().previous = Rc::downgrade(&he);
+    let report = shell.validate();
+    let problem = report.of_type(ProblemType::BrokenLink).next().unwrap();
+    assert!(problem.ids.contains(&next.borrow().id));
+  }
+
+  #[test]
+  fn vertex_off_curve() {
+    let cube = features::make_cube(1.5, 1.5, 1.5).unwrap();
+    let shell = &cube.solids[0].shells[0];
+    shell.vertices[0].borrow_mut().point.x += 0.1;
+    let report = shell.validate();
+    assert_eq!(report.of_type(ProblemType::VertexMismatch).count(), 3);
+    assert_eq!(report.of_type(ProblemType::EulerCharacteristic).count(), 0);
+  }
+
+  #[test]
+  fn open_shell() {
+    let mut cube = features::make_cube(1.5, 1.5, 1.5).unwrap();
+    let shell = &mut cube.solids[0].shells[0];
+    let face = shell.faces.pop().unwrap();
+    let report = shell.validate();
+    assert_eq!(report.of_type(ProblemType::OpenShell).count(), face.borrow().outer_ring.borrow().iter().count());
+    let problem = report.of_type(ProblemType::EulerCharacteristic).next().unwrap();
+    assert_eq!(problem.message, "Euler characteristic is 1 instead of 2 for 0 handles");
+  }
+}
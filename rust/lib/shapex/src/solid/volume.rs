@@ -1,10 +1,172 @@
+use cgmath::Zero;
+
 use crate::solid::*;
+use crate::mesh::Meshable;
 
 
 pub trait Volume {
   fn surface_area(&self) -> f64;
   fn volume(&self) -> f64;
   fn contains_point(&self, p: Point3) -> bool;
+  fn mass_properties(&self, density: Option<f64>) -> MassProperties;
+}
+
+
+/// Physical properties of a body with uniform density
+/// Density defaults to one, which makes mass equal to volume
+#[derive(Debug, Clone, PartialEq)]
+pub struct MassProperties {
+  pub area: f64,
+  pub volume: f64,
+  pub mass: f64,
+  pub center_of_mass: Point3,
+  pub inertia: Matrix3, // Inertia tensor about the center of mass
+}
+
+
+// Volume integrals of a body, accumulated from integrals over its boundary using the divergence theorem
+#[derive(Debug, Clone, Copy)]
+struct Moments {
+  area: f64,
+  volume: f64,
+  first: Vec3, // Integral of position
+  second: Matrix3, // Integral of the outer product of position with itself
+}
+
+impl Moments {
+  fn new() -> Self {
+    Self {
+      area: 0.0,
+      volume: 0.0,
+      first: Vec3::zero(),
+      second: Matrix3::zero(),
+    }
+  }
+
+  fn of_face(face: &Face, shell: &Shell, size: f64) -> Self {
+    let mut moments = Self::new();
+    let orientation = Self::orientation(face, shell, size);
+    face.make_surface().integrate(&mut |p, normal, weight| moments.add(p, normal * orientation, weight) );
+    moments
+  }
+
+  // Surface normals may point into the body, which is checked by casting
+  // a ray along the normal from a point slightly in front of the face
+  fn orientation(face: &Face, shell: &Shell, size: f64) -> f64 {
+    let (p, normal) = match &face.surface {
+      SurfaceType::Planar(surface) => (inner_point(&face.make_surface()), surface.plane.normal()),
+      _ => {
+        // Odd parameters keep the ray away from seams and symmetry planes
+        let surface = face.surface.as_surface();
+        let (u, v) = (0.37, 0.43);
+        (surface.sample(u, v), surface.normal_at(u, v))
+      },
+    };
+    let origin = p + normal * size * 1.0e-6;
+    if count_hits(shell, origin, normal * size * 10.0) % 2 == 0 { 1.0 } else { -1.0 }
+  }
+
+  // Shells are integrated with positive volume, regardless of their orientation
+  fn of_shell(shell: &Shell) -> Self {
+    let size = shell_size(shell);
+    let mut moments = shell.faces.iter().fold(Self::new(), |acc, face| acc.combine(&Self::of_face(&face.borrow(), shell, size), 1.0) );
+    if moments.volume < 0.0 {
+      let area = moments.area;
+      moments = Self::new().combine(&moments, -1.0);
+      moments.area = area;
+    }
+    moments
+  }
+
+  fn add(&mut self, p: Point3, normal: Vec3, weight: f64) {
+    let x = p.to_vec();
+    self.area += weight;
+    self.volume += x.dot(normal) * weight / 3.0;
+    for i in 0..3 {
+      self.first[i] += x[i] * x[i] * normal[i] * weight / 2.0;
+      for j in 0..3 {
+        self.second[j][i] += if i == j {
+          x[i] * x[i] * x[i] * normal[i] / 3.0
+        } else {
+          // Average both possible vector fields to keep the tensor symmetric
+          (x[i] * x[i] * x[j] * normal[i] + x[j] * x[j] * x[i] * normal[j]) / 4.0
+        } * weight;
+      }
+    }
+  }
+
+  fn combine(&self, other: &Self, sign: f64) -> Self {
+    Self {
+      area: self.area + other.area,
+      volume: self.volume + other.volume * sign,
+      first: self.first + other.first * sign,
+      second: self.second + other.second * sign,
+    }
+  }
+
+  fn into_properties(self, density: f64) -> MassProperties {
+    let mass = self.volume * density;
+    let center = if self.volume == 0.0 { Vec3::zero() } else { self.first / self.volume };
+    // Shift second moments to the center of mass
+    let second = (self.second - outer_product(center, center) * self.volume) * density;
+    let trace = second.x.x + second.y.y + second.z.z;
+    MassProperties {
+      area: self.area,
+      volume: self.volume,
+      mass,
+      center_of_mass: Point3::from_vec(center),
+      inertia: Matrix3::from_value(trace) - second,
+    }
+  }
+}
+
+impl Shell {
+  // Sign of a face's surface normal, which is positive when it points out of the shell
+  pub(crate) fn face_orientation(&self, face: &Face) -> f64 {
+    Moments::orientation(face, self, shell_size(self))
+  }
+}
+
+// Centroid of the largest triangle of a tesselated face
+fn inner_point(surface: &TrimmedSurface) -> Point3 {
+  let mesh = surface.tesselate();
+  mesh.faces.chunks(3).map(|tri| {
+    let (a, b, c) = (mesh.vertices[tri[0]], mesh.vertices[tri[1]], mesh.vertices[tri[2]]);
+    ((b - a).cross(c - a).magnitude(), Point3::centroid(&[a, b, c]))
+  }).max_by(|a, b| a.0.partial_cmp(&b.0).unwrap() )
+  .map_or_else(|| surface.base.as_surface().sample(0.0, 0.0), |(_, p)| p )
+}
+
+// Diagonal of the bounding box of a shell, including samples of its curved faces
+fn shell_size(shell: &Shell) -> f64 {
+  let vertices = shell.vertices.iter().map(|vertex| vertex.borrow().point );
+  let samples = shell.faces.iter().flat_map(|face| {
+    let surface = face.borrow().surface.clone();
+    let num_samples = if let SurfaceType::Planar(_) = surface { 0 } else { 9 };
+    (0..num_samples * num_samples).map(move |i| {
+      surface.as_surface().sample((i / num_samples) as f64 / 8.0, (i % num_samples) as f64 / 8.0)
+    })
+  });
+  let (min, max) = boolean::points_bounding_box(vertices.chain(samples));
+  min.distance(max).max(EPSILON)
+}
+
+// Number of faces crossed by a ray
+fn count_hits(shell: &Shell, origin: Point3, ray: Vec3) -> usize {
+  let ray = TrimmedCurve::new(Line::new(origin, origin + ray).into_enum());
+  shell.faces.iter().flat_map(|face| {
+    let intersections = ray.intersect_surface(&face.borrow().make_surface());
+    intersections.iter().map(|isect| match isect {
+      CurveSurfaceIntersectionType::Pierce(_)
+      | CurveSurfaceIntersectionType::Cross(_)
+        => 1,
+      _ => 0,
+    }).collect::<Vec<usize>>()
+  }).sum()
+}
+
+fn outer_product(a: Vec3, b: Vec3) -> Matrix3 {
+  Matrix3::from_cols(a * b.x, a * b.y, a * b.z)
 }
 
 
@@ -20,6 +182,14 @@ impl Volume for Solid {
   fn contains_point(&self, p: Point3) -> bool {
     self.shells[0].contains_point(p) && !self.shells.iter().skip(1).any(|shell| shell.contains_point(p) )
   }
+
+  // Inner shells are voids, that get subtracted from the outer shell
+  fn mass_properties(&self, density: Option<f64>) -> MassProperties {
+    let moments = self.shells.iter().skip(1).fold(Moments::of_shell(&self.shells[0]), |acc, shell| {
+      acc.combine(&Moments::of_shell(shell), -1.0)
+    });
+    moments.into_properties(density.unwrap_or(1.0))
+  }
 }
 
 
@@ -30,26 +200,81 @@ impl Volume for Shell {
   }
 
   fn volume(&self) -> f64 {
-    0.0 //XXX
+    Moments::of_shell(self).volume
   }
 
   fn contains_point(&self, p: Point3) -> bool {
-    let ray = TrimmedCurve::new(Line::new(p, p + Vec3::unit_x() * 9999999.0).into_enum());
-    let num_hits: usize = self.faces.iter().flat_map(|face| {
-      let intersections = ray.intersect_surface(&face.borrow().make_surface());
-      intersections.iter().map(|isect| match isect {
-        CurveSurfaceIntersectionType::Pierce(_)
-        | CurveSurfaceIntersectionType::Cross(_)
-          => 1,
-        _ => 0,
-      }).collect::<Vec<usize>>()
-    }).sum();
-    num_hits % 2 != 0
+    count_hits(self, p, Vec3::unit_x() * 9999999.0) % 2 != 0
+  }
+
+  fn mass_properties(&self, density: Option<f64>) -> MassProperties {
+    Moments::of_shell(self).into_properties(density.unwrap_or(1.0))
   }
 }
 
 
 #[cfg(test)]
 mod tests {
+  use super::*;
+  use crate::solid::features;
+  use crate::test_data::polygon_wire;
+
+  #[test]
+  fn point_in_cylinder() {
+    let compound = features::make_cylinder(1.0, 2.0).unwrap();
+    let cylinder = &compound.solids[0];
+    assert!(cylinder.contains_point(Point3::new(0.0, 0.0, 1.0)));
+    assert!(cylinder.contains_point(Point3::new(0.5, 0.5, 0.5)));
+    assert!(!cylinder.contains_point(Point3::new(0.9, 0.9, 1.0)));
+    assert!(!cylinder.contains_point(Point3::new(0.0, 0.0, 3.0)));
+  }
 
+  #[test]
+  fn cube_mass_properties() {
+    let compound = features::make_cube(1.0, 1.0, 2.0).unwrap();
+    let cube = &compound.solids[0];
+    almost_eq(cube.volume(), 2.0);
+    almost_eq(cube.surface_area(), 10.0);
+    let props = cube.mass_properties(Some(3.0));
+    almost_eq(props.mass, 6.0);
+    almost_eq(props.center_of_mass.z, 1.0);
+    almost_eq(props.inertia.x.x, 6.0 * 5.0 / 12.0);
+    almost_eq(props.inertia.y.y, 6.0 * 5.0 / 12.0);
+    almost_eq(props.inertia.z.z, 6.0 * 2.0 / 12.0);
+    almost_eq(props.inertia.x.y, 0.0);
+  }
+
+  #[test]
+  fn tube_mass_properties() {
+    // Rectangle in the XY plane, revolved by a quarter turn about the Y axis
+    let wire = polygon_wire(&[
+      Point3::new(1.0, 0.0, 0.0),
+      Point3::new(2.0, 0.0, 0.0),
+      Point3::new(2.0, 1.0, 0.0),
+      Point3::new(1.0, 1.0, 0.0),
+    ]);
+    let compound = features::revolve(&vec![wire], Axis::new(Point3::origin(), Vec3::unit_y()), Deg(90.0)).unwrap();
+    let tube = &compound.solids[0];
+    let props = tube.mass_properties(Some(2.0));
+    let pi = std::f64::consts::PI;
+    // Curved faces are integrated up to the tolerance of their surface derivatives
+    let close = |a: f64, b: f64| assert!((a - b).abs() < 1.0e-8, "{} != {}", a, b);
+    close(props.volume, 0.75 * pi);
+    close(props.area, 3.0 * pi + 2.0);
+    close(props.mass, 1.5 * pi);
+    close(props.center_of_mass.y, 0.5);
+    let center = props.center_of_mass.to_vec();
+    let offset = 28.0 * 2.0_f64.sqrt() / 9.0 / pi;
+    close((center.x.powi(2) + center.z.powi(2)).sqrt(), offset);
+    // Thick walled tube about its axis, shifted to the center of mass
+    close(props.inertia.y.y, props.mass * (4.0 + 1.0) / 2.0 - props.mass * offset.powi(2));
+  }
+
+  #[test]
+  fn point_in_cube() {
+    let compound = features::make_cube(1.0, 1.0, 1.0).unwrap();
+    let cube = &compound.solids[0];
+    assert!(cube.contains_point(Point3::new(0.0, 0.5, 0.5)));
+    assert!(!cube.contains_point(Point3::new(0.0, -0.5, 0.5)));
+  }
 }
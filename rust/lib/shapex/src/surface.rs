@@ -12,47 +12,41 @@ pub use intersection::SurfaceIntersectionType;
 pub use intersection::CurveSurfaceIntersectionType;
 pub use intersection::CurveSurfaceIntersection;
 
+pub mod tesselation;
+
 #[allow(unused_imports)]
 use crate::log;
 
 
+// Number of spans used to approximate mapped profiles and surfaces
+const APPROXIMATION_SPANS: usize = 8;
+
+
 pub trait Surface: Transformable {
   fn sample(&self, u: f64, v: f64) -> Point3;
   fn unsample(&self, p: Point3) -> (f64, f64);
   fn normal_at(&self, u: f64, v: f64) -> Vec3;
-  fn tesselate(&self, profile: &Profile) -> Mesh;
   fn flip(&mut self); //XXX use Face::flip_normal instead
+  fn offset(&mut self, distance: f64); // Move surface along its normals
 
-  fn tesselate_fixed(&self, u_res: usize, v_res: usize, _profile: &Profile) -> Mesh {
-    let mut vertices: Vec<Point3> = vec![];
-    let mut vertex_normals: Vec<Vec3> = vec![];
-    let mut faces: Vec<usize> = vec![];
-    let u_steps = u_res + 1;
-    for j in 0..=v_res {
-      let v = j as f64 / v_res as f64;
-      for i in 0..=u_res {
-        let u = i as f64 / u_res as f64;
-        let vertex = self.sample(u, v);
-        let normal = self.normal_at(u, v);
-        vertices.push(vertex);
-        vertex_normals.push(normal);
-        if j == 0 || i == 0 { continue }
-        // Triangle
-        faces.push(j * u_steps + (i - 1) );
-        faces.push((j - 1) * u_steps + i);
-        faces.push(j * u_steps + i);
-        // Triangle
-        faces.push(j * u_steps + (i - 1) );
-        faces.push((j - 1) * u_steps + (i - 1) );
-        faces.push((j - 1) * u_steps + i);
-      }
-    }
-    let normals = faces.iter().map(|index| vertex_normals[*index] ).collect();
-    Mesh {
-      vertices,
-      faces,
-      normals,
-    }
+  fn tesselate(&self, profile: &Profile) -> Mesh {
+    self.tesselate_adaptive(profile, 0.025, Deg(20.0))
+  }
+
+  // Grid resolution and boundary samples follow chordal deviation and angle between segments
+  fn tesselate_adaptive(&self, profile: &Profile, max_deviation: f64, max_angle: Deg<f64>) -> Mesh {
+    let mut mesh = Mesh::default();
+    let rings = tesselation::profile_rings(profile, &mut mesh, |tcurve| tcurve.tesselate_adaptive(max_deviation, max_angle, (0.0, 1.0)) );
+    let steps = tesselation::grid_steps(self, max_deviation, max_angle);
+    tesselation::tesselate_rings(self, &rings, steps, 1.0, &mut mesh);
+    mesh
+  }
+
+  fn tesselate_fixed(&self, u_res: usize, v_res: usize, profile: &Profile) -> Mesh {
+    let mut mesh = Mesh::default();
+    let rings = tesselation::profile_rings(profile, &mut mesh, |tcurve| tcurve.tesselate() );
+    tesselation::tesselate_rings(self, &rings, (u_res, v_res), 1.0, &mut mesh);
+    mesh
   }
 
   fn closest_point(&self, p: Point3) -> Point3 {
@@ -64,6 +58,10 @@ pub trait Surface: Transformable {
     let (u, v) = self.unsample(p);
     u >= 0.0 && u <= 1.0 && v >= 0.0 && v <= 1.0 && self.sample(u, v).almost(p)
   }
+
+  fn distance(&self, p: Point3) -> f64 {
+    self.closest_point(p).distance(p)
+  }
 }
 
 
@@ -96,22 +94,22 @@ impl SurfaceType {
       // PlanarSurface
       SurfaceType::Planar(plane) => match other {
         SurfaceType::Planar(surface) => intersection::plane_plane(&plane.plane, &surface.plane).map_or(vec![], |isect| vec![isect] ),
-        SurfaceType::Revolution(_surface) => vec![],
-        SurfaceType::Spline(_surface) => vec![],
+        SurfaceType::Revolution(surface) => intersection::plane_revolution(&plane.plane, surface),
+        SurfaceType::Spline(surface) => intersection::plane_spline(&plane.plane, surface),
       },
 
       // RevolutionSurface
-      SurfaceType::Revolution(_surface) => match other {
-        SurfaceType::Planar(_surface) => vec![],
-        SurfaceType::Revolution(_surface) => vec![],
-        SurfaceType::Spline(_surface) => vec![],
+      SurfaceType::Revolution(revolution) => match other {
+        SurfaceType::Planar(surface) => intersection::plane_revolution(&surface.plane, revolution),
+        SurfaceType::Revolution(surface) => intersection::revolution_revolution(revolution, surface),
+        SurfaceType::Spline(surface) => intersection::revolution_spline(revolution, surface),
       },
 
       // SplineSurface
-      SurfaceType::Spline(_surface) => match other {
-        SurfaceType::Planar(_surface) => vec![],
-        SurfaceType::Revolution(_surface) => vec![],
-        SurfaceType::Spline(_surface) => vec![],
+      SurfaceType::Spline(spline) => match other {
+        SurfaceType::Planar(surface) => intersection::plane_spline(&surface.plane, spline),
+        SurfaceType::Revolution(surface) => intersection::revolution_spline(surface, spline),
+        SurfaceType::Spline(surface) => intersection::spline_spline(spline, surface),
       },
     }
   }
@@ -124,29 +122,29 @@ impl CurveType {
       // Line
       CurveType::Line(line) => match other {
         SurfaceType::Planar(surface) => intersection::line_plane(line, &surface.plane).map_or(vec![], |isect| vec![isect] ),
-        SurfaceType::Revolution(_surface) => vec![],
-        SurfaceType::Spline(_surface) => vec![],
+        SurfaceType::Revolution(surface) => intersection::line_revolution(line, surface),
+        SurfaceType::Spline(surface) => intersection::line_spline(line, surface),
       },
 
       // Arc
-      CurveType::Arc(_arc) => match other {
-        SurfaceType::Planar(_surface) => vec![],
-        SurfaceType::Revolution(_surface) => vec![],
-        SurfaceType::Spline(_surface) => vec![],
+      CurveType::Arc(arc) => match other {
+        SurfaceType::Planar(surface) => intersection::arc_plane(arc, &surface.plane),
+        SurfaceType::Revolution(surface) => intersection::curve_revolution(arc, surface),
+        SurfaceType::Spline(surface) => intersection::curve_spline(arc, surface),
       },
 
       // Circle
-      CurveType::Circle(_circle) => match other {
-        SurfaceType::Planar(_surface) => vec![],
-        SurfaceType::Revolution(_surface) => vec![],
-        SurfaceType::Spline(_surface) => vec![],
+      CurveType::Circle(circle) => match other {
+        SurfaceType::Planar(surface) => intersection::circle_plane(circle, &surface.plane),
+        SurfaceType::Revolution(surface) => intersection::curve_revolution(circle, surface),
+        SurfaceType::Spline(surface) => intersection::curve_spline(circle, surface),
       },
 
       // Spline
-      CurveType::Spline(_spline) => match other {
-        SurfaceType::Planar(_surface) => vec![],
-        SurfaceType::Revolution(_surface) => vec![],
-        SurfaceType::Spline(_surface) => vec![],
+      CurveType::Spline(spline) => match other {
+        SurfaceType::Planar(surface) => intersection::spline_plane(spline, &surface.plane),
+        SurfaceType::Revolution(surface) => intersection::curve_revolution(spline, surface),
+        SurfaceType::Spline(surface) => intersection::curve_spline(spline, surface),
       },
     }
   }
@@ -167,7 +165,7 @@ impl TrimmedCurve {
         => {
           isect.t = self.param_from_base(isect.t);
           let first_at_end = isect.t.almost(0.0) || isect.t.almost(1.0);
-          if other.contains_point(isect.point) {
+          if other.on_surface(isect.u, isect.v) {
             if first_at_end {
               CurveSurfaceIntersectionType::Pierce(isect)
             } else if 0.0 <= isect.t && isect.t <= 1.0 {
@@ -200,12 +198,48 @@ impl TrimmedSurface {
   }
 
   pub fn area(&self) -> f64 {
-    0.0
+    let mut area = 0.0;
+    self.integrate(&mut |_, _, weight| area += weight );
+    area
+  }
+
+  // Integrate over the trimmed region with Gauss-Legendre quadrature
+  // The integrand receives points on the surface, along with their normal and the area they represent
+  pub fn integrate(&self, integrand: &mut impl FnMut(Point3, Vec3, f64)) {
+    match &self.base {
+      SurfaceType::Planar(surface) => for (i, wire) in self.profile.iter().enumerate() {
+        // Outer rings add to the integral and holes subtract from it, regardless of their orientation
+        let mut signed_area = 0.0;
+        integrate_wire(&surface.plane, wire, &mut |_, _, weight| signed_area += weight );
+        let sign = if (signed_area >= 0.0) == (i == 0) { 1.0 } else { -1.0 };
+        integrate_wire(&surface.plane, wire, &mut |p, normal, weight| integrand(p, normal, weight * sign) );
+      },
+      // Curved surfaces are bounded by their parameter range
+      _ => integrate_patch(self.base.as_surface(), integrand),
+    }
   }
 
+  // Check if the given surface parameters lie inside the trimmed region
+  // Curved surfaces are trimmed in parameter space, or bounded by their parameter range where profiles enclose nothing
   pub fn on_surface(&self, u: f64, v: f64) -> bool {
-    let p = Point3::new(u, v, 0.0);
-    geom2d::point_in_region(p, &self.profile[0]) && !self.profile.iter().skip(1).any(|wire| geom2d::point_in_region(p, wire) )
+    match &self.base {
+      SurfaceType::Planar(surface) => {
+        // Profile is stored in world space
+        let trans_inv = surface.plane.as_transform().invert().unwrap();
+        let local_profile: Profile = self.profile.iter().map(|wire| {
+          wire.iter().map(|curve| {
+            let mut curve = curve.clone();
+            curve.transform(&trans_inv);
+            curve
+          }).collect()
+        }).collect();
+        let p = Point3::new(u, v, 0.0);
+        geom2d::point_in_region(p, &local_profile[0]) && !local_profile.iter().skip(1).any(|wire| geom2d::point_in_region(p, wire) )
+      },
+      _ => tesselation::params_in_profile(self.base.as_surface(), &self.profile, u, v).unwrap_or_else(||
+        (-EPSILON..=1.0 + EPSILON).contains(&u) && (-EPSILON..=1.0 + EPSILON).contains(&v)
+      ),
+    }
   }
 
   pub fn contains_point(&self, p: Point3) -> bool {
@@ -213,8 +247,179 @@ impl TrimmedSurface {
     self.base.as_surface().sample(u, v).almost(p) && self.on_surface(u, v)
   }
 
-  pub fn intersect(&self, _other: Self) -> SurfaceIntersectionType {
-    todo!()
+  // Intersect the base surfaces and keep those sections of the resulting curves,
+  // that lie inside the trim profiles of both surfaces
+  pub fn intersect(&self, other: &Self) -> Vec<SurfaceIntersectionType> {
+    self.base.intersect(&other.base).into_iter().flat_map(|intersection| {
+      match intersection {
+        SurfaceIntersectionType::Contained
+        => vec![intersection],
+
+        SurfaceIntersectionType::Touch(curve)
+        => self.clip(&curve, other).into_iter().map(SurfaceIntersectionType::Touch).collect(),
+
+        SurfaceIntersectionType::Cross(curve)
+        => self.clip(&curve, other).into_iter().map(SurfaceIntersectionType::Cross).collect(),
+
+        // Curves on the extension of bounded surfaces never touch their trimmed versions
+        SurfaceIntersectionType::Extended(_)
+        => vec![],
+      }
+    }).collect()
+  }
+
+  // Split curve where it crosses the trim profiles and keep the sections inside both surfaces
+  fn clip(&self, curve: &CurveType, other: &Self) -> Vec<CurveType> {
+    // Lines resulting from intersections are unbounded, so they get stretched to cover both profiles
+    let curve = if let CurveType::Line(line) = curve {
+      let direction = line.points.1 - line.points.0;
+      let params: Vec<f64> = self.profile.iter().chain(other.profile.iter())
+      .flatten()
+      .flat_map(|tcurve| tcurve.cache.as_curve().tesselate() )
+      .map(|p| (p - line.points.0).dot(direction) / direction.magnitude2() )
+      .collect();
+      let min = params.iter().cloned().fold(f64::MAX, f64::min);
+      let max = params.iter().cloned().fold(f64::MIN, f64::max);
+      Line::new(line.sample(min - 1.0), line.sample(max + 1.0)).into_enum()
+    } else {
+      curve.clone()
+    };
+    let mut params = self.cut_params(&curve);
+    params.append(&mut other.cut_params(&curve));
+    params.sort_by(|a, b| a.partial_cmp(b).unwrap() );
+    params.dedup_by(|a, b| a.almost(*b) );
+    let mut ranges: Vec<(f64, f64)> = params.windows(2).map(|pair| (pair[0], pair[1]) ).collect();
+    if curve.as_curve().is_closed() {
+      match params.len() {
+        0 => ranges.push((0.0, 1.0)),
+        _ => ranges.push((*params.last().unwrap(), params[0] + 1.0)),
+      }
+    } else {
+      if params.first().is_none_or(|t| !t.almost(0.0) ) {
+        ranges.insert(0, (0.0, params.first().cloned().unwrap_or(1.0)));
+      }
+      if params.last().is_some_and(|t| !t.almost(1.0) ) {
+        ranges.push((*params.last().unwrap(), 1.0));
+      }
+    }
+    ranges.into_iter()
+    .filter(|(start, end)| !start.almost(*end) )
+    .filter(|(start, end)| {
+      let mid = curve.as_curve().sample(((start + end) / 2.0) % 1.0);
+      self.contains_param_point(mid) && other.contains_param_point(mid)
+    })
+    .map(|range| trim_curve(&curve, range) )
+    .collect()
+  }
+
+  // Parameters where a curve on the surface crosses the trim profile
+  fn cut_params(&self, curve: &CurveType) -> Vec<f64> {
+    let mut curve = curve.clone();
+    let mut cutters: Vec<CurveType> = self.profile.iter().flatten().map(|tcurve| tcurve.cache.clone() ).collect();
+    // Planar profiles are cut in plane space
+    if let SurfaceType::Planar(surface) = &self.base {
+      let trans_inv = surface.plane.as_transform().invert().unwrap();
+      curve.as_curve_mut().transform(&trans_inv);
+      for cutter in &mut cutters {
+        cutter.as_curve_mut().transform(&trans_inv);
+      }
+    }
+    cutters.iter()
+    .flat_map(|cutter| curve.intersect(cutter) )
+    .filter_map(|isect| isect.get_intersection(false).map(|isect| isect.t1 ) )
+    .collect()
+  }
+
+  // Check if point lies inside the trimmed region, assuming it touches the surface
+  fn contains_param_point(&self, p: Point3) -> bool {
+    let (u, v) = self.base.as_surface().unsample(p);
+    self.on_surface(u, v)
+  }
+}
+
+// Section of a curve between two of its parameters
+// Ranges of closed curves may cross their seam
+fn trim_curve(curve: &CurveType, range: (f64, f64)) -> CurveType {
+  match curve {
+    CurveType::Line(line) => Line::new(line.sample(range.0), line.sample(range.1)).into_enum(),
+    CurveType::Arc(arc) => {
+      let start = arc.bounds.0 + arc.range() * range.0;
+      let end = arc.bounds.0 + arc.range() * range.1;
+      Arc::from_plane(arc.plane.clone(), arc.radius, start, end).into_enum()
+    },
+    CurveType::Circle(circle) => if (range.1 - range.0).almost(1.0) {
+      curve.clone()
+    } else {
+      Arc::from_plane(circle.plane.clone(), circle.radius, range.0, range.1).into_enum()
+    },
+    CurveType::Spline(spline) => if range.0.almost(0.0) && range.1.almost(1.0) {
+      curve.clone()
+    } else {
+      let points: Vec<Point3> = (0..=32).map(|i| {
+        let t = range.0 + (range.1 - range.0) * i as f64 / 32.0;
+        spline.sample(if t > 1.0 { t - 1.0 } else { t })
+      }).collect();
+      Spline::fit(&points).into_enum()
+    },
+  }
+}
+
+// Green's theorem turns the area integral over a planar region into a line integral along its boundary
+// The inner integral along the plane's u axis is evaluated by another quadrature
+fn integrate_wire(plane: &Plane, wire: &Wire, integrand: &mut impl FnMut(Point3, Vec3, f64)) {
+  let normal = plane.normal();
+  let trans_inv = plane.as_transform().invert().unwrap();
+  for tcurve in wire {
+    let local = |t: f64| trans_inv.transform_point(tcurve.sample(t));
+    let segments = if let CurveType::Line(_) = tcurve.base { 1 } else { 16 };
+    for i in 0..segments {
+      let half = 0.5 / segments as f64;
+      let center = (2 * i + 1) as f64 * half;
+      for (x, w) in GAUSS_LEGENDRE {
+        let t = center + half * x;
+        let p = local(t);
+        let weight = derivative(local, t).y * w * half * p.x / 2.0;
+        for (s, ws) in GAUSS_LEGENDRE {
+          integrand(plane.sample(p.x * (1.0 + s) / 2.0, p.y), normal, weight * ws);
+        }
+      }
+    }
+  }
+}
+
+// Tensor product quadrature over the parameter range of a surface,
+// with cells being refined until the area converges
+fn integrate_patch(surface: &dyn Surface, integrand: &mut impl FnMut(Point3, Vec3, f64)) {
+  let area = |cells| {
+    let mut area = 0.0;
+    patch_quadrature(surface, cells, &mut |_, _, weight| area += weight );
+    area
+  };
+  let mut cells = 2;
+  let mut last_area = area(cells);
+  while cells < 64 {
+    cells *= 2;
+    let refined = area(cells);
+    if (refined - last_area).abs() <= 1.0e-9 * refined.max(1.0) { break }
+    last_area = refined;
+  }
+  patch_quadrature(surface, cells, integrand);
+}
+
+fn patch_quadrature(surface: &dyn Surface, cells: usize, integrand: &mut impl FnMut(Point3, Vec3, f64)) {
+  let size = 1.0 / cells as f64;
+  for i in 0..cells {
+    for j in 0..cells {
+      for (x, wx) in GAUSS_LEGENDRE {
+        for (y, wy) in GAUSS_LEGENDRE {
+          let u = (i as f64 + (1.0 + x) / 2.0) * size;
+          let v = (j as f64 + (1.0 + y) / 2.0) * size;
+          let (su, sv) = partial_derivatives(surface, u, v);
+          let weight = su.cross(sv).magnitude() * wx * wy * size * size / 4.0;
+          integrand(surface.sample(u, v), surface.normal_at(u, v), weight);
+        }
+      }
+    }
   }
 }
 
@@ -259,23 +464,13 @@ impl Surface for PlanarSurface {
     self.plane.normal()
   }
 
-  fn tesselate(&self, profile: &Profile) -> Mesh {
-    let mut local_profile = profile.clone();
-    let trans = self.plane.as_transform();
-    let trans_inv = trans.invert().unwrap(); //XXX Profile should be in plane space
-    for wire in &mut local_profile {
-      for curve in wire.iter_mut() {
-        curve.transform(&trans_inv);
-      }
-    }
-    let mut mesh = geom2d::tesselate_profile(&local_profile, self.plane.normal());
-    mesh.transform(&trans);
-    mesh
-  }
-
   fn flip(&mut self) {
     self.plane.flip();
   }
+
+  fn offset(&mut self, distance: f64) {
+    self.plane.origin += self.plane.normal() * distance;
+  }
 }
 
 impl Transformable for PlanarSurface {
@@ -285,6 +480,18 @@ impl Transformable for PlanarSurface {
 }
 
 
+/// Analytic shapes that revolution surfaces can take
+/// Heights and radii are measured along and away from the axis of the surface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RevolutionShape {
+  Cylinder { radius: f64 },
+  Cone { height: f64, radius: f64, semi_angle: f64 }, // Radius at height, angle is positive when widening along the axis
+  Sphere { height: f64, radius: f64 },
+  Torus { height: f64, major_radius: f64, minor_radius: f64 },
+  Other,
+}
+
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RevolutionSurface {
   pub axis: Axis,
@@ -300,10 +507,9 @@ impl RevolutionSurface {
   pub fn with_bounds(axis: Axis, mut curve: CurveType, u_bounds: (f64, f64)) -> Self {
     let base_transform = axis.as_transform().invert().unwrap();
     curve.as_curve_mut().transform(&base_transform);
-    let mut p = curve.as_curve().endpoints().0;
-    p.z = 0.0;
+    let p = curve.as_curve().endpoints().0;
     // Move curve onto local XZ plane
-    let angle = p.to_vec().angle(Vec3::unit_x());
+    let angle = Rad(p.y.atan2(p.x));
     curve.as_curve_mut().transform(&Matrix4::from_angle_z(-angle));
     // Offset bounds to account for difference between curve plane and natural start of circle
     let degrees: Deg<f64> = angle.into();
@@ -322,6 +528,73 @@ impl RevolutionSurface {
     }
   }
 
+  // Cone with its apex on the axis at the given height
+  pub fn cone(axis: Axis, radius: f64, height: f64) -> Self {
+    Self {
+      axis,
+      curve: Line::new(Point3::new(radius, 0.0, 0.0), Point3::new(0.0, 0.0, height)).into_enum(),
+      u_bounds: (0.0, 1.0),
+    }
+  }
+
+  // Sphere with its poles on the z axis, running from south to north in v direction
+  pub fn sphere(center: Point3, radius: f64) -> Self {
+    let plane = Plane { origin: Point3::origin(), u: Vec3::unit_x(), v: Vec3::unit_z() };
+    Self {
+      axis: Axis::new(center, Vec3::unit_z()),
+      curve: Arc::from_plane(plane, radius, 0.5, 0.0).into_enum(),
+      u_bounds: (0.0, 1.0),
+    }
+  }
+
+  pub fn torus(axis: Axis, major_radius: f64, minor_radius: f64) -> Self {
+    let plane = Plane { origin: Point3::new(major_radius, 0.0, 0.0), u: Vec3::unit_x(), v: -Vec3::unit_z() };
+    Self {
+      axis,
+      curve: Circle::from_plane(plane, minor_radius).into_enum(),
+      u_bounds: (0.0, 1.0),
+    }
+  }
+
+  // Recognize analytic shapes, so they can be treated exactly and exchanged as such
+  pub fn shape(&self) -> RevolutionShape {
+    match &self.curve {
+      CurveType::Line(line) => {
+        let (p1, p2) = line.points;
+        let (r1, r2) = (p1.x.hypot(p1.y), p2.x.hypot(p2.y));
+        if p1.z.almost(p2.z) {
+          RevolutionShape::Other
+        } else if r1.almost(r2) {
+          RevolutionShape::Cylinder { radius: r1 }
+        } else {
+          RevolutionShape::Cone { height: p1.z, radius: r1, semi_angle: ((r2 - r1) / (p2.z - p1.z)).atan() }
+        }
+      },
+      CurveType::Arc(Arc { plane, radius, .. })
+      | CurveType::Circle(Circle { plane, radius, .. })
+      => {
+        // Profile has to lie in a plane containing the axis
+        if !plane.normal().z.almost(0.0) { return RevolutionShape::Other }
+        let center = plane.origin;
+        let offset = center.x.hypot(center.y);
+        if offset.almost(0.0) {
+          RevolutionShape::Sphere { height: center.z, radius: *radius }
+        } else {
+          RevolutionShape::Torus { height: center.z, major_radius: offset, minor_radius: *radius }
+        }
+      },
+      CurveType::Spline(_) => RevolutionShape::Other,
+    }
+  }
+
+  // Principal curvatures along the profile and around the axis
+  pub fn curvatures_at(&self, u: f64, v: f64) -> (f64, f64) {
+    let sample = self.sample_local(u, v);
+    let radius = sample.x.hypot(sample.y);
+    let normal = self.axis.as_transform().invert().unwrap().transform_vector(self.normal_at(u, v));
+    (self.curve.as_curve().curvature_at(v), normal.x.hypot(normal.y) / radius)
+  }
+
   fn convert_param(&self, u: f64) -> f64 {
     let u = self.u_bounds.0 + u * (self.u_bounds.1 - self.u_bounds.0);
     if u > 1.0 {
@@ -333,6 +606,18 @@ impl RevolutionSurface {
     }
   }
 
+  // Surface parameter in u direction for a point given in the local space of the axis
+  // Points outside of partial revolutions map to parameters above one
+  pub(crate) fn angle_param(&self, q: Point3) -> f64 {
+    let angle = q.y.atan2(q.x) / std::f64::consts::PI / 2.0;
+    let range = self.u_bounds.1 - self.u_bounds.0;
+    // Measure angle from start of bounds in the direction of the revolution
+    let mut offset = (angle - self.u_bounds.0) * range.signum();
+    offset -= offset.floor();
+    if offset.almost(1.0) { offset = 0.0 }
+    offset / range.abs()
+  }
+
   fn sample_local(&self, u: f64, v: f64) -> Point3 {
     let mut sample = self.curve.as_curve().sample(v);
     let height = sample.z;
@@ -343,6 +628,34 @@ impl RevolutionSurface {
     Point3::new(u.cos() * radius, u.sin() * radius, height)
   }
 
+  // Move points of the profile, given their position and surface normal in world space
+  // Lines stay straight, other profiles get approximated by splines
+  pub fn map_profile(&mut self, map: &dyn Fn(Point3, Vec3) -> Point3) {
+    self.curve = if let CurveType::Line(_) = self.curve {
+      Line::new(self.map_profile_point(0.0, map), self.map_profile_point(1.0, map)).into_enum()
+    } else {
+      let points: Vec<Point3> = (0..=APPROXIMATION_SPANS).map(|i| {
+        self.map_profile_point(i as f64 / APPROXIMATION_SPANS as f64, map)
+      }).collect();
+      Spline::fit(&points).into_enum()
+    };
+  }
+
+  // Profile point at v, mapped in world space and rotated back into the profile plane
+  fn map_profile_point(&self, v: f64, map: &dyn Fn(Point3, Vec3) -> Point3) -> Point3 {
+    let p = map(self.sample(0.0, v), self.normal_at(0.0, v));
+    let q = self.axis.as_transform().invert().unwrap().transform_point(p);
+    Point3::new(q.x.hypot(q.y), 0.0, q.z)
+  }
+
+  // Profile curve rotated into place at parameter u
+  pub fn profile_at(&self, u: f64) -> CurveType {
+    let mut curve = self.curve.clone();
+    let rotation = Matrix4::from_angle_z(Deg(self.convert_param(u) * 360.0));
+    curve.as_curve_mut().transform(&(self.axis.as_transform() * rotation));
+    curve
+  }
+
   pub fn v_tangent_at(&self, u: f64, v: f64) -> Vec3 {
     let u = self.convert_param(u);
     let v_tangent = self.curve.as_curve().tangent_at(v);
@@ -350,6 +663,11 @@ impl RevolutionSurface {
     self.axis.as_transform().transform_vector(rotation.transform_vector(v_tangent))
   }
 
+  // Full revolutions close up in u direction
+  pub fn is_periodic(&self) -> bool {
+    (self.u_bounds.1 - self.u_bounds.0).abs().almost(1.0)
+  }
+
   pub fn into_enum(self) -> SurfaceType {
     SurfaceType::Revolution(self)
   }
@@ -361,8 +679,19 @@ impl Surface for RevolutionSurface {
     self.axis.as_transform().transform_point(p)
   }
 
-  fn unsample(&self, _p: Point3) -> (f64, f64) {
-    todo!()
+  fn unsample(&self, p: Point3) -> (f64, f64) {
+    let q = self.axis.as_transform().invert().unwrap().transform_point(p);
+    let mut u = self.angle_param(q);
+    if u > 1.0 {
+      // Points beside partial revolutions snap to the closer bound
+      let range = (self.u_bounds.1 - self.u_bounds.0).abs();
+      let offset = u * range;
+      u = if offset - range < 1.0 - offset { 1.0 } else { 0.0 };
+    }
+    // Rotate point into the plane of the profile curve
+    let profile_point = Point3::new(q.x.hypot(q.y), 0.0, q.z);
+    let v = self.curve.as_curve().unsample(&profile_point).clamp(0.0, 1.0);
+    (u, v)
   }
 
   fn normal_at(&self, u: f64, v: f64) -> Vec3 {
@@ -380,25 +709,20 @@ impl Surface for RevolutionSurface {
     }
   }
 
-  fn tesselate(&self, profile: &Profile) -> Mesh {
-    let circle_steps = 80;
-    let u_steps = (circle_steps as f64 * (self.u_bounds.1 - self.u_bounds.0)) as usize;
-    let v_steps = match &self.curve {
-      CurveType::Line(_) => 1,
-      CurveType::Arc(arc) => (circle_steps as f64 * (arc.bounds.1 - arc.bounds.0)) as usize,
-      CurveType::Circle(_) => circle_steps,
-      CurveType:: Spline(spline) => if spline.degree == 1 {
-        1
-      } else {
-        (spline.controls.len() - 1) * 10
-      },
-    };
-    self.tesselate_fixed(u_steps, v_steps, profile)
-  }
-
   fn flip(&mut self) {
     self.u_bounds = (self.u_bounds.1, self.u_bounds.0);
   }
+
+  // Arcs and circles stay concentric, other profiles get moved point by point
+  fn offset(&mut self, distance: f64) {
+    let map = |p: Point3, normal: Vec3| p + normal * distance;
+    let mid = self.map_profile_point(0.5, &map);
+    match &mut self.curve {
+      CurveType::Arc(arc) => arc.radius = arc.plane.origin.distance(mid),
+      CurveType::Circle(circle) => circle.radius = circle.plane.origin.distance(mid),
+      CurveType::Line(_) | CurveType::Spline(_) => self.map_profile(&map),
+    }
+  }
 }
 
 impl Transformable for RevolutionSurface {
@@ -429,6 +753,43 @@ impl SplineSurface {
     }
   }
 
+  // Surface passing through sections at the given parameters
+  // All sections must share the same degree and knots.
+  pub fn skinned(sections: &[Spline], params: &[f64]) -> Self {
+    let columns: Vec<Spline> = (0..sections[0].controls.len()).map(|i| {
+      let points: Vec<Point3> = sections.iter().map(|section| section.controls[i] ).collect();
+      Spline::fit_with_params(&points, params)
+    }).collect();
+    Self {
+      degree: (sections[0].degree, columns[0].degree),
+      controls: (0..columns[0].controls.len()).map(|j| {
+        columns.iter().map(|column| column.controls[j] ).collect()
+      }).collect(),
+      knots: (sections[0].knots.clone(), columns[0].knots.clone()),
+    }
+  }
+
+  // Surface connecting neighbouring sections with straight lines
+  pub fn ruled(sections: &[Spline]) -> Self {
+    let column = Spline::polyline(sections.iter().map(|section| section.controls[0] ).collect());
+    Self {
+      degree: (sections[0].degree, 1),
+      controls: sections.iter().map(|section| section.controls.clone() ).collect(),
+      knots: (sections[0].knots.clone(), column.knots),
+    }
+  }
+
+  // Move points of the surface, given their position and normal,
+  // approximating the result by skinning a grid of mapped samples
+  pub fn map_points(&mut self, map: &dyn Fn(Point3, Vec3) -> Point3) {
+    let params: Vec<f64> = (0..=APPROXIMATION_SPANS).map(|i| i as f64 / APPROXIMATION_SPANS as f64 ).collect();
+    let sections: Vec<Spline> = params.iter().map(|&v| {
+      let points: Vec<Point3> = params.iter().map(|&u| map(self.sample(u, v), self.normal_at(u, v)) ).collect();
+      Spline::fit_with_params(&points, &params)
+    }).collect();
+    *self = Self::skinned(&sections, &params);
+  }
+
   fn get_basis_function(degree: usize, t: f64, knots: &Vec<f64>) -> Vec<f64> {
     // Remap t to actual curve range
     let low = knots[degree];
@@ -463,14 +824,6 @@ impl SplineSurface {
     }
   }
 
-  fn tesselation_steps(&self, degree: usize, num_cvs: usize) -> usize {
-    if degree == 1 {
-      1
-    } else {
-      (num_cvs - 1) * 20
-    }
-  }
-
   pub fn into_enum(self) -> SurfaceType {
     SurfaceType::Spline(self)
   }
@@ -487,26 +840,39 @@ impl Surface for SplineSurface {
     })
   }
 
-  fn unsample(&self, _p: Point3) -> (f64, f64) {
-    todo!()
-  }
-
-  fn normal_at(&self, _u: f64, _v: f64) -> Vec3 {
-    Vec3::unit_x()
+  fn unsample(&self, p: Point3) -> (f64, f64) {
+    // Seed with the closest of a number of samples, that grows with the number of control points
+    let res_u = ((self.controls[0].len() - 1) * 4).clamp(4, 64);
+    let res_v = ((self.controls.len() - 1) * 4).clamp(4, 64);
+    let mut seed = (0.0, 0.0);
+    let mut min_dist = f64::MAX;
+    for j in 0..=res_v {
+      let v = j as f64 / res_v as f64;
+      for i in 0..=res_u {
+        let u = i as f64 / res_u as f64;
+        let dist = self.sample(u, v).distance2(p);
+        if dist < min_dist {
+          min_dist = dist;
+          seed = (u, v);
+        }
+      }
+    }
+    project_point(self, p, seed, false)
   }
 
-  fn tesselate(&self, profile: &Profile) -> Mesh {
-    self.tesselate_fixed(
-      self.tesselation_steps(self.degree.0, self.controls[0].len()),
-      self.tesselation_steps(self.degree.1, self.controls.len()),
-      profile
-    )
+  fn normal_at(&self, u: f64, v: f64) -> Vec3 {
+    let (su, sv) = partial_derivatives(self, u, v);
+    su.cross(sv).normalize()
   }
 
   fn flip(&mut self) {
     self.controls = self.controls.iter().rev().cloned().collect();
     mem::swap(&mut self.knots.1, &mut self.knots.0);
   }
+
+  fn offset(&mut self, distance: f64) {
+    self.map_points(&|p, normal| p + normal * distance );
+  }
 }
 
 impl Transformable for SplineSurface {
@@ -520,6 +886,64 @@ impl Transformable for SplineSurface {
 }
 
 
+
+// Closest point on a surface patch by Newton iteration on the squared distance,
+// starting from the given parameters.
+// Parameters in u direction wrap around on periodic surfaces.
+pub(crate) fn project_point(surface: &dyn Surface, p: Point3, (mut u, mut v): (f64, f64), periodic: bool) -> (f64, f64) {
+  for _ in 0..32 {
+    let d = surface.sample(u, v) - p;
+    let (su, sv) = partial_derivatives(surface, u, v);
+    let gradient = (su.dot(d), sv.dot(d));
+    let mut h11 = su.dot(su);
+    let mut h12 = su.dot(sv);
+    let mut h22 = sv.dot(sv);
+    // Curvature terms only matter for points away from the surface
+    if d.magnitude2() > EPSILON {
+      let (suu, suv, svv) = second_derivatives(surface, u, v);
+      let (n11, n12, n22) = (h11 + suu.dot(d), h12 + suv.dot(d), h22 + svv.dot(d));
+      // Keep Gauss-Newton steps where the full Hessian is not positive definite
+      if n11 > 0.0 && n11 * n22 - n12 * n12 > 0.0 {
+        (h11, h12, h22) = (n11, n12, n22);
+      }
+    }
+    let determinant = h11 * h22 - h12 * h12;
+    if determinant.abs() <= EPSILON * h11 * h22 { break }
+    let mut delta_u = -(gradient.0 * h22 - h12 * gradient.1) / determinant;
+    let mut delta_v = -(h11 * gradient.1 - h12 * gradient.0) / determinant;
+    // Limit steps to stay in the basin of the seed
+    let max_delta = delta_u.abs().max(delta_v.abs());
+    if max_delta > 0.25 {
+      delta_u *= 0.25 / max_delta;
+      delta_v *= 0.25 / max_delta;
+    }
+    u = if periodic { (u + delta_u).rem_euclid(1.0) } else { (u + delta_u).clamp(0.0, 1.0) };
+    v = (v + delta_v).clamp(0.0, 1.0);
+    if delta_u.abs() <= EPSILON && delta_v.abs() <= EPSILON { break }
+  }
+  (u, v)
+}
+
+fn partial_derivatives(surface: &dyn Surface, u: f64, v: f64) -> (Vec3, Vec3) {
+  (derivative(|u| surface.sample(u, v), u), derivative(|v| surface.sample(u, v), v))
+}
+
+fn second_derivatives(surface: &dyn Surface, u: f64, v: f64) -> (Vec3, Vec3, Vec3) {
+  let h = 1.0e-4;
+  let (u0, u1) = ((u - h).max(0.0), (u + h).min(1.0));
+  let (v0, v1) = ((v - h).max(0.0), (v + h).min(1.0));
+  let suu = (partial_derivatives(surface, u1, v).0 - partial_derivatives(surface, u0, v).0) / (u1 - u0);
+  let suv = (partial_derivatives(surface, u, v1).0 - partial_derivatives(surface, u, v0).0) / (v1 - v0);
+  let svv = (partial_derivatives(surface, u, v1).1 - partial_derivatives(surface, u, v0).1) / (v1 - v0);
+  (suu, suv, svv)
+}
+
+fn derivative(sample: impl Fn(f64) -> Point3, t: f64) -> Vec3 {
+  let t0 = (t - 1.0e-6).max(0.0);
+  let t1 = (t + 1.0e-6).min(1.0);
+  (sample(t1) - sample(t0)) / (t1 - t0)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -567,4 +991,137 @@ mod tests {
     almost_eq(vec, normal);
     almost_eq(normal, gen_normal);
   }
+
+  fn square(plane: Plane, size: f64) -> TrimmedSurface {
+    let corners = [(-size, -size), (size, -size), (size, size), (-size, size)];
+    let wire = (0..4).map(|i| {
+      let (u1, v1) = corners[i];
+      let (u2, v2) = corners[(i + 1) % 4];
+      TrimmedCurve::new(Line::new(plane.sample(u1, v1), plane.sample(u2, v2)).into_enum())
+    }).collect();
+    TrimmedSurface::new(PlanarSurface::new(plane).into_enum(), wire)
+  }
+
+  #[test]
+  fn trimmed_surface_intersection() {
+    let floor = square(Plane::new(), 1.0);
+    let wall = square(Plane::from_normal(Point3::new(0.5, 0.0, 0.0), Vec3::unit_x()), 0.25);
+    let hits = floor.intersect(&wall);
+    assert_eq!(hits.len(), 1);
+    let line = hits[0].get_line().unwrap();
+    let (start, end) = line.endpoints();
+    almost_eq(start.x, 0.5);
+    almost_eq(start.z, 0.0);
+    almost_eq(start.y.abs(), 0.25);
+    almost_eq(start.distance(end), 0.5);
+    // Walls beside the floor are missed
+    let beside = square(Plane::from_normal(Point3::new(2.0, 0.0, 0.0), Vec3::unit_x()), 0.25);
+    assert_eq!(floor.intersect(&beside), vec![]);
+  }
+
+  #[test]
+  fn trimmed_cylinder_on_surface() {
+    let cylinder = RevolutionSurface::cylinder(Axis::new(Point3::origin(), Vec3::unit_z()), 1.0, 2.0).into_enum();
+    let window = |(u1, v1): (f64, f64), (u2, v2): (f64, f64)| {
+      let corners = [(u1, v1), (u2, v1), (u2, v2), (u1, v2)];
+      let wire = (0..4).map(|i| {
+        let (u1, v1) = corners[i];
+        let (u2, v2) = corners[(i + 1) % 4];
+        TrimmedCurve::new(Line::new(cylinder.as_surface().sample(u1, v1), cylinder.as_surface().sample(u2, v2)).into_enum())
+      }).collect();
+      TrimmedSurface::new(cylinder.clone(), wire)
+    };
+    let patch = window((0.25, 0.25), (0.3, 0.75));
+    assert!(patch.on_surface(0.27, 0.5));
+    assert!(!patch.on_surface(0.1, 0.5));
+    assert!(!patch.on_surface(0.27, 0.9));
+    // Windows across the seam
+    let patch = window((0.97, 0.25), (0.03, 0.75));
+    assert!(patch.on_surface(0.99, 0.5));
+    assert!(patch.on_surface(0.01, 0.5));
+    assert!(!patch.on_surface(0.5, 0.5));
+  }
+
+  #[test]
+  fn unsample_cylinder() {
+    let cylinder = RevolutionSurface::cylinder(Axis::new(Point3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 1.0, 0.0)), 1.0, 2.0);
+    for (u, v) in [(0.0, 0.0), (0.3, 0.5), (0.75, 1.0), (0.9, 0.1)] {
+      let (u2, v2) = cylinder.unsample(cylinder.sample(u, v));
+      almost_eq(u2, u);
+      almost_eq(v2, v);
+    }
+    let axis_point = Point3::new(1.0, 2.0, 3.0) + Vec3::new(1.0, 1.0, 0.0).normalize();
+    almost_eq(cylinder.distance(axis_point + Vec3::new(0.0, 0.0, 3.0)), 2.0);
+    almost_eq(cylinder.distance(axis_point), 1.0);
+  }
+
+  #[test]
+  fn unsample_partial_revolution() {
+    let axis = Axis::new(Point3::origin(), Vec3::unit_z());
+    let curve = Line::new(Point3::new(0.0, -1.0, 0.0), Point3::new(0.0, -1.0, 1.0)).into_enum();
+    let quarter = RevolutionSurface::with_bounds(axis, curve, (0.0, 0.25));
+    let p = quarter.sample(0.5, 0.5);
+    almost_eq(p, Point3::new(1.0_f64.sqrt() * 0.5_f64.sqrt(), -0.5_f64.sqrt(), 0.5));
+    let (u, v) = quarter.unsample(p);
+    almost_eq(u, 0.5);
+    almost_eq(v, 0.5);
+    // Points beyond the bounds snap to the closer one
+    assert_eq!(quarter.unsample(Point3::new(-1.0, -0.1, 0.5)).0, 0.0);
+    assert_eq!(quarter.unsample(Point3::new(0.1, 1.0, 0.5)).0, 1.0);
+  }
+
+  #[test]
+  fn unsample_spline_surface() {
+    let surface = SplineSurface::tabulated(&crate::test_data::s_curve(), Vec3::new(0.0, 0.0, 1.0));
+    for (u, v) in [(0.0, 0.0), (0.2, 0.5), (0.5, 0.5), (0.8, 0.3), (1.0, 1.0)] {
+      let p = surface.sample(u, v);
+      let (u2, v2) = surface.unsample(p);
+      almost_eq(surface.sample(u2, v2), p);
+    }
+    // S-curve passes through the origin with a slope of one
+    let offset = Vec3::new(-1.0, 1.0, 0.0).normalize() * 0.1;
+    almost_eq(surface.distance(Point3::new(0.0, 0.0, 0.5) + offset), 0.1);
+  }
+
+  #[test]
+  fn offset_surfaces() {
+    let mut plane = PlanarSurface::new(Plane::new());
+    plane.offset(0.5);
+    almost_eq(plane.plane.origin.z, 0.5);
+    let mut cylinder = RevolutionSurface::cylinder(Axis::new(Point3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 1.0, 0.0)), 1.0, 2.0);
+    let normal = cylinder.normal_at(0.3, 0.5);
+    let p = cylinder.sample(0.3, 0.5) + normal * 0.25;
+    cylinder.offset(0.25);
+    almost_eq(cylinder.distance(p), 0.0);
+    almost_eq(cylinder.normal_at(0.3, 0.5), normal);
+    let mut surface = SplineSurface::tabulated(&crate::test_data::s_curve(), Vec3::new(0.0, 0.0, 1.0));
+    let original = surface.clone();
+    surface.offset(0.1);
+    for (u, v) in [(0.2, 0.5), (0.5, 0.5), (0.8, 0.3)] {
+      assert!((original.distance(surface.sample(u, v)) - 0.1).abs() < 1e-3);
+    }
+  }
+
+  #[test]
+  fn analytic_shapes() {
+    let axis = Axis::new(Point3::new(1.0, 0.0, 2.0), Vec3::unit_z());
+    assert_eq!(RevolutionSurface::cylinder(axis.clone(), 1.5, 2.0).shape(), RevolutionShape::Cylinder { radius: 1.5 });
+    let cone = RevolutionSurface::cone(axis.clone(), 1.0, 1.0);
+    assert!(matches!(cone.shape(), RevolutionShape::Cone { radius, semi_angle, .. }
+      if radius.almost(1.0) && semi_angle.almost(-std::f64::consts::FRAC_PI_4)));
+    let torus = RevolutionSurface::torus(axis, 3.0, 0.5);
+    assert!(matches!(torus.shape(), RevolutionShape::Torus { major_radius, minor_radius, .. }
+      if major_radius.almost(3.0) && minor_radius.almost(0.5)));
+    let center = Point3::new(1.0, 2.0, 3.0);
+    let sphere = RevolutionSurface::sphere(center, 2.0);
+    assert!(matches!(sphere.shape(), RevolutionShape::Sphere { radius, .. } if radius.almost(2.0)));
+    for (u, v) in [(0.1, 0.3), (0.6, 0.5), (0.9, 0.8)] {
+      let p = sphere.sample(u, v);
+      almost_eq(p.distance(center), 2.0);
+      almost_eq(sphere.normal_at(u, v), (p - center).normalize());
+      let (k1, k2) = sphere.curvatures_at(u, v);
+      almost_eq(k1, 0.5);
+      almost_eq(k2, 0.5);
+    }
+  }
 }
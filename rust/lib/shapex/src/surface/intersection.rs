@@ -1,4 +1,6 @@
 use crate::surface::*;
+use crate::curve::intersection::line_circle_points;
+use crate::curve::intersection::circle_circle_points;
 
 // use crate::log;
 
@@ -63,31 +65,37 @@ impl CurveSurfaceIntersectionType {
 }
 
 
+/// Geometric intersection between a curve and a surface
+/// * `point` - Point of intersection
+/// * `t` - Parameter on curve
+/// * `u` - First parameter on surface
+/// * `v` - Second parameter on surface
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CurveSurfaceIntersection {
   pub point: Point3,
   pub t: f64,
-  // pub u: f64,
-  // pub v: f64,
+  pub u: f64,
+  pub v: f64,
 }
 
 impl CurveSurfaceIntersection {
-  pub fn new(point: Point3, t: f64) -> Self {
+  pub fn new(point: Point3, t: f64, u: f64, v: f64) -> Self {
     Self {
       point,
       t,
-      // u, v,
+      u,
+      v,
     }
   }
 }
 
 
-#[allow(dead_code)]
 pub fn line_plane(line: &Line, plane: &Plane) -> Option<CurveSurfaceIntersectionType> {
   let n = plane.normal();
-  let u = line.points.1 - line.points.0;
-  let n_dot_u = n.dot(u);
-  if n_dot_u.almost(0.0) {
+  let direction = line.points.1 - line.points.0;
+  let n_dot_dir = n.dot(direction);
+  if n_dot_dir.almost(0.0) {
     // Line is parallel to plane
     if plane.contains_point(line.points.0) {
       // Line lies completely on plane
@@ -96,20 +104,298 @@ pub fn line_plane(line: &Line, plane: &Plane) -> Option<CurveSurfaceIntersection
       None
     }
   } else {
-    let s = n.dot(plane.origin - line.points.0) / n_dot_u;
-    let p = line.points.0 + u * s;
-    if s >= 0.0 && s <= 1.0 {
-      // Line segment intersects plane
-      if s.almost(0.0) || s.almost(1.0) {
-        Some(CurveSurfaceIntersectionType::Pierce(CurveSurfaceIntersection::new(p, s)))
+    // The ray along the given line intersects plane, possibly outside the segment
+    let s = n.dot(plane.origin - line.points.0) / n_dot_dir;
+    let p = line.points.0 + direction * s;
+    let (u, v) = plane.unsample(p);
+    Some(classify(CurveSurfaceIntersection::new(p, s, u, v), false))
+  }
+}
+
+
+pub fn arc_plane(arc: &Arc, plane: &Plane) -> Vec<CurveSurfaceIntersectionType> {
+  match circle_plane_points(&arc.plane, arc.radius, plane) {
+    Some(points) => points.into_iter().map(|p| {
+      let (u, v) = plane.unsample(p);
+      classify(CurveSurfaceIntersection::new(p, arc.unsample(&p), u, v), arc.is_closed())
+    }).collect(),
+    None => vec![CurveSurfaceIntersectionType::Contained],
+  }
+}
+
+
+pub fn circle_plane(circle: &Circle, plane: &Plane) -> Vec<CurveSurfaceIntersectionType> {
+  match circle_plane_points(&circle.plane, circle.radius, plane) {
+    Some(points) => points.into_iter().map(|p| {
+      let (u, v) = plane.unsample(p);
+      classify(CurveSurfaceIntersection::new(p, circle.unsample(&p), u, v), true)
+    }).collect(),
+    None => vec![CurveSurfaceIntersectionType::Contained],
+  }
+}
+
+
+// Points where a circle meets a plane
+// Returns None when the circle lies in the plane
+fn circle_plane_points(own: &Plane, radius: f64, plane: &Plane) -> Option<Vec<Point3>> {
+  match plane_plane(own, plane) {
+    Some(SurfaceIntersectionType::Contained) => None,
+    Some(SurfaceIntersectionType::Cross(CurveType::Line(line))) => {
+      Some(line_circle_points(&line, own, radius).into_iter().map(|(p, _)| p ).collect())
+    },
+    _ => Some(vec![]),
+  }
+}
+
+
+// Roots of the signed distance to the plane, bracketed by sampling the spline
+pub fn spline_plane(spline: &Spline, plane: &Plane) -> Vec<CurveSurfaceIntersectionType> {
+  let normal = plane.normal().normalize();
+  let distance = |t: f64| normal.dot(spline.sample(t) - plane.origin);
+  let num_samples = (spline.controls.len() * 16).max(32);
+  let samples: Vec<(f64, f64)> = (0..=num_samples).map(|i| {
+    let t = i as f64 / num_samples as f64;
+    (t, distance(t))
+  }).collect();
+  if samples.iter().all(|(_, d)| d.almost(0.0) ) {
+    return vec![CurveSurfaceIntersectionType::Contained]
+  }
+  let mut params: Vec<f64> = vec![];
+  for pair in samples.windows(2) {
+    let (mut t0, mut d0) = pair[0];
+    let (mut t1, mut d1) = pair[1];
+    if d0.almost(0.0) {
+      params.push(t0);
+      continue
+    }
+    if d1.almost(0.0) || d0.signum() == d1.signum() { continue }
+    // Bisect the bracketed root
+    for _ in 0..64 {
+      let t = (t0 + t1) / 2.0;
+      let d = distance(t);
+      if d.signum() == d0.signum() {
+        (t0, d0) = (t, d);
       } else {
-        Some(CurveSurfaceIntersectionType::Cross(CurveSurfaceIntersection::new(p, s)))
+        (t1, d1) = (t, d);
+      }
+      if d.abs() <= EPSILON || (t1 - t0) <= EPSILON { break }
+    }
+    params.push(if d0.abs() < d1.abs() { t0 } else { t1 });
+  }
+  if samples.last().unwrap().1.almost(0.0) {
+    params.push(1.0);
+  }
+  params.into_iter().map(|t| {
+    let p = spline.sample(t);
+    let (u, v) = plane.unsample(p);
+    classify(CurveSurfaceIntersection::new(p, t, u, v), spline.is_closed())
+  }).collect()
+}
+
+
+// Lines are intersected analytically with cylinders and cones
+// Revolutions of other curves are handled numerically
+pub fn line_revolution(line: &Line, surface: &RevolutionSurface) -> Vec<CurveSurfaceIntersectionType> {
+  if let CurveType::Line(generatrix) = &surface.curve {
+    line_cone(line, surface, generatrix)
+  } else if let Some((clipped, range)) = clip_line(line, surface) {
+    curve_surface(&clipped, surface).into_iter().map(|isect| {
+      let t = range.0 + isect.t * (range.1 - range.0);
+      classify(CurveSurfaceIntersection::new(isect.point, t, isect.u, isect.v), false)
+    }).collect()
+  } else {
+    vec![]
+  }
+}
+
+
+pub fn curve_revolution(curve: &dyn Curve, surface: &RevolutionSurface) -> Vec<CurveSurfaceIntersectionType> {
+  curve_surface(curve, surface).into_iter().map(|isect| {
+    classify(isect, curve.is_closed())
+  }).collect()
+}
+
+
+pub fn line_spline(line: &Line, surface: &SplineSurface) -> Vec<CurveSurfaceIntersectionType> {
+  if let Some((clipped, range)) = clip_line(line, surface) {
+    curve_surface(&clipped, surface).into_iter().map(|isect| {
+      let t = range.0 + isect.t * (range.1 - range.0);
+      classify(CurveSurfaceIntersection::new(isect.point, t, isect.u, isect.v), false)
+    }).collect()
+  } else {
+    vec![]
+  }
+}
+
+
+pub fn curve_spline(curve: &dyn Curve, surface: &SplineSurface) -> Vec<CurveSurfaceIntersectionType> {
+  curve_surface(curve, surface).into_iter().map(|isect| {
+    classify(isect, curve.is_closed())
+  }).collect()
+}
+
+
+// Intersect a line with the surface generated by revolving another line,
+// which covers cylinders, cones and annular discs.
+// Both are brought into the local space of the axis, where points on the surface satisfy
+// x^2 + y^2 = r(v)^2 with both r and z changing linearly along the generatrix.
+fn line_cone(line: &Line, surface: &RevolutionSurface, generatrix: &Line) -> Vec<CurveSurfaceIntersectionType> {
+  let to_local = surface.axis.as_transform().invert().unwrap();
+  let a = to_local.transform_point(line.points.0);
+  let d = to_local.transform_vector(line.points.1 - line.points.0);
+  let (g0, g1) = generatrix.points;
+  let r0 = g0.x.hypot(g0.y);
+  let dr = g1.x.hypot(g1.y) - r0;
+  let dz = g1.z - g0.z;
+  let params: Vec<(f64, f64)> = if dz.abs() <= EPSILON * dr.abs() {
+    // Generatrix is perpendicular to axis
+    if d.z.almost(0.0) {
+      return if a.z.almost(g0.z) { vec![CurveSurfaceIntersectionType::Contained] } else { vec![] }
+    }
+    let s = (g0.z - a.z) / d.z;
+    let q = a + d * s;
+    vec![(s, (q.x.hypot(q.y) - r0) / dr)]
+  } else {
+    // Express radius of generatrix at the height of the line as k0 + s * k1
+    let v0 = (a.z - g0.z) / dz;
+    let vs = d.z / dz;
+    let k0 = r0 + dr * v0;
+    let k1 = dr * vs;
+    let qa = d.x * d.x + d.y * d.y - k1 * k1;
+    let qb = (a.x * d.x + a.y * d.y - k0 * k1) * 2.0;
+    let qc = a.x * a.x + a.y * a.y - k0 * k0;
+    let scale = d.magnitude2();
+    let roots = if qa.abs() <= EPSILON * scale {
+      if qb.abs() <= EPSILON * scale {
+        // Line runs along the surface
+        return if qc.abs() <= EPSILON * scale.max(1.0) { vec![CurveSurfaceIntersectionType::Contained] } else { vec![] }
       }
+      vec![-qc / qb]
+    } else {
+      let discriminant = qb * qb - 4.0 * qa * qc;
+      if discriminant < 0.0 { return vec![] }
+      let discriminant = discriminant.sqrt();
+      let s1 = (-qb - discriminant) / (2.0 * qa);
+      let s2 = (-qb + discriminant) / (2.0 * qa);
+      // Tangent lines touch the surface only once
+      if s1.almost(s2) { vec![s1] } else { vec![s1.min(s2), s1.max(s2)] }
+    };
+    // Reject hits on the mirrored half of a double cone
+    roots.into_iter()
+    .filter(|s| k0 + s * k1 >= -EPSILON )
+    .map(|s| (s, v0 + s * vs) )
+    .collect()
+  };
+  params.into_iter()
+  .filter(|(_, v)| *v >= -EPSILON && *v <= 1.0 + EPSILON )
+  .map(|(s, v)| {
+    let q = a + d * s;
+    let u = surface.angle_param(q);
+    classify(CurveSurfaceIntersection::new(line.sample(s), s, u, v.clamp(0.0, 1.0)), false)
+  }).collect()
+}
+
+
+// Classify a hit by the position of its parameter on the curve
+// Closed curves have no endpoints and can only be crossed
+fn classify(isect: CurveSurfaceIntersection, closed: bool) -> CurveSurfaceIntersectionType {
+  if !closed && (isect.t.almost(0.0) || isect.t.almost(1.0)) {
+    CurveSurfaceIntersectionType::Pierce(isect)
+  } else if (0.0..=1.0).contains(&isect.t) {
+    CurveSurfaceIntersectionType::Cross(isect)
+  } else {
+    CurveSurfaceIntersectionType::Extended(isect)
+  }
+}
+
+
+// Restrict an unbounded line to the section passing the bounding box of a surface
+// Returns the clipped line together with its parameter range on the original line
+fn clip_line(line: &Line, surface: &dyn Surface) -> Option<(Line, (f64, f64))> {
+  let (min, max) = surface_box(surface);
+  let origin = line.points.0;
+  let direction = line.points.1 - line.points.0;
+  let mut range = (f64::MIN, f64::MAX);
+  for i in 0..3 {
+    if direction[i].abs() <= EPSILON {
+      if origin[i] < min[i] || origin[i] > max[i] { return None }
     } else {
-      // The ray along the given line intersects plane
-      Some(CurveSurfaceIntersectionType::Extended(CurveSurfaceIntersection::new(p, s)))
+      let t0 = (min[i] - origin[i]) / direction[i];
+      let t1 = (max[i] - origin[i]) / direction[i];
+      range.0 = range.0.max(t0.min(t1));
+      range.1 = range.1.min(t0.max(t1));
     }
   }
+  if range.0 > range.1 { return None }
+  Some((Line::new(line.sample(range.0), line.sample(range.1)), range))
+}
+
+// Bounding box of a surface patch, padded to account for bulges between samples
+fn surface_box(surface: &dyn Surface) -> (Point3, Point3) {
+  let (min, max) = bounding_box(sample_grid(surface).into_iter().map(|(_, _, p)| p ));
+  let padding = max.distance(min) * 0.1 + EPSILON * 10.0;
+  let padding = Vec3::new(padding, padding, padding);
+  (min - padding, max + padding)
+}
+
+fn sample_grid(surface: &dyn Surface) -> Vec<(f64, f64, Point3)> {
+  let res = 16;
+  (0..=res).flat_map(|j| {
+    let v = j as f64 / res as f64;
+    (0..=res).map(move |i| {
+      let u = i as f64 / res as f64;
+      (u, v, surface.sample(u, v))
+    })
+  }).collect()
+}
+
+
+// Find intersections of arbitrary curves with bounded surfaces
+// Samples along the curve are seeded with the closest point of a surface grid
+// and refined with Newton's method
+fn curve_surface(curve: &dyn Curve, surface: &dyn Surface) -> Vec<CurveSurfaceIntersection> {
+  let grid = sample_grid(surface);
+  let num_samples = 32;
+  let mut hits: Vec<CurveSurfaceIntersection> = vec![];
+  for i in 0..=num_samples {
+    let t = i as f64 / num_samples as f64;
+    let p = curve.sample(t);
+    let seed = grid.iter().min_by(|a, b| {
+      a.2.distance2(p).partial_cmp(&b.2.distance2(p)).unwrap()
+    }).unwrap();
+    if let Some(isect) = refine(curve, surface, t, seed.0, seed.1) {
+      if hits.iter().all(|other| !other.point.almost(isect.point) ) {
+        hits.push(isect);
+      }
+    }
+  }
+  hits.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap() );
+  hits
+}
+
+fn refine(curve: &dyn Curve, surface: &dyn Surface, mut t: f64, mut u: f64, mut v: f64) -> Option<CurveSurfaceIntersection> {
+  for _ in 0..32 {
+    let f = curve.sample(t) - surface.sample(u, v);
+    if f.magnitude() <= EPSILON { break }
+    let dt = derivative(|t| curve.sample(t), t);
+    let du = derivative(|u| surface.sample(u, v), u);
+    let dv = derivative(|v| surface.sample(u, v), v);
+    // Solve [dt -du -dv] * [Δt Δu Δv] = -f
+    let inverse = match Matrix3::from_cols(dt, -du, -dv).invert() {
+      Some(inverse) => inverse,
+      None => break,
+    };
+    let delta = inverse * -f;
+    t = (t + delta.x).clamp(0.0, 1.0);
+    u = (u + delta.y).clamp(0.0, 1.0);
+    v = (v + delta.z).clamp(0.0, 1.0);
+  }
+  let p = curve.sample(t);
+  if p.distance(surface.sample(u, v)) <= EPSILON * 10.0 {
+    Some(CurveSurfaceIntersection::new(p, t, u, v))
+  } else {
+    None
+  }
 }
 
 
@@ -132,9 +418,379 @@ pub fn plane_plane(plane: &Plane, other: &Plane) -> Option<SurfaceIntersectionTy
 }
 
 
+
+// Planes perpendicular to the axis cut circles, planes containing the axis cut copies of the generatrix.
+// Cylinders yield lines or ellipses for all other planes, while general revolutions are traced numerically.
+pub fn plane_revolution(plane: &Plane, surface: &RevolutionSurface) -> Vec<SurfaceIntersectionType> {
+  let to_world = surface.axis.as_transform();
+  let to_local = to_world.invert().unwrap();
+  let normal = to_local.transform_vector(plane.normal()).normalize();
+  let origin = to_local.transform_point(plane.origin);
+  let distance = normal.dot(origin.to_vec());
+  if normal.z.abs().almost(1.0) {
+    // Revolve hits of the generatrix with the plane into circles
+    let local_plane = PlanarSurface::new(Plane::from_point(Point3::new(0.0, 0.0, origin.z))).into_enum();
+    let hits = surface.curve.intersect_surface(&local_plane);
+    if hits.contains(&CurveSurfaceIntersectionType::Contained) {
+      return vec![SurfaceIntersectionType::Contained]
+    }
+    hits.into_iter().filter_map(|hit| {
+      let is_extended = matches!(hit, CurveSurfaceIntersectionType::Extended(_));
+      let p = hit.get_point(true).unwrap();
+      let radius = p.x.hypot(p.y);
+      if radius.almost(0.0) { return None }
+      let circle = revolution_circle(surface, p.z, radius);
+      Some(if is_extended {
+        SurfaceIntersectionType::Extended(circle)
+      } else {
+        SurfaceIntersectionType::Cross(circle)
+      })
+    }).collect()
+  } else if normal.z.almost(0.0) && distance.almost(0.0) {
+    // Plane contains the axis
+    let direction = Vec3::unit_z().cross(normal);
+    [direction, -direction].iter().map(|dir| {
+      let mut curve = surface.curve.clone();
+      curve.as_curve_mut().transform(&(to_world * Matrix4::from_angle_z(Rad(dir.y.atan2(dir.x)))));
+      if surface.angle_param(Point3::from_vec(*dir)) <= 1.0 {
+        SurfaceIntersectionType::Cross(curve)
+      } else {
+        SurfaceIntersectionType::Extended(curve)
+      }
+    }).collect()
+  } else if let Some((radius, heights)) = cylinder_dimensions(surface) {
+    if normal.z.almost(0.0) {
+      // Plane is parallel to the axis
+      if distance.abs() > radius + EPSILON { return vec![] }
+      let foot = normal * distance;
+      let direction = Vec3::unit_z().cross(normal);
+      let offset = (radius.powi(2) - distance.powi(2)).max(0.0).sqrt();
+      let points = if offset.almost(0.0) { vec![foot] } else { vec![foot + direction * offset, foot - direction * offset] };
+      let is_tangent = points.len() == 1;
+      points.into_iter().map(|p| {
+        let line = Line::new(
+          to_world.transform_point(Point3::new(p.x, p.y, heights.0)),
+          to_world.transform_point(Point3::new(p.x, p.y, heights.1)),
+        ).into_enum();
+        if surface.angle_param(Point3::from_vec(p)) > 1.0 {
+          SurfaceIntersectionType::Extended(line)
+        } else if is_tangent {
+          SurfaceIntersectionType::Touch(line)
+        } else {
+          SurfaceIntersectionType::Cross(line)
+        }
+      }).collect()
+    } else {
+      // Oblique planes cut ellipses, centered where the axis pierces the plane
+      let center = Point3::new(0.0, 0.0, distance / normal.z);
+      let minor = normal.cross(Vec3::unit_z()).normalize();
+      let major = minor.cross(normal).normalize() * (radius / normal.z.abs());
+      let mut spline = ellipse(center, major, minor * radius);
+      spline.transform(&to_world);
+      vec![SurfaceIntersectionType::Cross(spline.into_enum())]
+    }
+  } else {
+    surface_surface(Patch::Plane(plane), Patch::Bounded(surface, surface.is_periodic()))
+  }
+}
+
+
+pub fn plane_spline(plane: &Plane, surface: &SplineSurface) -> Vec<SurfaceIntersectionType> {
+  surface_surface(Patch::Plane(plane), Patch::Bounded(surface, false))
+}
+
+
+// Cylinders with parallel axes meet in lines, all other revolutions are traced numerically
+pub fn revolution_revolution(own: &RevolutionSurface, other: &RevolutionSurface) -> Vec<SurfaceIntersectionType> {
+  if let (Some((own_radius, heights)), Some((other_radius, _))) = (cylinder_dimensions(own), cylinder_dimensions(other)) {
+    if own.axis.direction.cross(other.axis.direction).magnitude().almost(0.0) {
+      let to_world = own.axis.as_transform();
+      let mut other_center = to_world.invert().unwrap().transform_point(other.axis.origin);
+      other_center.z = 0.0;
+      let points = match circle_circle_points(&Plane::new(), own_radius, &Plane::from_point(other_center), other_radius) {
+        Some(points) => points,
+        None => return vec![SurfaceIntersectionType::Contained],
+      };
+      let is_tangent = points.len() == 1;
+      return points.into_iter().map(|p| {
+        let line = Line::new(
+          to_world.transform_point(Point3::new(p.x, p.y, heights.0)),
+          to_world.transform_point(Point3::new(p.x, p.y, heights.1)),
+        ).into_enum();
+        if is_tangent {
+          SurfaceIntersectionType::Touch(line)
+        } else {
+          SurfaceIntersectionType::Cross(line)
+        }
+      }).collect()
+    }
+  }
+  surface_surface(Patch::Bounded(own, own.is_periodic()), Patch::Bounded(other, other.is_periodic()))
+}
+
+
+pub fn revolution_spline(own: &RevolutionSurface, other: &SplineSurface) -> Vec<SurfaceIntersectionType> {
+  surface_surface(Patch::Bounded(own, own.is_periodic()), Patch::Bounded(other, false))
+}
+
+
+pub fn spline_spline(own: &SplineSurface, other: &SplineSurface) -> Vec<SurfaceIntersectionType> {
+  surface_surface(Patch::Bounded(own, false), Patch::Bounded(other, false))
+}
+
+
+// Radius and height range of revolutions that form a cylinder
+fn cylinder_dimensions(surface: &RevolutionSurface) -> Option<(f64, (f64, f64))> {
+  if let CurveType::Line(line) = &surface.curve {
+    let (p1, p2) = line.points;
+    if p1.x.almost(p2.x) && p1.y.almost(p2.y) {
+      return Some((p1.x.hypot(p1.y), (p1.z, p2.z)))
+    }
+  }
+  None
+}
+
+// Circle around the axis of a revolution, covering the same range of angles as the surface
+fn revolution_circle(surface: &RevolutionSurface, height: f64, radius: f64) -> CurveType {
+  // Circles start at the local x axis and turn towards the y axis like the revolution
+  let mut plane = Plane {
+    origin: Point3::new(0.0, 0.0, height),
+    u: Vec3::unit_y(),
+    v: Vec3::unit_x(),
+  };
+  plane.transform(&surface.axis.as_transform());
+  if surface.is_periodic() {
+    Circle::from_plane(plane, radius).into_enum()
+  } else {
+    Arc::from_plane(plane, radius, surface.u_bounds.0, surface.u_bounds.1).into_enum()
+  }
+}
+
+// Exact ellipse as rational quadratic spline
+fn ellipse(center: Point3, major: Vec3, minor: Vec3) -> Spline {
+  let controls = vec![
+    center + major,
+    center + major + minor,
+    center + minor,
+    center - major + minor,
+    center - major,
+    center - major - minor,
+    center - minor,
+    center + major - minor,
+    center + major,
+  ];
+  let corner = std::f64::consts::FRAC_1_SQRT_2;
+  let mut spline = Spline::new(controls);
+  spline.degree = 2;
+  spline.knots = vec![0.0, 0.0, 0.0, 0.25, 0.25, 0.5, 0.5, 0.75, 0.75, 1.0, 1.0, 1.0];
+  spline.weights = vec![1.0, corner, 1.0, corner, 1.0, corner, 1.0, corner, 1.0];
+  spline
+}
+
+
+// Surfaces taking part in marching are either infinite planes,
+// or patches bounded by their parameter range, which may wrap around in u direction
+#[derive(Clone, Copy)]
+enum Patch<'a> {
+  Plane(&'a Plane),
+  Bounded(&'a dyn Surface, bool),
+}
+
+impl Patch<'_> {
+  // Closest point on surface and its normal, starting from the given parameters
+  fn project(&self, p: Point3, uv: (f64, f64)) -> (Point3, Vec3, (f64, f64)) {
+    match self {
+      Self::Plane(plane) => {
+        let normal = plane.normal().normalize();
+        (p - normal * normal.dot(p - plane.origin), normal, uv)
+      },
+      Self::Bounded(surface, periodic) => {
+        let (u, v) = project_point(*surface, p, uv, *periodic);
+        (surface.sample(u, v), surface_normal(*surface, u, v), (u, v))
+      },
+    }
+  }
+
+  fn grid(&self) -> Option<Vec<(f64, f64, Point3)>> {
+    match self {
+      Self::Plane(_) => None,
+      Self::Bounded(surface, _) => Some(sample_grid(*surface)),
+    }
+  }
+}
+
+
+// Trace intersection curves of two surfaces by marching along the cross product of their normals.
+// Marching starts from seeds where the signed distance to one surface changes sign
+// between neighbours on the sample grid of the other.
+// Traced points are fitted with splines.
+fn surface_surface(own: Patch, other: Patch) -> Vec<SurfaceIntersectionType> {
+  // Seed on the grid of a bounded surface
+  let (seeded, target, swapped) = if let Some(grid) = other.grid() {
+    (grid, own, true)
+  } else {
+    (own.grid().unwrap(), other, false)
+  };
+  let (min, max) = bounding_box(seeded.iter().map(|(_, _, p)| *p ));
+  let size = min.distance(max);
+  let target_grid = target.grid();
+  let distances: Vec<(f64, f64, Point3, f64)> = seeded.iter().map(|&(u, v, p)| {
+    let seed = target_grid.as_ref().map_or((0.0, 0.0), |grid| closest_sample(grid, p) );
+    let (closest, normal, _) = target.project(p, seed);
+    (u, v, p, normal.dot(p - closest))
+  }).collect();
+  let res = (distances.len() as f64).sqrt() as usize;
+  let mut seeds = vec![];
+  for j in 0..res {
+    for i in 0..res {
+      let (u1, v1, p1, d1) = distances[j * res + i];
+      let neighbours = [(i + 1, j), (i, j + 1)];
+      for (ni, nj) in neighbours {
+        if ni >= res || nj >= res { continue }
+        let (u2, v2, p2, d2) = distances[nj * res + ni];
+        if d1.signum() == d2.signum() && !d1.almost(0.0) { continue }
+        let ratio = if (d1 - d2).abs() > 0.0 { d1 / (d1 - d2) } else { 0.5 };
+        seeds.push((p1 + (p2 - p1) * ratio, (u1 + (u2 - u1) * ratio, v1 + (v2 - v1) * ratio)));
+      }
+    }
+  }
+  let mut curves: Vec<(Vec<Point3>, bool)> = vec![];
+  let step = size / 64.0;
+  for (p, uv) in seeds {
+    let seed_uv = target_grid.as_ref().map_or((0.0, 0.0), |grid| closest_sample(grid, p) );
+    let uvs = if swapped { (seed_uv, uv) } else { (uv, seed_uv) };
+    if let Some(start) = converge(own, other, p, uvs, None, size) {
+      // Skip seeds on curves that were traced already
+      if curves.iter().any(|(points, _)| polyline_distance(points, start.0) < step * 2.0 ) { continue }
+      curves.push(march(own, other, start, step, size));
+    }
+  }
+  curves.into_iter().filter(|(points, _)| points.len() >= 2 ).map(|(points, _)| {
+    SurfaceIntersectionType::Cross(fit_polyline(&points).into_enum())
+  }).collect()
+}
+
+type MarchPoint = (Point3, ((f64, f64), (f64, f64)));
+
+// Trace a curve through the given point in both directions
+// Returns the traced points and whether the curve closed up
+fn march(own: Patch, other: Patch, start: MarchPoint, max_step: f64, size: f64) -> (Vec<Point3>, bool) {
+  let mut halves = vec![];
+  for sign in [1.0, -1.0] {
+    let mut points = vec![start.0];
+    let mut current = start;
+    let mut direction = march_direction(own, other, current) * sign;
+    let mut step = max_step;
+    let mut closed = false;
+    while points.len() < 4096 && step > size * 1.0e-7 {
+      let predicted = current.0 + direction * step;
+      let next = match converge(own, other, predicted, current.1, Some(direction), size) {
+        Some(next) => next,
+        None => {
+          step /= 2.0;
+          continue
+        },
+      };
+      let next_direction = march_direction(own, other, next);
+      let next_direction = if next_direction.dot(direction) < 0.0 { -next_direction } else { next_direction };
+      // Refine steps in tightly curved sections
+      if next_direction.dot(direction) < 0.98 && step > max_step / 64.0 {
+        step /= 2.0;
+        continue
+      }
+      if points.len() > 2 && next.0.distance(start.0) < step {
+        points.push(start.0);
+        closed = true;
+        break
+      }
+      points.push(next.0);
+      current = next;
+      direction = next_direction;
+      step = (step * 1.5).min(max_step);
+    }
+    if closed { return (points, true) }
+    halves.push(points);
+  }
+  let mut points: Vec<Point3> = halves[1].iter().rev().cloned().collect();
+  points.extend(halves[0].iter().skip(1));
+  (points, false)
+}
+
+fn march_direction(own: Patch, other: Patch, point: MarchPoint) -> Vec3 {
+  let (_, own_normal, _) = own.project(point.0, point.1.0);
+  let (_, other_normal, _) = other.project(point.0, point.1.1);
+  own_normal.cross(other_normal).normalize()
+}
+
+// Move a point onto both surfaces, by intersecting their tangent planes
+// with the plane perpendicular to the marching direction
+fn converge(own: Patch, other: Patch, mut p: Point3, mut uvs: ((f64, f64), (f64, f64)), direction: Option<Vec3>, size: f64) -> Option<MarchPoint> {
+  let tolerance = size.max(1.0) * 1.0e-9;
+  let anchor = p;
+  for _ in 0..32 {
+    let (own_p, own_normal, own_uv) = own.project(p, uvs.0);
+    let (other_p, other_normal, other_uv) = other.project(p, uvs.1);
+    uvs = (own_uv, other_uv);
+    if own_p.distance(other_p) <= tolerance && own_p.distance(p) <= tolerance {
+      return Some((own_p, uvs))
+    }
+    let tangent = own_normal.cross(other_normal);
+    if tangent.magnitude() <= 1.0e-6 { return None }
+    let stepping = direction.unwrap_or(tangent);
+    let rows = Matrix3::from_cols(own_normal, other_normal, stepping).transpose();
+    let rhs = Vec3::new(own_normal.dot(own_p.to_vec()), other_normal.dot(other_p.to_vec()), stepping.dot(anchor.to_vec()));
+    p = Point3::from_vec(rows.invert()? * rhs);
+  }
+  None
+}
+
+
+// Surface normal from partial derivatives
+fn surface_normal(surface: &dyn Surface, u: f64, v: f64) -> Vec3 {
+  let du = derivative(|u| surface.sample(u, v), u);
+  let dv = derivative(|v| surface.sample(u, v), v);
+  du.cross(dv).normalize()
+}
+
+fn closest_sample(grid: &[(f64, f64, Point3)], p: Point3) -> (f64, f64) {
+  let closest = grid.iter().min_by(|a, b| {
+    a.2.distance2(p).partial_cmp(&b.2.distance2(p)).unwrap()
+  }).unwrap();
+  (closest.0, closest.1)
+}
+
+fn polyline_distance(points: &[Point3], p: Point3) -> f64 {
+  if points.len() == 1 { return points[0].distance(p) }
+  points.windows(2).map(|pair| {
+    let vec = pair[1] - pair[0];
+    let t = ((p - pair[0]).dot(vec) / vec.magnitude2().max(EPSILON)).clamp(0.0, 1.0);
+    p.distance(pair[0] + vec * t)
+  }).fold(f64::MAX, f64::min)
+}
+
+// Fit a spline through an evenly thinned out selection of traced points
+fn fit_polyline(points: &[Point3]) -> Spline {
+  let max_points = 48;
+  if points.len() <= max_points { return Spline::fit(points) }
+  let last = points.len() - 1;
+  let selection: Vec<Point3> = (0..max_points).map(|i| points[i * last / (max_points - 1)] ).collect();
+  Spline::fit(&selection)
+}
+
+fn bounding_box(points: impl Iterator<Item = Point3>) -> (Point3, Point3) {
+  let mut min = Point3::new(f64::MAX, f64::MAX, f64::MAX);
+  let mut max = Point3::new(f64::MIN, f64::MIN, f64::MIN);
+  for p in points {
+    min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+    max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+  }
+  (min, max)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::test_data;
 
   #[test]
   fn plane_intersection() {
@@ -150,4 +806,207 @@ mod tests {
       } else { panic!("Intersection was no line") }
     } else { panic!("No intersection detected") }
   }
+
+  #[test]
+  fn line_cylinder() {
+    let cylinder = RevolutionSurface::cylinder(Axis::new(Point3::origin(), Vec3::unit_z()), 1.0, 2.0);
+    let line = Line::new(Point3::new(-2.0, 0.0, 1.0), Point3::new(2.0, 0.0, 1.0));
+    let hits = line_revolution(&line, &cylinder);
+    assert_eq!(hits.len(), 2);
+    let isect = hits[0].get_intersection(false).unwrap();
+    almost_eq(isect.point, Point3::new(-1.0, 0.0, 1.0));
+    almost_eq(isect.t, 0.25);
+    almost_eq(isect.v, 0.5);
+    almost_eq(cylinder.sample(isect.u, isect.v), isect.point);
+    let isect = hits[1].get_intersection(false).unwrap();
+    almost_eq(isect.point, Point3::new(1.0, 0.0, 1.0));
+    almost_eq(cylinder.sample(isect.u, isect.v), isect.point);
+    // Lines above the cylinder miss it, lines on its mantle are contained
+    let above = Line::new(Point3::new(-2.0, 0.0, 3.0), Point3::new(2.0, 0.0, 3.0));
+    assert_eq!(line_revolution(&above, &cylinder), vec![]);
+    let along = Line::new(Point3::new(1.0, 0.0, 0.0), Point3::new(1.0, 0.0, 1.0));
+    assert_eq!(line_revolution(&along, &cylinder), vec![CurveSurfaceIntersectionType::Contained]);
+  }
+
+  #[test]
+  fn line_torus() {
+    let axis = Axis::new(Point3::origin(), Vec3::unit_z());
+    let profile = Circle::from_plane(Plane::from_normal(Point3::new(2.0, 0.0, 0.0), Vec3::unit_y()), 0.5);
+    let torus = RevolutionSurface::new(axis, profile.into_enum());
+    let line = Line::new(Point3::new(-4.0, 0.0, 0.0), Point3::new(4.0, 0.0, 0.0));
+    let hits = line_revolution(&line, &torus);
+    let xs: Vec<f64> = hits.iter().map(|hit| hit.get_point(false).unwrap().x ).collect();
+    assert_eq!(xs.len(), 4);
+    for (x, expected) in xs.iter().zip([-2.5, -1.5, 1.5, 2.5]) {
+      almost_eq(*x, expected);
+    }
+  }
+
+  #[test]
+  fn circle_plane_cross() {
+    let circle = Circle::new(Point3::origin(), 1.0);
+    let plane = Plane::from_normal(Point3::origin(), Vec3::unit_x());
+    let hits = circle_plane(&circle, &plane);
+    assert_eq!(hits.len(), 2);
+    for hit in &hits {
+      assert!(matches!(hit, CurveSurfaceIntersectionType::Cross(_)));
+      let isect = hit.get_intersection(false).unwrap();
+      almost_eq(isect.point.x, 0.0);
+      almost_eq(isect.point.y.abs(), 1.0);
+      almost_eq(circle.sample(isect.t), isect.point);
+      almost_eq(plane.sample(isect.u, isect.v), isect.point);
+    }
+    assert_eq!(circle_plane(&circle, &Plane::new()), vec![CurveSurfaceIntersectionType::Contained]);
+  }
+
+  #[test]
+  fn arc_plane_pierce() {
+    let arc = Arc::new(Point3::origin(), 1.0, 0.0, 0.25);
+    let plane = Plane::from_normal(Point3::origin(), Vec3::unit_y());
+    let hits = arc_plane(&arc, &plane);
+    assert!(hits.iter().any(|hit| matches!(hit, CurveSurfaceIntersectionType::Pierce(_)) ));
+    assert!(hits.iter().all(|hit| !matches!(hit, CurveSurfaceIntersectionType::Cross(_)) ));
+  }
+
+  #[test]
+  fn spline_plane_cross() {
+    let spline = test_data::s_curve();
+    let plane = Plane::from_normal(Point3::origin(), Vec3::unit_x());
+    let hits = spline_plane(&spline, &plane);
+    assert_eq!(hits.len(), 1);
+    let isect = hits[0].get_intersection(false).unwrap();
+    almost_eq(isect.point, Point3::origin());
+    almost_eq(isect.t, 0.5);
+  }
+
+  #[test]
+  fn line_spline_surface() {
+    let surface = SplineSurface::tabulated(&test_data::s_curve(), Vec3::new(0.0, 0.0, 1.0));
+    let line = Line::new(Point3::new(0.0, -5.0, 0.5), Point3::new(0.0, 5.0, 0.5));
+    let hits = line_spline(&line, &surface);
+    assert_eq!(hits.len(), 1);
+    assert!(matches!(hits[0], CurveSurfaceIntersectionType::Cross(_)));
+    let isect = hits[0].get_intersection(false).unwrap();
+    almost_eq(isect.point, Point3::new(0.0, 0.0, 0.5));
+    almost_eq(isect.t, 0.5);
+    almost_eq(surface.sample(isect.u, isect.v), isect.point);
+  }
+
+  fn unit_cylinder() -> RevolutionSurface {
+    RevolutionSurface::cylinder(Axis::new(Point3::origin(), Vec3::unit_z()), 1.0, 2.0)
+  }
+
+  fn curves(hits: Vec<SurfaceIntersectionType>) -> Vec<CurveType> {
+    hits.into_iter().map(|hit| match hit {
+      SurfaceIntersectionType::Cross(curve) => curve,
+      _ => panic!("Intersection was no crossing"),
+    }).collect()
+  }
+
+  fn samples(curve: &CurveType) -> Vec<Point3> {
+    (0..=64).map(|i| curve.as_curve().sample(i as f64 / 64.0) ).collect()
+  }
+
+  #[test]
+  fn plane_cylinder_circle() {
+    let plane = Plane::from_point(Point3::new(0.0, 0.0, 0.5));
+    let curves = curves(plane_revolution(&plane, &unit_cylinder()));
+    assert_eq!(curves.len(), 1);
+    if let CurveType::Circle(circle) = &curves[0] {
+      almost_eq(circle.radius, 1.0);
+      almost_eq(circle.plane.origin, Point3::new(0.0, 0.0, 0.5));
+    } else { panic!("Intersection was no circle") }
+    // Planes beyond the height of the cylinder only meet its extension
+    let above = Plane::from_point(Point3::new(0.0, 0.0, 3.0));
+    assert!(matches!(plane_revolution(&above, &unit_cylinder())[0], SurfaceIntersectionType::Extended(_)));
+  }
+
+  #[test]
+  fn plane_cylinder_lines() {
+    let plane = Plane::from_normal(Point3::new(0.5, 0.0, 0.0), Vec3::unit_x());
+    let curves = curves(plane_revolution(&plane, &unit_cylinder()));
+    assert_eq!(curves.len(), 2);
+    for curve in &curves {
+      let (start, end) = curve.as_curve().endpoints();
+      almost_eq(start.x, 0.5);
+      almost_eq(start.y.abs(), 0.75_f64.sqrt());
+      almost_eq(end - start, Vec3::new(0.0, 0.0, 2.0));
+    }
+  }
+
+  #[test]
+  fn plane_cylinder_ellipse() {
+    let plane = Plane::from_normal(Point3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0).normalize());
+    let curves = curves(plane_revolution(&plane, &unit_cylinder()));
+    assert_eq!(curves.len(), 1);
+    for p in samples(&curves[0]) {
+      almost_eq(p.x.hypot(p.y), 1.0);
+      almost_eq(plane.normal().dot(p - plane.origin), 0.0);
+    }
+  }
+
+  #[test]
+  fn plane_torus() {
+    let axis = Axis::new(Point3::origin(), Vec3::unit_z());
+    let profile = Circle::from_plane(Plane::from_normal(Point3::new(2.0, 0.0, 0.0), Vec3::unit_y()), 0.5);
+    let torus = RevolutionSurface::new(axis, profile.into_enum());
+    // Equator
+    let mut radii: Vec<f64> = curves(plane_revolution(&Plane::new(), &torus)).iter().map(|curve| {
+      if let CurveType::Circle(circle) = curve { circle.radius } else { panic!("Intersection was no circle") }
+    }).collect();
+    radii.sort_by(|a, b| a.partial_cmp(b).unwrap() );
+    assert_eq!(radii.len(), 2);
+    almost_eq(radii[0], 1.5);
+    almost_eq(radii[1], 2.5);
+    // Meridian
+    let meridian = Plane::from_normal(Point3::origin(), Vec3::unit_x());
+    let curves = curves(plane_revolution(&meridian, &torus));
+    assert_eq!(curves.len(), 2);
+    for curve in &curves {
+      for p in samples(curve) {
+        almost_eq(p.x, 0.0);
+        almost_eq((p.y.abs() - 2.0).hypot(p.z), 0.5);
+      }
+    }
+  }
+
+  #[test]
+  fn parallel_cylinders() {
+    let other = RevolutionSurface::cylinder(Axis::new(Point3::new(1.0, 0.0, 0.0), Vec3::unit_z()), 1.0, 2.0);
+    let curves = curves(revolution_revolution(&unit_cylinder(), &other));
+    assert_eq!(curves.len(), 2);
+    for curve in &curves {
+      almost_eq(curve.as_curve().endpoints().0.x, 0.5);
+    }
+    assert_eq!(revolution_revolution(&unit_cylinder(), &unit_cylinder()), vec![SurfaceIntersectionType::Contained]);
+  }
+
+  #[test]
+  fn crossing_cylinders() {
+    let own = RevolutionSurface::cylinder(Axis::new(Point3::new(0.0, 0.0, -2.0), Vec3::unit_z()), 1.0, 4.0);
+    let other = RevolutionSurface::cylinder(Axis::new(Point3::new(-2.0, 0.0, 0.0), Vec3::unit_x()), 0.5, 4.0);
+    let curves = curves(revolution_revolution(&own, &other));
+    // Smaller cylinder pierces the larger one on both sides
+    assert_eq!(curves.len(), 2);
+    for curve in &curves {
+      let points = samples(curve);
+      almost_eq(points[0], points[64]);
+      for p in points {
+        assert!((p.x.hypot(p.y) - 1.0).abs() < 1.0e-3);
+        assert!((p.y.hypot(p.z) - 0.5).abs() < 1.0e-3);
+      }
+    }
+  }
+
+  #[test]
+  fn plane_spline_surface() {
+    let surface = SplineSurface::tabulated(&test_data::s_curve(), Vec3::new(0.0, 0.0, 1.0));
+    let plane = Plane::from_point(Point3::new(0.0, 0.0, 0.5));
+    let curves = curves(plane_spline(&plane, &surface));
+    assert_eq!(curves.len(), 1);
+    for p in samples(&curves[0]) {
+      almost_eq(p.z, 0.5);
+      assert!(surface.distance(p) < 1.0e-3);
+    }
+  }
 }
@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+
+use crate::surface::*;
+
+
+// Number of samples along iso lines, used to estimate the resolution of the parameter grid
+const ISO_SAMPLES: usize = 64;
+
+// Grid points closer to the boundary than this fraction of the grid spacing are dropped
+const BOUNDARY_CLEARANCE: f64 = 0.3;
+
+const MAX_FLIP_PASSES: usize = 100;
+
+
+/// Ring of a face, given as segments of indices into the vertices of a mesh.
+/// Adjacent faces use the same segments for their shared edges, which keeps the mesh watertight.
+pub type IndexRing = Vec<Vec<usize>>;
+
+
+#[derive(Debug, Clone, Copy)]
+struct ParamPoint {
+  uv: Point2,
+  index: usize,
+}
+
+
+// Number of grid cells across the parameter range, that keep iso lines within the given tolerances
+pub fn grid_steps<S: Surface + ?Sized>(surface: &S, max_deviation: f64, max_angle: Deg<f64>) -> (usize, usize) {
+  let max_angle: Rad<f64> = max_angle.into();
+  let params = [0.0, 0.25, 0.5, 0.75, 1.0];
+  let u_steps = params.iter().map(|&v| iso_steps(|u| surface.sample(u, v), max_deviation, max_angle.0) ).max().unwrap();
+  let v_steps = params.iter().map(|&u| iso_steps(|v| surface.sample(u, v), max_deviation, max_angle.0) ).max().unwrap();
+  (u_steps, v_steps)
+}
+
+fn iso_steps(sample: impl Fn(f64) -> Point3, max_deviation: f64, max_angle: f64) -> usize {
+  let segments: Vec<Vec3> = (0..=ISO_SAMPLES)
+  .map(|i| sample(i as f64 / ISO_SAMPLES as f64) )
+  .collect::<Vec<Point3>>()
+  .windows(2)
+  .map(|pair| pair[1] - pair[0] )
+  .filter(|segment| segment.magnitude() > EPSILON )
+  .collect();
+  let length: f64 = segments.iter().map(|segment| segment.magnitude() ).sum();
+  let mut turning = 0.0;
+  let mut max_curvature: f64 = 0.0;
+  for pair in segments.windows(2) {
+    let angle = pair[0].cross(pair[1]).magnitude().atan2(pair[0].dot(pair[1]));
+    turning += angle;
+    max_curvature = max_curvature.max(2.0 * angle / (pair[0].magnitude() + pair[1].magnitude()));
+  }
+  // Chords of length c deviate by about c^2 * k / 8 from an arc of curvature k
+  let by_deviation = length * (max_curvature / (8.0 * max_deviation)).sqrt();
+  (turning / max_angle).max(by_deviation).ceil().max(1.0) as usize
+}
+
+
+// Sample the curves of a profile into the mesh and return its wires as rings of index segments
+pub fn profile_rings(profile: &Profile, mesh: &mut Mesh, sample: impl Fn(&TrimmedCurve) -> PolyLine) -> Vec<IndexRing> {
+  profile.iter().map(|wire| {
+    let start = mesh.vertices.len();
+    let mut ring: IndexRing = wire.iter().map(|tcurve| {
+      let poly = sample(tcurve);
+      let first = mesh.vertices.len();
+      mesh.vertices.extend(poly.iter().take(poly.len() - 1));
+      // Segments end on the first vertex of the next one
+      (first..=mesh.vertices.len()).collect()
+    }).collect();
+    if let Some(segment) = ring.last_mut() {
+      *segment.last_mut().unwrap() = start;
+    }
+    ring
+  }).collect()
+}
+
+
+/// Triangulate the region of a surface bounded by the given rings and add it to the mesh.
+/// Vertices of the rings have to be present in the mesh already.
+/// Triangles get wound counter-clockwise around the surface normal, multiplied by orientation.
+pub fn tesselate_rings<S: Surface + ?Sized>(surface: &S, rings: &[IndexRing], steps: (usize, usize), orientation: f64, mesh: &mut Mesh) {
+  let domain = Domain::new(surface);
+  let loops = domain.param_loops(rings, &mesh.vertices);
+  if loops.is_empty() { return }
+  let mut points: Vec<ParamPoint> = loops.concat();
+  let mut boundary = vec![];
+  for param_loop in &loops {
+    let offset = boundary.len();
+    boundary.extend((0..param_loop.len()).map(|i| (offset + i, offset + (i + 1) % param_loop.len()) ));
+  }
+  // Refine the interior with points of a regular grid
+  for uv in domain.grid_points(&loops, steps) {
+    points.push(ParamPoint { uv, index: mesh.vertices.len() });
+    mesh.vertices.push(domain.sample(uv));
+  }
+  let scaled: Vec<Point2> = points.iter().map(|p| domain.scaled(p.uv) ).collect();
+  let mut triangles = Triangulation::new(&scaled).constrain(&boundary);
+  make_delaunay(&mut triangles, &scaled);
+  // Counter-clockwise triangles in parameter space face along the cross product of the surface derivatives
+  let largest = triangles.iter().max_by(|a, b| {
+    let area = |tri: &&[usize; 3]| orient(scaled[tri[0]], scaled[tri[1]], scaled[tri[2]]);
+    area(a).partial_cmp(&area(b)).unwrap()
+  });
+  let winding = match largest {
+    Some(tri) => domain.winding(Point2::centroid(&tri.map(|i| points[i].uv ))) * orientation,
+    None => return,
+  };
+  let poles: Vec<bool> = points.iter().map(|p| domain.is_pole(p.uv) ).collect();
+  for tri in triangles {
+    let tri = if winding < 0.0 { [tri[0], tri[2], tri[1]] } else { tri };
+    let indices = tri.map(|i| points[i].index );
+    // Triangles touching a pole twice collapse
+    if indices[0] == indices[1] || indices[1] == indices[2] || indices[2] == indices[0] { continue }
+    let [a, b, c] = indices.map(|i| mesh.vertices[i] );
+    let normal = (b - a).cross(c - a);
+    if normal.magnitude() < EPSILON * EPSILON { continue }
+    for i in tri {
+      mesh.faces.push(points[i].index);
+      // Normals are undefined at poles
+      mesh.normals.push(if poles[i] { normal.normalize() } else { domain.normal(points[i].uv) * orientation });
+    }
+  }
+}
+
+
+/// Check whether surface parameters lie inside the region bounded by the profile.
+/// Returns None when the profile encloses no area in parameter space.
+pub fn params_in_profile<S: Surface + ?Sized>(surface: &S, profile: &Profile, u: f64, v: f64) -> Option<bool> {
+  let mut mesh = Mesh::default();
+  let rings = profile_rings(profile, &mut mesh, |tcurve| tcurve.tesselate() );
+  let domain = Domain::new(surface);
+  let loops = domain.param_loops(&rings, &mesh.vertices);
+  let loop_segments = |param_loop: &Vec<ParamPoint>| -> Vec<(Point2, Point2)> {
+    param_loop.iter().zip(param_loop.iter().cycle().skip(1)).map(|(a, b)| (a.uv, b.uv) ).collect()
+  };
+  // Rings running around closed directions without seams enclose nothing,
+  // and loops unwrapped across more than a period overlap themselves
+  let area: f64 = loops.iter().map(|param_loop| {
+    loop_segments(param_loop).iter().map(|(a, b)| a.x * b.y - b.x * a.y ).sum::<f64>().abs()
+  }).sum();
+  let span = |closed: bool, param: fn(&Point2) -> f64| closed && loops.iter().any(|param_loop| {
+    let (min, max) = param_loop.iter().fold((f64::MAX, f64::MIN), |(min, max), p| (min.min(param(&p.uv)), max.max(param(&p.uv))) );
+    max - min > 1.0 + EPSILON
+  });
+  if area < EPSILON || span(domain.closed.0, |uv| uv.x ) || span(domain.closed.1, |uv| uv.y ) { return None }
+  let segments: Vec<(Point2, Point2)> = loops.iter().flat_map(loop_segments).collect();
+  // Unwrapped loops may reach into neighbouring periods of closed directions
+  let shifts = |closed: bool| if closed { vec![-1.0, 0.0, 1.0] } else { vec![0.0] };
+  let inside = shifts(domain.closed.0).iter().any(|du| shifts(domain.closed.1).iter().any(|dv| {
+    let p = Point2::new(u + du, v + dv);
+    point_in_loops(p, &segments) || segments.iter().any(|&(a, b)| segment_distance(p, a, b) < EPSILON )
+  }));
+  Some(inside)
+}
+
+
+// Parameter space of a surface, that gets unwrapped across seams to give
+// closed loops for all rings and is scaled to approximate distances on the surface
+struct Domain<'a, S: Surface + ?Sized> {
+  surface: &'a S,
+  closed: (bool, bool),
+  scale: (f64, f64),
+}
+
+impl<'a, S: Surface + ?Sized> Domain<'a, S> {
+  fn new(surface: &'a S) -> Self {
+    let params = [0.0, 0.25, 0.5, 0.75, 1.0];
+    let iso_length = |sample: &dyn Fn(f64) -> Point3| {
+      (0..16).map(|i| sample(i as f64 / 16.0).distance(sample((i + 1) as f64 / 16.0)) ).sum::<f64>()
+    };
+    let scale = (
+      params.iter().map(|&v| iso_length(&|u| surface.sample(u, v)) ).fold(EPSILON, f64::max),
+      params.iter().map(|&u| iso_length(&|v| surface.sample(u, v)) ).fold(EPSILON, f64::max),
+    );
+    let tolerance = 1.0e-6 * scale.0.max(scale.1);
+    let closed = (
+      params[1..4].iter().all(|&v| surface.sample(0.0, v).distance(surface.sample(1.0, v)) < tolerance ),
+      params[1..4].iter().all(|&u| surface.sample(u, 0.0).distance(surface.sample(u, 1.0)) < tolerance ),
+    );
+    Self { surface, closed, scale }
+  }
+
+  fn wrap(&self, uv: Point2) -> (f64, f64) {
+    (
+      if self.closed.0 { uv.x - uv.x.floor() } else { uv.x },
+      if self.closed.1 { uv.y - uv.y.floor() } else { uv.y },
+    )
+  }
+
+  // Shift closed parameters by whole periods to get as close as possible to the reference
+  fn unwrap(&self, uv: Point2, reference: Point2) -> Point2 {
+    Point2::new(
+      if self.closed.0 { uv.x + (reference.x - uv.x).round() } else { uv.x },
+      if self.closed.1 { uv.y + (reference.y - uv.y).round() } else { uv.y },
+    )
+  }
+
+  fn sample(&self, uv: Point2) -> Point3 {
+    let (u, v) = self.wrap(uv);
+    self.surface.sample(u, v)
+  }
+
+  fn normal(&self, uv: Point2) -> Vec3 {
+    let (u, v) = self.wrap(uv);
+    self.surface.normal_at(u, v)
+  }
+
+  fn scaled(&self, uv: Point2) -> Point2 {
+    Point2::new(uv.x * self.scale.0, uv.y * self.scale.1)
+  }
+
+  // Sign of the surface normal along the cross product of the derivatives
+  fn winding(&self, uv: Point2) -> f64 {
+    let (u, v) = self.wrap(uv);
+    let su = derivative(|u| self.surface.sample(u, v), u);
+    let sv = derivative(|v| self.surface.sample(u, v), v);
+    su.cross(sv).dot(self.surface.normal_at(u, v)).signum()
+  }
+
+  // Points where the surface collapses in u direction can take any u parameter
+  fn is_pole(&self, uv: Point2) -> bool {
+    let (u, v) = self.wrap(uv);
+    derivative(|u| self.surface.sample(u, v), u).magnitude() < 1.0e-6 * self.scale.0
+  }
+
+  // Map rings to loops in parameter space, that are cut open along seams and poles
+  fn param_loops(&self, rings: &[IndexRing], vertices: &[Point3]) -> Vec<Vec<ParamPoint>> {
+    let mut placed: Vec<(&[usize], Vec<Point2>)> = vec![];
+    let mut loops = vec![];
+    for ring in rings {
+      let mut param_loop: Vec<ParamPoint> = vec![];
+      for segment in ring {
+        let previous = param_loop.last().map(|p| p.uv );
+        let uvs = self.map_segment(segment, vertices, &placed, previous);
+        for (&uv, &index) in uvs.iter().zip(segment) {
+          if param_loop.last().is_some_and(|p| p.index == index && p.uv.distance(uv) < EPSILON ) { continue }
+          param_loop.push(ParamPoint { uv, index });
+        }
+        placed.push((segment, uvs));
+      }
+      let first = param_loop[0];
+      if param_loop.last().is_some_and(|p| p.index == first.index && p.uv.distance(first.uv) < EPSILON ) {
+        param_loop.pop();
+      }
+      if param_loop.len() >= 3 { loops.push(param_loop) }
+    }
+    loops
+  }
+
+  fn map_segment(&self, segment: &[usize], vertices: &[Point3], placed: &[(&[usize], Vec<Point2>)], previous: Option<Point2>) -> Vec<Point2> {
+    // Both sides of a seam run through the same face, one period apart
+    let copy = placed.iter().find_map(|(other, uvs)| {
+      if other.iter().rev().eq(segment.iter()) {
+        Some(uvs.iter().rev().cloned().collect::<Vec<Point2>>())
+      } else if *other == segment {
+        Some(uvs.clone())
+      } else { None }
+    });
+    if let Some(copy) = copy {
+      let mut shifts = vec![];
+      if self.closed.0 { shifts.extend([Vec2::new(1.0, 0.0), Vec2::new(-1.0, 0.0)]) }
+      if self.closed.1 { shifts.extend([Vec2::new(0.0, 1.0), Vec2::new(0.0, -1.0)]) }
+      let reference = previous.unwrap_or(copy[0]);
+      let shift = shifts.into_iter().min_by(|a, b| {
+        let dist = |shift: Vec2| self.scaled(copy[0] + shift).distance(self.scaled(reference));
+        dist(*a).partial_cmp(&dist(*b)).unwrap()
+      }).unwrap_or(Vec2::new(0.0, 0.0));
+      return copy.into_iter().map(|uv| uv + shift ).collect()
+    }
+    let mut uvs: Vec<Point2> = segment.iter().map(|&i| {
+      let (u, v) = self.surface.unsample(vertices[i]);
+      Point2::new(u, v)
+    }).collect();
+    // Poles take the u parameter of their neighbours on the segment
+    let poles: Vec<bool> = uvs.iter().map(|&uv| self.is_pole(uv) ).collect();
+    if let Some(regular) = poles.iter().position(|&pole| !pole ) {
+      for i in 0..uvs.len() {
+        if !poles[i] { continue }
+        let neighbour = if i < regular { regular } else { (0..i).rev().find(|&j| !poles[j] ).unwrap() };
+        uvs[i].x = uvs[neighbour].x;
+      }
+    }
+    // Keep parameters continuous along the loop
+    let mut reference = previous;
+    for i in 0..uvs.len() {
+      if let Some(reference) = reference {
+        let unwrapped = self.unwrap(uvs[i], reference);
+        // Loops can jump to any u parameter at poles
+        uvs[i] = if poles[i] || i > 0 && poles[i - 1] || i == 0 && self.is_pole(reference) {
+          Point2::new(uvs[i].x, unwrapped.y)
+        } else { unwrapped };
+      }
+      reference = Some(uvs[i]);
+    }
+    uvs
+  }
+
+  // Points of a regular grid inside the loops, keeping clear of their boundary
+  fn grid_points(&self, loops: &[Vec<ParamPoint>], steps: (usize, usize)) -> Vec<Point2> {
+    let points = loops.iter().flatten();
+    let min = points.clone().fold(Point2::new(f64::MAX, f64::MAX), |acc, p| Point2::new(acc.x.min(p.uv.x), acc.y.min(p.uv.y)) );
+    let max = points.fold(Point2::new(f64::MIN, f64::MIN), |acc, p| Point2::new(acc.x.max(p.uv.x), acc.y.max(p.uv.y)) );
+    let us = grid_values(min.x, max.x, steps.0, self.closed.0);
+    let vs = grid_values(min.y, max.y, steps.1, self.closed.1);
+    let clearance = BOUNDARY_CLEARANCE * (self.scale.0 / steps.0 as f64).min(self.scale.1 / steps.1 as f64);
+    let segments: Vec<(Point2, Point2)> = loops.iter().flat_map(|param_loop| {
+      param_loop.iter().zip(param_loop.iter().cycle().skip(1)).map(|(a, b)| (self.scaled(a.uv), self.scaled(b.uv)) )
+    }).collect();
+    vs.iter().flat_map(|&v| us.iter().map(move |&u| Point2::new(u, v) ) )
+    .filter(|&uv| {
+      let p = self.scaled(uv);
+      point_in_loops(p, &segments) && segments.iter().all(|&(a, b)| segment_distance(p, a, b) > clearance )
+    }).collect()
+  }
+}
+
+// Multiples of the grid spacing strictly inside the given range, and inside the parameter range of open directions
+fn grid_values(min: f64, max: f64, steps: usize, closed: bool) -> Vec<f64> {
+  let (min, max) = if closed { (min, max) } else { (min.max(0.0), max.min(1.0)) };
+  let steps = steps as f64;
+  let first = (min * steps).floor() as i64 + 1;
+  let last = (max * steps).ceil() as i64 - 1;
+  (first..=last)
+  .map(|i| i as f64 / steps )
+  .filter(|&x| x > min && x < max )
+  .collect()
+}
+
+// Even-odd rule over the segments of all loops
+fn point_in_loops(p: Point2, segments: &[(Point2, Point2)]) -> bool {
+  segments.iter().filter(|(a, b)| {
+    (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x)
+  }).count() % 2 == 1
+}
+
+fn segment_distance(p: Point2, a: Point2, b: Point2) -> f64 {
+  let ab = b - a;
+  let t = if ab.magnitude2() > 0.0 { ((p - a).dot(ab) / ab.magnitude2()).clamp(0.0, 1.0) } else { 0.0 };
+  p.distance(a + ab * t)
+}
+
+fn orient(a: Point2, b: Point2, c: Point2) -> f64 {
+  (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+// Positive when d lies inside the circumcircle of the counter-clockwise triangle abc
+fn in_circle(a: Point2, b: Point2, c: Point2, d: Point2) -> f64 {
+  let (ad, bd, cd) = (a - d, b - d, c - d);
+  ad.magnitude2() * (bd.x * cd.y - cd.x * bd.y)
+  - bd.magnitude2() * (ad.x * cd.y - cd.x * ad.y)
+  + cd.magnitude2() * (ad.x * bd.y - bd.x * ad.y)
+}
+
+// Flip interior edges until the triangulation is delaunay again, after boundary segments were flipped in
+fn make_delaunay(triangles: &mut [[usize; 3]], points: &[Point2]) {
+  for _ in 0..MAX_FLIP_PASSES {
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+    for (t, tri) in triangles.iter().enumerate() {
+      for k in 0..3 { edges.insert((tri[k], tri[(k + 1) % 3]), t); }
+    }
+    // Triangles get flipped at most once per pass, as the edge map goes stale for them
+    let mut touched = vec![false; triangles.len()];
+    let mut flipped = false;
+    for t in 0..triangles.len() {
+      for k in 0..3 {
+        if touched[t] { break }
+        let tri = triangles[t];
+        let (a, b, c) = (tri[k], tri[(k + 1) % 3], tri[(k + 2) % 3]);
+        if let Some(&other) = edges.get(&(b, a)) {
+          if touched[other] || other == t { continue }
+          let d = *triangles[other].iter().find(|&&i| i != a && i != b ).unwrap();
+          let (pa, pb, pc, pd) = (points[a], points[b], points[c], points[d]);
+          let size = (pa - pd).magnitude2().max((pb - pd).magnitude2()).max((pc - pd).magnitude2());
+          let is_convex = orient(pc, pd, pa) * orient(pc, pd, pb) < 0.0;
+          if is_convex && in_circle(pa, pb, pc, pd) > 1.0e-9 * size * size {
+            triangles[t] = [a, d, c];
+            triangles[other] = [d, b, c];
+            touched[t] = true;
+            touched[other] = true;
+            flipped = true;
+          }
+        }
+      }
+    }
+    if !flipped { break }
+  }
+}
+
+
+// Delaunay triangulation, built by inserting points one by one into a triangle enclosing all of them
+struct Triangulation {
+  points: Vec<Point2>,
+  triangles: Vec<[usize; 3]>, // Counter-clockwise
+  edges: HashMap<(usize, usize), usize>, // Directed edges of triangles
+  aliases: Vec<usize>, // Points replaced by an earlier point at the same location
+  tolerance: f64,
+}
+
+impl Triangulation {
+  fn new(points: &[Point2]) -> Self {
+    let min = points.iter().fold(Point2::new(f64::MAX, f64::MAX), |acc, p| Point2::new(acc.x.min(p.x), acc.y.min(p.y)) );
+    let max = points.iter().fold(Point2::new(f64::MIN, f64::MIN), |acc, p| Point2::new(acc.x.max(p.x), acc.y.max(p.y)) );
+    let size = (max.x - min.x).max(max.y - min.y).max(EPSILON);
+    let center = min.midpoint(max);
+    let num_points = points.len();
+    let mut this = Self {
+      points: points.to_vec(),
+      triangles: vec![],
+      edges: HashMap::new(),
+      aliases: (0..num_points).collect(),
+      tolerance: 1.0e-9 * size,
+    };
+    let far = 100.0 * size;
+    this.points.extend([
+      Point2::new(center.x - far, center.y - far),
+      Point2::new(center.x + far, center.y - far),
+      Point2::new(center.x, center.y + far),
+    ]);
+    this.add([num_points, num_points + 1, num_points + 2]);
+    for i in 0..num_points { this.insert(i) }
+    this
+  }
+
+  // Flip boundary segments into the triangulation and return the triangles enclosed by them
+  fn constrain(mut self, segments: &[(usize, usize)]) -> Vec<[usize; 3]> {
+    for &(a, b) in segments {
+      self.enforce(self.aliases[a], self.aliases[b]);
+    }
+    let num_points = self.aliases.len();
+    let lines: Vec<(Point2, Point2)> = segments.iter().map(|&(a, b)| (self.points[a], self.points[b]) ).collect();
+    self.triangles.iter()
+    .filter(|tri| tri.iter().all(|&i| i < num_points ) )
+    .filter(|tri| point_in_loops(Point2::centroid(&tri.map(|i| self.points[i] )), &lines) )
+    .cloned()
+    .collect()
+  }
+
+  fn add(&mut self, tri: [usize; 3]) {
+    self.triangles.push(tri);
+    for k in 0..3 { self.edges.insert((tri[k], tri[(k + 1) % 3]), self.triangles.len() - 1); }
+  }
+
+  fn set(&mut self, t: usize, tri: [usize; 3]) {
+    let old = self.triangles[t];
+    for k in 0..3 {
+      let edge = (old[k], old[(k + 1) % 3]);
+      if self.edges.get(&edge) == Some(&t) { self.edges.remove(&edge); }
+    }
+    self.triangles[t] = tri;
+    for k in 0..3 { self.edges.insert((tri[k], tri[(k + 1) % 3]), t); }
+  }
+
+  fn opposite(&self, t: usize, a: usize, b: usize) -> usize {
+    *self.triangles[t].iter().find(|&&i| i != a && i != b ).unwrap()
+  }
+
+  // Signed distances of a point from the edges of a triangle
+  fn distances(&self, t: usize, p: Point2) -> [f64; 3] {
+    let tri = self.triangles[t];
+    [0, 1, 2].map(|k| {
+      let (a, b) = (self.points[tri[k]], self.points[tri[(k + 1) % 3]]);
+      orient(a, b, p) / a.distance(b).max(EPSILON)
+    })
+  }
+
+  // Walk towards the point from the latest triangle, falling back to a full search
+  fn locate(&self, p: Point2) -> usize {
+    let mut t = self.triangles.len() - 1;
+    'walk: for _ in 0..self.triangles.len() {
+      let tri = self.triangles[t];
+      for k in 0..3 {
+        let (a, b) = (tri[k], tri[(k + 1) % 3]);
+        if orient(self.points[a], self.points[b], p) < 0.0 {
+          match self.edges.get(&(b, a)) {
+            Some(&next) => { t = next; continue 'walk },
+            None => break 'walk,
+          }
+        }
+      }
+      return t
+    }
+    let clearance = |t: usize| self.distances(t, p).into_iter().fold(f64::MAX, f64::min);
+    (0..self.triangles.len()).max_by(|&a, &b| clearance(a).partial_cmp(&clearance(b)).unwrap() ).unwrap()
+  }
+
+  fn insert(&mut self, i: usize) {
+    let p = self.points[i];
+    let t = self.locate(p);
+    let tri = self.triangles[t];
+    if let Some(&j) = tri.iter().find(|&&j| self.points[j].distance(p) < self.tolerance ) {
+      self.aliases[i] = j;
+      return
+    }
+    let distances = self.distances(t, p);
+    let on_edge = (0..3).find(|&k| distances[k] < self.tolerance );
+    if let Some(k) = on_edge {
+      let (a, b, c) = (tri[k], tri[(k + 1) % 3], tri[(k + 2) % 3]);
+      if let Some(&other) = self.edges.get(&(b, a)) {
+        let d = self.opposite(other, a, b);
+        self.set(t, [i, c, a]);
+        self.set(other, [i, d, b]);
+        self.add([i, b, c]);
+        self.add([i, a, d]);
+        self.legalize(i, vec![(c, a), (d, b), (b, c), (a, d)]);
+        return
+      }
+    }
+    let [a, b, c] = tri;
+    self.set(t, [i, a, b]);
+    self.add([i, b, c]);
+    self.add([i, c, a]);
+    self.legalize(i, vec![(a, b), (b, c), (c, a)]);
+  }
+
+  // Flip edges opposite of a new point, whose neighbours reach into the circumcircle of its triangles
+  fn legalize(&mut self, i: usize, mut stack: Vec<(usize, usize)>) {
+    while let Some((a, b)) = stack.pop() {
+      let (t, other) = match (self.edges.get(&(a, b)), self.edges.get(&(b, a))) {
+        (Some(&t), Some(&other)) => (t, other),
+        _ => continue,
+      };
+      if !self.triangles[t].contains(&i) { continue }
+      let d = self.opposite(other, a, b);
+      let (pi, pa, pb, pd) = (self.points[i], self.points[a], self.points[b], self.points[d]);
+      if in_circle(pi, pa, pb, pd) > self.tolerance * self.tolerance {
+        self.set(t, [i, a, d]);
+        self.set(other, [i, d, b]);
+        stack.push((a, d));
+        stack.push((d, b));
+      }
+    }
+  }
+
+  // Flip edges crossing a segment until the segment is part of the triangulation
+  fn enforce(&mut self, a: usize, b: usize) {
+    if a == b { return }
+    let (pa, pb) = (self.points[a], self.points[b]);
+    for _ in 0..self.triangles.len() {
+      if self.edges.contains_key(&(a, b)) || self.edges.contains_key(&(b, a)) { return }
+      let flip = (0..self.triangles.len()).flat_map(|t| (0..3).map(move |k| (t, k)) ).find_map(|(t, k)| {
+        let tri = self.triangles[t];
+        let (c, d, e) = (tri[k], tri[(k + 1) % 3], tri[(k + 2) % 3]);
+        let (pc, pd) = (self.points[c], self.points[d]);
+        let crosses = orient(pa, pb, pc) * orient(pa, pb, pd) < 0.0 && orient(pc, pd, pa) * orient(pc, pd, pb) < 0.0;
+        if !crosses { return None }
+        let other = *self.edges.get(&(d, c))?;
+        let f = self.opposite(other, c, d);
+        let pe = self.points[e];
+        let pf = self.points[f];
+        let is_convex = orient(pe, pf, pc) * orient(pe, pf, pd) < 0.0;
+        is_convex.then_some((t, other, c, d, e, f))
+      });
+      match flip {
+        Some((t, other, c, d, e, f)) => {
+          self.set(t, [c, f, e]);
+          self.set(other, [f, d, e]);
+        },
+        None => return,
+      }
+    }
+  }
+}
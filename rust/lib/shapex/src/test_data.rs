@@ -1,5 +1,7 @@
 use crate::internal::*;
+use crate::transform::*;
 use crate::curve::*;
+use crate::solid::*;
 
 pub fn crossing_lines() -> Vec<Line> {
   vec![
@@ -132,3 +134,32 @@ pub fn make_generic<T: Curve + Splittable>(elems: Vec<T>) -> Vec<CurveType> {
 pub fn make_region(elems: Vec<CurveType>) -> Region {
   elems.into_iter().map(|elem| TrimmedCurve::new(elem)).collect()
 }
+
+// Closed wire of lines connecting the given points
+pub fn polygon_wire(points: &[Point3]) -> Wire {
+  (0..points.len()).map(|i| TrimmedCurve::new(Line::new(points[i], points[(i + 1) % points.len()]).into_enum()) ).collect()
+}
+
+// Axis aligned square in the XY plane
+pub fn square_wire(size: f64, offset: f64) -> Wire {
+  polygon_wire(&[
+    Point3::new(offset, offset, 0.0),
+    Point3::new(offset + size, offset, 0.0),
+    Point3::new(offset + size, offset + size, 0.0),
+    Point3::new(offset, offset + size, 0.0),
+  ])
+}
+
+// Axis aligned box between two corners
+pub fn cube_between(min: Point3, max: Point3) -> Compound {
+  let size = max - min;
+  let mut cube = features::make_cube(size.x, size.y, size.z).unwrap();
+  // Cubes are made rotated about the Z axis
+  cube.transform(&(Matrix4::from_translation(min.to_vec()) * Matrix4::from_angle_z(Deg(-45.0))));
+  cube
+}
+
+// Vertices, edges, faces and Euler characteristic
+pub fn shell_counts(shell: &Shell) -> (usize, usize, usize, i32) {
+  (shell.vertices.len(), shell.edges.len(), shell.faces.len(), shell.euler_characteristics())
+}
@@ -116,7 +116,7 @@ impl Document {
       } else {
         let repair_error = feature.feature_type.as_feature().modified_components().iter()
           .find_map(|id| new_comp.find_child_mut(id).unwrap().compound.repair().err() )
-          .map(|error| FeatureError::Error(error) );
+          .map(FeatureError::Invalid);
         if repair_error.is_some() {
           feature.error = repair_error;
           comp.deep_clone()
@@ -40,7 +40,12 @@ pub enum FeatureType {
   CreateSketch(CreateSketchFeature),
   Extrusion(ExtrusionFeature),
   Revolution(RevolutionFeature),
+  Sweep(SweepFeature),
+  Loft(LoftFeature),
   Draft(DraftFeature),
+  Fillet(FilletFeature),
+  Chamfer(ChamferFeature),
+  Shell(ShellFeature),
 }
 
 impl FeatureType {
@@ -50,7 +55,12 @@ impl FeatureType {
       Self::CreateSketch(f) => f,
       Self::Extrusion(f) => f,
       Self::Revolution(f) => f,
+      Self::Sweep(f) => f,
+      Self::Loft(f) => f,
       Self::Draft(f) => f,
+      Self::Fillet(f) => f,
+      Self::Chamfer(f) => f,
+      Self::Shell(f) => f,
     }
   }
 
@@ -60,7 +70,12 @@ impl FeatureType {
       Self::CreateSketch(f) => f,
       Self::Extrusion(f) => f,
       Self::Revolution(f) => f,
+      Self::Sweep(f) => f,
+      Self::Loft(f) => f,
       Self::Draft(f) => f,
+      Self::Fillet(f) => f,
+      Self::Chamfer(f) => f,
+      Self::Shell(f) => f,
     }
   }
 }
@@ -70,6 +85,7 @@ impl FeatureType {
 pub enum FeatureError {
   Warning(String),
   Error(String),
+  Invalid(ValidationReport), // Feature produced broken geometry
 }
 
 impl std::fmt::Display for FeatureError {
@@ -77,6 +93,7 @@ impl std::fmt::Display for FeatureError {
     match self {
       Self::Warning(str) => write!(f, "{}", str),
       Self::Error(str) => write!(f, "{}", str),
+      Self::Invalid(report) => write!(f, "{}", report),
     }
   }
 }
@@ -118,7 +135,7 @@ fn update_profiles(profiles: &mut Vec<ProfileRef>) -> Result<(), FeatureError> {
   for profile_ref in profiles {
     let result = profile_ref.update();
     match result {
-      Err(FeatureError::Error(_)) => return result,
+      Err(FeatureError::Error(_) | FeatureError::Invalid(_)) => return result,
       Err(FeatureError::Warning(_)) => res = result,
       Ok(_) => {},
     }
@@ -126,6 +143,22 @@ fn update_profiles(profiles: &mut Vec<ProfileRef>) -> Result<(), FeatureError> {
   res
 }
 
+// Overlapping solids with curved faces can't be fused yet,
+// so joins keep them alongside each other and warn about it
+fn join_or_keep(compound: &mut Compound, tool: Compound) -> Result<(), FeatureError> {
+  compound.join(tool.clone()).or_else(|error| {
+    compound.create(tool).map_err(FeatureError::Error)?;
+    Err(FeatureError::Warning(format!("Bodies were not joined: {}", error)))
+  })
+}
+
+fn apply_tool(compound: &mut Compound, tool: Compound, op: BooleanType) -> Result<(), FeatureError> {
+  match op {
+    BooleanType::Join => join_or_keep(compound, tool),
+    _ => compound.boolean(tool, op).map_err(FeatureError::Error),
+  }
+}
+
 
 #[derive(Debug, Clone)]
 pub struct CreateComponentFeature {
@@ -200,13 +233,13 @@ impl ExtrusionFeature {
     FeatureType::Extrusion(self)
   }
 
-  fn make_tool(&self, profiles: &Vec<ProfileRef>) -> Result<Compound, FeatureError> {
+  fn make_tool(&self, profiles: &Vec<ProfileRef>, result: &mut Result<(), FeatureError>) -> Result<Compound, FeatureError> {
     let mut tool = Compound::default();
     for profile_ref in profiles {
       let mut profile = profile_ref.profile.clone();
       profile_ref.sketch.borrow().transform_profile(&mut profile);
       match features::extrude(&profile, self.distance) {
-        Ok(compound) => tool.join(compound),
+        Ok(compound) => if let Err(warning) = join_or_keep(&mut tool, compound) { *result = Err(warning) },
         Err(error) => return Err(FeatureError::Error(error)),
       }
     }
@@ -218,21 +251,20 @@ impl FeatureTrait for ExtrusionFeature {
   fn preview(&self) -> Option<Compound> {
     let mut profiles = self.profiles.clone();
     match update_profiles(&mut profiles) {
-      Err(FeatureError::Error(_)) => None,
-      Err(FeatureError::Warning(_)) | Ok(_) => self.make_tool(&profiles).ok(),
+      Err(FeatureError::Error(_) | FeatureError::Invalid(_)) => None,
+      Err(FeatureError::Warning(_)) | Ok(_) => self.make_tool(&profiles, &mut Ok(())).ok(),
     }
   }
 
   fn execute(&mut self, top_comp: &mut Component) -> Result<(), FeatureError> {
     let mut profiles = self.profiles.clone();
-    let result = update_profiles(&mut profiles);
+    let mut result = update_profiles(&mut profiles);
     if let Err(FeatureError::Error(_)) = result {
       return result;
     }
-    let tool = self.make_tool(&profiles)?;
+    let tool = self.make_tool(&profiles, &mut result)?;
     let comp = top_comp.find_child_mut(&self.component_id).unwrap();
-    comp.compound.boolean(tool.clone(), self.op);
-    result
+    apply_tool(&mut comp.compound, tool, self.op).and(result)
   }
 
   fn modified_components(&self) -> Vec<CompRef> {
@@ -260,14 +292,14 @@ impl RevolutionFeature {
     FeatureType::Revolution(self)
   }
 
-  fn make_tool(&self, profiles: &Vec<ProfileRef>, top_comp: &Component) -> Result<Compound, FeatureError> {
+  fn make_tool(&self, profiles: &Vec<ProfileRef>, top_comp: &Component, result: &mut Result<(), FeatureError>) -> Result<Compound, FeatureError> {
     let mut tool = Compound::default();
     if let Some(axis) = self.axis.get_axis(top_comp) {
       for profile_ref in profiles {
         let mut profile = profile_ref.profile.clone();
         profile_ref.sketch.borrow().transform_profile(&mut profile);
         match features::revolve(&profile, axis.clone(), self.angle) {
-          Ok(compound) => tool.join(compound),
+          Ok(compound) => if let Err(warning) = join_or_keep(&mut tool, compound) { *result = Err(warning) },
           Err(error) => return Err(FeatureError::Error(error)),
         }
       }
@@ -286,15 +318,138 @@ impl FeatureTrait for RevolutionFeature {
   fn execute(&mut self, top_comp: &mut Component) -> Result<(), FeatureError> {
     self.preview_compound = None;
     let mut profiles = self.profiles.clone();
-    let result = update_profiles(&mut profiles);
+    let mut result = update_profiles(&mut profiles);
     if let Err(FeatureError::Error(_)) = result {
       return result;
     }
-    let tool = self.make_tool(&profiles, top_comp)?;
+    let tool = self.make_tool(&profiles, top_comp, &mut result)?;
     let comp = top_comp.find_child_mut(&self.component_id).unwrap();
-    comp.compound.boolean(tool.clone(), self.op);
+    let applied = apply_tool(&mut comp.compound, tool.clone(), self.op);
+    if let Err(FeatureError::Error(_)) = applied {
+      return applied;
+    }
     self.preview_compound = Some(tool);
-    result
+    applied.and(result)
+  }
+
+  fn modified_components(&self) -> Vec<CompRef> {
+    vec![self.component_id]
+  }
+
+  fn repair(&mut self, _top_comp: &Component) {
+    update_profiles(&mut self.profiles).ok();
+  }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct SweepFeature {
+  pub component_id: Uuid,
+  pub profiles: Vec<ProfileRef>,
+  pub rail: CurveRef,
+  pub bounds: (f64, f64),
+  pub framing: features::Framing,
+  pub op: BooleanType,
+}
+
+impl SweepFeature {
+  pub fn into_enum(self) -> FeatureType {
+    FeatureType::Sweep(self)
+  }
+
+  fn make_tool(&self, profiles: &Vec<ProfileRef>, result: &mut Result<(), FeatureError>) -> Result<Compound, FeatureError> {
+    let mut rail = self.rail.curve.borrow().clone();
+    rail.as_curve_mut().transform(&self.rail.sketch.borrow().work_plane);
+    let mut tool = Compound::default();
+    for profile_ref in profiles {
+      let mut profile = profile_ref.profile.clone();
+      profile_ref.sketch.borrow().transform_profile(&mut profile);
+      match features::sweep(&profile, &rail, self.bounds, self.framing) {
+        Ok(compound) => if let Err(warning) = join_or_keep(&mut tool, compound) { *result = Err(warning) },
+        Err(error) => return Err(FeatureError::Error(error)),
+      }
+    }
+    Ok(tool)
+  }
+}
+
+impl FeatureTrait for SweepFeature {
+  fn preview(&self) -> Option<Compound> {
+    let mut profiles = self.profiles.clone();
+    match update_profiles(&mut profiles) {
+      Err(FeatureError::Error(_) | FeatureError::Invalid(_)) => None,
+      Err(FeatureError::Warning(_)) | Ok(_) => self.make_tool(&profiles, &mut Ok(())).ok(),
+    }
+  }
+
+  fn execute(&mut self, top_comp: &mut Component) -> Result<(), FeatureError> {
+    let mut profiles = self.profiles.clone();
+    let mut result = update_profiles(&mut profiles);
+    if let Err(FeatureError::Error(_) | FeatureError::Invalid(_)) = result {
+      return result;
+    }
+    let tool = self.make_tool(&profiles, &mut result)?;
+    let comp = top_comp.find_child_mut(&self.component_id).unwrap();
+    apply_tool(&mut comp.compound, tool, self.op).and(result)
+  }
+
+  fn modified_components(&self) -> Vec<CompRef> {
+    vec![self.component_id]
+  }
+
+  fn repair(&mut self, _top_comp: &Component) {
+    update_profiles(&mut self.profiles).ok();
+  }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct LoftFeature {
+  pub component_id: Uuid,
+  pub profiles: Vec<ProfileRef>,
+  pub guides: Vec<CurveRef>,
+  pub mode: features::LoftMode,
+  pub op: BooleanType,
+}
+
+impl LoftFeature {
+  pub fn into_enum(self) -> FeatureType {
+    FeatureType::Loft(self)
+  }
+
+  fn make_tool(&self, profiles: &[ProfileRef]) -> Result<Compound, FeatureError> {
+    let profiles: Vec<Profile> = profiles.iter().map(|profile_ref| {
+      let mut profile = profile_ref.profile.clone();
+      profile_ref.sketch.borrow().transform_profile(&mut profile);
+      profile
+    }).collect();
+    let guides: Vec<CurveType> = self.guides.iter().map(|guide| {
+      let mut curve = guide.curve.borrow().clone();
+      curve.as_curve_mut().transform(&guide.sketch.borrow().work_plane);
+      curve
+    }).collect();
+    features::loft(&profiles, &guides, self.mode).map_err(FeatureError::Error)
+  }
+}
+
+impl FeatureTrait for LoftFeature {
+  fn preview(&self) -> Option<Compound> {
+    let mut profiles = self.profiles.clone();
+    match update_profiles(&mut profiles) {
+      Err(FeatureError::Error(_) | FeatureError::Invalid(_)) => None,
+      Err(FeatureError::Warning(_)) | Ok(_) => self.make_tool(&profiles).ok(),
+    }
+  }
+
+  fn execute(&mut self, top_comp: &mut Component) -> Result<(), FeatureError> {
+    let mut profiles = self.profiles.clone();
+    let result = update_profiles(&mut profiles);
+    if let Err(FeatureError::Error(_) | FeatureError::Invalid(_)) = result {
+      return result;
+    }
+    let tool = self.make_tool(&profiles)?;
+    let comp = top_comp.find_child_mut(&self.component_id).unwrap();
+    apply_tool(&mut comp.compound, tool, self.op).and(result)
   }
 
   fn modified_components(&self) -> Vec<CompRef> {
@@ -347,3 +502,179 @@ impl FeatureTrait for DraftFeature {
     self.faces.retain(|face| face.get_face(top_comp).is_some() );
   }
 }
+
+
+// Blend edges grouped by the components they belong to
+fn blend_edges(
+  top_comp: &mut Component,
+  edge_refs: &[EdgeRef],
+  size: f64,
+  blend: fn(&mut Compound, &[Ref<Edge>], f64) -> Result<(), String>,
+) -> Result<(), FeatureError> {
+  let mut found = 0;
+  for comp_id in unique_components(edge_refs) {
+    let edges: Vec<Ref<Edge>> = edge_refs.iter()
+      .filter(|edge| edge.component_id == comp_id )
+      .filter_map(|edge| edge.get_edge(top_comp) )
+      .cloned()
+      .collect();
+    found += edges.len();
+    let comp = top_comp.find_child_mut(&comp_id).unwrap();
+    blend(&mut comp.compound, &edges, size).map_err(FeatureError::Error)?;
+  }
+  if found == edge_refs.len() {
+    Ok(())
+  } else {
+    Err(FeatureError::Warning("Some edges could not be found".into()))
+  }
+}
+
+fn unique_components(edge_refs: &[EdgeRef]) -> Vec<CompRef> {
+  let mut ids: Vec<Uuid> = edge_refs.iter().map(|edge| edge.component_id ).collect();
+  ids.sort_unstable();
+  ids.dedup();
+  ids
+}
+
+
+#[derive(Debug, Clone)]
+pub struct FilletFeature {
+  pub edges: Vec<EdgeRef>,
+  pub radius: f64,
+}
+
+impl FilletFeature {
+  pub fn into_enum(self) -> FeatureType {
+    FeatureType::Fillet(self)
+  }
+}
+
+impl FeatureTrait for FilletFeature {
+  fn execute(&mut self, top_comp: &mut Component) -> Result<(), FeatureError> {
+    blend_edges(top_comp, &self.edges, self.radius, features::fillet)
+  }
+
+  fn modified_components(&self) -> Vec<CompRef> {
+    unique_components(&self.edges)
+  }
+
+  fn repair(&mut self, top_comp: &Component) {
+    self.edges.retain(|edge| edge.get_edge(top_comp).is_some() );
+  }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct ChamferFeature {
+  pub edges: Vec<EdgeRef>,
+  pub distance: f64,
+}
+
+impl ChamferFeature {
+  pub fn into_enum(self) -> FeatureType {
+    FeatureType::Chamfer(self)
+  }
+}
+
+impl FeatureTrait for ChamferFeature {
+  fn execute(&mut self, top_comp: &mut Component) -> Result<(), FeatureError> {
+    blend_edges(top_comp, &self.edges, self.distance, features::chamfer)
+  }
+
+  fn modified_components(&self) -> Vec<CompRef> {
+    unique_components(&self.edges)
+  }
+
+  fn repair(&mut self, top_comp: &Component) {
+    self.edges.retain(|edge| edge.get_edge(top_comp).is_some() );
+  }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct ShellFeature {
+  pub component_id: CompRef,
+  pub faces: Vec<FaceRef>,
+  pub thickness: f64,
+}
+
+impl ShellFeature {
+  pub fn into_enum(self) -> FeatureType {
+    FeatureType::Shell(self)
+  }
+}
+
+impl FeatureTrait for ShellFeature {
+  fn execute(&mut self, top_comp: &mut Component) -> Result<(), FeatureError> {
+    let found_faces: Vec<Ref<Face>> = self.faces.iter()
+      .filter(|face| face.component_id == self.component_id )
+      .filter_map(|face| face.get_face(top_comp) )
+      .cloned()
+      .collect();
+    // Shelling without open faces would silently produce a hollow solid instead
+    if found_faces.is_empty() && !self.faces.is_empty() {
+      return Err(FeatureError::Error("Faces to remove could not be found".into()))
+    }
+    let comp = top_comp.find_child_mut(&self.component_id).unwrap();
+    match features::shell(&mut comp.compound, &found_faces, self.thickness) {
+      Err(error) => Err(FeatureError::Error(error)),
+      Ok(()) if found_faces.len() < self.faces.len() => Err(FeatureError::Warning("Some faces could not be found".into())),
+      Ok(()) => Ok(()),
+    }
+  }
+
+  fn modified_components(&self) -> Vec<CompRef> {
+    vec![self.component_id]
+  }
+
+  fn repair(&mut self, top_comp: &Component) {
+    self.faces.retain(|face| face.get_face(top_comp).is_some() );
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use shapex::internal::rc;
+  use shapex::test_data::{cube_between, shell_counts};
+
+  fn cube_component() -> Component {
+    Component {
+      compound: cube_between(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0)),
+      ..Default::default()
+    }
+  }
+
+  fn circle_extrusion(component_id: Uuid, op: BooleanType) -> ExtrusionFeature {
+    let mut sketch = Sketch::default();
+    sketch.elements.push(rc(Circle::new(Point3::new(0.5, 0.5, 0.0), 0.25).into_enum()));
+    let sketch = rc(sketch);
+    let profile = sketch.borrow().get_profiles(false).remove(0);
+    ExtrusionFeature {
+      component_id,
+      profiles: vec![ProfileRef { sketch, profile }],
+      distance: 2.0,
+      op,
+    }
+  }
+
+  #[test]
+  fn failed_cut_keeps_component() {
+    let mut top_comp = cube_component();
+    let mut feature = circle_extrusion(top_comp.id, BooleanType::Cut);
+    let result = feature.execute(&mut top_comp);
+    assert!(matches!(result, Err(FeatureError::Error(_))));
+    assert_eq!(top_comp.compound.solids.len(), 1);
+    assert_eq!(shell_counts(&top_comp.compound.solids[0].shells[0]), (8, 12, 6, 2));
+  }
+
+  #[test]
+  fn curved_join_warns() {
+    let mut top_comp = cube_component();
+    let mut feature = circle_extrusion(top_comp.id, BooleanType::Join);
+    let result = feature.execute(&mut top_comp);
+    assert!(matches!(result, Err(FeatureError::Warning(_))));
+    assert_eq!(top_comp.compound.solids.len(), 2);
+  }
+}
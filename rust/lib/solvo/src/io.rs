@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+
 // use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 
 use shapex::*;
+use shapex::io::step;
+
+use crate::Uuid;
 
 // use crate::Sketch;
 
@@ -58,6 +63,38 @@ use shapex::*;
 // }
 
 
+// Export solids of a component and its children as a STEP assembly.
+// Products are named by the titles of their components.
+pub fn export_step(comp: &crate::Component, titles: &HashMap<Uuid, String>, time_stamp: &str) -> String {
+  step::export_assembly(&step_part(comp, titles), "", time_stamp, false)
+}
+
+// Rebuild a component tree from a STEP file, along with warnings about skipped entities
+pub fn import_step(text: &str) -> Result<(crate::Component, Vec<String>), String> {
+  let import = step::import(text)?;
+  Ok((step_component(&import.assembly, &import.compound), import.warnings))
+}
+
+fn step_component(assembly: &step::Assembly, compound: &Compound) -> crate::Component {
+  crate::Component {
+    transform: assembly.transform,
+    compound: Compound {
+      solids: assembly.solids.iter().map(|&i| compound.solids[i].deep_clone() ).collect(),
+    },
+    children: assembly.children.iter().map(|child| step_component(child, compound) ).collect(),
+    ..Default::default()
+  }
+}
+
+fn step_part<'a>(comp: &'a crate::Component, titles: &HashMap<Uuid, String>) -> step::Part<'a> {
+  let name = titles.get(&comp.id).map_or("Component", |title| title.as_str() );
+  let mut part = step::Part::new(name, comp.compound.solids.iter().collect());
+  part.transform = comp.transform;
+  part.children = comp.children.iter().map(|child| step_part(child, titles) ).collect();
+  part
+}
+
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Component {
   pub sketches: Vec<Vec<CurveType>>,
@@ -3,6 +3,7 @@ use std::collections::HashSet;
 use shapex::Profile;
 use shapex::Axis;
 use shapex::Face;
+use shapex::Edge;
 use shapex::Curve;
 use shapex::Plane;
 use shapex::CurveType;
@@ -58,7 +59,14 @@ impl FaceRef {
 #[derive(Debug, Clone)]
 pub struct EdgeRef {
   pub component_id: CompRef,
-  pub edge_id: Uuid,
+  pub edge_id: Uuid, // Id of the edge's curve, which survives regeneration
+}
+
+impl EdgeRef {
+  pub fn get_edge<'a>(&self, top_comp: &'a Component) -> Option<&'a Ref<Edge>> {
+    let comp = top_comp.find_child(&self.component_id).unwrap();
+    comp.compound.find_edge(self.edge_id)
+  }
 }
 
 
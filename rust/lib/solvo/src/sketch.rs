@@ -144,7 +144,7 @@ impl Sketch {
   }
 
   fn get_wires(cut_elements: Vec<TrimmedCurve>, include_outer: bool) -> Vec<Wire> {
-    let (circles, mut others) = cut_elements.into_iter().partition(|elem| match elem.base {
+    let (circles, mut others) = cut_elements.into_iter().partition(|elem| match elem.cache {
       CurveType::Circle(_) => true,
       _ => false,
     });
@@ -247,8 +247,10 @@ impl Sketch {
       if used_backward.contains(&start_elem_id) { return regions }
       used_backward.insert(start_elem_id);
     }
-    // Add start_elem to path
-    path.push(start_elem.clone());
+    // Add start_elem to path, running away from the start point
+    let mut elem = start_elem.clone();
+    if path.is_empty() && !start_point.almost(elem.bounds.0) { elem.flip() }
+    path.push(elem);
     // Find connected segments
     let end_point = start_elem.other_bound(&start_point);
     let mut connected_elems: Vec<&TrimmedCurve> = all_elements.iter().filter(|other_elem| {
@@ -258,11 +260,12 @@ impl Sketch {
     }).collect();
     if connected_elems.len() > 0 {
       // Sort connected segments in clockwise order
+      let incoming_point = Self::point_near(start_elem, &end_point);
       connected_elems.sort_by(|a, b| { //XXX min_by_key
-        let final_point_a = a.other_bound(&end_point);
-        let final_point_b = b.other_bound(&end_point);
-        geom2d::clockwise(*start_point, end_point, final_point_b).partial_cmp(
-          &geom2d::clockwise(*start_point, end_point, final_point_a)
+        let final_point_a = Self::point_near(a, &end_point);
+        let final_point_b = Self::point_near(b, &end_point);
+        geom2d::clockwise(incoming_point, end_point, final_point_b).partial_cmp(
+          &geom2d::clockwise(incoming_point, end_point, final_point_a)
         ).unwrap_or(Ordering::Less)
       });
       // Follow the leftmost segment to complete loop in anti-clockwise order
@@ -286,10 +289,21 @@ impl Sketch {
     regions
   }
 
+  // Tesselated point next to the given end of an element
+  // Curved elements can't be told apart by their endpoints alone
+  fn point_near(elem: &TrimmedCurve, end_point: &Point3) -> Point3 {
+    let polyline = elem.tesselate();
+    if end_point.almost(polyline[0]) {
+      polyline[1]
+    } else {
+      polyline[polyline.len() - 2]
+    }
+  }
+
   fn remove_outer_loop(loops: &mut Vec<Wire>) {
     if loops.len() <= 1 { return }
     loops.retain(|region| {
-      !geom2d::is_clockwise(&geom2d::poly_from_wirebounds(region))
+      !geom2d::is_clockwise(&geom2d::tesselate_wire(region))
     });
   }
 
@@ -492,4 +506,15 @@ mod tests {
     assert_eq!(profiles[0].len(), 2);
     assert_eq!(profiles[1].len(), 1);
   }
+
+  #[test]
+  fn overlapping_circles_profile() {
+    let mut sketch = Sketch::default();
+    sketch.elements.push(rc(Circle::new(Point3::origin(), 1.0).into_enum()));
+    sketch.elements.push(rc(Circle::new(Point3::new(1.0, 0.0, 0.0), 1.0).into_enum()));
+    let cut_elements = Sketch::all_split(&sketch.elements);
+    assert_eq!(cut_elements.len(), 4, "{} cut_elements found instead of 4", cut_elements.len());
+    let profiles = sketch.get_profiles(false);
+    assert_eq!(profiles.len(), 3, "{} profiles found instead of 3", profiles.len());
+  }
 }
@@ -1,17 +1,35 @@
+use std::collections::HashMap;
+
 use js_sys::Array;
+use serde::Deserialize;
 use wasm_bindgen::prelude::*;
 
 use solvo::*;
 use shapex::*;
 use shapex::internal::Ref;
+use shapex::io::{self, obj, threemf};
 
 use crate::solid::JsSolid;
+use crate::buffer_geometry::JsBufferGeometry;
 use crate::sketch::JsSketch;
 use crate::construction_helper::JsConstructionHelper;
 
 // use crate::log;
 
 
+#[derive(Deserialize)]
+struct ComponentInfo {
+  title: String,
+  materials: HashMap<Uuid, MaterialInfo>, // By solid id
+}
+
+#[derive(Deserialize)]
+struct MaterialInfo {
+  name: String,
+  color: [u8; 4],
+}
+
+
 #[wasm_bindgen]
 pub struct JsComponent {
   #[wasm_bindgen(skip)]
@@ -65,24 +83,114 @@ impl JsComponent {
     ).collect()
   }
 
-  pub fn export_stl(&self, title: &str) -> String {
+  pub fn export_stl(&self, title: &str, binary: bool, include_children: bool) -> Vec<u8> {
+    let doc = self.document.borrow();
+    let mut mesh = Mesh::default();
+    Self::mesh_all(self.get_comp(&doc), &Matrix4::identity(), include_children, &mut mesh);
+    if binary {
+      shapex::io::stl::export_binary(&mesh, title)
+    } else {
+      shapex::io::stl::export(&mesh, title).into_bytes()
+    }
+  }
+
+  // Components only hold solids, so imported meshes are handed back for display
+  pub fn import_stl(data: &[u8]) -> Result<JsBufferGeometry, JsValue> {
+    let mesh = shapex::io::stl::import(data)?;
+    Ok(JsBufferGeometry::from(mesh.to_buffer_geometry()))
+  }
+
+  // Info maps component ids to their titles
+  pub fn export_step(&self, info: JsValue) -> Result<String, JsValue> {
+    let info: HashMap<Uuid, ComponentInfo> = info.into_serde().map_err(|e| e.to_string() )?;
+    let titles = info.into_iter().map(|(id, comp_info)| (id, comp_info.title) ).collect();
     let doc = self.document.borrow();
-    let comp = self.get_comp(&doc);
-    let mesh = comp.compound.solids[0].tesselate();
-    shapex::io::stl::export(&mesh, title)
+    let time_stamp: String = js_sys::Date::new_0().to_iso_string().into();
+    Ok(solvo::io::export_step(self.get_comp(&doc), &titles, &time_stamp))
   }
 
-  pub fn export_3mf(&self) -> String {
-    let meshes = Self::tesselate_all(&self.get_comp(&self.document.borrow()));
-    shapex::io::threemf::export(&meshes, "millimeter")
+  // Info maps component ids to their titles and the materials of their solids
+  pub fn export_3mf(&self, info: JsValue, thumbnail: Option<Vec<u8>>) -> Result<Vec<u8>, JsValue> {
+    let info: HashMap<Uuid, ComponentInfo> = info.into_serde().map_err(|e| e.to_string() )?;
+    let doc = self.document.borrow();
+    let mut materials = vec![];
+    let root = Self::export_part(self.get_comp(&doc), &info, &mut materials);
+    Ok(threemf::export(&threemf::Package {
+      unit: "millimeter".into(),
+      materials,
+      root,
+      thumbnail,
+    })?)
   }
 
-  fn tesselate_all(comp: &Component) -> Vec<Mesh> {
-    let mut meshes: Vec<Mesh> = comp.compound.solids.iter().map(|body| body.tesselate() ).collect();
+  // Parts of the package get merged into a single mesh
+  pub fn import_3mf(data: &[u8]) -> Result<JsBufferGeometry, JsValue> {
+    let package = threemf::import(data)?;
+    Ok(JsBufferGeometry::from(package.root.flatten().to_buffer_geometry()))
+  }
+
+  // Materials are referenced from the given material library, if any
+  pub fn export_obj(&self, info: JsValue, mtllib: Option<String>) -> Result<String, JsValue> {
+    let info: HashMap<Uuid, ComponentInfo> = info.into_serde().map_err(|e| e.to_string() )?;
+    let doc = self.document.borrow();
+    let mut materials = vec![];
+    let root = Self::export_part(self.get_comp(&doc), &info, &mut materials);
+    Ok(obj::export(&root, &materials, mtllib.as_deref()))
+  }
+
+  // Groups of the file get merged into a single mesh
+  pub fn import_obj(text: &str) -> Result<JsBufferGeometry, JsValue> {
+    let mut mesh = Mesh::default();
+    for (_, group) in obj::import(text)? {
+      mesh.append(group);
+    }
+    Ok(JsBufferGeometry::from(mesh.to_buffer_geometry()))
+  }
+
+  pub fn export_mtl(&self, info: JsValue) -> Result<String, JsValue> {
+    let info: HashMap<Uuid, ComponentInfo> = info.into_serde().map_err(|e| e.to_string() )?;
+    let doc = self.document.borrow();
+    let mut materials = vec![];
+    Self::export_part(self.get_comp(&doc), &info, &mut materials);
+    Ok(obj::export_mtl(&materials))
+  }
+
+  fn export_part(comp: &Component, info: &HashMap<Uuid, ComponentInfo>, materials: &mut Vec<io::Material>) -> io::Part {
+    let comp_info = info.get(&comp.id);
+    let title = comp_info.map(|comp_info| comp_info.title.as_str() ).unwrap_or("Component");
+    let objects = comp.compound.solids.iter().enumerate().map(|(i, body)| {
+      // Materials are shared between bodies by name
+      let material = comp_info.and_then(|comp_info| comp_info.materials.get(&body.id) ).map(|material| {
+        materials.iter().position(|other| other.name == material.name ).unwrap_or_else(|| {
+          materials.push(io::Material { name: material.name.clone(), color: material.color });
+          materials.len() - 1
+        })
+      });
+      let mut mesh = body.tesselate();
+      mesh.heal();
+      io::Object { name: format!("{} Body {}", title, i + 1), mesh, material }
+    }).collect();
+    let mut part = io::Part::new(title, objects);
+    part.children = comp.children.iter().map(|child| {
+      let mut child_part = Self::export_part(child, info, materials);
+      child_part.transform = child.transform;
+      child_part
+    }).collect();
+    part
+  }
+
+  // Merge solids into a single mesh, placing children with their transforms
+  fn mesh_all(comp: &Component, transform: &Matrix4, include_children: bool, mesh: &mut Mesh) {
+    for body in &comp.compound.solids {
+      let mut body_mesh = body.tesselate();
+      body_mesh.heal();
+      body_mesh.transform(transform);
+      mesh.append(body_mesh);
+    }
+    if !include_children { return }
     for child in &comp.children {
-      meshes.append(&mut Self::tesselate_all(&child));
+      Self::mesh_all(child, &(transform * child.transform), true, mesh);
     }
-    meshes
   }
 
   // pub fn serialize(&self) -> String {
@@ -14,6 +14,7 @@ use crate::utils::point_to_js;
 use crate::controllable::as_controllable_mut;
 use crate::controllable::as_controllable;
 use crate::feature::JsAxialRef;
+use crate::feature::JsCurveRef;
 
 
 #[wasm_bindgen]
@@ -38,6 +39,10 @@ impl JsCurve {
     JsValue::from_serde(&self.real.borrow().get_id()).unwrap()
   }
 
+  pub fn get_id(&self) -> JsValue {
+    self.id()
+  }
+
   pub fn typename(&self) -> String {
     match *self.real.borrow() {
       CurveType::Line(_) => "Line",
@@ -121,4 +126,11 @@ impl JsCurve {
       _ => unreachable!(),
     }
   }
+
+  pub fn make_curve_reference(&self) -> JsValue {
+    JsValue::from(JsCurveRef::new(CurveRef {
+      curve: self.real.clone(),
+      sketch: self.sketch.clone(),
+    }))
+  }
 }
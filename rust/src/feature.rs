@@ -9,6 +9,7 @@ use solvo::*;
 use crate::document::JsDocument;
 use crate::region::JsRegion;
 use crate::solid::JsFace;
+use crate::solid::JsEdge;
 use crate::curve::JsCurve;
 use crate::buffer_geometry::JsBufferGeometry;
 use crate::construction_helper::JsConstructionHelper;
@@ -90,6 +91,60 @@ impl JsAxialRef {
 }
 
 
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct JsEdgeRef {
+  real: EdgeRef,
+  document: Ref<Document>,
+}
+
+impl JsEdgeRef {
+  pub fn new(real: EdgeRef, document: Ref<Document>) -> Self {
+    Self {
+      real,
+      document,
+    }
+  }
+}
+
+#[wasm_bindgen]
+impl JsEdgeRef {
+  pub fn get_item(&self) -> JsValue {
+    if let Some(edge) = self.real.get_edge(self.document.borrow().get_tree()) {
+      JsValue::from(JsEdge::from(edge, self.real.component_id, self.document.clone()))
+    } else {
+      JsValue::undefined()
+    }
+  }
+
+  pub fn get_item_id(&self) -> JsValue {
+    JsValue::from_serde(&self.real.edge_id).unwrap()
+  }
+}
+
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct JsCurveRef(CurveRef);
+
+impl JsCurveRef {
+  pub fn new(real: CurveRef) -> Self {
+    Self(real)
+  }
+}
+
+#[wasm_bindgen]
+impl JsCurveRef {
+  pub fn get_item(&self) -> JsValue {
+    JsValue::from(JsCurve::from(self.0.curve.clone(), self.0.sketch.clone()))
+  }
+
+  pub fn get_item_id(&self) -> JsValue {
+    JsValue::from_serde(&self.0.curve.borrow().get_id()).unwrap()
+  }
+}
+
+
 #[wasm_bindgen]
 #[derive(Debug, Clone)]
 pub struct JsFaceRef {
@@ -183,6 +238,48 @@ impl JsProfileRefList {
 }
 
 
+#[wasm_bindgen]
+pub struct JsEdgeRefList {
+  edges: Vec<JsEdgeRef>,
+}
+
+#[wasm_bindgen]
+impl JsEdgeRefList {
+
+  #[wasm_bindgen(constructor)]
+  pub fn new() -> Self {
+    Self {
+      edges: vec![],
+    }
+  }
+
+  pub fn push(&mut self, edge: &JsEdgeRef) {
+    self.edges.push(edge.clone());
+  }
+}
+
+
+#[wasm_bindgen]
+pub struct JsCurveRefList {
+  curves: Vec<JsCurveRef>,
+}
+
+#[wasm_bindgen]
+impl JsCurveRefList {
+
+  #[wasm_bindgen(constructor)]
+  pub fn new() -> Self {
+    Self {
+      curves: vec![],
+    }
+  }
+
+  pub fn push(&mut self, curve: &JsCurveRef) {
+    self.curves.push(curve.clone());
+  }
+}
+
+
 #[wasm_bindgen]
 pub struct JsFaceRefList {
   faces: Vec<JsFaceRef>,
@@ -253,7 +350,7 @@ impl JsFeature {
     if let Some(real) = self.real.as_ref() {
       real.borrow().error.as_ref().map_or(JsValue::undefined(), |e| JsValue::from(vec![e.to_string(), match e {
         FeatureError::Warning(_) => "warning".into(),
-        FeatureError::Error(_) => "error".into(),
+        FeatureError::Error(_) | FeatureError::Invalid(_) => "error".into(),
       }].iter().map(|item| JsValue::from(item) ).collect::<Array>()))
     } else {
       JsValue::undefined()
@@ -312,6 +409,42 @@ impl JsFeature {
     self.process_feature(feature);
   }
 
+  pub fn sweep(&mut self, comp_ref: JsValue, profiles: JsProfileRefList, rail: &JsCurveRef, start: f64, end: f64, fixed_normal: bool, op: &str) {
+    let profiles = &profiles.profiles;
+    // Fixed profiles keep their orientation towards the plane of the rail's sketch
+    let framing = if fixed_normal {
+      features::Framing::FixedNormal(rail.0.sketch.borrow().work_plane.z.truncate())
+    } else {
+      features::Framing::Frenet
+    };
+    let feature = Feature::new(
+      SweepFeature {
+        component_id: comp_ref.into_serde().unwrap(),
+        profiles: profiles.iter().map(|profile| profile.0.clone() ).collect(),
+        rail: rail.0.clone(),
+        bounds: (start, end),
+        framing,
+        op: get_op(op),
+      }.into_enum(),
+    );
+    self.process_feature(feature);
+  }
+
+  pub fn loft(&mut self, comp_ref: JsValue, profiles: JsProfileRefList, guides: JsCurveRefList, tangent: bool, op: &str) {
+    let profiles = &profiles.profiles;
+    let guides = &guides.curves;
+    let feature = Feature::new(
+      LoftFeature {
+        component_id: comp_ref.into_serde().unwrap(),
+        profiles: profiles.iter().map(|profile| profile.0.clone() ).collect(),
+        guides: guides.iter().map(|guide| guide.0.clone() ).collect(),
+        mode: if tangent { features::LoftMode::Tangent } else { features::LoftMode::Ruled },
+        op: get_op(op),
+      }.into_enum(),
+    );
+    self.process_feature(feature);
+  }
+
   pub fn draft(&mut self, faces: JsFaceRefList, ref_plane: &JsPlanarRef, angle: f64) {
     let faces = &faces.faces;
     let feature = Feature::new(
@@ -324,6 +457,40 @@ impl JsFeature {
     self.process_feature(feature);
   }
 
+  pub fn fillet(&mut self, edges: JsEdgeRefList, radius: f64) {
+    let edges = &edges.edges;
+    let feature = Feature::new(
+      FilletFeature {
+        edges: edges.iter().map(|edge| edge.real.clone() ).collect(),
+        radius,
+      }.into_enum(),
+    );
+    self.process_feature(feature);
+  }
+
+  pub fn chamfer(&mut self, edges: JsEdgeRefList, distance: f64) {
+    let edges = &edges.edges;
+    let feature = Feature::new(
+      ChamferFeature {
+        edges: edges.iter().map(|edge| edge.real.clone() ).collect(),
+        distance,
+      }.into_enum(),
+    );
+    self.process_feature(feature);
+  }
+
+  pub fn shell(&mut self, comp_ref: JsValue, faces: JsFaceRefList, thickness: f64) {
+    let faces = &faces.faces;
+    let feature = Feature::new(
+      ShellFeature {
+        component_id: comp_ref.into_serde().unwrap(),
+        faces: faces.iter().map(|face| face.real.clone() ).collect(),
+        thickness,
+      }.into_enum(),
+    );
+    self.process_feature(feature);
+  }
+
   fn process_feature(&mut self, mut feature: Feature) {
     let mut doc = self.document.borrow_mut();
     if let Some(this) = &mut self.real {
@@ -372,10 +539,22 @@ impl JsFeature {
 
   pub fn get_face_refs(&self) -> Array {
     if let Some(real) = &self.real {
-      if let FeatureType::Draft(feature) = &real.borrow().feature_type {
-        feature.faces.iter().map(|face_ref| JsValue::from(JsFaceRef::new(face_ref.clone(), self.document.clone())) ).collect()
-      } else {
-        Array::new()
+      match &real.borrow().feature_type {
+        FeatureType::Draft(DraftFeature { faces, .. }) | FeatureType::Shell(ShellFeature { faces, .. })
+        => faces.iter().map(|face_ref| JsValue::from(JsFaceRef::new(face_ref.clone(), self.document.clone())) ).collect(),
+        _ => Array::new(),
+      }
+    } else {
+      Array::new()
+    }
+  }
+
+  pub fn get_edge_refs(&self) -> Array {
+    if let Some(real) = &self.real {
+      match &real.borrow().feature_type {
+        FeatureType::Fillet(FilletFeature { edges, .. }) | FeatureType::Chamfer(ChamferFeature { edges, .. })
+        => edges.iter().map(|edge_ref| JsValue::from(JsEdgeRef::new(edge_ref.clone(), self.document.clone())) ).collect(),
+        _ => Array::new(),
       }
     } else {
       Array::new()
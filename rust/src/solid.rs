@@ -8,6 +8,7 @@ use shapex::internal::Ref;
 
 use crate::feature::JsPlanarRef;
 use crate::feature::JsFaceRef;
+use crate::feature::JsEdgeRef;
 use crate::buffer_geometry::JsBufferGeometry;
 use crate::utils::point_to_js;
 use crate::utils::points_to_js;
@@ -81,7 +82,7 @@ impl JsFace {
   pub fn tesselate(&self) -> JsBufferGeometry {
     let this = self.real.borrow();
     JsBufferGeometry::from(
-      this.make_surface().tesselate().to_buffer_geometry()
+      this.tesselate().to_buffer_geometry()
     )
   }
 
@@ -112,24 +113,43 @@ impl JsFace {
 
 #[wasm_bindgen]
 pub struct JsEdge {
+  component_id: Uuid,
   real: Ref<Edge>,
+  document: Ref<Document>,
 }
 
-#[wasm_bindgen]
 impl JsEdge {
-  fn from(edge: &Ref<Edge>) -> Self {
+  pub fn from(edge: &Ref<Edge>, component_id: Uuid, document: Ref<Document>) -> Self {
     Self {
+      component_id,
       real: edge.clone(),
+      document,
     }
   }
+}
 
+#[wasm_bindgen]
+impl JsEdge {
+  // Edges are identified by their curve, like edge references
   pub fn get_id(&self) -> JsValue {
-    JsValue::from_serde(&self.real.borrow().id).unwrap()
+    JsValue::from_serde(&self.real.borrow().curve.get_id()).unwrap()
+  }
+
+  pub fn get_center(&self) -> JsValue {
+    let points = self.real.borrow().left_half.borrow().make_curve().tesselate();
+    point_to_js(points[points.len() / 2])
   }
 
   pub fn tesselate(&self) -> Array {
     points_to_js(self.real.borrow().left_half.borrow().make_curve().tesselate())
   }
+
+  pub fn make_edge_reference(&self) -> JsValue {
+    JsValue::from(JsEdgeRef::new(EdgeRef {
+      component_id: self.component_id,
+      edge_id: self.real.borrow().curve.get_id(),
+    }, self.document.clone()))
+  }
 }
 
 
@@ -153,7 +173,7 @@ impl JsSolid {
       if edge.borrow().is_inner() {
         None
       } else {
-        Some(JsValue::from(JsEdge::from(edge)))
+        Some(JsValue::from(JsEdge::from(edge, component_id, document.clone())))
       }
     }).collect();
     // Faces